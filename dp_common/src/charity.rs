@@ -0,0 +1,218 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey, program_error::ProgramError, msg};
+use crate::pod::PodU64;
+
+// the number of charities that were supported before the registry, which CharityData.charity_totals still tracks
+pub const N_CHARITIES : usize = 7;
+
+// the max number of charities that can be added to a registry, and the max length of their names in bytes
+pub const MAX_CHARITIES : usize = 32;
+pub const MAX_CHARITY_NAME_LEN : usize = 32;
+
+// seed used to derive the charity registry account for each program
+pub const CHARITY_REGISTRY_SEED : &[u8] = b"charity_registry";
+
+// the charities each registry was seeded with, which keep the indices the clients used before the registry
+pub const DEFAULT_CHARITIES : [(&str, &str); N_CHARITIES] = [
+    ("EvidenceAction", "9fF5EQV6FVy7V5SaHBXfAaTUBvuyimQ9X3jarc2mRHzi"),
+    ("GirlsWhoCode", "5qrmDeRFhBTnEkqJsRKJAkTJzrZnyC9bWmRhL6RZqWt1"),
+    ("OneTreePlanted", "GeCaNYhRswBFoTxtNaf9wKYJEBZoxHa9Fao6aQKzDDo2"),
+    ("OutrightActionInt", "AiY4t79umvBqGvR43f5rL8jR8F2JZwG87mB55adAF2cf"),
+    ("TheLifeYouCanSave", "8qQpHYjLkNiKvLtFzrjzgFZfveNJZ9AnQuBUoQj1t3DB"),
+    ("UkraineERF", "E6TPLh77cx9b5aWsmxM8geit2PBLVEBVAvF6ye9Qe4ZQ"),
+    ("WaterOrg", "5UNSVwtiSdfsCbJokL4fHtzV28mVNi8fQkMjPQw6v7Xd")
+];
+
+// on chain data that saves summary stats of the donations
+// this is embedded in the game state so it uses the byte aligned integer types to allow zero-copy access
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct CharityData {
+    // the total donated to each of the original charities, totals for every charity are kept in the registry
+    pub charity_totals : [PodU64 ; N_CHARITIES],
+    // the total donated overall
+    pub donated_total : PodU64,
+    // the total paid overall
+    pub paid_total : PodU64,
+    // the number of participating accounts
+    pub n_donations : PodU64
+}
+
+impl CharityData {
+    // add a single donation to the summary stats, returning None and leaving them unchanged if any total would overflow
+    pub fn record_donation(&mut self, charity_index : u8, amount_charity : u64, total_paid : u64) -> Option<()> {
+        let charity_index = charity_index as usize;
+
+        let charity_total = match self.charity_totals.get(charity_index) {
+            Some(total) => Some(total.get().checked_add(amount_charity)?),
+            None => None
+        };
+        let donated_total = self.donated_total.get().checked_add(amount_charity)?;
+        let paid_total = self.paid_total.get().checked_add(total_paid)?;
+        let n_donations = self.n_donations.get().checked_add(1)?;
+
+        if let Some(charity_total) = charity_total {
+            self.charity_totals[charity_index].set(charity_total);
+        }
+        self.donated_total.set(donated_total);
+        self.paid_total.set(paid_total);
+        self.n_donations.set(n_donations);
+
+        Some(())
+    }
+}
+
+/// Determines and reports the size of the charity data.
+pub fn get_charity_size() -> usize {
+    std::mem::size_of::<CharityData>()
+}
+
+// a single charity in the registry
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct CharityEntry {
+    // utf8 name, padded with zeros
+    pub name : [u8; MAX_CHARITY_NAME_LEN],
+    // the SOL address that donations to the charity are sent to
+    pub address : Pubkey,
+    // retired charities keep their index and total but can no longer receive donations
+    pub active : u8,
+    // the total donated to this charity
+    pub total : PodU64
+}
+
+impl CharityEntry {
+    pub fn get_name(&self) -> String {
+        let len = self.name.iter().position(|c| *c == 0).unwrap_or(MAX_CHARITY_NAME_LEN);
+        String::from_utf8_lossy(&self.name[..len]).to_string()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active != 0
+    }
+
+    // add a donation to the charity's total, returning the new total or None if it would overflow
+    pub fn add_donation(&mut self, amount : u64) -> Option<u64> {
+        let total = self.total.get().checked_add(amount)?;
+        self.total.set(total);
+
+        Some(total)
+    }
+}
+
+// the list of charities a program can donate to, which the program's admin can add to and update
+// like the game state this is accessed in place with CharityRegistry::load and CharityRegistry::load_mut
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct CharityRegistry {
+    // the number of charities that have been added, up to MAX_CHARITIES
+    pub n_charities : u8,
+    pub charities : [CharityEntry; MAX_CHARITIES]
+}
+
+impl CharityRegistry {
+    // view the registry account without copying it
+    pub fn load(data : &[u8]) -> Result<&CharityRegistry, ProgramError> {
+        match data.get(..get_charity_registry_size()) {
+            Some(bytes) => Ok(bytemuck::from_bytes(bytes)),
+            None => {
+                msg!("charity registry account is too small {} < {}", data.len(), get_charity_registry_size());
+                Err(ProgramError::AccountDataTooSmall)
+            }
+        }
+    }
+
+    pub fn load_mut(data : &mut [u8]) -> Result<&mut CharityRegistry, ProgramError> {
+        let data_len = data.len();
+        match data.get_mut(..get_charity_registry_size()) {
+            Some(bytes) => Ok(bytemuck::from_bytes_mut(bytes)),
+            None => {
+                msg!("charity registry account is too small {} < {}", data_len, get_charity_registry_size());
+                Err(ProgramError::AccountDataTooSmall)
+            }
+        }
+    }
+
+    // add a new active charity, returning its index, or None if the registry is full or the name is too long
+    pub fn add_charity(&mut self, name : &str, address : Pubkey) -> Option<u8> {
+        let index = self.n_charities as usize;
+        if index >= MAX_CHARITIES || name.is_empty() || name.len() > MAX_CHARITY_NAME_LEN {
+            return None;
+        }
+
+        let mut entry = CharityEntry::zeroed();
+        entry.name[..name.len()].copy_from_slice(name.as_bytes());
+        entry.address = address;
+        entry.active = 1;
+
+        self.charities[index] = entry;
+        self.n_charities += 1;
+
+        Some(index as u8)
+    }
+
+    // the charity at index, if it has been added
+    pub fn get_charity(&self, index : u8) -> Option<&CharityEntry> {
+        self.charities[..self.n_charities as usize].get(index as usize)
+    }
+
+    pub fn get_charity_mut(&mut self, index : u8) -> Option<&mut CharityEntry> {
+        self.charities[..self.n_charities as usize].get_mut(index as usize)
+    }
+
+    // the charity at index, if it exists and can currently receive donations
+    pub fn get_active_charity_mut(&mut self, index : u8) -> Option<&mut CharityEntry> {
+        self.get_charity_mut(index).filter(|entry| entry.is_active())
+    }
+}
+
+/// Determines and reports the size of the charity registry.
+pub fn get_charity_registry_size() -> usize {
+    std::mem::size_of::<CharityRegistry>()
+}
+
+pub fn get_expected_charity_registry_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[CHARITY_REGISTRY_SEED], program_id)
+}
+
+// check that the registry account is the one belonging to this program before it is loaded. both programs share the
+// registry layout but not their error codes, so each passes the error it reports for a wrong or uninitialised registry
+pub fn check_charity_registry(
+    program_id :  &Pubkey,
+    charity_registry_account_info : &AccountInfo,
+    invalid_registry_error : ProgramError
+) -> ProgramResult
+{
+    let (expected_registry_key, _bump_seed) = get_expected_charity_registry_key(program_id);
+
+    if charity_registry_account_info.key != &expected_registry_key {
+        msg!("expected charity registry account {}", expected_registry_key);
+        return Err(invalid_registry_error);
+    }
+
+    if charity_registry_account_info.owner != program_id {
+        msg!("charity registry has not been initialised");
+        return Err(invalid_registry_error);
+    }
+
+    Ok(())
+}
+
+// instruction data used by both programs to manage their registry
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct AddCharityData {
+    pub name : String,
+    pub address : Pubkey
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct UpdateCharityData {
+    // the index of the charity in the registry
+    pub index : u8,
+    // the address that donations should now be sent to
+    pub address : Pubkey,
+    // set to false to retire the charity
+    pub active : bool
+}
@@ -0,0 +1,168 @@
+use spl_associated_token_account::get_associated_token_address;
+use solana_program::{pubkey::Pubkey, declare_id, bpf_loader_upgradeable};
+use crate::game::state::{Config, RandomnessSource};
+pub use crate::keys::{get_expected_daoplays_key, get_expected_token_mint_key, get_expected_daoplays_token_key};
+// functions to calculate expected public keys
+
+// the keys below are the mainnet values, which the program reads from its config account rather than
+// comparing against directly. they are used to build the default config in get_mainnet_config
+
+mod btc_oracle {
+    use super::*;
+    declare_id!("GVXRSBjFk6e6J3NbVPXohDJetcTjaeeuykUpbQF8UoMU");   
+}
+mod eth_oracle {
+    use super::*;
+    declare_id!("JBu1AL4obBcCMqKBBxhpWCNUt136ijcuMZLFvTP7iWdB");   
+}
+mod sol_oracle {
+    use super::*;
+    declare_id!("H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG");   
+}
+
+// seeds used to derive the program's accounts
+pub const PDA_SEED : &[u8] = b"token_account";
+pub const DATA_ACCOUNT_SEED : &str = "data_account";
+pub const CONFIG_SEED : &[u8] = b"config";
+pub const COMMIT_SEED : &[u8] = b"commit";
+pub const VOTE_TALLY_SEED : &[u8] = b"vote_tally";
+pub const INPUT_LOG_SEED : &[u8] = b"input_log";
+pub const MODE_BALLOT_SEED : &[u8] = b"mode_ballot";
+pub const PUSH_STATS_SEED : &[u8] = b"push_stats";
+pub const CRANK_TIP_SEED : &[u8] = b"crank_tip";
+
+pub fn get_expected_btc_key() -> Pubkey
+{
+    btc_oracle::ID
+}
+
+pub fn get_expected_eth_key() -> Pubkey
+{
+    eth_oracle::ID
+}
+
+pub fn get_expected_sol_key() -> Pubkey
+{
+    sol_oracle::ID
+}
+
+pub fn get_pda_bump() -> u8
+{
+    255
+}
+
+pub fn get_expected_program_address_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    let program_address = Pubkey::create_program_address(&[PDA_SEED, &[get_pda_bump()]], program_id).unwrap();
+
+    (program_address, get_pda_bump())
+}
+
+// the data account is created off chain by the config authority
+pub fn get_expected_data_account_key(authority : &Pubkey, program_id : &Pubkey) -> Pubkey
+{
+    Pubkey::create_with_seed(
+        authority,
+        DATA_ACCOUNT_SEED,
+        program_id,
+    ).unwrap()
+}
+
+pub fn get_expected_program_token_key(program_id : &Pubkey, token_mint : &Pubkey) -> Pubkey
+{
+    get_associated_token_address(
+        &get_expected_program_address_key(program_id).0, 
+        token_mint
+    )
+}
+
+pub fn get_expected_config_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+// crank tips are paid from this address and its token account, which are funded separately from the prize pool
+pub fn get_expected_crank_tip_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[CRANK_TIP_SEED], program_id)
+}
+
+pub fn get_expected_crank_tip_token_key(program_id : &Pubkey, token_mint : &Pubkey) -> Pubkey
+{
+    get_associated_token_address(
+        &get_expected_crank_tip_key(program_id).0,
+        token_mint
+    )
+}
+
+// the upgradeable loader's program data account, which records the program's upgrade authority
+pub fn get_expected_program_data_key(program_id : &Pubkey) -> Pubkey
+{
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+// the config used for the mainnet deployment
+pub fn get_mainnet_config(program_id : &Pubkey) -> Config
+{
+    Config {
+        authority : get_expected_daoplays_key(),
+        data_account : get_expected_data_account_key(&get_expected_daoplays_key(), program_id),
+        token_mint : get_expected_token_mint_key(),
+        btc_oracle : get_expected_btc_key(),
+        eth_oracle : get_expected_eth_key(),
+        sol_oracle : get_expected_sol_key(),
+        tokens_won : 100,
+        max_winners : 4,
+        min_bid : 100000,
+        selection_time : 5 * 60,
+        protect_larger_bids : false,
+        max_oracle_slot_age : 25,
+        randomness_source : RandomnessSource::Oracles,
+        reveal_timeout_slots : 150,
+        commit_bond : 100000000,
+        crank_tip_lamports : 10000,
+        crank_tip_tokens : 0,
+        input_window_slots : 5,
+        mode_vote_slots : 9000,
+        treasury : get_expected_daoplays_key(),
+        push_burn_bps : 0,
+        push_treasury_bps : 0,
+        cancel_refund_tokens_per_sol : 1000
+    }
+}
+
+// each bidder has a data account that records their position in the bid ladder
+pub fn get_expected_bidder_data_key(program_id : &Pubkey, bidder : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[&bidder.to_bytes()], program_id)
+}
+
+// the account holding the commitment and bond in commit-reveal mode
+pub fn get_expected_commit_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[COMMIT_SEED], program_id)
+}
+
+// the button votes of recent slots are tallied in the vote tally account
+pub fn get_expected_vote_tally_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[VOTE_TALLY_SEED], program_id)
+}
+
+// the inputs ResolveInput picks from the votes are recorded in the input log account
+pub fn get_expected_input_log_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[INPUT_LOG_SEED], program_id)
+}
+
+// the mode ballot, which holds the input mode and the votes to change it
+pub fn get_expected_mode_ballot_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[MODE_BALLOT_SEED], program_id)
+}
+
+// the totals of where the tokens pushed into the game went are kept in the push stats account
+pub fn get_expected_push_stats_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[PUSH_STATS_SEED], program_id)
+}
@@ -0,0 +1,519 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::{program_error::ProgramError, msg};
+use std::ops::{Deref, DerefMut};
+use crate::game::error::DaoPlaysError;
+use crate::game::state::{Capacity, check_state_layout, LegacyState, State, StateHeader, LEGACY_CAPACITY, STATE_DISCRIMINATOR, STATE_LAYOUT_VERSION, get_legacy_array_offsets, get_state_layout_version, get_state_sections, get_state_header_size, get_state_size, take_slice};
+use crate::layout::{LayoutHeader, LEGACY_LAYOUT_VERSION};
+use crate::pod::{PodU16, PodU64};
+
+// an index over the bid ladder that is stored in the data account straight after the State, so that sampling a
+// winner, finding a slot for a new bid and finding the oldest bid don't need to scan every bid.
+// it holds a Fenwick tree of the bid amounts, a stack of the empty slots, and a list of the bids in the order
+// they were last bid on. it has to be kept in step with the ladder, so every change to a bid in the State
+// is made to the index as well
+
+// marks the end of the age list
+pub const NO_BID : u16 = u16::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct BidIndexHeader {
+    // set once the index has been built from the ladder by CreateDataAccount
+    pub is_initialized : u8,
+
+    // the number of empty slots on the free_slots stack
+    pub n_free : PodU16,
+
+    // the ends of the age list
+    pub oldest : PodU16,
+    pub newest : PodU16
+}
+
+// the index has an entry in each array for every slot in the ladder
+#[derive(Debug)]
+pub struct BidIndex<'a> {
+    header : &'a mut BidIndexHeader,
+
+    // the Fenwick tree of bid amounts, where amount_tree[i] is the sum of the bids in (i + 1 - lowbit(i + 1), i]
+    pub amount_tree : &'a mut [PodU64],
+
+    // the empty slots in the ladder, used as a stack
+    pub free_slots : &'a mut [PodU16],
+
+    // a doubly linked list of the active bids from the oldest to the newest
+    pub older : &'a mut [PodU16],
+    pub newer : &'a mut [PodU16]
+}
+
+impl Deref for BidIndex<'_> {
+    type Target = BidIndexHeader;
+
+    fn deref(&self) -> &BidIndexHeader {
+        self.header
+    }
+}
+
+impl DerefMut for BidIndex<'_> {
+    fn deref_mut(&mut self) -> &mut BidIndexHeader {
+        self.header
+    }
+}
+
+impl<'a> BidIndex<'a> {
+    // view an index with bid_capacity slots at the start of data
+    fn from_bytes(mut data : &'a mut [u8], bid_capacity : usize) -> BidIndex<'a> {
+        let header = bytemuck::from_bytes_mut(take_slice::<u8>(&mut data, std::mem::size_of::<BidIndexHeader>()));
+        let amount_tree = take_slice(&mut data, bid_capacity);
+        let free_slots = take_slice(&mut data, bid_capacity);
+        let older = take_slice(&mut data, bid_capacity);
+        let newer = take_slice(&mut data, bid_capacity);
+
+        BidIndex {header, amount_tree, free_slots, older, newer}
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.is_initialized != 0
+    }
+
+    // the number of slots in the ladder
+    pub fn get_capacity(&self) -> usize {
+        self.amount_tree.len()
+    }
+
+    // rebuild the index from the ladder. empty slots are handed out lowest first, and bids made at the same
+    // time are ordered by their position in the ladder
+    pub fn rebuild(&mut self, state : &State) {
+        *self.header = BidIndexHeader::zeroed();
+        self.oldest.set(NO_BID);
+        self.newest.set(NO_BID);
+
+        let mut active : Vec<usize> = Vec::new();
+        for index in (0..self.get_capacity()).rev() {
+            self.older[index].set(NO_BID);
+            self.newer[index].set(NO_BID);
+
+            if state.bid_times[index].get() == 0 {
+                self.push_free(index);
+            }
+            else {
+                active.push(index);
+            }
+        }
+
+        active.sort_by_key(|index| (state.bid_times[*index].get(), *index));
+        for index in active {
+            self.push_newest(index);
+        }
+
+        self.rebuild_tree(state);
+        self.is_initialized = 1;
+    }
+
+    // rebuild the Fenwick tree from the bid amounts in a single pass. the ladder's totals can't overflow, so
+    // neither can the tree's
+    pub fn rebuild_tree(&mut self, state : &State) {
+        let capacity = self.get_capacity();
+        for (node, amount) in self.amount_tree.iter_mut().zip(state.bid_amounts.iter()) {
+            *node = *amount;
+        }
+
+        for index in 0..capacity {
+            let parent = index | (index + 1);
+            if parent < capacity {
+                let value = self.amount_tree[parent].get().wrapping_add(self.amount_tree[index].get());
+                self.amount_tree[parent].set(value);
+            }
+        }
+    }
+
+    // the functions below mirror State::insert_bid, add_to_bid and remove_bid, and return None if the tree
+    // would overflow
+
+    // take an empty slot for a new bid, if there is one
+    pub fn take_free_slot(&mut self) -> Option<usize> {
+        let n_free = self.n_free.get().checked_sub(1)?;
+        self.n_free.set(n_free);
+
+        Some(self.free_slots[n_free as usize].get() as usize)
+    }
+
+    // the slot of the bid that was bid on longest ago
+    pub fn get_oldest(&self) -> Option<usize> {
+        to_slot(self.oldest.get())
+    }
+
+    // record a new bid in a slot returned by take_free_slot
+    pub fn insert_bid(&mut self, index : usize, amount : u64) -> Option<()> {
+        self.checked_increase(index, amount)?;
+        self.push_newest(index);
+
+        Some(())
+    }
+
+    // record an addition to the bid at index, which makes it the newest bid
+    pub fn add_to_bid(&mut self, index : usize, amount : u64) -> Option<()> {
+        self.checked_increase(index, amount)?;
+        self.unlink(index);
+        self.push_newest(index);
+
+        Some(())
+    }
+
+    // record the removal of the bid of amount at index, which frees its slot
+    pub fn remove_bid(&mut self, index : usize, amount : u64) -> Option<()> {
+        self.decrease(index, amount)?;
+        self.unlink(index);
+        self.push_free(index);
+
+        Some(())
+    }
+
+    // the sum of all the bids
+    pub fn get_total(&self) -> u64 {
+        match self.get_capacity() {
+            0 => 0,
+            capacity => self.get_prefix_total(capacity - 1)
+        }
+    }
+
+    // the sum of the bids in slots 0 to index inclusive
+    pub fn get_prefix_total(&self, index : usize) -> u64 {
+        let mut total : u64 = 0;
+        let mut node = index + 1;
+        while node > 0 {
+            total = total.wrapping_add(self.amount_tree[node - 1].get());
+            node &= node - 1;
+        }
+
+        total
+    }
+
+    // the first slot where the running total of the bids passes target, if the total of all the bids does
+    pub fn find(&self, target : u64) -> Option<usize> {
+        let capacity = self.get_capacity();
+        if capacity == 0 {
+            return None;
+        }
+
+        let mut position : usize = 0;
+        let mut remaining = target;
+
+        let mut step = 1 << (usize::BITS - 1 - capacity.leading_zeros());
+        while step > 0 {
+            let next = position + step;
+            if next <= capacity && self.amount_tree[next - 1].get() <= remaining {
+                position = next;
+                remaining -= self.amount_tree[next - 1].get();
+            }
+            step >>= 1;
+        }
+
+        (position < capacity).then_some(position)
+    }
+
+    // the slots of the bids last bid on at or after time, newest first. the clock doesn't go backwards so these
+    // are all at the new end of the age list
+    pub fn get_bids_since(&self, state : &State, time : i64) -> Vec<usize> {
+        let mut bids : Vec<usize> = Vec::new();
+        let mut current = to_slot(self.newest.get());
+        while let Some(index) = current {
+            if state.bid_times[index].get() < time {
+                break;
+            }
+            bids.push(index);
+            current = to_slot(self.older[index].get());
+        }
+
+        bids
+    }
+
+    // the number of bids made before time and their total, as with State::get_bid_totals
+    pub fn get_bid_totals(&self, state : &State, time : i64) -> Option<(u16, u64)> {
+        let mut n_bidders = state.n_bidders.get();
+        let mut total_bid = self.get_total();
+        for index in self.get_bids_since(state, time) {
+            n_bidders = n_bidders.checked_sub(1)?;
+            total_bid = total_bid.checked_sub(state.bid_amounts[index].get())?;
+        }
+
+        Some((n_bidders, total_bid))
+    }
+
+    // add amount to the tree at index. the caller makes sure the total can't overflow
+    pub fn increase(&mut self, index : usize, amount : u64) {
+        let mut node = index + 1;
+        while node <= self.get_capacity() {
+            let value = self.amount_tree[node - 1].get();
+            self.amount_tree[node - 1].set(value.wrapping_add(amount));
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    // the bid at index according to the tree
+    pub fn get_bid(&self, index : usize) -> u64 {
+        let previous = if index == 0 { 0 } else { self.get_prefix_total(index - 1) };
+        self.get_prefix_total(index).wrapping_sub(previous)
+    }
+
+    // take amount from the tree at index, if the bid at index is at least that big
+    pub fn decrease(&mut self, index : usize, amount : u64) -> Option<()> {
+        self.get_bid(index).checked_sub(amount)?;
+
+        let mut node = index + 1;
+        while node <= self.get_capacity() {
+            let value = self.amount_tree[node - 1].get();
+            self.amount_tree[node - 1].set(value.wrapping_sub(amount));
+            node += node & node.wrapping_neg();
+        }
+
+        Some(())
+    }
+
+    fn checked_increase(&mut self, index : usize, amount : u64) -> Option<()> {
+        self.get_total().checked_add(amount)?;
+        self.increase(index, amount);
+
+        Some(())
+    }
+
+    fn push_free(&mut self, index : usize) {
+        let n_free = self.n_free.get();
+        self.free_slots[n_free as usize].set(index as u16);
+        self.n_free.set(n_free + 1);
+    }
+
+    fn push_newest(&mut self, index : usize) {
+        let newest = self.newest.get();
+
+        self.older[index].set(newest);
+        self.newer[index].set(NO_BID);
+
+        match to_slot(newest) {
+            Some(newest) => self.newer[newest].set(index as u16),
+            None => self.oldest.set(index as u16)
+        }
+        self.newest.set(index as u16);
+    }
+
+    fn unlink(&mut self, index : usize) {
+        let older = self.older[index].get();
+        let newer = self.newer[index].get();
+
+        match to_slot(older) {
+            Some(older) => self.newer[older].set(newer),
+            None => self.oldest.set(newer)
+        }
+        match to_slot(newer) {
+            Some(newer) => self.older[newer].set(older),
+            None => self.newest.set(older)
+        }
+
+        self.older[index].set(NO_BID);
+        self.newer[index].set(NO_BID);
+    }
+}
+
+fn to_slot(index : u16) -> Option<usize> {
+    (index != NO_BID).then_some(index as usize)
+}
+
+// the size in bytes of the index's header and each of its arrays, in the order they are stored
+pub fn get_bid_index_sections(capacity : &Capacity) -> [usize; 5] {
+    [
+        std::mem::size_of::<BidIndexHeader>(),
+        capacity.bids * std::mem::size_of::<PodU64>(),
+        capacity.bids * std::mem::size_of::<PodU16>(),
+        capacity.bids * std::mem::size_of::<PodU16>(),
+        capacity.bids * std::mem::size_of::<PodU16>()
+    ]
+}
+
+pub fn get_bid_index_size(capacity : &Capacity) -> usize {
+    get_bid_index_sections(capacity).iter().sum()
+}
+
+// the data account holds the State followed by the BidIndex
+pub fn get_data_account_sections(capacity : &Capacity) -> Vec<usize> {
+    get_state_sections(capacity).into_iter().chain(get_bid_index_sections(capacity)).collect()
+}
+
+pub fn get_data_account_size(capacity : &Capacity) -> usize {
+    get_data_account_sections(capacity).iter().sum()
+}
+
+fn get_header_mut(data : &mut [u8]) -> Result<&mut StateHeader, ProgramError> {
+    let data_len = data.len();
+    match data.get_mut(..get_state_header_size()) {
+        Some(bytes) => Ok(bytemuck::from_bytes_mut(bytes)),
+        None => {
+            msg!("data account is too small to hold the state header {} < {}", data_len, get_state_header_size());
+            Err(ProgramError::AccountDataTooSmall)
+        }
+    }
+}
+
+// split the data account into the State and the BidIndex that follows it
+fn split_ladder_mut(data : &mut [u8]) -> Result<(State<'_>, BidIndex<'_>), ProgramError> {
+    check_state_layout(data)?;
+    let capacity = get_header_mut(data)?.get_capacity();
+
+    let data_len = data.len();
+    let data_size = get_data_account_size(&capacity);
+    if data_len < data_size {
+        msg!("data account is too small to hold the bid index {} < {}", data_len, data_size);
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let (state_bytes, index_bytes) = data[..data_size].split_at_mut(data_size - get_bid_index_size(&capacity));
+    Ok((State::load_mut(state_bytes)?, BidIndex::from_bytes(index_bytes, capacity.bids)))
+}
+
+// view the State and its BidIndex, to change them together
+pub fn load_ladder_mut(data : &mut [u8]) -> Result<(State<'_>, BidIndex<'_>), ProgramError> {
+    let (state, bid_index) = split_ladder_mut(data)?;
+    if !bid_index.is_initialized() {
+        msg!("the bid index has not been built");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok((state, bid_index))
+}
+
+// set up a new data account with room for capacity, if it hasn't been already, and build the index
+pub fn initialize_ladder(data : &mut [u8], capacity : &Capacity) -> Result<(), ProgramError> {
+    if !capacity.is_valid() {
+        msg!("invalid capacity {:?}", capacity);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    match get_state_layout_version(data) {
+        Some(STATE_LAYOUT_VERSION) => {},
+        Some(layout_version) => {
+            msg!("the data account has layout version {}, upgrade it with MigrateState", layout_version);
+            return Err(DaoPlaysError::UnsupportedStateLayout.into());
+        },
+        None => {
+            let header = get_header_mut(data)?;
+            header.layout = LayoutHeader::new(STATE_DISCRIMINATOR, STATE_LAYOUT_VERSION);
+            header.bid_capacity.set(capacity.bids as u16);
+            header.winner_capacity = capacity.winners as u8;
+        }
+    }
+
+    let (state, mut bid_index) = split_ladder_mut(data)?;
+    if !bid_index.is_initialized() {
+        bid_index.rebuild(&state);
+    }
+
+    Ok(())
+}
+
+// grow the ladder in place to hold capacity. the account must already have been reallocated to at least
+// get_data_account_size(capacity). each array is moved up to its new offset, starting with the last so that
+// nothing is overwritten before it has been moved, and the new slots are zeroed and added to the index
+pub fn resize_ladder(data : &mut [u8], capacity : &Capacity) -> Result<(), ProgramError> {
+    let old_capacity = {
+        let (state, bid_index) = load_ladder_mut(data)?;
+        if bid_index.n_free.get() as usize + state.n_bidders.get() as usize != state.bid_keys.len() {
+            msg!("the bid index is out of sync with the ladder");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        state.get_capacity()
+    };
+
+    if !capacity.is_valid() || !capacity.contains(&old_capacity) {
+        msg!("can't resize the ladder from {:?} to {:?}", old_capacity, capacity);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let data_size = get_data_account_size(capacity);
+    if data.len() < data_size {
+        msg!("data account is too small to be resized {} < {}", data.len(), data_size);
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let old_sections = get_data_account_sections(&old_capacity);
+    let new_sections = get_data_account_sections(capacity);
+
+    let get_offsets = |sections : &[usize]| -> Vec<usize> {
+        sections.iter().scan(0, |offset, len| {
+            let start = *offset;
+            *offset += len;
+            Some(start)
+        }).collect()
+    };
+    let old_offsets = get_offsets(&old_sections);
+    let new_offsets = get_offsets(&new_sections);
+
+    for section in (0..old_sections.len()).rev() {
+        let (old_start, new_start) = (old_offsets[section], new_offsets[section]);
+        data.copy_within(old_start..old_start + old_sections[section], new_start);
+        data[new_start + old_sections[section]..new_start + new_sections[section]].fill(0);
+    }
+
+    let header = get_header_mut(data)?;
+    header.bid_capacity.set(capacity.bids as u16);
+    header.winner_capacity = capacity.winners as u8;
+
+    // the tree's nodes cover different ranges with more slots, so it is rebuilt, and the new slots are empty
+    let (state, mut bid_index) = load_ladder_mut(data)?;
+    bid_index.rebuild_tree(&state);
+    for index in (old_capacity.bids..capacity.bids).rev() {
+        bid_index.older[index].set(NO_BID);
+        bid_index.newer[index].set(NO_BID);
+        bid_index.push_free(index);
+    }
+
+    Ok(())
+}
+
+// rewrite a data account in the legacy layout in the current one, in place. the account must already have been
+// reallocated to at least get_data_account_size(&LEGACY_CAPACITY). the legacy header fields are copied out first,
+// as the arrays are moved up over them to make room for the StateHeader, starting with the last so that nothing
+// is overwritten before it has been moved, and then the index is built from the ladder
+pub fn migrate_legacy_ladder(data : &mut [u8]) -> Result<(), ProgramError> {
+    if get_state_layout_version(data) != Some(LEGACY_LAYOUT_VERSION) {
+        msg!("the data account is not in the legacy layout");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let data_size = get_data_account_size(&LEGACY_CAPACITY);
+    if data.len() < data_size {
+        msg!("data account is too small to be migrated {} < {}", data.len(), data_size);
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let header = {
+        let legacy = LegacyState::load(data)?;
+        StateHeader {
+            layout : LayoutHeader::new(STATE_DISCRIMINATOR, STATE_LAYOUT_VERSION),
+            bid_capacity : PodU16::new(LEGACY_CAPACITY.bids as u16),
+            winner_capacity : LEGACY_CAPACITY.winners as u8,
+            prev_choose_winners_time : legacy.prev_choose_winners_time,
+            n_bidders : legacy.n_bidders,
+            total_bid_amount : legacy.total_bid_amount,
+            n_winners : legacy.n_winners,
+            // the legacy program sent prizes straight to the winners, so nothing is waiting to be claimed
+            total_claimable : PodU64::new(0),
+            charity_data : legacy.charity_data
+        }
+    };
+
+    // the arrays keep their lengths, and follow each other in the same order in both layouts
+    let sections = get_state_sections(&LEGACY_CAPACITY);
+    let mut new_start = get_state_size(&LEGACY_CAPACITY);
+    for (section, legacy_start) in get_legacy_array_offsets().iter().enumerate().rev() {
+        let len = sections[section + 1];
+        new_start -= len;
+        data.copy_within(*legacy_start..*legacy_start + len, new_start);
+    }
+
+    // what is left of the legacy charity data sits where the index goes
+    data[get_state_size(&LEGACY_CAPACITY)..data_size].fill(0);
+    *get_header_mut(data)? = header;
+
+    let (state, mut bid_index) = split_ladder_mut(data)?;
+    bid_index.rebuild(&state);
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use crate::game::error::DaoPlaysError;
+use crate::game::randomness::get_draw_randoms;
+use crate::game::bid_index::BidIndex;
+use crate::game::state::{State, LegacyState, LEGACY_MAX_BIDDERS};
+use solana_program::pubkey::Pubkey;
+
+// the search of the bid ladder that selects winners. the program runs it on the data account and the verifier runs
+// it on a copy of the ladder from before the draw, so any difference between the two is a difference in the inputs
+
+// a winner chosen by the draw
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawnWinner {
+    pub winner_key : Pubkey,
+    // the position in the ladder and the bid that won
+    pub index : usize,
+    pub bid_amount : u64,
+    // the total of the bids still in the draw when this winner was chosen
+    pub total_bid : u64,
+    // the random number for this winner, and the point on the cumulative bids that it selected
+    pub random : f64,
+    pub threshold : u64,
+    // the cumulative bids up to and including the winning bid
+    pub cumulative_bid : u64
+}
+
+// select n_winners from the bids made before threshold_time, weighted by bid, using seed. each winner is the first
+// bid where the running total of the bids in the draw passes the winner's random fraction of their sum. each
+// winner's bid is removed from the ladder and added to state.winners, so state and bid_index end up as the
+// program leaves them
+pub fn draw_winners(state : &mut State, bid_index : &mut BidIndex, n_winners : u8, threshold_time : i64, seed : u64) -> Result<Vec<DrawnWinner>, DaoPlaysError> {
+
+    let n_winners = (n_winners as usize).min(state.winners.len());
+
+    // take the bids made since the threshold out of the tree while the winners are drawn
+    let recent_bids = bid_index.get_bids_since(state, threshold_time);
+    for index in recent_bids.iter() {
+        bid_index.decrease(*index, state.bid_amounts[*index].get()).ok_or(DaoPlaysError::StateOutOfSync)?;
+    }
+
+    let mut valid_total_bid = bid_index.get_total();
+    let mut winners : Vec<DrawnWinner> = Vec::new();
+
+    for (current_winner, random_f64) in get_draw_randoms(seed, n_winners as u8).into_iter().enumerate() {
+
+        if valid_total_bid == 0 {
+            break;
+        }
+
+        let threshold = (((valid_total_bid as f64) * random_f64) as u64).min(valid_total_bid - 1);
+        let winner_index = bid_index.find(threshold).ok_or(DaoPlaysError::StateOutOfSync)?;
+        let current_bid = state.bid_amounts[winner_index].get();
+
+        // get the winners key from the ladder and insert it into the winners array
+        state.winners[current_winner] = state.bid_keys[winner_index];
+
+        winners.push(DrawnWinner {
+            winner_key : state.bid_keys[winner_index],
+            index : winner_index,
+            bid_amount : current_bid,
+            total_bid : valid_total_bid,
+            random : random_f64,
+            threshold,
+            cumulative_bid : bid_index.get_prefix_total(winner_index)
+        });
+
+        // now clear the winners bid from the ladder, which also decrements the number of bidders
+        // and the total bid amount
+        state.remove_bid(winner_index).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        bid_index.remove_bid(winner_index, current_bid).ok_or(DaoPlaysError::StateOutOfSync)?;
+
+        // and remove it from the total of the bids in the draw
+        valid_total_bid = valid_total_bid.checked_sub(current_bid).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+    }
+
+    // and put the recent bids back
+    for index in recent_bids.iter() {
+        bid_index.increase(*index, state.bid_amounts[*index].get());
+    }
+
+    Ok(winners)
+}
+
+// the legacy ladder was searched in blocks of this many bids
+pub const LEGACY_BID_BLOCK : usize = 64;
+
+// the draw as SelectWinners made it on the legacy layout, before the bid index, so that those draws can still be
+// checked. the ladder is walked a block at a time, and within each block the winners that haven't been found yet
+// are searched for in order of their random number. a winner not found in the block moves the running total on to
+// the end of it and the walk moves to the next block. each winner's bid is zeroed in the block, and their key is
+// cleared, before the next winner is searched for. the sums wrap as they did on chain, and the quirks of the walk,
+// such as a later winner landing on a bid that has already won, are kept
+pub fn draw_legacy_winners(state : &LegacyState, n_winners : u8, threshold_time : i64, seed : u64) -> Vec<DrawnWinner> {
+
+    let mut bid_keys = state.bid_keys;
+
+    // the bids made before the threshold are in the draw
+    let mut valid_total_bid : u64 = 0;
+    for (amount, time) in state.bid_amounts.iter().zip(state.bid_times.iter()) {
+        if time.get() < threshold_time && amount.get() > 0 {
+            valid_total_bid = valid_total_bid.wrapping_add(amount.get());
+        }
+    }
+
+    let ran_vec = get_draw_randoms(seed, n_winners);
+
+    let mut cumulative_total : u64 = 0;
+    let mut winners_found = vec![false; n_winners as usize];
+    let mut winners : Vec<DrawnWinner> = Vec::new();
+
+    for block_start in (0..LEGACY_MAX_BIDDERS).step_by(LEGACY_BID_BLOCK) {
+
+        let mut bids : Vec<u64> = state.bid_amounts[block_start..block_start + LEGACY_BID_BLOCK].iter().map(|amount| amount.get()).collect();
+        let times = &state.bid_times[block_start..block_start + LEGACY_BID_BLOCK];
+
+        for current_winner in 0..n_winners as usize {
+
+            if winners_found[current_winner] {
+                continue;
+            }
+
+            let random_f64 = ran_vec[current_winner];
+            let threshold = ((valid_total_bid as f64) * random_f64) as u64;
+
+            let mut sub_total = cumulative_total;
+            for bid_index in 0..LEGACY_BID_BLOCK {
+
+                if times[bid_index].get() >= threshold_time {
+                    continue;
+                }
+
+                let current_bid = bids[bid_index];
+                sub_total = sub_total.wrapping_add(current_bid);
+
+                if sub_total > threshold {
+
+                    winners_found[current_winner] = true;
+
+                    let winner_index = block_start + bid_index;
+
+                    winners.push(DrawnWinner {
+                        winner_key : bid_keys[winner_index],
+                        index : winner_index,
+                        bid_amount : current_bid,
+                        total_bid : valid_total_bid,
+                        random : random_f64,
+                        threshold,
+                        cumulative_bid : sub_total
+                    });
+
+                    bid_keys[winner_index] = solana_program::system_program::id();
+
+                    // the program gave up on the rest of the draw if this happened
+                    if current_bid > valid_total_bid {
+                        return winners;
+                    }
+
+                    valid_total_bid -= current_bid;
+                    bids[bid_index] = 0;
+
+                    break;
+                }
+            }
+
+            // if this winner wasn't found in this block, move onto the next block
+            if !winners_found[current_winner] {
+                cumulative_total = sub_total;
+                break;
+            }
+        }
+    }
+
+    winners
+}
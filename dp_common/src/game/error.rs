@@ -0,0 +1,243 @@
+use thiserror::Error;
+use solana_program::{decode_error::DecodeError, program_error::ProgramError};
+
+// the numbers are part of the program's interface, as clients only see Custom(n), so existing variants must
+// keep their value and new variants should be added at the end
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DaoPlaysError {
+    /// Invalid instruction
+    #[error("Invalid Instruction")]
+    InvalidInstruction = 0,
+
+    #[error("Invalid bid amount for button press")]
+    InvalidButtonBid = 1,
+
+    #[error("Unsupported instruction version")]
+    UnsupportedInstructionVersion = 2,
+
+    #[error("Invalid config")]
+    InvalidConfig = 3,
+
+    #[error("Invalid charity")]
+    InvalidCharity = 4,
+
+    #[error("No active bid to cancel")]
+    NoActiveBid = 5,
+
+    #[error("Bid is too small to replace the oldest bid")]
+    BidTooSmallToEvict = 6,
+
+    #[error("Evicted bidder accounts missing or invalid")]
+    InvalidEvictedBidder = 7,
+
+    #[error("Account is not the config authority")]
+    InvalidAuthority = 8,
+
+    #[error("Account is not the program's config account")]
+    InvalidConfigAccount = 9,
+
+    #[error("Account is not the program derived address")]
+    InvalidProgramAddress = 10,
+
+    #[error("Account is not the program's data account")]
+    InvalidDataAccount = 11,
+
+    #[error("Account is not the expected token account")]
+    InvalidTokenAccount = 12,
+
+    #[error("Account is not the token mint")]
+    InvalidTokenMint = 13,
+
+    #[error("Account is not the expected program")]
+    InvalidProgramAccount = 14,
+
+    #[error("Account is not one of the config's oracles")]
+    InvalidOracle = 15,
+
+    #[error("Account is not the bidder's data account")]
+    InvalidBidderDataAccount = 16,
+
+    #[error("Account is not the chosen charity's address")]
+    InvalidCharityAccount = 17,
+
+    #[error("Account is not the program's charity registry")]
+    InvalidCharityRegistry = 18,
+
+    #[error("Insufficient tokens")]
+    InsufficientTokens = 19,
+
+    #[error("Bid is below the minimum")]
+    BidBelowMinimum = 20,
+
+    #[error("Winners have already been selected")]
+    WinnersAlreadySelected = 21,
+
+    #[error("Winner accounts don't match the selected winners")]
+    WinnersAccountMismatch = 22,
+
+    #[error("Bid state is out of sync")]
+    StateOutOfSync = 23,
+
+    #[error("Arithmetic overflow")]
+    ArithmeticOverflow = 24,
+
+    #[error("Oracle account is not a pyth price account")]
+    OracleDataInvalid = 25,
+
+    #[error("Oracle price is not trading")]
+    OraclePriceUnavailable = 26,
+
+    #[error("Oracle price is stale")]
+    OraclePriceStale = 27,
+
+    #[error("Oracle price is negative")]
+    OraclePriceNegative = 28,
+
+    #[error("None of the oracles have a usable price")]
+    NoHealthyOracles = 29,
+
+    #[error("The config uses a different randomness source")]
+    RandomnessSourceMismatch = 30,
+
+    #[error("A commitment is already waiting to be revealed")]
+    CommitPending = 31,
+
+    #[error("There is no commitment to reveal")]
+    NoActiveCommit = 32,
+
+    #[error("Account is not the committer")]
+    InvalidCommitter = 33,
+
+    #[error("Secret doesn't match the commitment")]
+    InvalidReveal = 34,
+
+    #[error("Commitment can't be revealed in the slot it was made")]
+    RevealTooEarly = 35,
+
+    #[error("Commitment has timed out")]
+    RevealTimedOut = 36,
+
+    #[error("Commitment can't be slashed until it has timed out")]
+    CommitNotExpired = 37,
+
+    #[error("Account is not the program's commit account")]
+    InvalidCommitAccount = 38,
+
+    #[error("Slot hash for the commitment is not available")]
+    SlotHashUnavailable = 39,
+
+    #[error("Data account can't be resized to that capacity")]
+    InvalidCapacity = 40,
+
+    #[error("Data account layout version is not supported, older accounts can be upgraded with MigrateState")]
+    UnsupportedStateLayout = 41,
+
+    #[error("Bidder has no prize to claim")]
+    NothingToClaim = 42,
+
+    #[error("Nothing for the crank to do")]
+    NothingToCrank = 43,
+
+    #[error("Account is not the program's vote tally account")]
+    InvalidVoteTallyAccount = 44,
+
+    #[error("Input window has not closed yet")]
+    InputWindowOpen = 45,
+
+    #[error("Account is not the program's input log account")]
+    InvalidInputLogAccount = 46,
+
+    #[error("Account is not the program's mode ballot account")]
+    InvalidModeBallotAccount = 47,
+
+    #[error("Mode ballot has closed and is waiting to be settled")]
+    ModeBallotClosed = 48,
+
+    #[error("Invalid mode vote")]
+    InvalidModeVote = 49,
+
+    #[error("Invalid button sequence")]
+    InvalidSequence = 50,
+
+    #[error("Account is not the program's push stats account")]
+    InvalidPushStatsAccount = 51,
+
+    #[error("Config account layout version is not supported, older configs can be upgraded with UpdateConfig")]
+    UnsupportedConfigLayout = 52
+}
+
+// every variant, so clients can map a custom error code back to its name
+pub const ALL_ERRORS : [DaoPlaysError; 53] = [
+    DaoPlaysError::InvalidInstruction,
+    DaoPlaysError::InvalidButtonBid,
+    DaoPlaysError::UnsupportedInstructionVersion,
+    DaoPlaysError::InvalidConfig,
+    DaoPlaysError::InvalidCharity,
+    DaoPlaysError::NoActiveBid,
+    DaoPlaysError::BidTooSmallToEvict,
+    DaoPlaysError::InvalidEvictedBidder,
+    DaoPlaysError::InvalidAuthority,
+    DaoPlaysError::InvalidConfigAccount,
+    DaoPlaysError::InvalidProgramAddress,
+    DaoPlaysError::InvalidDataAccount,
+    DaoPlaysError::InvalidTokenAccount,
+    DaoPlaysError::InvalidTokenMint,
+    DaoPlaysError::InvalidProgramAccount,
+    DaoPlaysError::InvalidOracle,
+    DaoPlaysError::InvalidBidderDataAccount,
+    DaoPlaysError::InvalidCharityAccount,
+    DaoPlaysError::InvalidCharityRegistry,
+    DaoPlaysError::InsufficientTokens,
+    DaoPlaysError::BidBelowMinimum,
+    DaoPlaysError::WinnersAlreadySelected,
+    DaoPlaysError::WinnersAccountMismatch,
+    DaoPlaysError::StateOutOfSync,
+    DaoPlaysError::ArithmeticOverflow,
+    DaoPlaysError::OracleDataInvalid,
+    DaoPlaysError::OraclePriceUnavailable,
+    DaoPlaysError::OraclePriceStale,
+    DaoPlaysError::OraclePriceNegative,
+    DaoPlaysError::NoHealthyOracles,
+    DaoPlaysError::RandomnessSourceMismatch,
+    DaoPlaysError::CommitPending,
+    DaoPlaysError::NoActiveCommit,
+    DaoPlaysError::InvalidCommitter,
+    DaoPlaysError::InvalidReveal,
+    DaoPlaysError::RevealTooEarly,
+    DaoPlaysError::RevealTimedOut,
+    DaoPlaysError::CommitNotExpired,
+    DaoPlaysError::InvalidCommitAccount,
+    DaoPlaysError::SlotHashUnavailable,
+    DaoPlaysError::InvalidCapacity,
+    DaoPlaysError::UnsupportedStateLayout,
+    DaoPlaysError::NothingToClaim,
+    DaoPlaysError::NothingToCrank,
+    DaoPlaysError::InvalidVoteTallyAccount,
+    DaoPlaysError::InputWindowOpen,
+    DaoPlaysError::InvalidInputLogAccount,
+    DaoPlaysError::InvalidModeBallotAccount,
+    DaoPlaysError::ModeBallotClosed,
+    DaoPlaysError::InvalidModeVote,
+    DaoPlaysError::InvalidSequence,
+    DaoPlaysError::InvalidPushStatsAccount,
+    DaoPlaysError::UnsupportedConfigLayout
+];
+
+impl DaoPlaysError {
+    // the error for a Custom(code) returned by the program
+    pub fn from_code(code : u32) -> Option<DaoPlaysError> {
+        ALL_ERRORS.iter().find(|e| **e as u32 == code).copied()
+    }
+}
+
+impl From<DaoPlaysError> for ProgramError {
+    fn from(e: DaoPlaysError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for DaoPlaysError {
+    fn type_of() -> &'static str {
+        "DaoPlaysError"
+    }
+}
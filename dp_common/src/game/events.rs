@@ -0,0 +1,241 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{pubkey::Pubkey, log::sol_log_data};
+use crate::game::instruction::{Button, SequenceStep};
+use crate::game::modes::InputMode;
+
+// every event is logged with sol_log_data as [EVENT_DISCRIMINATOR, EVENT_VERSION, GameEvent], so indexers can pick
+// the game's events out of the "Program data:" log lines and skip any version they don't understand
+pub const EVENT_DISCRIMINATOR : [u8; 8] = *b"dp_event";
+pub const EVENT_VERSION : u8 = 1;
+
+// emitted by CreateDataAccount
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct DataAccountCreated {
+    pub authority : Pubkey,
+    pub data_account : Pubkey,
+    // the number of tokens sent to the program
+    pub amount : u64
+}
+
+// emitted by PushButton
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ButtonPressed {
+    pub player : Pubkey,
+    pub button : Button,
+    // the number of tokens pushed into the game
+    pub amount : u64,
+    pub time : i64
+}
+
+// emitted by PushSequence, with every step of the sequence in the order they are played
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SequencePushed {
+    pub player : Pubkey,
+    pub steps : Vec<SequenceStep>,
+    // the number of tokens pushed into the game for the whole sequence
+    pub amount : u64,
+    pub time : i64
+}
+
+// emitted by PlaceBid once the bid is in the ladder
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidPlaced {
+    // the bidder's token account, which is the key stored in the ladder
+    pub bidder_key : Pubkey,
+    pub charity : u8,
+    pub amount_charity : u64,
+    pub amount_dao : u64,
+    // the position in the ladder and the bidder's total bid there
+    pub index : u16,
+    pub bid_amount : u64,
+    pub time : i64
+}
+
+// emitted by PlaceBid when the ladder is full and a new bidder replaces the oldest bid
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidEvicted {
+    // the token account that was stored with the evicted bid
+    pub evicted_key : Pubkey,
+    // the token account of the new bidder that replaced it
+    pub new_key : Pubkey,
+    // the position in the ladder
+    pub index : u16,
+    // the amount of the evicted bid in lamports, and when it was made
+    pub amount : u64,
+    pub bid_time : i64
+}
+
+// emitted by CancelBid
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidCancelled {
+    pub bidder_key : Pubkey,
+    pub index : u16,
+    pub amount : u64,
+    // the tokens sent back to the bidder's token account for the lamports of the bid
+    pub refund : u64
+}
+
+// emitted by SelectWinners for each winner
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct WinnerSelected {
+    // the winner's token account, which the prize is sent to once it has been credited and claimed
+    pub winner_key : Pubkey,
+    // the position in the ladder and the bid that won
+    pub index : u16,
+    pub bid_amount : u64,
+    // the total of the bids that were in the draw
+    pub total_bid : u64,
+    pub time : i64
+}
+
+// emitted by ClaimPrize when a winner's prizes are sent to their token account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct TokensSent {
+    pub winner_key : Pubkey,
+    pub amount : u64
+}
+
+// emitted by CommitSeed
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedCommitted {
+    pub committer : Pubkey,
+    pub commitment : [u8; 32],
+    pub commit_slot : u64,
+    pub bond : u64
+}
+
+// emitted by RevealSeed, with everything needed to recompute the seed used for the draw
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedRevealed {
+    pub committer : Pubkey,
+    pub secret : [u8; 32],
+    pub commit_slot : u64,
+    pub slot_hash : [u8; 32],
+    pub seed : u64,
+    // only bids made before the commitment are in the draw
+    pub commit_time : i64
+}
+
+// emitted by SlashCommit when a commitment wasn't revealed in time
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CommitSlashed {
+    pub committer : Pubkey,
+    pub commit_slot : u64,
+    pub bond : u64
+}
+
+// emitted by ResizeState once the ladder has been moved into the larger account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct StateResized {
+    pub data_account : Pubkey,
+    pub bid_capacity : u16,
+    pub winner_capacity : u8,
+    pub data_size : u64
+}
+
+// emitted by MigrateState once the data account has been upgraded
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct StateMigrated {
+    pub data_account : Pubkey,
+    pub from_version : u8,
+    pub to_version : u8,
+    pub data_size : u64
+}
+
+// emitted by CreditWinners for each winner whose prize is added to their bidder data account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct PrizeCredited {
+    pub winner_key : Pubkey,
+    pub bidder : Pubkey,
+    pub amount : u64,
+    // the tokens waiting for the bidder to claim, including this prize
+    pub claimable : u64
+}
+
+// emitted by Crank, recording what it did and the tip paid to the cranker
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Cranked {
+    pub cranker : Pubkey,
+    pub n_credited : u8,
+    pub n_selected : u8,
+    pub tip_lamports : u64,
+    pub tip_tokens : u64
+}
+
+// emitted by ResolveInput with the button picked for a window of slots, see game::inputs
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InputResolved {
+    pub start_slot : u64,
+    pub end_slot : u64,
+    // the index of the button, or NO_BUTTON
+    pub button : u8,
+    pub total : u64,
+    pub random : u64,
+    pub complete : bool
+}
+
+// emitted by VoteInputMode once the vote has been added to the ballot
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ModeVoted {
+    pub voter : Pubkey,
+    pub mode : InputMode,
+    pub amount : u64,
+    pub ballot_end_slot : u64
+}
+
+// emitted by ResolveInput when it settles a closed mode ballot, with the mode that applies from start_slot on
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ModeBallotSettled {
+    pub previous_mode : InputMode,
+    pub mode : InputMode,
+    pub start_slot : u64,
+    pub n_votes : u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    DataAccountCreated(DataAccountCreated),
+    ButtonPressed(ButtonPressed),
+    BidPlaced(BidPlaced),
+    BidEvicted(BidEvicted),
+    BidCancelled(BidCancelled),
+    WinnerSelected(WinnerSelected),
+    TokensSent(TokensSent),
+    SeedCommitted(SeedCommitted),
+    SeedRevealed(SeedRevealed),
+    CommitSlashed(CommitSlashed),
+    StateResized(StateResized),
+    StateMigrated(StateMigrated),
+    PrizeCredited(PrizeCredited),
+    Cranked(Cranked),
+    InputResolved(InputResolved),
+    ModeVoted(ModeVoted),
+    ModeBallotSettled(ModeBallotSettled),
+    SequencePushed(SequencePushed)
+}
+
+impl GameEvent {
+    // the bytes that are written to the program logs
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = EVENT_DISCRIMINATOR.to_vec();
+        data.push(EVENT_VERSION);
+        data.extend(self.try_to_vec().unwrap());
+
+        data
+    }
+
+    // read an event back from the logged bytes, returning None for data that isn't a game event of this version
+    pub fn unpack(data : &[u8]) -> Option<GameEvent> {
+        let rest = data.strip_prefix(&EVENT_DISCRIMINATOR[..])?;
+        let (version, event) = rest.split_first()?;
+        if *version != EVENT_VERSION {
+            return None;
+        }
+
+        GameEvent::try_from_slice(event).ok()
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.pack()]);
+    }
+}
@@ -0,0 +1,323 @@
+use bytemuck::{Pod, Zeroable};
+use solana_program::{hash::hashv, program_error::ProgramError, msg};
+use crate::game::instruction::{SequenceStep, DEFAULT_HOLD_FRAMES, N_BUTTONS};
+use crate::game::modes::InputMode;
+use crate::game::votes::{VOTE_HISTORY_SLOTS, WindowVotes};
+use crate::layout::{LayoutHeader, get_layout_header_size};
+use crate::pod::{PodU16, PodU64};
+
+// ResolveInput turns the votes in the vote tally into the game's input, one window of slots at a time, and records
+// each result in the input log. how the button for a window is chosen depends on the input mode, see
+// dp_common/src/game/modes.rs. in the lottery mode it is picked at random, weighted by the tokens pushed into each
+// button, with a random number anyone can recompute from the window and its tallies:
+//
+//     random = the first 8 bytes, little endian, of sha256("dp_input" || start_slot || end_slot || tallies)
+//
+// where the slots and the 8 tallies, in the order of the Button variants, are little endian u64s. the button is
+// the first one whose running total of tokens is greater than random % total
+
+pub const INPUT_LOG_DISCRIMINATOR : [u8; 8] = *b"dp_input";
+pub const INPUT_LOG_LAYOUT_VERSION : u8 = 1;
+
+// the number of resolved windows the log keeps
+pub const INPUT_LOG_SIZE : usize = 128;
+
+// the number of steps the press log keeps. together with the windows this keeps the account within the 10KB the
+// program can create it with
+pub const PRESS_LOG_SIZE : usize = 224;
+
+// recorded as the button of a window in which no button was pressed
+pub const NO_BUTTON : u8 = u8::MAX;
+
+// a window has to fit in the vote tally for all of its votes to be counted
+pub const MAX_INPUT_WINDOW_SLOTS : u64 = VOTE_HISTORY_SLOTS as u64;
+
+pub fn get_input_random(start_slot : u64, end_slot : u64, tallies : &[u64; N_BUTTONS]) -> u64 {
+    let mut tally_bytes = [0u8; 8 * N_BUTTONS];
+    for (chunk, tally) in tally_bytes.chunks_exact_mut(8).zip(tallies.iter()) {
+        chunk.copy_from_slice(&tally.to_le_bytes());
+    }
+
+    let hash = hashv(&[b"dp_input", &start_slot.to_le_bytes(), &end_slot.to_le_bytes(), &tally_bytes]).to_bytes();
+
+    let mut random_bytes = [0u8; 8];
+    random_bytes.copy_from_slice(&hash[..8]);
+
+    u64::from_le_bytes(random_bytes)
+}
+
+// the index of the button picked by random, or None if no tokens were pushed
+pub fn pick_button(tallies : &[u64; N_BUTTONS], random : u64) -> Option<u8> {
+    let total = tallies.iter().try_fold(0u64, |total, tally| total.checked_add(*tally))?;
+    if total == 0 {
+        return None;
+    }
+
+    let threshold = random % total;
+
+    let mut cumulative : u64 = 0;
+    for (index, tally) in tallies.iter().enumerate() {
+        cumulative += tally;
+        if cumulative > threshold {
+            return Some(index as u8);
+        }
+    }
+
+    None
+}
+
+// the index of the button with the most tokens pushed into it, a tie going to the first of the Button variants, or
+// None if no tokens were pushed
+pub fn plurality_button(tallies : &[u64; N_BUTTONS]) -> Option<u8> {
+    let (index, tally) = tallies.iter().enumerate().rev().max_by_key(|(_, tally)| **tally)?;
+    (*tally > 0).then_some(index as u8)
+}
+
+// the button for a window under mode, or NO_BUTTON. in anarchy every press in the window is an input, in the order
+// they were made, so there is no single button to record and the window's presses are read from the press log
+pub fn resolve_button(mode : InputMode, votes : &WindowVotes, random : u64) -> u8 {
+    let button = match mode {
+        InputMode::Lottery => pick_button(&votes.tallies, random),
+        InputMode::Anarchy => None,
+        InputMode::Democracy => plurality_button(&votes.tallies),
+        InputMode::Auction => Some(votes.top_button)
+    };
+
+    button.unwrap_or(NO_BUTTON)
+}
+
+// the input for one window of slots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct ResolvedInput {
+    pub start_slot : PodU64,
+    // the first slot after the window
+    pub end_slot : PodU64,
+    // the index of the button, or NO_BUTTON
+    pub button : u8,
+    // the InputMode the window was resolved with
+    pub mode : u8,
+    // zero if some of the window's votes had already left the vote tally when it was resolved
+    pub complete : u8,
+    pub total : PodU64,
+    pub random : PodU64,
+    // the window's entries in the press log, which are the inputs of an anarchy window
+    pub first_press : PodU64,
+    pub n_presses : PodU16
+}
+
+// a step of a press made with PushButton or PushSequence, in the order they were made. a press takes one entry for
+// each of its steps, a PushButton press being a single step held for DEFAULT_HOLD_FRAMES
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct LoggedPress {
+    pub slot : PodU64,
+    // the tokens pushed for the whole press, on its first step, and zero on the steps after it
+    pub amount : PodU64,
+    // the index of the button
+    pub button : u8,
+    pub hold_frames : u8,
+    // the number of steps in the press on its first step, and zero on the steps after it
+    pub n_steps : u8
+}
+
+impl LoggedPress {
+    pub fn is_first_step(&self) -> bool {
+        self.n_steps > 0
+    }
+}
+
+// the input log account, a ring buffer of the most recently resolved windows. windows are resolved in order, each
+// one starting where the last one ended, so the log is a single unbroken stream of inputs. it also keeps a ring of
+// the most recent presses, which every press is added to whatever the mode as the mode of a window is only known
+// once it is resolved. as with the vote tally it is only ever viewed in place
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct InputLog {
+    pub layout : LayoutHeader,
+    // the first slot of the next window to resolve
+    pub next_slot : PodU64,
+    // the number of windows resolved since the log was created
+    pub n_resolved : PodU64,
+    // the number of presses logged since the log was created
+    pub n_logged : PodU64,
+    // the first press that isn't yet part of a resolved window
+    pub next_press : PodU64,
+    pub inputs : [ResolvedInput; INPUT_LOG_SIZE],
+    pub presses : [LoggedPress; PRESS_LOG_SIZE]
+}
+
+impl InputLog {
+    pub fn load(data : &[u8]) -> Result<&InputLog, ProgramError> {
+        check_input_log_layout(data)?;
+        Ok(bytemuck::from_bytes(&data[..get_input_log_size()]))
+    }
+
+    pub fn load_mut(data : &mut [u8]) -> Result<&mut InputLog, ProgramError> {
+        check_input_log_layout(data)?;
+        Ok(bytemuck::from_bytes_mut(&mut data[..get_input_log_size()]))
+    }
+
+    // write the layout header to a newly created log, which resolves its first window from first_slot
+    pub fn initialize(data : &mut [u8], first_slot : u64) -> Result<(), ProgramError> {
+        if data.len() < get_input_log_size() {
+            msg!("input log account is too small {} < {}", data.len(), get_input_log_size());
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let header : &mut LayoutHeader = bytemuck::from_bytes_mut(&mut data[..get_layout_header_size()]);
+        *header = LayoutHeader::new(INPUT_LOG_DISCRIMINATOR, INPUT_LOG_LAYOUT_VERSION);
+
+        let input_log = InputLog::load_mut(data)?;
+        input_log.next_slot.set(first_slot);
+
+        Ok(())
+    }
+
+    // add the next window to the log, which must start where the last one ended
+    pub fn record(&mut self, input : ResolvedInput) -> Option<()> {
+        if input.start_slot != self.next_slot || input.end_slot.get() <= input.start_slot.get() {
+            return None;
+        }
+
+        let n_resolved = self.n_resolved.get();
+        self.inputs[(n_resolved % INPUT_LOG_SIZE as u64) as usize] = input;
+
+        self.n_resolved.set(n_resolved.checked_add(1)?);
+        self.next_slot = input.end_slot;
+
+        Some(())
+    }
+
+    // add each step of a press to the press log, overwriting the oldest once the ring is full
+    pub fn log_press(&mut self, slot : u64, steps : &[SequenceStep], amount : u64) -> Option<()> {
+        let n_steps = u8::try_from(steps.len()).ok()?;
+
+        for (index, step) in steps.iter().enumerate() {
+            let n_logged = self.n_logged.get();
+            self.presses[(n_logged % PRESS_LOG_SIZE as u64) as usize] = LoggedPress {
+                slot : slot.into(),
+                amount : if index == 0 { amount.into() } else { 0.into() },
+                button : step.button.clone() as u8,
+                hold_frames : step.hold_frames,
+                n_steps : if index == 0 { n_steps } else { 0 }
+            };
+
+            self.n_logged.set(n_logged.checked_add(1)?);
+        }
+
+        Some(())
+    }
+
+    // the position of the first press and the number of presses made before end_slot that aren't yet part of a
+    // window, which become part of the window being resolved. also returns false if some of them have already been
+    // overwritten
+    pub fn take_window_presses(&mut self, end_slot : u64) -> (u64, u16, bool) {
+        let n_logged = self.n_logged.get();
+        let oldest_press = n_logged.saturating_sub(PRESS_LOG_SIZE as u64);
+
+        let complete = self.next_press.get() >= oldest_press;
+        let first_press = self.next_press.get().max(oldest_press);
+
+        let mut n_presses : u64 = 0;
+        while first_press + n_presses < n_logged && self.presses[((first_press + n_presses) % PRESS_LOG_SIZE as u64) as usize].slot.get() < end_slot {
+            n_presses += 1;
+        }
+
+        self.next_press.set(first_press + n_presses);
+
+        (first_press, n_presses as u16, complete)
+    }
+
+    // the steps of a resolved window's presses in the order they were made, or None if they are no longer in the log
+    pub fn get_presses(&self, input : &ResolvedInput) -> Option<impl Iterator<Item = &LoggedPress>> {
+        let first_press = input.first_press.get();
+        let end_press = first_press.checked_add(input.n_presses.get() as u64)?;
+
+        if first_press < self.n_logged.get().saturating_sub(PRESS_LOG_SIZE as u64) || end_press > self.n_logged.get() {
+            return None;
+        }
+
+        Some((first_press..end_press).map(move |press| &self.presses[(press % PRESS_LOG_SIZE as u64) as usize]))
+    }
+
+    // the buttons the game plays for a resolved window and the frames each is held for. in anarchy these are every
+    // step of every press in the window, in auction the steps of the largest press, the earliest winning a tie, and
+    // otherwise the window's button. returns None if the presses the window needs are no longer in the log
+    pub fn get_window_inputs(&self, input : &ResolvedInput) -> Option<Vec<(u8, u8)>> {
+        let mode = InputMode::from_u8(input.mode)?;
+
+        if input.button == NO_BUTTON && mode != InputMode::Anarchy {
+            return Some(Vec::new());
+        }
+
+        // an auction window that lost some of its presses before it was resolved can't tell which was the largest
+        if mode != InputMode::Anarchy && (mode != InputMode::Auction || input.complete == 0) {
+            return Some(vec![(input.button, DEFAULT_HOLD_FRAMES)]);
+        }
+
+        // put the steps back together into presses, dropping the steps at the start of the window whose press has
+        // already been overwritten
+        let mut presses : Vec<(u64, Vec<(u8, u8)>)> = Vec::new();
+        for step in self.get_presses(input)? {
+            if step.is_first_step() {
+                presses.push((step.amount.get(), Vec::new()));
+            }
+
+            if let Some((_, steps)) = presses.last_mut() {
+                steps.push((step.button, step.hold_frames));
+            }
+        }
+
+        if mode == InputMode::Anarchy {
+            return Some(presses.into_iter().flat_map(|(_, steps)| steps).collect());
+        }
+
+        let mut top_press : Option<(u64, Vec<(u8, u8)>)> = None;
+        for press in presses {
+            match &top_press {
+                Some((top_bid, _)) if press.0 <= *top_bid => {},
+                _ => top_press = Some(press)
+            }
+        }
+
+        Some(top_press.map(|(_, steps)| steps).unwrap_or_default())
+    }
+
+    // the input for the window containing slot, if it is still in the log
+    pub fn get_input(&self, slot : u64) -> Option<&ResolvedInput> {
+        self.iter_recent().find(|input| input.start_slot.get() <= slot && slot < input.end_slot.get())
+    }
+
+    // the most recent window first
+    pub fn iter_recent(&self) -> impl Iterator<Item = &ResolvedInput> {
+        let n_resolved = self.n_resolved.get();
+        let n_kept = n_resolved.min(INPUT_LOG_SIZE as u64);
+
+        (0..n_kept).map(move |i| &self.inputs[((n_resolved - 1 - i) % INPUT_LOG_SIZE as u64) as usize])
+    }
+}
+
+pub fn get_input_log_size() -> usize {
+    std::mem::size_of::<InputLog>()
+}
+
+fn check_input_log_layout(data : &[u8]) -> Result<(), ProgramError> {
+    if data.len() < get_input_log_size() {
+        msg!("input log account is too small {} < {}", data.len(), get_input_log_size());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let header : &LayoutHeader = bytemuck::from_bytes(&data[..get_layout_header_size()]);
+    if header.discriminator != INPUT_LOG_DISCRIMINATOR {
+        msg!("input log account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if header.layout_version != INPUT_LOG_LAYOUT_VERSION {
+        msg!("input log layout version {} is not supported", header.layout_version);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
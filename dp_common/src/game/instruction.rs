@@ -0,0 +1,283 @@
+use solana_program::{program_error::ProgramError, msg};
+use borsh::{BorshDeserialize, BorshSerialize};
+use crate::charity::{AddCharityData, UpdateCharityData};
+use crate::game::error::DaoPlaysError::{self, InvalidInstruction, UnsupportedInstructionVersion};
+use crate::game::modes::InputMode;
+use crate::game::state::Config;
+
+// the number of Button variants, which the vote tally keeps a total for
+pub const N_BUTTONS : usize = 8;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum Button {
+    A,
+    B,
+    Up,
+    Down,
+    Left,
+    Right,
+    Start,
+    Select,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ButtonData {
+    pub button: Button,
+    pub amount: u64
+}
+
+// the most steps a sequence can have, and the longest a button can be held for in one step, which at 60 frames a
+// second lets a step walk a few tiles or wait out a short animation
+pub const MAX_SEQUENCE_STEPS : usize = 16;
+pub const MAX_HOLD_FRAMES : u8 = 120;
+
+// the frames a button pressed with PushButton, or picked for a window, is held for
+pub const DEFAULT_HOLD_FRAMES : u8 = 5;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SequenceStep {
+    pub button : Button,
+    // the number of frames the button is held down for
+    pub hold_frames : u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SequenceData {
+    pub steps : Vec<SequenceStep>,
+    // the number of DPTTs pushed into the game for the whole sequence
+    pub amount : u64
+}
+
+impl SequenceData {
+    // a sequence needs between one and MAX_SEQUENCE_STEPS steps that each hold their button for at least a frame,
+    // and has to cost at least a token a step so it is never cheaper than pressing the buttons one at a time
+    pub fn validate(&self) -> Result<(), DaoPlaysError> {
+        if self.steps.is_empty() || self.steps.len() > MAX_SEQUENCE_STEPS {
+            msg!("a sequence must have between 1 and {} steps, not {}", MAX_SEQUENCE_STEPS, self.steps.len());
+            return Err(DaoPlaysError::InvalidSequence);
+        }
+
+        if let Some(step) = self.steps.iter().find(|step| step.hold_frames == 0 || step.hold_frames > MAX_HOLD_FRAMES) {
+            msg!("a button must be held for between 1 and {} frames, not {}", MAX_HOLD_FRAMES, step.hold_frames);
+            return Err(DaoPlaysError::InvalidSequence);
+        }
+
+        if self.amount < self.steps.len() as u64 {
+            msg!("a sequence of {} steps must cost at least {} tokens, not {}", self.steps.len(), self.steps.len(), self.amount);
+            return Err(DaoPlaysError::InvalidButtonBid);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ModeVoteData {
+    pub mode : InputMode,
+    // the number of DPTTs voted for the mode
+    pub amount : u64
+}
+
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidData {
+    // the amount in lamports that will be donated to charity
+    pub amount_charity : u64,
+    // the amount in lamports being paid to the developers
+    pub amount_dao : u64,
+    // the index of the chosen charity in the charity registry
+    pub charity : u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InitData {
+    // the amount of DPTTs to be sent to the program
+    pub amount : u64,
+    // the initial config, only used if the config account doesn't exist yet
+    pub config : Config
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ResizeData {
+    // the number of bids the ladder should hold, and the number of winners that can be chosen in one go.
+    // neither can be less than the data account already holds
+    pub bid_capacity : u16,
+    pub winner_capacity : u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum DPPInstruction {
+
+    CreateDataAccount {
+        init_data : InitData
+    },
+
+    PushButton {
+        button_data: ButtonData
+    },
+
+    PlaceBid {
+        bid_data: BidData
+    },
+
+    SelectWinners,
+
+    // retired, the program no longer sends prizes directly. the variant keeps its place so that the tags of the
+    // instructions after it don't change
+    SendTokens,
+
+    UpdateConfig {
+        config : Config
+    },
+
+    AddCharity {
+        charity_data : AddCharityData
+    },
+
+    UpdateCharity {
+        charity_data : UpdateCharityData
+    },
+
+    CancelBid,
+
+    CommitSeed {
+        commitment : [u8; 32]
+    },
+
+    RevealSeed {
+        secret : [u8; 32]
+    },
+
+    SlashCommit,
+
+    ResizeState {
+        resize_data : ResizeData
+    },
+
+    MigrateState,
+
+    ClaimPrize,
+
+    Crank,
+
+    ResolveInput,
+
+    VoteInputMode {
+        mode_vote_data : ModeVoteData
+    },
+
+    PushSequence {
+        sequence_data : SequenceData
+    },
+
+    CreditWinners
+}
+
+// instructions can either be sent bare, as the Borsh encoding of DPPInstruction, or wrapped in a versioned envelope
+// of the form [ENVELOPE_TAG, version, DPPInstruction].  The tag can never collide with a DPPInstruction variant, so
+// the program can tell the two apart from the first byte
+pub const ENVELOPE_TAG : u8 = 255;
+pub const INSTRUCTION_VERSION : u8 = 1;
+
+// the tag for each instruction, which must match the order of the variants in DPPInstruction
+// so that the program decodes the same layout that Borsh encodes in the clients
+pub const CREATE_DATA_ACCOUNT_TAG : u8 = 0;
+pub const PUSH_BUTTON_TAG : u8 = 1;
+pub const PLACE_BID_TAG : u8 = 2;
+pub const SELECT_WINNERS_TAG : u8 = 3;
+pub const SEND_TOKENS_TAG : u8 = 4;
+pub const UPDATE_CONFIG_TAG : u8 = 5;
+pub const ADD_CHARITY_TAG : u8 = 6;
+pub const UPDATE_CHARITY_TAG : u8 = 7;
+pub const CANCEL_BID_TAG : u8 = 8;
+pub const COMMIT_SEED_TAG : u8 = 9;
+pub const REVEAL_SEED_TAG : u8 = 10;
+pub const SLASH_COMMIT_TAG : u8 = 11;
+pub const RESIZE_STATE_TAG : u8 = 12;
+pub const MIGRATE_STATE_TAG : u8 = 13;
+pub const CLAIM_PRIZE_TAG : u8 = 14;
+pub const CRANK_TAG : u8 = 15;
+pub const RESOLVE_INPUT_TAG : u8 = 16;
+pub const VOTE_INPUT_MODE_TAG : u8 = 17;
+pub const PUSH_SEQUENCE_TAG : u8 = 18;
+pub const CREDIT_WINNERS_TAG : u8 = 19;
+
+impl DPPInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+
+        // if this is a versioned envelope check we understand the version before decoding the instruction
+        if *tag == ENVELOPE_TAG {
+            let (version, instruction) = rest.split_first().ok_or(InvalidInstruction)?;
+            if *version != INSTRUCTION_VERSION {
+                msg!("unsupported instruction version {}, expected {}", version, INSTRUCTION_VERSION);
+                return Err(UnsupportedInstructionVersion.into());
+            }
+
+            return Self::unpack_instruction(instruction);
+        }
+
+        Self::unpack_instruction(input)
+    }
+
+    fn unpack_instruction(input: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        Ok(match *tag {
+            CREATE_DATA_ACCOUNT_TAG => Self::CreateDataAccount  {
+                init_data: InitData::try_from_slice(rest)?,
+            },
+            PUSH_BUTTON_TAG => Self::PushButton {
+                button_data: ButtonData::try_from_slice(rest)?,
+            },
+            PLACE_BID_TAG => Self::PlaceBid{
+                bid_data: BidData::try_from_slice(rest)?,
+            },
+            SELECT_WINNERS_TAG if rest.is_empty() => Self::SelectWinners,
+            // old clients sending SendTokens get an error rather than CreditWinners, which takes different accounts
+            SEND_TOKENS_TAG => {
+                msg!("SendTokens has been retired, winners are paid with CreditWinners and ClaimPrize");
+                return Err(InvalidInstruction.into());
+            },
+            UPDATE_CONFIG_TAG => Self::UpdateConfig {
+                config: Config::try_from_slice(rest)?,
+            },
+            ADD_CHARITY_TAG => Self::AddCharity {
+                charity_data: AddCharityData::try_from_slice(rest)?,
+            },
+            UPDATE_CHARITY_TAG => Self::UpdateCharity {
+                charity_data: UpdateCharityData::try_from_slice(rest)?,
+            },
+            CANCEL_BID_TAG if rest.is_empty() => Self::CancelBid,
+            COMMIT_SEED_TAG => Self::CommitSeed {
+                commitment: <[u8; 32]>::try_from_slice(rest)?,
+            },
+            REVEAL_SEED_TAG => Self::RevealSeed {
+                secret: <[u8; 32]>::try_from_slice(rest)?,
+            },
+            SLASH_COMMIT_TAG if rest.is_empty() => Self::SlashCommit,
+            RESIZE_STATE_TAG => Self::ResizeState {
+                resize_data: ResizeData::try_from_slice(rest)?,
+            },
+            MIGRATE_STATE_TAG if rest.is_empty() => Self::MigrateState,
+            CLAIM_PRIZE_TAG if rest.is_empty() => Self::ClaimPrize,
+            CRANK_TAG if rest.is_empty() => Self::Crank,
+            RESOLVE_INPUT_TAG if rest.is_empty() => Self::ResolveInput,
+            VOTE_INPUT_MODE_TAG => Self::VoteInputMode {
+                mode_vote_data: ModeVoteData::try_from_slice(rest)?,
+            },
+            PUSH_SEQUENCE_TAG => Self::PushSequence {
+                sequence_data: SequenceData::try_from_slice(rest)?,
+            },
+            CREDIT_WINNERS_TAG if rest.is_empty() => Self::CreditWinners,
+            _ => return Err(InvalidInstruction.into()),
+        })
+    }
+
+    // wrap the Borsh encoding of the instruction in a versioned envelope
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = vec![ENVELOPE_TAG, INSTRUCTION_VERSION];
+        data.extend(self.try_to_vec().unwrap());
+
+        data
+    }
+}
@@ -0,0 +1,14 @@
+pub mod accounts;
+pub mod bid_index;
+pub mod draw;
+pub mod error;
+pub mod events;
+pub mod inputs;
+pub mod instruction;
+pub mod modes;
+pub mod randomness;
+pub mod state;
+pub mod votes;
+
+// the address of the game program on mainnet
+solana_program::declare_id!("GRxdexptfCKuXfGpTGREEjtwTrZPTwZSfdSXiWDC11me");
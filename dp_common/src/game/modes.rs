@@ -0,0 +1,162 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{program_error::ProgramError, msg};
+use crate::layout::{LayoutHeader, get_layout_header_size};
+use crate::pod::PodU64;
+
+// the rule ResolveInput uses to turn a window of button votes into the game's input. the players choose it by
+// voting with their tokens, so the game can switch to whatever suits the number of people playing
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputMode {
+    // a button picked at random, weighted by the tokens pushed into each button
+    Lottery,
+    // every press is an input, in the order they were made
+    Anarchy,
+    // the button with the most tokens pushed into it
+    Democracy,
+    // the button of the largest single press
+    Auction
+}
+
+// the number of InputMode variants, which the mode ballot keeps a total for
+pub const N_INPUT_MODES : usize = 4;
+
+impl InputMode {
+    pub fn from_u8(value : u8) -> Option<InputMode> {
+        match value {
+            0 => Some(InputMode::Lottery),
+            1 => Some(InputMode::Anarchy),
+            2 => Some(InputMode::Democracy),
+            3 => Some(InputMode::Auction),
+            _ => None
+        }
+    }
+}
+
+pub const MODE_BALLOT_DISCRIMINATOR : [u8; 8] = *b"dp_modes";
+pub const MODE_BALLOT_LAYOUT_VERSION : u8 = 1;
+
+// the mode ballot account holds the current input mode and the votes to change it. the first vote opens a ballot
+// that runs for the config's mode_vote_slots, and once it has closed ResolveInput settles it before resolving the
+// first window that starts after it, so the mode only ever changes between windows. the mode with the most tokens
+// wins, with a tie, or a ballot nobody voted in, keeping the current mode
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct ModeBallot {
+    pub layout : LayoutHeader,
+    // the current InputMode
+    pub mode : u8,
+    // the first window start slot the current mode applied to
+    pub mode_start_slot : PodU64,
+    // the first slot after the open ballot, or zero if there isn't one
+    pub ballot_end_slot : PodU64,
+    // the tokens voted for each mode, in the order of the InputMode variants
+    pub votes : [PodU64; N_INPUT_MODES],
+    pub n_votes : PodU64
+}
+
+impl ModeBallot {
+    pub fn load(data : &[u8]) -> Result<&ModeBallot, ProgramError> {
+        check_mode_ballot_layout(data)?;
+        Ok(bytemuck::from_bytes(&data[..get_mode_ballot_size()]))
+    }
+
+    pub fn load_mut(data : &mut [u8]) -> Result<&mut ModeBallot, ProgramError> {
+        check_mode_ballot_layout(data)?;
+        Ok(bytemuck::from_bytes_mut(&mut data[..get_mode_ballot_size()]))
+    }
+
+    // write the layout header to a newly created ballot account, which starts in the lottery mode
+    pub fn initialize(data : &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() < get_mode_ballot_size() {
+            msg!("mode ballot account is too small {} < {}", data.len(), get_mode_ballot_size());
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let header : &mut LayoutHeader = bytemuck::from_bytes_mut(&mut data[..get_layout_header_size()]);
+        *header = LayoutHeader::new(MODE_BALLOT_DISCRIMINATOR, MODE_BALLOT_LAYOUT_VERSION);
+
+        Ok(())
+    }
+
+    pub fn get_mode(&self) -> Result<InputMode, ProgramError> {
+        InputMode::from_u8(self.mode).ok_or(ProgramError::InvalidAccountData)
+    }
+
+    // true if a ballot has been opened and slot is past its end, so it can take no more votes
+    pub fn is_closed(&self, slot : u64) -> bool {
+        self.ballot_end_slot.get() != 0 && slot >= self.ballot_end_slot.get()
+    }
+
+    // add a vote, opening a ballot that runs for ballot_slots if there isn't one. returns None if a total would
+    // overflow
+    pub fn add_vote(&mut self, slot : u64, mode : InputMode, amount : u64, ballot_slots : u64) -> Option<()> {
+        if self.ballot_end_slot.get() == 0 {
+            self.ballot_end_slot.set(slot.checked_add(ballot_slots)?);
+        }
+
+        let votes = &mut self.votes[mode as usize];
+        votes.set(votes.get().checked_add(amount)?);
+        self.n_votes.set(self.n_votes.get().checked_add(1)?);
+
+        Some(())
+    }
+
+    // the mode with the most tokens, a tie going to the current mode and then to the first of the InputMode variants
+    pub fn get_leader(&self) -> Result<InputMode, ProgramError> {
+        let mut leader = self.get_mode()?;
+        for (index, votes) in self.votes.iter().enumerate() {
+            if votes.get() > self.votes[leader as usize].get() {
+                leader = InputMode::from_u8(index as u8).ok_or(ProgramError::InvalidAccountData)?;
+            }
+        }
+
+        Ok(leader)
+    }
+
+    // settle a ballot that closed at or before window_start_slot, switching to the leading mode from that window
+    // on and clearing the votes. returns the mode before the ballot if one was settled
+    pub fn settle(&mut self, window_start_slot : u64) -> Result<Option<InputMode>, ProgramError> {
+        if !self.is_closed(window_start_slot) {
+            return Ok(None);
+        }
+
+        let previous_mode = self.get_mode()?;
+        let mode = self.get_leader()?;
+
+        if mode != previous_mode {
+            self.mode = mode as u8;
+            self.mode_start_slot.set(window_start_slot);
+        }
+
+        self.ballot_end_slot.set(0);
+        self.votes = [PodU64::new(0); N_INPUT_MODES];
+        self.n_votes.set(0);
+
+        Ok(Some(previous_mode))
+    }
+}
+
+pub fn get_mode_ballot_size() -> usize {
+    std::mem::size_of::<ModeBallot>()
+}
+
+fn check_mode_ballot_layout(data : &[u8]) -> Result<(), ProgramError> {
+    if data.len() < get_mode_ballot_size() {
+        msg!("mode ballot account is too small {} < {}", data.len(), get_mode_ballot_size());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let header : &LayoutHeader = bytemuck::from_bytes(&data[..get_layout_header_size()]);
+    if header.discriminator != MODE_BALLOT_DISCRIMINATOR {
+        msg!("mode ballot account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if header.layout_version != MODE_BALLOT_LAYOUT_VERSION {
+        msg!("mode ballot layout version {} is not supported", header.layout_version);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use solana_program::hash::hashv;
+
+// commit-reveal randomness. the cranker picks a random secret and commits to its hash with CommitSeed. in a later
+// slot they reveal the secret with RevealSeed, and the seed for the draw is the hash of the secret and the slot hash
+// of the commit's slot. the cranker can't know the slot hash when they commit, and everyone else can't know the
+// secret until it is revealed, so nobody can pick the seed. the cranker does learn it first, once the commit's slot
+// hash exists, so until the commitment is revealed or times out the bids already in the draw can't be cancelled,
+// topped up or evicted. what the cranker can still do is withhold the reveal and have the draw made again from a
+// new commitment, which costs them the commit_bond, so the bond should be worth more than a round's prizes
+
+// the commitment for a secret
+pub fn get_commitment(secret : &[u8; 32]) -> [u8; 32] {
+    hashv(&[b"dp_commit", secret]).to_bytes()
+}
+
+// the seed for the draw, as used in place of the oracle seed by SelectWinners
+pub fn get_revealed_seed(secret : &[u8; 32], slot_hash : &[u8; 32]) -> u64 {
+    let hash = hashv(&[b"dp_reveal", secret, slot_hash]).to_bytes();
+
+    let mut seed_bytes = [0u8; 8];
+    seed_bytes.copy_from_slice(&hash[..8]);
+
+    u64::from_le_bytes(seed_bytes)
+}
+
+// the seed and random numbers used to select winners. these are shared with the program so that a draw can be
+// reproduced off chain from the values recorded in its transaction
+
+// A xorshift* generator as suggested by Marsaglia.
+// The following 64-bit generator with 64 bits of state has a maximal period of 2^64−1
+// and fails only the MatrixRank test of BigCrush
+// see https://en.wikipedia.org/wiki/Xorshift
+pub fn shift_seed(mut seed : u64) -> u64 {
+    seed ^= seed >> 12;
+    seed ^= seed << 25;
+    seed ^= seed >> 27;
+    seed = seed.wrapping_mul(0x2545F4914F6CDD1D);
+
+    seed
+}
+
+// a float in [0, 1) from the lower 52 bits of seed
+pub fn generate_random(seed : u64) -> f64 {
+    let tmp = 0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF);
+    let result : f64 = f64::from_bits(tmp);
+
+    result - 1.0
+}
+
+// a usable price read from one of the oracles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub price : u64,
+    pub conf : u64,
+    pub expo : i32
+}
+
+// hash the btc, eth and sol prices, in that order, into a seed. oracles without a usable price are left out of
+// the seed, and None is returned if none of them have one
+pub fn get_oracle_seed(prices : &[Option<OraclePrice>; 3]) -> Option<u64> {
+    if prices.iter().all(Option::is_none) {
+        return None;
+    }
+
+    let mut seed_prices = [0u64; 9];
+    for (idx, oracle_price) in prices.iter().enumerate() {
+        if let Some(oracle_price) = oracle_price {
+            seed_prices[3 * idx] = shift_seed(shift_seed(oracle_price.price.saturating_add(oracle_price.conf)));
+            seed_prices[3 * idx + 1] = shift_seed(shift_seed(oracle_price.price));
+            seed_prices[3 * idx + 2] = shift_seed(shift_seed(oracle_price.price.saturating_sub(oracle_price.conf)));
+        }
+    }
+
+    let mut seed_bytes = [0u8; 72];
+    for (chunk, seed_price) in seed_bytes.chunks_exact_mut(8).zip(seed_prices.iter()) {
+        chunk.copy_from_slice(&seed_price.to_le_bytes());
+    }
+
+    let h = murmur3::murmur3_x64_128(&mut &seed_bytes[..], 0).ok()?;
+
+    // we can take our 128bit number and get two 64bit values
+    let lower = h as u64;
+    let upper = (h >> 64) as u64;
+
+    Some(lower ^ upper)
+}
+
+// the random number for each winner, in the ascending order they are searched for in the bid ladder
+pub fn get_draw_randoms(mut seed : u64, n_winners : u8) -> Vec<f64> {
+    let mut randoms : Vec<f64> = Vec::new();
+    for _winner in 0..n_winners {
+        seed = shift_seed(seed);
+        randoms.push(generate_random(seed));
+    }
+
+    randoms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    randoms
+}
@@ -0,0 +1,651 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{pubkey::Pubkey, program_error::ProgramError, native_token::LAMPORTS_PER_SOL, msg};
+use std::ops::{Deref, DerefMut};
+use crate::charity::CharityData;
+use crate::game::error::DaoPlaysError;
+use crate::game::inputs::MAX_INPUT_WINDOW_SLOTS;
+use crate::layout::{LayoutHeader, LEGACY_LAYOUT_VERSION, get_layout_header_size, get_layout_version};
+use crate::pod::{PodU16, PodU64, PodI64};
+
+// the most bids the ladder can be resized to hold, as slots are indexed with a u16 and u16::MAX marks the end of
+// the bid index's lists, and the most winners that can be selected in one go, which bounds the number of winners
+// waiting to be credited with CreditWinners
+pub const MAX_BIDDERS : usize = u16::MAX as usize - 1;
+pub const MAX_WINNERS : usize = 16;
+
+// the capacity CreateDataAccount gives a new data account, which has to fit in the 10KB an account can be created
+// with by the program
+pub const INITIAL_BID_CAPACITY : usize = 128;
+
+// a commitment has to be revealed while the slot hash of the slot it was made in is still in the SlotHashes sysvar
+pub const MAX_REVEAL_TIMEOUT_SLOTS : u64 = 512;
+
+// the shares of each press that are burned or sent to the treasury are in basis points of the amount pushed
+pub const MAX_BASIS_POINTS : u16 = 10_000;
+
+// where the randomness used to select winners comes from
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RandomnessSource {
+    // SelectWinners hashes the prices of the config's pyth oracles
+    Oracles,
+    // a cranker commits to a secret with CommitSeed and RevealSeed mixes it with the slot hash of the commit's slot
+    CommitReveal
+}
+
+// the config account starts with a LayoutHeader holding these. configs written before it was added hold just the
+// Borsh encoded Config of the time, which always began with the authority and the data account, and are rewritten
+// in the current layout by UpdateConfig, as are configs with an older layout version. version 2 added the cancel refund
+pub const CONFIG_DISCRIMINATOR : [u8; 8] = *b"dpconfig";
+pub const CONFIG_LAYOUT_VERSION : u8 = 2;
+
+// the smallest legacy config, which is enough to hold the authority and the data account
+pub const LEGACY_CONFIG_SIZE : usize = 64;
+
+// the tunable parameters of the game, stored in the config account so the same program can be
+// deployed to any cluster and retuned without an upgrade
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Config {
+    // the account that can update the config, which also receives the developer's share of bids
+    pub authority : Pubkey,
+    // the program's data account, which is fixed when the config is created
+    pub data_account : Pubkey,
+    // the token that is pushed into the game and paid out to winners
+    pub token_mint : Pubkey,
+    // the pyth price accounts used to seed the selection of winners
+    pub btc_oracle : Pubkey,
+    pub eth_oracle : Pubkey,
+    pub sol_oracle : Pubkey,
+    // the number of tokens sent to each winner
+    pub tokens_won : u64,
+    // the max number of winners to select in one go, up to MAX_WINNERS
+    pub max_winners : u8,
+    // the minimum amount in lamports that can be bid
+    pub min_bid : u64,
+    // on average a single bidder should wait this many seconds before being selected
+    pub selection_time : i64,
+    // when the ladder is full, only let a new bid replace the oldest bid if it is at least as large
+    pub protect_larger_bids : bool,
+    // oracle prices published more than this many slots ago are not used to select winners
+    pub max_oracle_slot_age : u64,
+    pub randomness_source : RandomnessSource,
+    // in commit-reveal mode a commitment must be revealed within this many slots, or the lamports
+    // the committer put up as a bond can be slashed. a committer who doesn't like the seed can let it time out
+    // and lose the bond, so the bond should be worth more than the prizes of a round
+    pub reveal_timeout_slots : u64,
+    pub commit_bond : u64,
+    // paid to whoever sends a Crank that moves the game on, from the lamports and token account of the crank tip
+    // address
+    pub crank_tip_lamports : u64,
+    pub crank_tip_tokens : u64,
+    // the number of slots of button votes ResolveInput turns into each input, up to MAX_INPUT_WINDOW_SLOTS
+    pub input_window_slots : u64,
+    // the number of slots a mode ballot stays open for once the first vote has been made
+    pub mode_vote_slots : u64,
+    // the wallet whose token account receives the treasury's share of the tokens pushed with PushButton
+    pub treasury : Pubkey,
+    // the basis points of each press that are burned and sent to the treasury, the rest being recycled into the
+    // prize pool in the program's token account
+    pub push_burn_bps : u16,
+    pub push_treasury_bps : u16,
+    // the tokens CancelBid sends back from the program's token account for each SOL of the cancelled bid. the lamports
+    // of a bid have already gone to the charity and the authority, so they can't be returned
+    pub cancel_refund_tokens_per_sol : u64
+}
+
+impl Config {
+    // check the values are ones the program can actually work with
+    pub fn is_valid(&self) -> bool {
+        self.tokens_won > 0 &&
+        self.max_winners > 0 && self.max_winners as usize <= MAX_WINNERS &&
+        self.min_bid > 0 &&
+        self.selection_time >= 0 &&
+        self.max_oracle_slot_age > 0 &&
+        self.reveal_timeout_slots > 0 && self.reveal_timeout_slots <= MAX_REVEAL_TIMEOUT_SLOTS &&
+        self.input_window_slots > 0 && self.input_window_slots <= MAX_INPUT_WINDOW_SLOTS &&
+        self.mode_vote_slots > 0 &&
+        self.push_burn_bps as u32 + self.push_treasury_bps as u32 <= MAX_BASIS_POINTS as u32
+    }
+
+    // how a press of amount tokens is divided up, with anything lost to rounding going to the prize pool
+    pub fn get_push_split(&self, amount : u64) -> PushSplit {
+        let share = |bps : u16| (amount as u128 * bps as u128 / MAX_BASIS_POINTS as u128) as u64;
+
+        let burn = share(self.push_burn_bps);
+        let treasury = share(self.push_treasury_bps);
+
+        PushSplit {burn, treasury, prizes : amount - burn - treasury}
+    }
+
+    // the tokens refunded for cancelling a bid of amount lamports, rounded down
+    pub fn get_cancel_refund(&self, amount : u64) -> Option<u64> {
+        u64::try_from(amount as u128 * self.cancel_refund_tokens_per_sol as u128 / LAMPORTS_PER_SOL as u128).ok()
+    }
+
+    // read the config from an account in the current layout
+    pub fn load(data : &[u8]) -> Result<Config, ProgramError> {
+        if get_config_layout_version(data) != Some(CONFIG_LAYOUT_VERSION) {
+            msg!("the config account does not have layout version {}, older configs can be upgraded with UpdateConfig", CONFIG_LAYOUT_VERSION);
+            return Err(DaoPlaysError::UnsupportedConfigLayout.into());
+        }
+
+        let bytes = data.get(get_layout_header_size()..get_config_size()).ok_or(ProgramError::AccountDataTooSmall)?;
+        Ok(Config::try_from_slice(bytes)?)
+    }
+
+    // the authority and the data account of a config in the legacy or any headed layout, which is all UpdateConfig
+    // needs to check before rewriting it
+    pub fn load_keys(data : &[u8]) -> Result<(Pubkey, Pubkey), ProgramError> {
+        match get_config_layout_version(data) {
+            Some(version) if version > LEGACY_LAYOUT_VERSION && version <= CONFIG_LAYOUT_VERSION => {
+                let bytes = data.get(get_layout_header_size()..get_layout_header_size() + LEGACY_CONFIG_SIZE).ok_or(ProgramError::AccountDataTooSmall)?;
+                let (authority, data_account) = <(Pubkey, Pubkey)>::try_from_slice(bytes)?;
+                Ok((authority, data_account))
+            },
+            Some(LEGACY_LAYOUT_VERSION) => {
+                let (authority, data_account) = <(Pubkey, Pubkey)>::try_from_slice(&data[..LEGACY_CONFIG_SIZE])?;
+                Ok((authority, data_account))
+            },
+            _ => {
+                msg!("the config account layout is not supported");
+                Err(DaoPlaysError::UnsupportedConfigLayout.into())
+            }
+        }
+    }
+
+    // write the config in the current layout, the account must already be at least get_config_size()
+    pub fn store(&self, data : &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() < get_config_size() {
+            msg!("config account is too small {} < {}", data.len(), get_config_size());
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let mut bytes = &mut data[..];
+        LayoutHeader::new(CONFIG_DISCRIMINATOR, CONFIG_LAYOUT_VERSION).serialize(&mut bytes)?;
+        self.serialize(&mut bytes)?;
+
+        Ok(())
+    }
+}
+
+// the layout version of the config account, or None if it is too small to hold any layout
+pub fn get_config_layout_version(data : &[u8]) -> Option<u8> {
+    get_layout_version(data, &CONFIG_DISCRIMINATOR, LEGACY_CONFIG_SIZE)
+}
+
+// where the tokens of a single press go, see Config::get_push_split
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PushSplit {
+    pub burn : u64,
+    pub treasury : u64,
+    pub prizes : u64
+}
+
+/// Determines and reports the size of the config data, including its layout header.
+pub fn get_config_size() -> usize {
+    let encoded = Config {
+        authority : Pubkey::default(),
+        data_account : Pubkey::default(),
+        token_mint : Pubkey::default(),
+        btc_oracle : Pubkey::default(),
+        eth_oracle : Pubkey::default(),
+        sol_oracle : Pubkey::default(),
+        tokens_won : 0,
+        max_winners : 0,
+        min_bid : 0,
+        selection_time : 0,
+        protect_larger_bids : false,
+        max_oracle_slot_age : 0,
+        randomness_source : RandomnessSource::Oracles,
+        reveal_timeout_slots : 0,
+        commit_bond : 0,
+        crank_tip_lamports : 0,
+        crank_tip_tokens : 0,
+        input_window_slots : 0,
+        mode_vote_slots : 0,
+        treasury : Pubkey::default(),
+        push_burn_bps : 0,
+        push_treasury_bps : 0,
+        cancel_refund_tokens_per_sol : 0
+    }
+        .try_to_vec().unwrap();
+
+    get_layout_header_size() + encoded.len()
+}
+
+// the commitment waiting to be revealed in commit-reveal mode, held in the commit account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct Commit {
+    // the cranker that made the commitment, or the default key when there is nothing to reveal
+    pub committer : Pubkey,
+    // the hash of the secret, see game::randomness::get_commitment
+    pub commitment : [u8; 32],
+    pub commit_slot : u64,
+    // only bids made before the commitment are included in the draw
+    pub commit_time : i64,
+    // the lamports held in the commit account until the commitment is revealed or slashed
+    pub bond : u64
+}
+
+impl Commit {
+    pub fn is_active(&self) -> bool {
+        self.committer != Pubkey::default()
+    }
+
+    // the last slot the commitment can be revealed in, after which it can be slashed
+    pub fn get_reveal_deadline(&self, reveal_timeout_slots : u64) -> u64 {
+        self.commit_slot.saturating_add(reveal_timeout_slots)
+    }
+}
+
+pub fn get_commit_size() -> usize {
+    let encoded = Commit::default()
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// the running totals of the tokens pushed with PushButton and PushSequence and where they went, held in the push
+// stats account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct PushStats {
+    pub n_presses : u64,
+    pub tokens_pushed : u64,
+    pub tokens_burned : u64,
+    pub tokens_to_treasury : u64,
+    pub tokens_to_prizes : u64
+}
+
+impl PushStats {
+    pub fn record(&mut self, split : &PushSplit) -> Option<()> {
+        let amount = split.burn.checked_add(split.treasury)?.checked_add(split.prizes)?;
+
+        *self = PushStats {
+            n_presses : self.n_presses.checked_add(1)?,
+            tokens_pushed : self.tokens_pushed.checked_add(amount)?,
+            tokens_burned : self.tokens_burned.checked_add(split.burn)?,
+            tokens_to_treasury : self.tokens_to_treasury.checked_add(split.treasury)?,
+            tokens_to_prizes : self.tokens_to_prizes.checked_add(split.prizes)?
+        };
+
+        Some(())
+    }
+}
+
+pub fn get_push_stats_size() -> usize {
+    let encoded = PushStats::default()
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct BidderData {
+    // the position of the bidder's current bid in the ladder
+    pub index : u16,
+    // the number of times one of their bids was replaced by a new bidder because the ladder was full
+    pub n_evictions : u32,
+    // the total amount of the bids they lost to evictions, in lamports
+    pub lamports_evicted : u64,
+    // the number of times they have been selected as a winner
+    pub n_wins : u32,
+    // the tokens they have won that are waiting to be sent to them with ClaimPrize
+    pub claimable : u64
+}
+
+// bidder data accounts created before evictions were recorded only hold the index
+pub const LEGACY_BIDDER_DATA_SIZE : usize = 2;
+
+impl BidderData {
+    // read the bidder data from an account with the legacy or any later layout, where fields that were added
+    // after the account was created read as zero
+    pub fn load(data : &[u8]) -> Result<BidderData, ProgramError> {
+        if data.len() < LEGACY_BIDDER_DATA_SIZE {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let mut bytes = vec![0u8; get_bid_status_size()];
+        let len = data.len().min(bytes.len());
+        bytes[..len].copy_from_slice(&data[..len]);
+
+        Ok(BidderData::try_from_slice(&bytes)?)
+    }
+
+    pub fn record_eviction(&mut self, amount : u64) -> Option<()> {
+        let n_evictions = self.n_evictions.checked_add(1)?;
+        let lamports_evicted = self.lamports_evicted.checked_add(amount)?;
+
+        self.n_evictions = n_evictions;
+        self.lamports_evicted = lamports_evicted;
+
+        Some(())
+    }
+
+    // credit a prize of amount tokens, to be claimed later
+    pub fn record_win(&mut self, amount : u64) -> Option<()> {
+        let n_wins = self.n_wins.checked_add(1)?;
+        let claimable = self.claimable.checked_add(amount)?;
+
+        self.n_wins = n_wins;
+        self.claimable = claimable;
+
+        Some(())
+    }
+
+    // write the bidder data back. older accounts only have room for the fields they were created with and must be
+    // resized to keep the rest, and a prize is never dropped, so storing one in an account that is too small fails
+    pub fn store(&self, data : &mut [u8]) -> Result<(), ProgramError> {
+        let encoded = self.try_to_vec()?;
+        let len = data.len().min(encoded.len());
+
+        if len < encoded.len() && (self.n_wins != 0 || self.claimable != 0) {
+            msg!("bidder data account is too small to hold their prizes {} < {}", data.len(), encoded.len());
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        data[..len].copy_from_slice(&encoded[..len]);
+        Ok(())
+    }
+}
+
+// the layout of the program's data account before it recorded its own capacity, when the ladder was fixed at
+// LEGACY_MAX_BIDDERS bids and LEGACY_MAX_WINNERS winners. it is kept so that accounts created with it can still
+// be read. every field has an alignment of one, so the offsets follow directly from the field order with no padding
+pub const LEGACY_MAX_BIDDERS : usize = 1024;
+pub const LEGACY_MAX_WINNERS : usize = 4;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct LegacyState {
+    pub prev_choose_winners_time: PodI64,
+    pub n_bidders: PodU16,
+    pub total_bid_amount : PodU64,
+    pub bid_keys : [Pubkey; LEGACY_MAX_BIDDERS],
+    pub bid_amounts: [PodU64; LEGACY_MAX_BIDDERS],
+    pub bid_times: [PodI64; LEGACY_MAX_BIDDERS],
+    pub n_winners : u8,
+    pub winners: [Pubkey; LEGACY_MAX_WINNERS],
+    pub charity_data : CharityData
+}
+
+impl LegacyState {
+    pub fn load(data : &[u8]) -> Result<&LegacyState, ProgramError> {
+        match data.get(..get_legacy_state_size()) {
+            Some(bytes) => Ok(bytemuck::from_bytes(bytes)),
+            None => {
+                msg!("data account is too small to hold the legacy state {} < {}", data.len(), get_legacy_state_size());
+                Err(ProgramError::AccountDataTooSmall)
+            }
+        }
+    }
+}
+
+pub fn get_legacy_state_size() -> usize {
+    std::mem::size_of::<LegacyState>()
+}
+
+// the offsets of bid_keys, bid_amounts, bid_times and winners in the legacy layout, which MigrateState moves up to
+// make room for the StateHeader
+pub fn get_legacy_array_offsets() -> [usize; 4] {
+    let bid_keys = std::mem::size_of::<PodI64>() + std::mem::size_of::<PodU16>() + std::mem::size_of::<PodU64>();
+    let bid_amounts = bid_keys + LEGACY_MAX_BIDDERS * std::mem::size_of::<Pubkey>();
+    let bid_times = bid_amounts + LEGACY_MAX_BIDDERS * std::mem::size_of::<PodU64>();
+    // n_winners sits between the bid times and the winners
+    let winners = bid_times + LEGACY_MAX_BIDDERS * std::mem::size_of::<PodI64>() + std::mem::size_of::<u8>();
+
+    [bid_keys, bid_amounts, bid_times, winners]
+}
+
+// the number of bids the ladder can hold and the number of winners that can be chosen in one go. these set the
+// length of the arrays in the data account, and are stored at the start of it so the program reads them at
+// runtime. ResizeState grows the account to raise them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capacity {
+    pub bids : usize,
+    pub winners : usize
+}
+
+// the capacity of a legacy account once it has been migrated
+pub const LEGACY_CAPACITY : Capacity = Capacity {bids : LEGACY_MAX_BIDDERS, winners : LEGACY_MAX_WINNERS};
+
+impl Capacity {
+    pub fn is_valid(&self) -> bool {
+        self.bids > 0 && self.bids <= MAX_BIDDERS &&
+        self.winners > 0 && self.winners <= MAX_WINNERS
+    }
+
+    // true if every array is at least as long as in other
+    pub fn contains(&self, other : &Capacity) -> bool {
+        self.bids >= other.bids && self.winners >= other.winners
+    }
+}
+
+// the data account starts with a LayoutHeader holding these, so the program can tell the current layout apart from
+// the legacy one, which has to be upgraded with MigrateState before it can be used
+pub const STATE_DISCRIMINATOR : [u8; 8] = *b"dp_state";
+pub const STATE_LAYOUT_VERSION : u8 = 1;
+
+// the layout version of the data account, or None if it hasn't been set up. CreateDataAccount always set
+// prev_choose_winners_time in legacy accounts, so a legacy sized account that is still zeroed is treated as new
+pub fn get_state_layout_version(data : &[u8]) -> Option<u8> {
+    match get_layout_version(data, &STATE_DISCRIMINATOR, get_legacy_state_size()) {
+        Some(LEGACY_LAYOUT_VERSION) if data[..std::mem::size_of::<PodI64>()] == [0; 8] => None,
+        layout_version => layout_version
+    }
+}
+
+// check the data account has been set up with the layout the program reads
+pub fn check_state_layout(data : &[u8]) -> Result<(), ProgramError> {
+    match get_state_layout_version(data) {
+        Some(STATE_LAYOUT_VERSION) => Ok(()),
+        Some(layout_version) => {
+            msg!("the data account has layout version {} but the program reads version {}, older accounts can be upgraded with MigrateState", layout_version, STATE_LAYOUT_VERSION);
+            Err(DaoPlaysError::UnsupportedStateLayout.into())
+        },
+        None => {
+            msg!("the data account has not been set up");
+            Err(ProgramError::UninitializedAccount)
+        }
+    }
+}
+
+// the fixed size start of the data account
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct StateHeader {
+    pub layout : LayoutHeader,
+
+    // the capacity of the arrays that follow, zero until CreateDataAccount sets up the account
+    pub bid_capacity : PodU16,
+    pub winner_capacity : u8,
+
+    // this is the last time we actually chose winners, and decides how soon in the future will we choose again
+    pub prev_choose_winners_time: PodI64,
+
+    // the number of active bids in the system up to bid_capacity
+    pub n_bidders: PodU16,
+    // the sum of all the current bids
+    pub total_bid_amount : PodU64,
+
+    // the number of winners to be chosen, up to winner_capacity
+    pub n_winners : u8,
+
+    // the prizes that have been credited to winners but not yet claimed, which are still held in the program's
+    // token account and so can't be counted towards new prizes
+    pub total_claimable : PodU64,
+
+    // summary of the charity stats for the auction
+    pub charity_data : CharityData
+}
+
+impl StateHeader {
+    pub fn get_capacity(&self) -> Capacity {
+        Capacity {bids : self.bid_capacity.get() as usize, winners : self.winner_capacity as usize}
+    }
+}
+
+// the program's view of the data account. the account is too large to deserialize on chain so the ladder is
+// accessed in place: the StateHeader is followed by bid_keys, bid_amounts and bid_times with a slot for each
+// bid, then the winners, and then the BidIndex. the header's fields can be used directly on the State
+#[derive(Debug)]
+pub struct State<'a> {
+    header : &'a mut StateHeader,
+
+    // for each bid we track the key, amount and time
+    pub bid_keys : &'a mut [Pubkey],
+    pub bid_amounts : &'a mut [PodU64],
+    pub bid_times : &'a mut [PodI64],
+
+    pub winners : &'a mut [Pubkey]
+}
+
+impl Deref for State<'_> {
+    type Target = StateHeader;
+
+    fn deref(&self) -> &StateHeader {
+        self.header
+    }
+}
+
+impl DerefMut for State<'_> {
+    fn deref_mut(&mut self) -> &mut StateHeader {
+        self.header
+    }
+}
+
+// take the first len elements of data as a slice of T, leaving the rest in data
+pub(crate) fn take_slice<'a, T : Pod>(data : &mut &'a mut [u8], len : usize) -> &'a mut [T] {
+    let (head, tail) = std::mem::take(data).split_at_mut(len * std::mem::size_of::<T>());
+    *data = tail;
+    bytemuck::cast_slice_mut(head)
+}
+
+impl<'a> State<'a> {
+    // view the start of the data account as the State without copying it
+    pub fn load_mut(data : &'a mut [u8]) -> Result<State<'a>, ProgramError> {
+        check_state_layout(data)?;
+
+        let capacity = match data.get(..get_state_header_size()) {
+            Some(bytes) => bytemuck::from_bytes::<StateHeader>(bytes).get_capacity(),
+            None => {
+                msg!("data account is too small to hold the state header {} < {}", data.len(), get_state_header_size());
+                return Err(ProgramError::AccountDataTooSmall);
+            }
+        };
+
+        if capacity.bids == 0 {
+            msg!("the data account has no room for bids");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let data_len = data.len();
+        let mut bytes = match data.get_mut(..get_state_size(&capacity)) {
+            Some(bytes) => bytes,
+            None => {
+                msg!("data account is too small to hold the state {} < {}", data_len, get_state_size(&capacity));
+                return Err(ProgramError::AccountDataTooSmall);
+            }
+        };
+
+        let header = bytemuck::from_bytes_mut(take_slice::<u8>(&mut bytes, get_state_header_size()));
+        let bid_keys = take_slice(&mut bytes, capacity.bids);
+        let bid_amounts = take_slice(&mut bytes, capacity.bids);
+        let bid_times = take_slice(&mut bytes, capacity.bids);
+        let winners = take_slice(&mut bytes, capacity.winners);
+
+        Ok(State {header, bid_keys, bid_amounts, bid_times, winners})
+    }
+
+    pub fn get_capacity(&self) -> Capacity {
+        self.header.get_capacity()
+    }
+
+    // the accounting functions below keep n_bidders and total_bid_amount consistent with the ladder, and return
+    // None, leaving the state unchanged, if any of the counts would overflow
+
+    // put a new bid into the empty slot at index
+    pub fn insert_bid(&mut self, index : usize, key : Pubkey, amount : u64, time : i64) -> Option<()> {
+        let n_bidders = self.n_bidders.get().checked_add(1)?;
+        let total_bid = self.total_bid_amount.get().checked_add(amount)?;
+
+        self.bid_keys[index] = key;
+        self.bid_amounts[index].set(amount);
+        self.bid_times[index].set(time);
+
+        self.n_bidders.set(n_bidders);
+        self.total_bid_amount.set(total_bid);
+
+        Some(())
+    }
+
+    // add to the existing bid at index, returning the new bid
+    pub fn add_to_bid(&mut self, index : usize, amount : u64, time : i64) -> Option<u64> {
+        let bid = self.bid_amounts[index].get().checked_add(amount)?;
+        let total_bid = self.total_bid_amount.get().checked_add(amount)?;
+
+        self.bid_amounts[index].set(bid);
+        self.bid_times[index].set(time);
+
+        self.total_bid_amount.set(total_bid);
+
+        Some(bid)
+    }
+
+    // clear the bid at index and return the amount that was removed
+    pub fn remove_bid(&mut self, index : usize) -> Option<u64> {
+        let amount = self.bid_amounts[index].get();
+        let n_bidders = self.n_bidders.get().checked_sub(1)?;
+        let total_bid = self.total_bid_amount.get().checked_sub(amount)?;
+
+        self.bid_amounts[index].set(0);
+        self.bid_times[index].set(0);
+        self.bid_keys[index] = Pubkey::default();
+
+        self.n_bidders.set(n_bidders);
+        self.total_bid_amount.set(total_bid);
+
+        Some(amount)
+    }
+
+    // count the bids made before max_time and sum their amounts
+    pub fn get_bid_totals(&self, max_time : i64) -> Option<(u16, u64)> {
+        let mut total_bid : u64 = 0;
+        let mut n_bidders : u16 = 0;
+        for (bid_amount, bid_time) in self.bid_amounts.iter().zip(self.bid_times.iter()) {
+            if bid_time.get() < max_time && bid_amount.get() > 0 {
+                total_bid = total_bid.checked_add(bid_amount.get())?;
+                n_bidders = n_bidders.checked_add(1)?;
+            }
+        }
+
+        Some((n_bidders, total_bid))
+    }
+}
+
+pub fn get_state_header_size() -> usize {
+    std::mem::size_of::<StateHeader>()
+}
+
+// the size in bytes of the header and each of the arrays that follow it, in the order they are stored
+pub fn get_state_sections(capacity : &Capacity) -> [usize; 5] {
+    [
+        get_state_header_size(),
+        capacity.bids * std::mem::size_of::<Pubkey>(),
+        capacity.bids * std::mem::size_of::<PodU64>(),
+        capacity.bids * std::mem::size_of::<PodI64>(),
+        capacity.winners * std::mem::size_of::<Pubkey>()
+    ]
+}
+
+// helper function to return the size of the State so we can check the lamports required to be rent-exempt
+pub fn get_state_size(capacity : &Capacity) -> usize {
+    get_state_sections(capacity).iter().sum()
+}
+
+/// Determines and reports the size of greeting data.
+pub fn get_bid_status_size() -> usize {
+    let encoded = BidderData::default()
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
@@ -0,0 +1,202 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{program_error::ProgramError, msg};
+use crate::game::inputs::NO_BUTTON;
+use crate::game::instruction::{Button, N_BUTTONS};
+use crate::layout::{LayoutHeader, get_layout_header_size};
+use crate::pod::PodU64;
+
+pub const VOTE_TALLY_DISCRIMINATOR : [u8; 8] = *b"dp_votes";
+pub const VOTE_TALLY_LAYOUT_VERSION : u8 = 1;
+
+// the number of slots the tally keeps, which keeps the account within the 10KB the program can create it with
+pub const VOTE_HISTORY_SLOTS : usize = 110;
+
+// the token weighted votes for each button made with PushButton in a single slot
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct SlotVotes {
+    pub slot : PodU64,
+    pub n_presses : PodU64,
+    // the tokens pushed into each button, indexed in the order of the Button variants
+    pub tallies : [PodU64; N_BUTTONS],
+    // the largest single press in the slot and its button, the earliest press winning a tie
+    pub top_bid : PodU64,
+    pub top_button : u8
+}
+
+impl SlotVotes {
+    pub fn get_tally(&self, button : &Button) -> u64 {
+        self.tallies[button.clone() as usize].get()
+    }
+
+    // the tokens pushed into every button in the slot
+    pub fn get_total(&self) -> Option<u64> {
+        self.tallies.iter().try_fold(0u64, |total, tally| total.checked_add(tally.get()))
+    }
+}
+
+// the vote tally account, a ring buffer of the votes of the most recent slots that had a button pressed.
+// like the charity registry this is accessed in place with VoteTally::load and VoteTally::load_mut. Borsh has no
+// encoding for an array of this length, so unlike the registry it can only be viewed in place
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct VoteTally {
+    pub layout : LayoutHeader,
+    // the position of the most recent slot in the ring
+    pub head : PodU64,
+    pub records : [SlotVotes; VOTE_HISTORY_SLOTS]
+}
+
+impl VoteTally {
+    pub fn load(data : &[u8]) -> Result<&VoteTally, ProgramError> {
+        check_vote_tally_layout(data)?;
+        Ok(bytemuck::from_bytes(&data[..get_vote_tally_size()]))
+    }
+
+    pub fn load_mut(data : &mut [u8]) -> Result<&mut VoteTally, ProgramError> {
+        check_vote_tally_layout(data)?;
+        Ok(bytemuck::from_bytes_mut(&mut data[..get_vote_tally_size()]))
+    }
+
+    // write the layout header to a newly created tally account
+    pub fn initialize(data : &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() < get_vote_tally_size() {
+            msg!("vote tally account is too small {} < {}", data.len(), get_vote_tally_size());
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let header : &mut LayoutHeader = bytemuck::from_bytes_mut(&mut data[..get_layout_header_size()]);
+        *header = LayoutHeader::new(VOTE_TALLY_DISCRIMINATOR, VOTE_TALLY_LAYOUT_VERSION);
+
+        Ok(())
+    }
+
+    // add a button press to the votes for slot, moving the ring on when this is the first press in the slot.
+    // returns None if a total would overflow
+    pub fn record_vote(&mut self, slot : u64, button : &Button, amount : u64) -> Option<&SlotVotes> {
+        let mut head = self.head.get() as usize % VOTE_HISTORY_SLOTS;
+
+        if self.records[head].slot.get() != slot {
+            head = (head + 1) % VOTE_HISTORY_SLOTS;
+            self.head.set(head as u64);
+
+            self.records[head] = SlotVotes::zeroed();
+            self.records[head].slot.set(slot);
+            self.records[head].top_button = NO_BUTTON;
+        }
+
+        let record = &mut self.records[head];
+        let tally = &mut record.tallies[button.clone() as usize];
+
+        tally.set(tally.get().checked_add(amount)?);
+        record.n_presses.set(record.n_presses.get().checked_add(1)?);
+
+        if amount > record.top_bid.get() {
+            record.top_bid.set(amount);
+            record.top_button = button.clone() as u8;
+        }
+
+        Some(record)
+    }
+
+    // the votes for slot, if it had a button pressed and is still in the ring
+    pub fn get_slot_votes(&self, slot : u64) -> Option<&SlotVotes> {
+        self.records.iter().find(|record| record.slot.get() == slot && record.n_presses.get() > 0)
+    }
+
+    // the total votes for each button in the slots from start_slot up to but not including end_slot, the number of
+    // presses, the largest single press, and whether the tally still has every slot in the range. returns None if a
+    // total would overflow
+    pub fn get_window_votes(&self, start_slot : u64, end_slot : u64) -> Option<WindowVotes> {
+        let mut window_votes = WindowVotes {tallies : [0; N_BUTTONS], n_presses : 0, top_bid : 0, top_button : NO_BUTTON, complete : true};
+        let mut top_slot = u64::MAX;
+
+        let mut oldest_slot = u64::MAX;
+        let mut n_records = 0;
+        for record in self.records.iter().filter(|record| record.n_presses.get() > 0) {
+            n_records += 1;
+            oldest_slot = oldest_slot.min(record.slot.get());
+
+            if record.slot.get() < start_slot || record.slot.get() >= end_slot {
+                continue;
+            }
+
+            for (total, tally) in window_votes.tallies.iter_mut().zip(record.tallies.iter()) {
+                *total = total.checked_add(tally.get())?;
+            }
+            window_votes.n_presses = window_votes.n_presses.checked_add(record.n_presses.get())?;
+
+            // a tie between slots goes to the earlier one
+            let top_bid = record.top_bid.get();
+            if top_bid > window_votes.top_bid || (top_bid > 0 && top_bid == window_votes.top_bid && record.slot.get() < top_slot) {
+                window_votes.top_bid = top_bid;
+                window_votes.top_button = record.top_button;
+                top_slot = record.slot.get();
+            }
+        }
+
+        // once the ring is full the slots older than the oldest record have been overwritten
+        window_votes.complete = n_records < VOTE_HISTORY_SLOTS || oldest_slot <= start_slot;
+
+        Some(window_votes)
+    }
+
+    // the first slot at or after slot that had a button pressed
+    pub fn get_next_voted_slot(&self, slot : u64) -> Option<u64> {
+        self.records.iter()
+            .filter(|record| record.n_presses.get() > 0 && record.slot.get() >= slot)
+            .map(|record| record.slot.get())
+            .min()
+    }
+
+    // the most recent slot first
+    pub fn iter_recent(&self) -> impl Iterator<Item = &SlotVotes> {
+        let head = self.head.get() as usize % VOTE_HISTORY_SLOTS;
+        (0..VOTE_HISTORY_SLOTS)
+            .map(move |i| &self.records[(head + VOTE_HISTORY_SLOTS - i) % VOTE_HISTORY_SLOTS])
+            .filter(|record| record.n_presses.get() > 0)
+    }
+
+}
+
+// the votes for a range of slots, see VoteTally::get_window_votes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowVotes {
+    pub tallies : [u64; N_BUTTONS],
+    pub n_presses : u64,
+    // the largest single press in the window and its button, or NO_BUTTON
+    pub top_bid : u64,
+    pub top_button : u8,
+    pub complete : bool
+}
+
+impl WindowVotes {
+    pub fn get_total(&self) -> Option<u64> {
+        self.tallies.iter().try_fold(0u64, |total, tally| total.checked_add(*tally))
+    }
+}
+
+pub fn get_vote_tally_size() -> usize {
+    std::mem::size_of::<VoteTally>()
+}
+
+fn check_vote_tally_layout(data : &[u8]) -> Result<(), ProgramError> {
+    if data.len() < get_vote_tally_size() {
+        msg!("vote tally account is too small {} < {}", data.len(), get_vote_tally_size());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let header : &LayoutHeader = bytemuck::from_bytes(&data[..get_layout_header_size()]);
+    if header.discriminator != VOTE_TALLY_DISCRIMINATOR {
+        msg!("vote tally account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if header.layout_version != VOTE_TALLY_LAYOUT_VERSION {
+        msg!("vote tally layout version {} is not supported", header.layout_version);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
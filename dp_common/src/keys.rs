@@ -0,0 +1,31 @@
+use spl_associated_token_account::get_associated_token_address;
+use solana_program::{pubkey::Pubkey, declare_id};
+// keys that are shared by both the game and the token launch
+
+mod daoplays {
+    use super::*;
+    declare_id!("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD");   
+}
+
+mod token_mint {
+    use super::*;
+    declare_id!("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki");   
+}
+
+pub fn get_expected_daoplays_key() -> Pubkey
+{
+    daoplays::ID
+}
+
+pub fn get_expected_token_mint_key() -> Pubkey
+{
+    token_mint::ID
+}
+
+pub fn get_expected_daoplays_token_key() -> Pubkey
+{
+    get_associated_token_address(
+        &get_expected_daoplays_key(), 
+        &get_expected_token_mint_key()
+    )
+}
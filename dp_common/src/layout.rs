@@ -0,0 +1,41 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+
+// accounts that are read in place start with a LayoutHeader, which names the type of account and the version of
+// the layout that follows it, so a program can tell an account it can read from one that has to be migrated first.
+// accounts created before the header was added are layout version 0, and are recognised by their size instead
+pub const LEGACY_LAYOUT_VERSION : u8 = 0;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct LayoutHeader {
+    pub discriminator : [u8; 8],
+    pub layout_version : u8
+}
+
+impl LayoutHeader {
+    pub fn new(discriminator : [u8; 8], layout_version : u8) -> LayoutHeader {
+        LayoutHeader {discriminator, layout_version}
+    }
+}
+
+pub fn get_layout_header_size() -> usize {
+    std::mem::size_of::<LayoutHeader>()
+}
+
+// the layout version of the account data, or None if it has never been set up. data without the discriminator is
+// taken to be in the legacy layout if it is at least legacy_size bytes long
+pub fn get_layout_version(data : &[u8], discriminator : &[u8; 8], legacy_size : usize) -> Option<u8> {
+    if let Some(bytes) = data.get(..get_layout_header_size()) {
+        let header : &LayoutHeader = bytemuck::from_bytes(bytes);
+        if &header.discriminator == discriminator {
+            return Some(header.layout_version);
+        }
+    }
+
+    if data.len() >= legacy_size {
+        return Some(LEGACY_LAYOUT_VERSION);
+    }
+
+    None
+}
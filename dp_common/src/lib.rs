@@ -0,0 +1,10 @@
+// types and key derivations shared between the on-chain programs and their clients, so that the
+// account layouts and instruction encodings are only ever defined in one place
+pub mod keys;
+pub mod pod;
+pub mod layout;
+pub mod charity;
+pub mod game;
+pub mod token_launch;
+#[cfg(feature = "mock-pyth")]
+pub mod mock_pyth;
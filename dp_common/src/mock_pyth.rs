@@ -0,0 +1,98 @@
+use pyth_sdk_solana::state::{AccountType, PriceAccount, PriceStatus, MAGIC, VERSION_2};
+use solana_program::{pubkey::Pubkey, declare_id};
+
+// pyth price accounts with chosen values, so that winners can be selected on a local validator or in program-test
+// where the mainnet oracles don't exist. the game reads its oracles from the config, so pointing the config at
+// these accounts is all that is needed
+
+// the owner given to the mock accounts, which is the pyth program on mainnet
+mod pyth_program {
+    use super::*;
+    declare_id!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+}
+
+// fixed addresses for the mock oracles on a local validator
+mod mock_btc_oracle {
+    use super::*;
+    declare_id!("EwWSDYw2G1b8RHZ3FedNW1TgRNYcJbpQpiALSgEHdDVW");
+}
+mod mock_eth_oracle {
+    use super::*;
+    declare_id!("BTsyvhP6JyA5iAyE17kaXWkBdGQsRgAAtXZaMirma34B");
+}
+mod mock_sol_oracle {
+    use super::*;
+    declare_id!("A8sSoTyNFthuQhLPCPVwh66YjvHLCYz19bdfbNj6Ezyw");
+}
+
+pub fn get_pyth_program_key() -> Pubkey
+{
+    pyth_program::ID
+}
+
+pub fn get_mock_btc_key() -> Pubkey
+{
+    mock_btc_oracle::ID
+}
+
+pub fn get_mock_eth_key() -> Pubkey
+{
+    mock_eth_oracle::ID
+}
+
+pub fn get_mock_sol_key() -> Pubkey
+{
+    mock_sol_oracle::ID
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MockPrice {
+    // the aggregate price is (price +/- conf) x 10^expo
+    pub price : i64,
+    pub conf : u64,
+    pub expo : i32,
+    pub status : PriceStatus,
+    // on chain pyth reports a trading price as unknown once it is more than 25 slots behind the clock,
+    // so the default of u64::MAX means the price never goes stale
+    pub pub_slot : u64
+}
+
+impl MockPrice {
+    pub fn trading(price : i64, conf : u64, expo : i32) -> MockPrice {
+        MockPrice {
+            price,
+            conf,
+            expo,
+            status : PriceStatus::Trading,
+            pub_slot : u64::MAX
+        }
+    }
+
+    // the account data of a pyth price account with this aggregate price
+    pub fn pack(&self) -> Vec<u8> {
+        let mut price_account = PriceAccount {
+            magic : MAGIC,
+            ver : VERSION_2,
+            atype : AccountType::Price as u32,
+            size : std::mem::size_of::<PriceAccount>() as u32,
+            expo : self.expo,
+            ..PriceAccount::default()
+        };
+
+        price_account.agg.price = self.price;
+        price_account.agg.conf = self.conf;
+        price_account.agg.status = self.status;
+        price_account.agg.pub_slot = self.pub_slot;
+
+        bytemuck::bytes_of(&price_account).to_vec()
+    }
+}
+
+// prices in the range of the real feeds, for the btc, eth and sol oracles in that order
+pub fn get_default_mock_prices() -> [MockPrice; 3] {
+    [
+        MockPrice::trading(2_000_000_000_000, 1_000_000_000, -8),
+        MockPrice::trading(150_000_000_000, 100_000_000, -8),
+        MockPrice::trading(4_000_000_000, 2_000_000, -8)
+    ]
+}
@@ -0,0 +1,56 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use std::fmt;
+
+// account data is only guaranteed to be byte aligned, so integers that live inside zero-copy layouts
+// are stored as little-endian byte arrays. this is the same encoding Borsh uses, so structs built from
+// these types have identical Borsh and in-memory representations
+macro_rules! pod_int {
+    ($name:ident, $int:ty, $size:expr) => {
+        #[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+        #[repr(transparent)]
+        pub struct $name([u8; $size]);
+
+        impl $name {
+            pub const fn new(value : $int) -> Self {
+                Self(value.to_le_bytes())
+            }
+
+            pub fn get(&self) -> $int {
+                <$int>::from_le_bytes(self.0)
+            }
+
+            pub fn set(&mut self, value : $int) {
+                self.0 = value.to_le_bytes();
+            }
+        }
+
+        impl From<$int> for $name {
+            fn from(value : $int) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for $int {
+            fn from(value : $name) -> Self {
+                value.get()
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Debug::fmt(&self.get(), f)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.get(), f)
+            }
+        }
+    };
+}
+
+pod_int!(PodU16, u16, 2);
+pod_int!(PodU64, u64, 8);
+pod_int!(PodI64, i64, 8);
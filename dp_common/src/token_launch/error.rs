@@ -0,0 +1,28 @@
+use thiserror::Error;
+use solana_program::program_error::ProgramError;
+
+
+#[derive(Error, Debug, Copy, Clone)]
+pub enum RNGError {
+    /// Invalid instruction
+    #[error("Invalid Instruction")]
+    InvalidInstruction,
+
+    #[error("Invalid charity")]
+    InvalidCharity,
+
+    #[error("Arithmetic overflow")]
+    ArithmeticOverflow,
+
+    #[error("Launch account layout version is not supported, older accounts can be upgraded with MigrateState")]
+    UnsupportedStateLayout,
+
+    #[error("Account is not the program's charity registry")]
+    InvalidCharityRegistry,
+}
+
+impl From<RNGError> for ProgramError {
+    fn from(e: RNGError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+pub mod accounts;
+pub mod error;
+pub mod instruction;
+pub mod state;
+
+// the address of the token launch program on mainnet
+solana_program::declare_id!("GwsxvpsHURySgnLrkMcnYuSH2Sbd4v9eZwB5ruiVxgjE");
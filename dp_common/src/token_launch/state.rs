@@ -0,0 +1,91 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, msg};
+use crate::charity::{CharityData, get_charity_size};
+use crate::layout::{LayoutHeader, LEGACY_LAYOUT_VERSION, get_layout_header_size, get_layout_version};
+use crate::token_launch::error::RNGError;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct JoinMeta {
+    // the amount in lamports that will be donated to charity
+    pub amount_charity : u64,
+    // the amount in lamports being paid to the developers
+    pub amount_dao : u64,
+    // the index of the chosen charity in the charity registry
+    pub charity : u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InitMeta {
+    // the amount of DPTTs to be sent to the program
+    pub amount : u64,
+    // the amount of supporter tokens to be send to the program
+    pub supporter_amount : u64
+}
+
+// on chain data that saves summary stats of the token launch, which has the same layout as the game's charity data
+pub type TokenLaunchData = CharityData;
+
+// the launch account starts with a LayoutHeader holding these. accounts created before it was added hold just
+// the TokenLaunchData, and have to be upgraded with MigrateState before they can be used
+pub const LAUNCH_DISCRIMINATOR : [u8; 8] = *b"dplaunch";
+pub const LAUNCH_LAYOUT_VERSION : u8 = 1;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct TokenLaunchState {
+    pub layout : LayoutHeader,
+    pub launch_data : TokenLaunchData
+}
+
+impl TokenLaunchState {
+    pub fn new(launch_data : TokenLaunchData) -> TokenLaunchState {
+        TokenLaunchState {layout : LayoutHeader::new(LAUNCH_DISCRIMINATOR, LAUNCH_LAYOUT_VERSION), launch_data}
+    }
+}
+
+// the layout version of the launch account, or None if it is too small to hold either layout
+pub fn get_launch_layout_version(data : &[u8]) -> Option<u8> {
+    get_layout_version(data, &LAUNCH_DISCRIMINATOR, get_legacy_state_size())
+}
+
+// read the launch data from an account in the current layout
+pub fn load_launch_data(data : &[u8]) -> Result<TokenLaunchData, ProgramError> {
+    if get_launch_layout_version(data) != Some(LAUNCH_LAYOUT_VERSION) {
+        msg!("the launch account does not have layout version {}, older accounts can be upgraded with MigrateState", LAUNCH_LAYOUT_VERSION);
+        return Err(RNGError::UnsupportedStateLayout.into());
+    }
+
+    let state = TokenLaunchState::try_from_slice(data.get(..get_state_size()).ok_or(ProgramError::AccountDataTooSmall)?)?;
+    Ok(state.launch_data)
+}
+
+pub fn store_launch_data(launch_data : &TokenLaunchData, data : &mut [u8]) -> Result<(), ProgramError> {
+    TokenLaunchState::new(*launch_data).serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+// rewrite a launch account in the legacy layout in the current one. the account must already have been
+// reallocated to at least get_state_size()
+pub fn migrate_legacy_launch_data(data : &mut [u8]) -> Result<(), ProgramError> {
+    if get_launch_layout_version(data) != Some(LEGACY_LAYOUT_VERSION) {
+        msg!("the launch account is not in the legacy layout");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if data.len() < get_state_size() {
+        msg!("launch account is too small to be migrated {} < {}", data.len(), get_state_size());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let launch_data = TokenLaunchData::try_from_slice(&data[..get_legacy_state_size()])?;
+    store_launch_data(&launch_data, data)
+}
+
+// helper function to return the size of the TokenLaunchState so we can check the lamports required to be rent-exempt
+pub fn get_state_size() -> usize {
+    get_layout_header_size() + get_charity_size()
+}
+
+// the size of a launch account created before the layout header was added
+pub fn get_legacy_state_size() -> usize {
+    get_charity_size()
+}
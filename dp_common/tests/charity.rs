@@ -0,0 +1,103 @@
+use bytemuck::Zeroable;
+use dp_common::charity::{
+    CharityData, CharityRegistry, DEFAULT_CHARITIES, MAX_CHARITIES, MAX_CHARITY_NAME_LEN, N_CHARITIES,
+    get_charity_registry_size,
+};
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+
+fn default_registry() -> CharityRegistry {
+    let mut registry = CharityRegistry::zeroed();
+    for (index, (name, address)) in DEFAULT_CHARITIES.iter().enumerate() {
+        let added = registry.add_charity(name, Pubkey::from_str(address).unwrap());
+        assert_eq!(added, Some(index as u8));
+    }
+
+    registry
+}
+
+#[test]
+fn default_charities_keep_their_indices() {
+    let registry = default_registry();
+    assert_eq!(registry.n_charities as usize, N_CHARITIES);
+
+    let water_org = registry.get_charity(6).unwrap();
+    assert_eq!(water_org.get_name(), "WaterOrg");
+    assert_eq!(water_org.address, Pubkey::from_str("5UNSVwtiSdfsCbJokL4fHtzV28mVNi8fQkMjPQw6v7Xd").unwrap());
+    assert!(water_org.is_active());
+
+    assert!(registry.get_charity(N_CHARITIES as u8).is_none());
+}
+
+#[test]
+fn add_charity_limits() {
+    let mut registry = default_registry();
+
+    assert!(registry.add_charity("", Pubkey::new_unique()).is_none());
+    assert!(registry.add_charity(&"x".repeat(MAX_CHARITY_NAME_LEN + 1), Pubkey::new_unique()).is_none());
+    assert_eq!(registry.add_charity(&"x".repeat(MAX_CHARITY_NAME_LEN), Pubkey::new_unique()), Some(N_CHARITIES as u8));
+
+    while (registry.n_charities as usize) < MAX_CHARITIES {
+        assert!(registry.add_charity("Charity", Pubkey::new_unique()).is_some());
+    }
+    assert!(registry.add_charity("OneTooMany", Pubkey::new_unique()).is_none());
+}
+
+#[test]
+fn retired_charities_are_not_active() {
+    let mut registry = default_registry();
+
+    registry.get_charity_mut(2).unwrap().active = 0;
+    assert!(registry.get_active_charity_mut(2).is_none());
+    assert!(registry.get_active_charity_mut(3).is_some());
+    assert!(registry.get_active_charity_mut(N_CHARITIES as u8).is_none());
+}
+
+#[test]
+fn registry_loads_in_place() {
+    let mut data = vec![0u8; get_charity_registry_size()];
+
+    let registry = CharityRegistry::load_mut(&mut data).unwrap();
+    let index = registry.add_charity("GiveDirectly", Pubkey::new_unique()).unwrap();
+    registry.get_active_charity_mut(index).unwrap().total.set(500);
+
+    let registry = CharityRegistry::load(&data).unwrap();
+    assert_eq!(registry.n_charities, 1);
+    assert_eq!(registry.get_charity(0).unwrap().total.get(), 500);
+
+    assert!(CharityRegistry::load(&data[1..]).is_err());
+}
+
+#[test]
+fn legacy_totals_only_track_original_charities() {
+    let mut charity_data = CharityData::zeroed();
+    charity_data.record_donation(6, 10, 15).unwrap();
+    charity_data.record_donation(N_CHARITIES as u8, 20, 25).unwrap();
+
+    assert_eq!(charity_data.charity_totals[6].get(), 10);
+    assert_eq!(charity_data.donated_total.get(), 30);
+    assert_eq!(charity_data.paid_total.get(), 40);
+    assert_eq!(charity_data.n_donations.get(), 2);
+}
+
+#[test]
+fn donation_totals_at_u64_boundaries() {
+    let mut charity_data = CharityData::zeroed();
+    charity_data.record_donation(0, u64::MAX - 1, u64::MAX - 1).unwrap();
+    charity_data.record_donation(0, 1, 1).unwrap();
+
+    // any total passing u64::MAX rejects the whole donation
+    let before = charity_data;
+    assert_eq!(charity_data.record_donation(1, 1, 1), None);
+    assert_eq!(charity_data, before);
+
+    charity_data = CharityData::zeroed();
+    charity_data.n_donations.set(u64::MAX);
+    assert_eq!(charity_data.record_donation(0, 0, 0), None);
+
+    let mut registry = default_registry();
+    let charity = registry.get_charity_mut(0).unwrap();
+    assert_eq!(charity.add_donation(u64::MAX), Some(u64::MAX));
+    assert_eq!(charity.add_donation(1), None);
+    assert_eq!(charity.total.get(), u64::MAX);
+}
@@ -0,0 +1,270 @@
+use dp_common::game::bid_index::{BidIndex, NO_BID, initialize_ladder, load_ladder_mut, migrate_legacy_ladder, resize_ladder, get_data_account_size, get_bid_index_size};
+use dp_common::game::error::DaoPlaysError;
+use dp_common::game::state::{Capacity, LegacyState, State, LEGACY_CAPACITY, LEGACY_MAX_BIDDERS, STATE_LAYOUT_VERSION, get_state_layout_version, get_state_size};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+const N_SLOTS : usize = 1024;
+
+fn get_ladder_data(bids : usize) -> Vec<u8> {
+    let capacity = Capacity {bids, winners : 4};
+    let mut data = vec![0u8; get_data_account_size(&capacity)];
+    initialize_ladder(&mut data, &capacity).unwrap();
+    data
+}
+
+fn get_age_order(bid_index : &BidIndex) -> Vec<usize> {
+    let mut order : Vec<usize> = Vec::new();
+    let mut current = bid_index.get_oldest();
+    while let Some(index) = current {
+        order.push(index);
+        let newer = bid_index.newer[index].get();
+        current = (newer != NO_BID).then_some(newer as usize);
+    }
+
+    order
+}
+
+// the index as built from scratch over state
+fn get_rebuilt_tree(state : &State) -> Vec<u64> {
+    let mut data = get_ladder_data(state.bid_amounts.len());
+    let (copy, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    copy.bid_amounts.copy_from_slice(state.bid_amounts);
+    copy.bid_times.copy_from_slice(state.bid_times);
+    bid_index.rebuild(&copy);
+    bid_index.amount_tree.iter().map(|node| node.get()).collect()
+}
+
+#[test]
+fn data_account_holds_state_then_index() {
+    let capacity = Capacity {bids : N_SLOTS, winners : 4};
+    assert_eq!(get_data_account_size(&capacity), get_state_size(&capacity) + get_bid_index_size(&capacity));
+    assert_eq!(get_bid_index_size(&capacity), 7 + N_SLOTS * 14);
+
+    // an account that hasn't been set up has no capacity
+    let mut data = vec![0u8; get_data_account_size(&capacity)];
+    assert_eq!(load_ladder_mut(&mut data).unwrap_err(), ProgramError::UninitializedAccount);
+
+    initialize_ladder(&mut data, &capacity).unwrap();
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.get_capacity(), capacity);
+    assert_eq!(state.n_bidders.get(), 0);
+    assert_eq!(bid_index.n_free.get() as usize, N_SLOTS);
+    assert_eq!(bid_index.get_oldest(), None);
+
+    // the account has to have room for the index as well
+    let mut short = vec![0u8; get_data_account_size(&capacity) - 1];
+    assert_eq!(initialize_ladder(&mut short, &capacity).unwrap_err(), ProgramError::AccountDataTooSmall);
+    assert_eq!(initialize_ladder(&mut data, &Capacity {bids : 0, winners : 1}).unwrap_err(), ProgramError::InvalidArgument);
+}
+
+#[test]
+fn rebuild_matches_ladder() {
+    let mut data = get_ladder_data(N_SLOTS);
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    state.insert_bid(5, Pubkey::new_unique(), 50, 300).unwrap();
+    state.insert_bid(0, Pubkey::new_unique(), 10, 200).unwrap();
+    state.insert_bid(9, Pubkey::new_unique(), 90, 200).unwrap();
+
+    bid_index.rebuild(&state);
+
+    assert_eq!(bid_index.get_total(), 150);
+    assert_eq!(bid_index.get_prefix_total(4), 10);
+    assert_eq!(bid_index.get_prefix_total(5), 60);
+    assert_eq!(bid_index.get_bid(9), 90);
+
+    // oldest first, with ties in the order of the ladder
+    assert_eq!(get_age_order(&bid_index), vec![0, 9, 5]);
+    assert_eq!(bid_index.n_free.get() as usize, N_SLOTS - 3);
+
+    assert_eq!(bid_index.get_bid_totals(&state, 300), state.get_bid_totals(300));
+    assert_eq!(bid_index.get_bid_totals(&state, 201), state.get_bid_totals(201));
+    assert_eq!(bid_index.get_bids_since(&state, 200), vec![5, 9, 0]);
+}
+
+#[test]
+fn find_matches_running_total() {
+    // a capacity that isn't a power of two
+    for n_slots in [N_SLOTS, 1000] {
+        let mut data = get_ladder_data(n_slots);
+        let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+        for index in (0..n_slots).step_by(5) {
+            state.insert_bid(index, Pubkey::new_unique(), index as u64 + 1, 1).unwrap();
+        }
+
+        bid_index.rebuild(&state);
+        let total = bid_index.get_total();
+
+        for target in (0..total).step_by(97).chain([total - 1]) {
+            let mut running_total = 0;
+            let expected = state.bid_amounts.iter().position(|amount| {
+                running_total += amount.get();
+                running_total > target
+            });
+            assert_eq!(bid_index.find(target), expected);
+        }
+
+        assert_eq!(bid_index.find(total), None);
+    }
+}
+
+#[test]
+fn free_slots_and_age_follow_the_ladder() {
+    let mut data = get_ladder_data(N_SLOTS);
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+
+    // empty slots are used lowest first
+    for time in 1..=3 {
+        let index = bid_index.take_free_slot().unwrap();
+        assert_eq!(index, time as usize - 1);
+
+        state.insert_bid(index, Pubkey::new_unique(), 10, time).unwrap();
+        bid_index.insert_bid(index, 10).unwrap();
+    }
+    assert_eq!(get_age_order(&bid_index), vec![0, 1, 2]);
+
+    // adding to a bid makes it the newest
+    state.add_to_bid(0, 5, 4).unwrap();
+    bid_index.add_to_bid(0, 5).unwrap();
+    assert_eq!(get_age_order(&bid_index), vec![1, 2, 0]);
+    assert_eq!(bid_index.get_oldest(), Some(1));
+
+    // removing a bid frees its slot for the next bid
+    state.remove_bid(2).unwrap();
+    bid_index.remove_bid(2, 10).unwrap();
+    assert_eq!(get_age_order(&bid_index), vec![1, 0]);
+    assert_eq!(bid_index.take_free_slot(), Some(2));
+
+    // the index can't remove more than was bid
+    assert_eq!(bid_index.remove_bid(1, 11), None);
+
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+
+    // and it agrees with an index rebuilt from the ladder
+    let tree : Vec<u64> = bid_index.amount_tree.iter().map(|node| node.get()).collect();
+    assert_eq!(get_rebuilt_tree(&state), tree);
+    let age_order = get_age_order(&bid_index);
+    bid_index.rebuild(&state);
+    assert_eq!(get_age_order(&bid_index), age_order);
+}
+
+#[test]
+fn full_ladder_has_no_free_slots() {
+    let mut data = get_ladder_data(N_SLOTS);
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    for index in 0..N_SLOTS {
+        state.insert_bid(index, Pubkey::new_unique(), 1, index as i64 + 1).unwrap();
+    }
+
+    bid_index.rebuild(&state);
+    assert_eq!(bid_index.take_free_slot(), None);
+    assert_eq!(bid_index.get_oldest(), Some(0));
+}
+
+#[test]
+fn resize_keeps_bids_and_index() {
+    let old_capacity = Capacity {bids : 100, winners : 2};
+    let new_capacity = Capacity {bids : 300, winners : 5};
+
+    let mut data = vec![0u8; get_data_account_size(&old_capacity)];
+    initialize_ladder(&mut data, &old_capacity).unwrap();
+
+    let mut keys : Vec<Pubkey> = Vec::new();
+    {
+        let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+        for time in 1..=90 {
+            let index = bid_index.take_free_slot().unwrap();
+            let key = Pubkey::new_unique();
+            state.insert_bid(index, key, time as u64 * 3, time).unwrap();
+            bid_index.insert_bid(index, time as u64 * 3).unwrap();
+            keys.push(key);
+        }
+        state.prev_choose_winners_time.set(1234);
+        state.winners[1] = keys[0];
+    }
+
+    // the account has to be grown first, and can't be shrunk
+    assert_eq!(resize_ladder(&mut data, &new_capacity).unwrap_err(), ProgramError::AccountDataTooSmall);
+    data.resize(get_data_account_size(&new_capacity), 0);
+    assert_eq!(resize_ladder(&mut data, &Capacity {bids : 99, winners : 5}).unwrap_err(), ProgramError::InvalidArgument);
+    assert_eq!(resize_ladder(&mut data, &Capacity {bids : 300, winners : 1}).unwrap_err(), ProgramError::InvalidArgument);
+
+    resize_ladder(&mut data, &new_capacity).unwrap();
+
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.get_capacity(), new_capacity);
+    assert_eq!(state.prev_choose_winners_time.get(), 1234);
+    assert_eq!(state.winners[..2], [Pubkey::default(), keys[0]]);
+    assert!(state.winners[2..].iter().all(|winner| *winner == Pubkey::default()));
+
+    for (index, key) in keys.iter().enumerate() {
+        assert_eq!(state.bid_keys[index], *key);
+        assert_eq!(state.bid_amounts[index].get(), (index as u64 + 1) * 3);
+        assert_eq!(state.bid_times[index].get(), index as i64 + 1);
+    }
+    assert!(state.bid_amounts[90..].iter().all(|amount| amount.get() == 0));
+
+    // the index matches one rebuilt from the resized ladder
+    let tree : Vec<u64> = bid_index.amount_tree.iter().map(|node| node.get()).collect();
+    assert_eq!(get_rebuilt_tree(&state), tree);
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+    assert_eq!(get_age_order(&bid_index), (0..90).collect::<Vec<usize>>());
+    assert_eq!(bid_index.n_free.get(), 210);
+
+    // and every empty slot can still be used
+    let mut free : Vec<usize> = (0..210).map(|_| bid_index.take_free_slot().unwrap()).collect();
+    free.sort();
+    assert_eq!(free, (90..300).collect::<Vec<usize>>());
+    assert_eq!(bid_index.take_free_slot(), None);
+
+    let index = free[0];
+    state.insert_bid(index, Pubkey::new_unique(), 7, 100).unwrap();
+    bid_index.insert_bid(index, 7).unwrap();
+    assert_eq!(bid_index.find(bid_index.get_total() - 1), Some(index.max(89)));
+}
+
+// a legacy data account captured from the v0 program (the game_app/program of the first commit) running in
+// program-test: CreateDataAccount, eight bids with one of them topped up, a draw whose winner was sent their tokens
+// with SendTokens, three more bids, one of which took the winner's place, and a draw whose winner is still waiting
+fn get_legacy_dump() -> Vec<u8> {
+    include_bytes!("fixtures/legacy_state_v0.bin").to_vec()
+}
+
+#[test]
+fn migrate_keeps_legacy_ladder() {
+    let mut data = get_legacy_dump();
+    let legacy = *LegacyState::load(&data).unwrap();
+    assert_eq!((legacy.n_bidders.get(), legacy.n_winners), (10, 1));
+
+    // the legacy layout can't be used until it has been migrated, and the account has to be grown first
+    assert_eq!(load_ladder_mut(&mut data).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedStateLayout));
+    assert_eq!(initialize_ladder(&mut data, &LEGACY_CAPACITY).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedStateLayout));
+    assert_eq!(migrate_legacy_ladder(&mut data).unwrap_err(), ProgramError::AccountDataTooSmall);
+
+    // realloc leaves whatever was there before at the end of the account zeroed
+    data.resize(get_data_account_size(&LEGACY_CAPACITY), 0);
+    migrate_legacy_ladder(&mut data).unwrap();
+    assert_eq!(get_state_layout_version(&data), Some(STATE_LAYOUT_VERSION));
+    assert_eq!(migrate_legacy_ladder(&mut data).unwrap_err(), ProgramError::InvalidAccountData);
+
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.get_capacity(), LEGACY_CAPACITY);
+    assert_eq!(state.prev_choose_winners_time, legacy.prev_choose_winners_time);
+    assert_eq!(state.n_bidders, legacy.n_bidders);
+    assert_eq!(state.total_bid_amount, legacy.total_bid_amount);
+    assert_eq!(state.bid_keys, &legacy.bid_keys[..]);
+    assert_eq!(state.bid_amounts, &legacy.bid_amounts[..]);
+    assert_eq!(state.bid_times, &legacy.bid_times[..]);
+    assert_eq!(state.n_winners, legacy.n_winners);
+    assert_eq!(state.winners, &legacy.winners[..]);
+    assert_eq!(state.charity_data, legacy.charity_data);
+
+    // the index is built from the migrated ladder
+    assert!(bid_index.is_initialized());
+    assert_eq!(bid_index.get_total(), legacy.total_bid_amount.get());
+    assert_eq!(bid_index.n_free.get() as usize + state.n_bidders.get() as usize, LEGACY_MAX_BIDDERS);
+    assert_eq!(get_rebuilt_tree(&state), bid_index.amount_tree.iter().map(|node| node.get()).collect::<Vec<u64>>());
+
+    let mut by_age : Vec<usize> = (0..LEGACY_MAX_BIDDERS).filter(|index| legacy.bid_times[*index].get() != 0).collect();
+    by_age.sort_by_key(|index| (legacy.bid_times[*index].get(), *index));
+    assert_eq!(get_age_order(&bid_index), by_age);
+}
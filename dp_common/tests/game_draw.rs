@@ -0,0 +1,236 @@
+use dp_common::game::bid_index::{initialize_ladder, load_ladder_mut, get_data_account_size};
+use bytemuck::Zeroable;
+use dp_common::game::draw::{draw_winners, draw_legacy_winners, LEGACY_BID_BLOCK};
+use dp_common::game::randomness::{get_draw_randoms, get_oracle_seed, OraclePrice};
+use dp_common::game::state::{Capacity, LegacyState, State};
+use solana_program::pubkey::Pubkey;
+
+const N_WINNERS : u8 = 4;
+
+// a ladder with a bid of (index % 7 + 1) lamports at each index, made at time index + 1, with every third slot empty
+fn get_ladder(n_slots : usize) -> Vec<u8> {
+    let capacity = Capacity {bids : n_slots, winners : N_WINNERS as usize};
+    let mut data = vec![0u8; get_data_account_size(&capacity)];
+    initialize_ladder(&mut data, &capacity).unwrap();
+
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    for index in (0..n_slots).filter(|index| index % 3 != 2) {
+        state.insert_bid(index, Pubkey::new_unique(), index as u64 % 7 + 1, index as i64 + 1).unwrap();
+    }
+    bid_index.rebuild(&state);
+
+    data
+}
+
+// the draw as a linear scan of the ladder, for comparison
+fn get_expected_winners(state : &State, n_winners : u8, threshold_time : i64, seed : u64) -> Vec<usize> {
+    let amounts : Vec<u64> = state.bid_amounts.iter().zip(state.bid_times.iter())
+        .map(|(amount, time)| if time.get() < threshold_time { amount.get() } else { 0 })
+        .collect();
+
+    scan_for_winners(amounts, n_winners, seed)
+}
+
+fn scan_for_winners(mut amounts : Vec<u64>, n_winners : u8, seed : u64) -> Vec<usize> {
+    let mut winners : Vec<usize> = Vec::new();
+    for random in get_draw_randoms(seed, n_winners) {
+        let total : u64 = amounts.iter().sum();
+        if total == 0 {
+            break;
+        }
+
+        let threshold = ((total as f64 * random) as u64).min(total - 1);
+        let mut running_total = 0;
+        let winner = amounts.iter().position(|amount| {
+            running_total += amount;
+            running_total > threshold
+        }).unwrap();
+
+        amounts[winner] = 0;
+        winners.push(winner);
+    }
+
+    winners
+}
+
+fn get_prices() -> [Option<OraclePrice>; 3] {
+    [
+        Some(OraclePrice {price : 2_000_000_000_000, conf : 1_000_000_000, expo : -8}),
+        Some(OraclePrice {price : 150_000_000_000, conf : 100_000_000, expo : -8}),
+        Some(OraclePrice {price : 4_000_000_000, conf : 2_000_000, expo : -8})
+    ]
+}
+
+#[test]
+fn oracle_seed_skips_missing_prices() {
+    let prices = get_prices();
+    let seed = get_oracle_seed(&prices).unwrap();
+    assert_eq!(get_oracle_seed(&prices), Some(seed));
+
+    let mut one_missing = prices;
+    one_missing[1] = None;
+    assert_ne!(get_oracle_seed(&one_missing).unwrap(), seed);
+
+    assert_eq!(get_oracle_seed(&[None; 3]), None);
+}
+
+#[test]
+fn draw_randoms_are_sorted_and_in_range() {
+    let randoms = get_draw_randoms(12345, 4);
+    assert_eq!(randoms.len(), 4);
+    assert!(randoms.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(randoms.iter().all(|random| (0.0..1.0).contains(random)));
+}
+
+#[test]
+fn single_bid_always_wins() {
+    for seed in [0, 1, u64::MAX] {
+        let mut data = get_ladder(1);
+        let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+        let winners = draw_winners(&mut state, &mut bid_index, 1, 10, seed).unwrap();
+
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0].index, 0);
+        assert_eq!(winners[0].bid_amount, 1);
+        assert_eq!(winners[0].total_bid, 1);
+    }
+}
+
+#[test]
+fn draw_matches_a_scan_of_the_ladder() {
+    let mut ladder = get_ladder(300);
+    let threshold_time = 200;
+
+    for seed in 0..50 {
+        let mut data = ladder.clone();
+        let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+        let winners = draw_winners(&mut state, &mut bid_index, N_WINNERS, threshold_time, seed).unwrap();
+
+        let winner_indices : Vec<usize> = winners.iter().map(|winner| winner.index).collect();
+        let (ladder_state, _) = load_ladder_mut(&mut ladder).unwrap();
+        assert_eq!(winner_indices, get_expected_winners(&ladder_state, N_WINNERS, threshold_time, seed));
+    }
+}
+
+#[test]
+fn draw_can_only_fill_the_winner_capacity() {
+    let mut data = get_ladder(300);
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(draw_winners(&mut state, &mut bid_index, N_WINNERS + 2, 200, 42).unwrap().len(), N_WINNERS as usize);
+}
+
+#[test]
+fn draw_is_reproducible_from_the_ladder() {
+    let mut ladder = get_ladder(300);
+    let threshold_time = 200;
+
+    let mut data = ladder.clone();
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).unwrap();
+    let winners = draw_winners(&mut state, &mut bid_index, 4, threshold_time, 987654321).unwrap();
+    assert_eq!(winners.len(), 4);
+
+    // a copy of the ladder and an index rebuilt from it give the same draw
+    let mut replay = ladder.clone();
+    {
+        let (mut replay_state, mut replay_index) = load_ladder_mut(&mut replay).unwrap();
+        replay_index.rebuild(&replay_state);
+        assert_eq!(draw_winners(&mut replay_state, &mut replay_index, 4, threshold_time, 987654321).unwrap(), winners);
+    }
+
+    let mut other = ladder.clone();
+    {
+        let (mut other_state, mut other_index) = load_ladder_mut(&mut other).unwrap();
+        assert_ne!(draw_winners(&mut other_state, &mut other_index, 4, threshold_time, 123456789).unwrap(), winners);
+    }
+
+    let (ladder_state, ladder_index) = load_ladder_mut(&mut ladder).unwrap();
+    let (_, total_bid) = ladder_state.get_bid_totals(threshold_time).unwrap();
+    let mut expected_total = total_bid;
+    for (current_winner, winner) in winners.iter().enumerate() {
+        // only bids made before the threshold can win, and each winner is removed from the ladder
+        assert!(ladder_state.bid_times[winner.index].get() < threshold_time);
+        assert_eq!(winner.winner_key, ladder_state.bid_keys[winner.index]);
+        assert_eq!(winner.bid_amount, ladder_state.bid_amounts[winner.index].get());
+        assert_eq!(state.winners[current_winner], winner.winner_key);
+        assert_eq!(state.bid_amounts[winner.index].get(), 0);
+
+        // the total in the draw goes down by each winning bid
+        assert_eq!(winner.total_bid, expected_total);
+        expected_total -= winner.bid_amount;
+    }
+
+    // the bids that were too recent are back in the index, and the winners' slots are free
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+    assert_eq!(bid_index.n_free.get(), ladder_index.n_free.get() + 4);
+    assert_eq!(state.n_bidders.get(), ladder_state.n_bidders.get() - 4);
+
+    // and the replay leaves the ladder as the draw did
+    assert_eq!(state.bid_amounts, load_ladder_mut(&mut replay).unwrap().0.bid_amounts);
+}
+
+// a legacy ladder with the same bids as get_ladder in its first n_slots slots
+fn get_legacy_ladder(n_slots : usize) -> Box<LegacyState> {
+    let mut state = Box::new(LegacyState::zeroed());
+    for index in (0..n_slots).filter(|index| index % 3 != 2) {
+        state.bid_keys[index] = Pubkey::new_unique();
+        state.bid_amounts[index].set(index as u64 % 7 + 1);
+        state.bid_times[index].set(index as i64 + 1);
+    }
+
+    state
+}
+
+fn get_legacy_amounts(state : &LegacyState, threshold_time : i64) -> Vec<u64> {
+    state.bid_amounts.iter().zip(state.bid_times.iter())
+        .map(|(amount, time)| if time.get() < threshold_time { amount.get() } else { 0 })
+        .collect()
+}
+
+#[test]
+fn legacy_draw_within_one_block_matches_a_scan_of_the_ladder() {
+    let state = get_legacy_ladder(LEGACY_BID_BLOCK);
+    let threshold_time = 50;
+
+    for seed in 0..50 {
+        let winners = draw_legacy_winners(&state, N_WINNERS, threshold_time, seed);
+
+        let winner_indices : Vec<usize> = winners.iter().map(|winner| winner.index).collect();
+        assert_eq!(winner_indices, scan_for_winners(get_legacy_amounts(&state, threshold_time), N_WINNERS, seed));
+    }
+}
+
+#[test]
+fn legacy_draw_of_one_winner_matches_a_scan_across_blocks() {
+    let state = get_legacy_ladder(300);
+    let threshold_time = 200;
+
+    for seed in 0..50 {
+        let winners = draw_legacy_winners(&state, 1, threshold_time, seed);
+
+        assert_eq!(winners.len(), 1);
+        assert_eq!(vec![winners[0].index], scan_for_winners(get_legacy_amounts(&state, threshold_time), 1, seed));
+        assert_eq!(winners[0].winner_key, state.bid_keys[winners[0].index]);
+    }
+}
+
+#[test]
+fn legacy_draw_can_pick_a_bid_that_has_already_won() {
+    // one bid in each of the first two blocks. when both randoms are past a half, neither winner is in the first
+    // block, so the running total moves on to 10. the first winner takes the bid in the second block, and the second
+    // winner's threshold, from the 10 left, is below the running total, so they land on the same bid again
+    let mut state = Box::new(LegacyState::zeroed());
+    for index in [0, LEGACY_BID_BLOCK] {
+        state.bid_keys[index] = Pubkey::new_unique();
+        state.bid_amounts[index].set(10);
+        state.bid_times[index].set(1);
+    }
+
+    let seed = (0..).find(|seed| get_draw_randoms(*seed, 2)[0] >= 0.5).unwrap();
+    let winners = draw_legacy_winners(&state, 2, 10, seed);
+
+    assert_eq!(winners.len(), 2);
+    assert_eq!((winners[0].index, winners[0].bid_amount, winners[0].total_bid), (LEGACY_BID_BLOCK, 10, 20));
+    assert_eq!(winners[0].winner_key, state.bid_keys[LEGACY_BID_BLOCK]);
+    assert_eq!((winners[1].index, winners[1].bid_amount, winners[1].total_bid), (LEGACY_BID_BLOCK, 0, 10));
+    assert_eq!(winners[1].winner_key, solana_program::system_program::id());
+}
@@ -0,0 +1,35 @@
+use dp_common::game::error::{DaoPlaysError, ALL_ERRORS};
+use solana_program::program_error::ProgramError;
+
+#[test]
+fn error_codes_are_stable() {
+    // clients only see the number, so these must never change
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidInstruction), ProgramError::Custom(0));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidButtonBid), ProgramError::Custom(1));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidCharity), ProgramError::Custom(4));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidAuthority), ProgramError::Custom(8));
+    assert_eq!(ProgramError::from(DaoPlaysError::BidBelowMinimum), ProgramError::Custom(20));
+    assert_eq!(ProgramError::from(DaoPlaysError::StateOutOfSync), ProgramError::Custom(23));
+    assert_eq!(ProgramError::from(DaoPlaysError::ArithmeticOverflow), ProgramError::Custom(24));
+    assert_eq!(ProgramError::from(DaoPlaysError::NoHealthyOracles), ProgramError::Custom(29));
+    assert_eq!(ProgramError::from(DaoPlaysError::SlotHashUnavailable), ProgramError::Custom(39));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidCapacity), ProgramError::Custom(40));
+    assert_eq!(ProgramError::from(DaoPlaysError::UnsupportedStateLayout), ProgramError::Custom(41));
+    assert_eq!(ProgramError::from(DaoPlaysError::NothingToClaim), ProgramError::Custom(42));
+    assert_eq!(ProgramError::from(DaoPlaysError::NothingToCrank), ProgramError::Custom(43));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidVoteTallyAccount), ProgramError::Custom(44));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidInputLogAccount), ProgramError::Custom(46));
+    assert_eq!(ProgramError::from(DaoPlaysError::ModeBallotClosed), ProgramError::Custom(48));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidSequence), ProgramError::Custom(50));
+    assert_eq!(ProgramError::from(DaoPlaysError::InvalidPushStatsAccount), ProgramError::Custom(51));
+}
+
+#[test]
+fn every_code_maps_back_to_its_error() {
+    for (code, error) in ALL_ERRORS.iter().enumerate() {
+        assert_eq!(*error as usize, code);
+        assert_eq!(DaoPlaysError::from_code(code as u32), Some(*error));
+    }
+
+    assert_eq!(DaoPlaysError::from_code(ALL_ERRORS.len() as u32), None);
+}
@@ -0,0 +1,59 @@
+use dp_common::game::events::{GameEvent, BidPlaced, ButtonPressed, Cranked, InputResolved, ModeBallotSettled, ModeVoted, PrizeCredited, SequencePushed, StateMigrated, StateResized, WinnerSelected, EVENT_DISCRIMINATOR, EVENT_VERSION};
+use dp_common::game::instruction::{Button, SequenceStep};
+use dp_common::game::modes::InputMode;
+use solana_program::pubkey::Pubkey;
+
+fn some_events() -> Vec<GameEvent> {
+    vec![
+        GameEvent::ButtonPressed(ButtonPressed { player: Pubkey::new_unique(), button: Button::Start, amount: 5, time: 1_650_000_000 }),
+        GameEvent::BidPlaced(BidPlaced {
+            bidder_key: Pubkey::new_unique(),
+            charity: 6,
+            amount_charity: 90000,
+            amount_dao: 10000,
+            index: 1023,
+            bid_amount: 200000,
+            time: 1_650_000_000,
+        }),
+        GameEvent::WinnerSelected(WinnerSelected { winner_key: Pubkey::new_unique(), index: 3, bid_amount: 100, total_bid: 1000, time: 1_650_000_300 }),
+        GameEvent::StateResized(StateResized { data_account: Pubkey::new_unique(), bid_capacity: 2048, winner_capacity: 8, data_size: 127_000 }),
+        GameEvent::StateMigrated(StateMigrated { data_account: Pubkey::new_unique(), from_version: 0, to_version: 1, data_size: 63_734 }),
+        GameEvent::PrizeCredited(PrizeCredited { winner_key: Pubkey::new_unique(), bidder: Pubkey::new_unique(), amount: 100, claimable: 300 }),
+        GameEvent::Cranked(Cranked { cranker: Pubkey::new_unique(), n_credited: 2, n_selected: 1, tip_lamports: 10000, tip_tokens: 5 }),
+        GameEvent::InputResolved(InputResolved { start_slot: 100, end_slot: 105, button: 2, total: 40, random: 17, complete: true }),
+        GameEvent::ModeVoted(ModeVoted { voter: Pubkey::new_unique(), mode: InputMode::Anarchy, amount: 10, ballot_end_slot: 9000 }),
+        GameEvent::ModeBallotSettled(ModeBallotSettled { previous_mode: InputMode::Lottery, mode: InputMode::Anarchy, start_slot: 9005, n_votes: 3 }),
+        GameEvent::SequencePushed(SequencePushed {
+            player: Pubkey::new_unique(),
+            steps: vec![SequenceStep { button: Button::Down, hold_frames: 32 }, SequenceStep { button: Button::B, hold_frames: 5 }],
+            amount: 4,
+            time: 1_650_000_000,
+        }),
+    ]
+}
+
+#[test]
+fn events_round_trip() {
+    for event in some_events() {
+        let data = event.pack();
+        assert_eq!(data[..8], EVENT_DISCRIMINATOR);
+        assert_eq!(data[8], EVENT_VERSION);
+        assert_eq!(GameEvent::unpack(&data), Some(event));
+    }
+}
+
+#[test]
+fn unpack_ignores_other_data() {
+    let mut data = some_events()[0].pack();
+
+    // a future version of the event
+    data[8] = EVENT_VERSION + 1;
+    assert_eq!(GameEvent::unpack(&data), None);
+
+    // data logged by some other program
+    data[0] = b'x';
+    data[8] = EVENT_VERSION;
+    assert_eq!(GameEvent::unpack(&data), None);
+
+    assert_eq!(GameEvent::unpack(&EVENT_DISCRIMINATOR), None);
+}
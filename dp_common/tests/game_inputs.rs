@@ -0,0 +1,212 @@
+use bytemuck::Zeroable;
+use dp_common::game::inputs::{InputLog, ResolvedInput, INPUT_LOG_SIZE, NO_BUTTON, PRESS_LOG_SIZE, get_input_log_size, get_input_random, pick_button, plurality_button, resolve_button};
+use dp_common::game::instruction::{Button, SequenceStep, DEFAULT_HOLD_FRAMES, N_BUTTONS};
+use dp_common::game::modes::InputMode;
+use dp_common::game::votes::{VoteTally, WindowVotes, VOTE_HISTORY_SLOTS, get_vote_tally_size};
+use dp_common::pod::PodU64;
+use solana_program::program_error::ProgramError;
+
+fn resolved(start_slot : u64, end_slot : u64, button : u8) -> ResolvedInput {
+    ResolvedInput {start_slot : PodU64::new(start_slot), end_slot : PodU64::new(end_slot), button, complete : 1, ..ResolvedInput::zeroed()}
+}
+
+fn press(button : Button) -> Vec<SequenceStep> {
+    vec![SequenceStep {button, hold_frames : DEFAULT_HOLD_FRAMES}]
+}
+
+#[test]
+fn input_log_fits_in_an_account_the_program_can_create() {
+    assert!(get_input_log_size() <= 10240);
+}
+
+#[test]
+fn pick_button_is_weighted_by_tokens() {
+    let mut tallies = [0u64; N_BUTTONS];
+    assert_eq!(pick_button(&tallies, 12345), None);
+
+    tallies[Button::B as usize] = 3;
+    tallies[Button::Start as usize] = 1;
+
+    // random % 4 picks B for 0, 1 and 2, and Start for 3
+    assert_eq!(pick_button(&tallies, 0), Some(Button::B as u8));
+    assert_eq!(pick_button(&tallies, 2), Some(Button::B as u8));
+    assert_eq!(pick_button(&tallies, 3), Some(Button::Start as u8));
+    assert_eq!(pick_button(&tallies, 7), Some(Button::Start as u8));
+
+    tallies[Button::A as usize] = u64::MAX;
+    assert_eq!(pick_button(&tallies, 0), None);
+}
+
+#[test]
+fn each_mode_resolves_its_own_button() {
+    let mut tallies = [0u64; N_BUTTONS];
+    tallies[Button::A as usize] = 4;
+    tallies[Button::B as usize] = 6;
+    tallies[Button::Up as usize] = 6;
+
+    // Up won the window with a single press of 5
+    let votes = WindowVotes {tallies, n_presses : 6, top_bid : 5, top_button : Button::Up as u8, complete : true};
+
+    assert_eq!(resolve_button(InputMode::Lottery, &votes, 3), Button::A as u8);
+    assert_eq!(resolve_button(InputMode::Lottery, &votes, 4), Button::B as u8);
+    assert_eq!(resolve_button(InputMode::Anarchy, &votes, 4), NO_BUTTON);
+    assert_eq!(resolve_button(InputMode::Democracy, &votes, 4), Button::B as u8);
+    assert_eq!(resolve_button(InputMode::Auction, &votes, 4), Button::Up as u8);
+
+    let no_votes = WindowVotes {tallies : [0; N_BUTTONS], n_presses : 0, top_bid : 0, top_button : NO_BUTTON, complete : true};
+    for mode in [InputMode::Lottery, InputMode::Anarchy, InputMode::Democracy, InputMode::Auction] {
+        assert_eq!(resolve_button(mode, &no_votes, 4), NO_BUTTON);
+    }
+
+    assert_eq!(plurality_button(&[0; N_BUTTONS]), None);
+}
+
+#[test]
+fn input_random_depends_on_the_window_and_votes() {
+    let mut tallies = [0u64; N_BUTTONS];
+    tallies[0] = 5;
+
+    let random = get_input_random(100, 105, &tallies);
+    assert_eq!(random, get_input_random(100, 105, &tallies));
+    assert_ne!(random, get_input_random(105, 110, &tallies));
+
+    tallies[1] = 1;
+    assert_ne!(random, get_input_random(100, 105, &tallies));
+}
+
+#[test]
+fn window_votes_count_only_slots_in_the_window() {
+    let mut data = vec![0u8; get_vote_tally_size()];
+    VoteTally::initialize(&mut data).unwrap();
+    let tally = VoteTally::load_mut(&mut data).unwrap();
+
+    tally.record_vote(9, &Button::A, 100).unwrap();
+    tally.record_vote(10, &Button::A, 5).unwrap();
+    tally.record_vote(12, &Button::Up, 2).unwrap();
+    tally.record_vote(12, &Button::A, 1).unwrap();
+    tally.record_vote(15, &Button::B, 100).unwrap();
+
+    let votes = tally.get_window_votes(10, 15).unwrap();
+    assert_eq!(votes.tallies[Button::A as usize], 6);
+    assert_eq!(votes.tallies[Button::Up as usize], 2);
+    assert_eq!(votes.tallies[Button::B as usize], 0);
+    assert_eq!((votes.n_presses, votes.get_total(), votes.complete), (3, Some(8), true));
+
+    assert_eq!(tally.get_next_voted_slot(13), Some(15));
+    assert_eq!(tally.get_next_voted_slot(16), None);
+
+    // once the ring has wrapped, a window older than the oldest slot it holds is incomplete
+    for slot in 100..100 + VOTE_HISTORY_SLOTS as u64 {
+        tally.record_vote(slot, &Button::Down, 1).unwrap();
+    }
+    assert!(!tally.get_window_votes(10, 15).unwrap().complete);
+    assert!(tally.get_window_votes(100, 105).unwrap().complete);
+}
+
+#[test]
+fn input_log_records_an_unbroken_stream() {
+    let mut data = vec![0u8; get_input_log_size()];
+    assert_eq!(InputLog::load(&data).unwrap_err(), ProgramError::UninitializedAccount);
+
+    InputLog::initialize(&mut data, 100).unwrap();
+    let input_log = InputLog::load_mut(&mut data).unwrap();
+    assert_eq!(input_log.next_slot.get(), 100);
+
+    // each window has to start where the last one ended
+    assert_eq!(input_log.record(resolved(101, 105, 0)), None);
+    assert_eq!(input_log.record(resolved(100, 100, 0)), None);
+    input_log.record(resolved(100, 105, 0)).unwrap();
+    input_log.record(resolved(105, 150, 3)).unwrap();
+    assert_eq!(input_log.next_slot.get(), 150);
+
+    assert_eq!(input_log.get_input(104).unwrap().button, 0);
+    assert_eq!(input_log.get_input(120).unwrap().button, 3);
+    assert!(input_log.get_input(150).is_none());
+
+    // the log keeps the most recent windows
+    let mut next_slot = 150;
+    for _ in 0..INPUT_LOG_SIZE {
+        input_log.record(resolved(next_slot, next_slot + 5, 1)).unwrap();
+        next_slot += 5;
+    }
+
+    assert_eq!(input_log.n_resolved.get(), INPUT_LOG_SIZE as u64 + 2);
+    assert_eq!(input_log.iter_recent().count(), INPUT_LOG_SIZE);
+    assert_eq!(input_log.iter_recent().next().unwrap().end_slot.get(), next_slot);
+    assert!(input_log.get_input(120).is_none());
+}
+
+#[test]
+fn windows_take_their_presses_from_the_press_log() {
+    let mut data = vec![0u8; get_input_log_size()];
+    InputLog::initialize(&mut data, 100).unwrap();
+    let input_log = InputLog::load_mut(&mut data).unwrap();
+
+    input_log.log_press(101, &press(Button::Up), 5).unwrap();
+    input_log.log_press(103, &press(Button::A), 1).unwrap();
+    input_log.log_press(105, &press(Button::B), 2).unwrap();
+
+    // a window takes the presses made before it ended that no earlier window took
+    let (first_press, n_presses, complete) = input_log.take_window_presses(105);
+    assert_eq!((first_press, n_presses, complete), (0, 2, true));
+
+    let input = ResolvedInput {first_press : first_press.into(), n_presses : n_presses.into(), ..resolved(100, 105, NO_BUTTON)};
+    input_log.record(input).unwrap();
+
+    let presses : Vec<(u64, u8, u64)> = input_log.get_presses(&input).unwrap().map(|press| (press.slot.get(), press.button, press.amount.get())).collect();
+    assert_eq!(presses, vec![(101, Button::Up as u8, 5), (103, Button::A as u8, 1)]);
+
+    assert_eq!(input_log.take_window_presses(110), (2, 1, true));
+    assert_eq!(input_log.take_window_presses(115), (3, 0, true));
+
+    // once the ring wraps the oldest presses are gone, and a window that lost some of them is incomplete
+    for slot in 0..PRESS_LOG_SIZE as u64 + 1 {
+        input_log.log_press(120 + slot, &press(Button::Down), 1).unwrap();
+    }
+    assert!(input_log.get_presses(&input).is_none());
+
+    let (first_press, n_presses, complete) = input_log.take_window_presses(u64::MAX);
+    assert_eq!((first_press, n_presses as usize, complete), (4, PRESS_LOG_SIZE, false));
+}
+
+#[test]
+fn window_inputs_play_whole_sequences() {
+    let mut data = vec![0u8; get_input_log_size()];
+    InputLog::initialize(&mut data, 100).unwrap();
+    let input_log = InputLog::load_mut(&mut data).unwrap();
+
+    // walking up two tiles and pressing A, then a single press of B that pushed fewer tokens
+    let walk = vec![
+        SequenceStep {button : Button::Up, hold_frames : 16},
+        SequenceStep {button : Button::Up, hold_frames : 16},
+        SequenceStep {button : Button::A, hold_frames : 5}
+    ];
+    input_log.log_press(101, &walk, 6).unwrap();
+    input_log.log_press(102, &press(Button::B), 4).unwrap();
+
+    let (first_press, n_presses, _) = input_log.take_window_presses(105);
+    assert_eq!((first_press, n_presses), (0, 4));
+
+    let steps : Vec<(u8, u8, u8)> = input_log.presses[..4].iter().map(|press| (press.button, press.hold_frames, press.n_steps)).collect();
+    assert_eq!(steps, vec![(Button::Up as u8, 16, 3), (Button::Up as u8, 16, 0), (Button::A as u8, 5, 0), (Button::B as u8, DEFAULT_HOLD_FRAMES, 1)]);
+
+    let window = |mode : InputMode, button : Button| ResolvedInput {
+        mode : mode as u8,
+        first_press : first_press.into(),
+        n_presses : n_presses.into(),
+        ..resolved(100, 105, button as u8)
+    };
+
+    // anarchy plays every step, auction the steps of the largest press and the other modes just the button
+    let anarchy = input_log.get_window_inputs(&window(InputMode::Anarchy, Button::A)).unwrap();
+    assert_eq!(anarchy, vec![(Button::Up as u8, 16), (Button::Up as u8, 16), (Button::A as u8, 5), (Button::B as u8, DEFAULT_HOLD_FRAMES)]);
+
+    let auction = input_log.get_window_inputs(&window(InputMode::Auction, Button::Up)).unwrap();
+    assert_eq!(auction, vec![(Button::Up as u8, 16), (Button::Up as u8, 16), (Button::A as u8, 5)]);
+
+    let democracy = input_log.get_window_inputs(&window(InputMode::Democracy, Button::Up)).unwrap();
+    assert_eq!(democracy, vec![(Button::Up as u8, DEFAULT_HOLD_FRAMES)]);
+
+    let empty = ResolvedInput {mode : InputMode::Lottery as u8, ..resolved(100, 105, NO_BUTTON)};
+    assert_eq!(input_log.get_window_inputs(&empty).unwrap(), vec![]);
+}
@@ -0,0 +1,117 @@
+use borsh::BorshSerialize;
+use dp_common::game::instruction::{
+    BidData, Button, ButtonData, DPPInstruction, InitData, ModeVoteData, ResizeData, SequenceData, SequenceStep, MAX_HOLD_FRAMES, MAX_SEQUENCE_STEPS, ENVELOPE_TAG, INSTRUCTION_VERSION,
+};
+use dp_common::game::modes::InputMode;
+use dp_common::charity::{AddCharityData, UpdateCharityData};
+use dp_common::game::accounts::get_mainnet_config;
+use dp_common::game::error::DaoPlaysError;
+use solana_program::pubkey::Pubkey;
+
+fn all_instructions() -> Vec<DPPInstruction> {
+    vec![
+        DPPInstruction::CreateDataAccount {
+            init_data: InitData { amount: 1000, config: get_mainnet_config(&dp_common::game::id()) },
+        },
+        DPPInstruction::PushButton {
+            button_data: ButtonData { button: Button::Start, amount: 1 },
+        },
+        DPPInstruction::PlaceBid {
+            bid_data: BidData { amount_charity: 90000, amount_dao: 10000, charity: 6 },
+        },
+        DPPInstruction::SelectWinners,
+        DPPInstruction::UpdateConfig {
+            config: get_mainnet_config(&dp_common::game::id()),
+        },
+        DPPInstruction::AddCharity {
+            charity_data: AddCharityData { name: "WaterOrg".to_string(), address: Pubkey::new_unique() },
+        },
+        DPPInstruction::UpdateCharity {
+            charity_data: UpdateCharityData { index: 3, address: Pubkey::new_unique(), active: false },
+        },
+        DPPInstruction::CancelBid,
+        DPPInstruction::CommitSeed { commitment: [7; 32] },
+        DPPInstruction::RevealSeed { secret: [9; 32] },
+        DPPInstruction::SlashCommit,
+        DPPInstruction::ResizeState {
+            resize_data: ResizeData { bid_capacity: 2048, winner_capacity: 8 },
+        },
+        DPPInstruction::MigrateState,
+        DPPInstruction::ClaimPrize,
+        DPPInstruction::Crank,
+        DPPInstruction::ResolveInput,
+        DPPInstruction::VoteInputMode {
+            mode_vote_data: ModeVoteData { mode: InputMode::Democracy, amount: 25 },
+        },
+        DPPInstruction::PushSequence {
+            sequence_data: SequenceData {
+                steps: vec![SequenceStep { button: Button::Up, hold_frames: 16 }, SequenceStep { button: Button::A, hold_frames: 5 }],
+                amount: 2,
+            },
+        },
+        DPPInstruction::CreditWinners,
+    ]
+}
+
+#[test]
+fn unpack_matches_borsh_encoding() {
+    for instruction in all_instructions() {
+        let data = instruction.try_to_vec().unwrap();
+        assert_eq!(DPPInstruction::unpack(&data).unwrap(), instruction);
+    }
+}
+
+#[test]
+fn unpack_versioned_envelope() {
+    for instruction in all_instructions() {
+        let data = instruction.pack();
+        assert_eq!(data[0], ENVELOPE_TAG);
+        assert_eq!(data[1], INSTRUCTION_VERSION);
+        assert_eq!(DPPInstruction::unpack(&data).unwrap(), instruction);
+    }
+}
+
+#[test]
+fn unpack_rejects_bad_data() {
+    // unknown tag, trailing bytes, and an envelope from a future version
+    assert!(DPPInstruction::unpack(&[20]).is_err());
+    assert!(DPPInstruction::unpack(&[3, 0]).is_err());
+    assert!(DPPInstruction::unpack(&[]).is_err());
+
+    let mut data = DPPInstruction::SelectWinners.pack();
+    data[1] = INSTRUCTION_VERSION + 1;
+    assert!(DPPInstruction::unpack(&data).is_err());
+
+    // SendTokens has been retired, so its tag doesn't decode to the instruction that replaced it
+    assert_eq!(DPPInstruction::SendTokens.try_to_vec().unwrap(), vec![4]);
+    assert!(DPPInstruction::unpack(&[4]).is_err());
+    assert!(DPPInstruction::unpack(&DPPInstruction::SendTokens.pack()).is_err());
+    assert_eq!(DPPInstruction::CreditWinners.try_to_vec().unwrap(), vec![19]);
+}
+
+#[test]
+fn sequence_validation() {
+    let step = SequenceStep { button: Button::Up, hold_frames: 16 };
+    let sequence = SequenceData { steps: vec![step.clone(); MAX_SEQUENCE_STEPS], amount: MAX_SEQUENCE_STEPS as u64 };
+    assert_eq!(sequence.validate(), Ok(()));
+
+    let mut too_long = sequence.clone();
+    too_long.steps.push(step.clone());
+    too_long.amount += 1;
+    assert_eq!(too_long.validate(), Err(DaoPlaysError::InvalidSequence));
+
+    let empty = SequenceData { steps: vec![], amount: 1 };
+    assert_eq!(empty.validate(), Err(DaoPlaysError::InvalidSequence));
+
+    // every step has to hold its button for a frame, but not for longer than MAX_HOLD_FRAMES
+    for hold_frames in [0, MAX_HOLD_FRAMES + 1] {
+        let mut bad_hold = sequence.clone();
+        bad_hold.steps[3].hold_frames = hold_frames;
+        assert_eq!(bad_hold.validate(), Err(DaoPlaysError::InvalidSequence));
+    }
+
+    // and the sequence has to cost at least a token a step
+    let mut too_cheap = sequence;
+    too_cheap.amount -= 1;
+    assert_eq!(too_cheap.validate(), Err(DaoPlaysError::InvalidButtonBid));
+}
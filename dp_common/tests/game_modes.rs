@@ -0,0 +1,71 @@
+use dp_common::game::modes::{InputMode, ModeBallot, MODE_BALLOT_DISCRIMINATOR, N_INPUT_MODES, get_mode_ballot_size};
+use solana_program::program_error::ProgramError;
+
+fn new_ballot_data() -> Vec<u8> {
+    let mut data = vec![0u8; get_mode_ballot_size()];
+    ModeBallot::initialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn ballot_starts_in_the_lottery_mode() {
+    let mut data = vec![0u8; get_mode_ballot_size()];
+    assert_eq!(ModeBallot::load(&data).unwrap_err(), ProgramError::UninitializedAccount);
+
+    ModeBallot::initialize(&mut data).unwrap();
+    assert_eq!(&data[..8], &MODE_BALLOT_DISCRIMINATOR);
+
+    let ballot = ModeBallot::load(&data).unwrap();
+    assert_eq!(ballot.get_mode(), Ok(InputMode::Lottery));
+    assert!(!ballot.is_closed(u64::MAX));
+
+    for index in 0..N_INPUT_MODES {
+        assert_eq!(InputMode::from_u8(index as u8).unwrap() as usize, index);
+    }
+    assert_eq!(InputMode::from_u8(N_INPUT_MODES as u8), None);
+}
+
+#[test]
+fn closed_ballot_switches_to_the_leading_mode() {
+    let mut data = new_ballot_data();
+    let ballot = ModeBallot::load_mut(&mut data).unwrap();
+
+    // the first vote opens the ballot
+    ballot.add_vote(100, InputMode::Anarchy, 5, 50).unwrap();
+    ballot.add_vote(120, InputMode::Democracy, 3, 50).unwrap();
+    ballot.add_vote(149, InputMode::Democracy, 3, 50).unwrap();
+    assert_eq!(ballot.ballot_end_slot.get(), 150);
+    assert!(!ballot.is_closed(149));
+    assert!(ballot.is_closed(150));
+
+    // it can't be settled for a window that starts before it closed
+    assert_eq!(ballot.settle(145), Ok(None));
+    assert_eq!(ballot.get_leader(), Ok(InputMode::Democracy));
+
+    assert_eq!(ballot.settle(155), Ok(Some(InputMode::Lottery)));
+    assert_eq!(ballot.get_mode(), Ok(InputMode::Democracy));
+    assert_eq!((ballot.mode_start_slot.get(), ballot.ballot_end_slot.get(), ballot.n_votes.get()), (155, 0, 0));
+    assert!(ballot.votes.iter().all(|votes| votes.get() == 0));
+}
+
+#[test]
+fn tie_keeps_the_current_mode() {
+    let mut data = new_ballot_data();
+    let ballot = ModeBallot::load_mut(&mut data).unwrap();
+
+    ballot.add_vote(100, InputMode::Auction, 5, 10).unwrap();
+    ballot.add_vote(101, InputMode::Lottery, 5, 10).unwrap();
+    assert_eq!(ballot.settle(110), Ok(Some(InputMode::Lottery)));
+    assert_eq!(ballot.get_mode(), Ok(InputMode::Lottery));
+    assert_eq!(ballot.mode_start_slot.get(), 0);
+
+    // a tie between two other modes goes to the first
+    ballot.add_vote(200, InputMode::Auction, 5, 10).unwrap();
+    ballot.add_vote(201, InputMode::Anarchy, 5, 10).unwrap();
+    ballot.settle(210).unwrap();
+    assert_eq!(ballot.get_mode(), Ok(InputMode::Anarchy));
+
+    // an overflowing vote is refused
+    assert!(ballot.add_vote(220, InputMode::Lottery, u64::MAX, 10).is_some());
+    assert!(ballot.add_vote(221, InputMode::Lottery, 1, 10).is_none());
+}
@@ -0,0 +1,19 @@
+use dp_common::game::randomness::{get_commitment, get_revealed_seed};
+
+#[test]
+fn commitment_depends_on_secret() {
+    assert_eq!(get_commitment(&[1; 32]), get_commitment(&[1; 32]));
+    assert_ne!(get_commitment(&[1; 32]), get_commitment(&[2; 32]));
+
+    // the commitment doesn't give the secret away
+    assert_ne!(get_commitment(&[1; 32]), [1; 32]);
+}
+
+#[test]
+fn seed_depends_on_secret_and_slot_hash() {
+    let seed = get_revealed_seed(&[1; 32], &[5; 32]);
+    assert_eq!(seed, get_revealed_seed(&[1; 32], &[5; 32]));
+
+    assert_ne!(seed, get_revealed_seed(&[2; 32], &[5; 32]));
+    assert_ne!(seed, get_revealed_seed(&[1; 32], &[6; 32]));
+}
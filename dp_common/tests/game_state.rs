@@ -0,0 +1,421 @@
+use borsh::BorshSerialize;
+use bytemuck::Zeroable;
+use dp_common::charity::{CharityData, get_charity_size};
+use dp_common::game::accounts::get_mainnet_config;
+use dp_common::game::error::DaoPlaysError;
+use dp_common::game::inputs::MAX_INPUT_WINDOW_SLOTS;
+use dp_common::game::state::{BidderData, Capacity, Commit, Config, LegacyState, State, StateHeader, INITIAL_BID_CAPACITY, LEGACY_MAX_BIDDERS, LEGACY_MAX_WINNERS, MAX_BIDDERS, MAX_WINNERS, MAX_REVEAL_TIMEOUT_SLOTS, LEGACY_BIDDER_DATA_SIZE, STATE_DISCRIMINATOR, STATE_LAYOUT_VERSION, get_legacy_array_offsets, get_legacy_state_size, get_state_layout_version, get_state_header_size, get_state_sections, get_state_size, get_config_size, get_bid_status_size, get_commit_size, PushSplit, PushStats, MAX_BASIS_POINTS, get_push_stats_size, CONFIG_DISCRIMINATOR, CONFIG_LAYOUT_VERSION, get_config_layout_version};
+use dp_common::layout::{LayoutHeader, LEGACY_LAYOUT_VERSION, get_layout_header_size};
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_error::ProgramError, pubkey::Pubkey};
+use std::mem::offset_of;
+
+fn get_state_data(capacity : &Capacity) -> Vec<u8> {
+    let mut data = vec![0u8; get_state_size(capacity)];
+    let header : &mut StateHeader = bytemuck::from_bytes_mut(&mut data[..get_state_header_size()]);
+    header.layout = LayoutHeader::new(STATE_DISCRIMINATOR, STATE_LAYOUT_VERSION);
+    header.bid_capacity.set(capacity.bids as u16);
+    header.winner_capacity = capacity.winners as u8;
+    data
+}
+
+#[test]
+fn legacy_state_size_matches_deployed_account() {
+    // the data account on mainnet was created with this many bytes
+    assert_eq!(get_legacy_state_size(), 49379);
+    assert_eq!(get_charity_size(), 80);
+
+    let state = LegacyState::zeroed();
+    assert_eq!(state.try_to_vec().unwrap().len(), get_legacy_state_size());
+}
+
+#[test]
+fn legacy_state_offsets_match_legacy_layout() {
+    assert_eq!(offset_of!(LegacyState, prev_choose_winners_time), 0);
+    assert_eq!(offset_of!(LegacyState, n_bidders), 8);
+    assert_eq!(offset_of!(LegacyState, total_bid_amount), 10);
+    assert_eq!(offset_of!(LegacyState, bid_keys), 18);
+    assert_eq!(offset_of!(LegacyState, bid_amounts), 32786);
+    assert_eq!(offset_of!(LegacyState, bid_times), 40978);
+    assert_eq!(offset_of!(LegacyState, n_winners), 49170);
+    assert_eq!(offset_of!(LegacyState, winners), 49171);
+    assert_eq!(offset_of!(LegacyState, charity_data), 49299);
+
+    let offsets = [offset_of!(LegacyState, bid_keys), offset_of!(LegacyState, bid_amounts), offset_of!(LegacyState, bid_times), offset_of!(LegacyState, winners)];
+    assert_eq!(get_legacy_array_offsets(), offsets);
+}
+
+#[test]
+fn legacy_zero_copy_view_matches_borsh() {
+    let mut state = LegacyState::zeroed();
+    state.prev_choose_winners_time.set(1_650_000_000);
+    state.n_bidders.set(2);
+    state.total_bid_amount.set(300);
+    state.bid_keys[0] = Pubkey::new_unique();
+    state.bid_keys[LEGACY_MAX_BIDDERS - 1] = Pubkey::new_unique();
+    state.bid_amounts[0].set(100);
+    state.bid_amounts[LEGACY_MAX_BIDDERS - 1].set(200);
+    state.bid_times[0].set(-1);
+    state.bid_times[LEGACY_MAX_BIDDERS - 1].set(i64::MAX);
+    state.n_winners = 1;
+    state.winners[LEGACY_MAX_WINNERS - 1] = Pubkey::new_unique();
+    state.charity_data.record_donation(6, 90, 100).unwrap();
+
+    let data = state.try_to_vec().unwrap();
+    assert_eq!(data.as_slice(), bytemuck::bytes_of(&state));
+    assert_eq!(*LegacyState::load(&data).unwrap(), state);
+    assert!(LegacyState::load(&data[1..]).is_err());
+}
+
+#[test]
+fn state_layout_follows_capacity() {
+    let capacity = Capacity {bids : 10, winners : 3};
+    assert_eq!(get_state_header_size(), 119);
+    assert_eq!(get_state_sections(&capacity), [119, 320, 80, 80, 96]);
+    assert_eq!(get_state_size(&capacity), 695);
+    assert_eq!(offset_of!(StateHeader, bid_capacity), 9);
+    assert_eq!(offset_of!(StateHeader, total_claimable), 31);
+
+    // a new account has to fit in what the program can create
+    let initial = Capacity {bids : INITIAL_BID_CAPACITY, winners : MAX_WINNERS};
+    assert!(initial.is_valid());
+    assert!(get_state_size(&initial) <= 10240);
+
+    assert!(!Capacity {bids : 0, winners : 1}.is_valid());
+    assert!(!Capacity {bids : MAX_BIDDERS + 1, winners : 1}.is_valid());
+    assert!(!Capacity {bids : 1, winners : MAX_WINNERS + 1}.is_valid());
+    assert!(Capacity {bids : MAX_BIDDERS, winners : MAX_WINNERS}.is_valid());
+}
+
+#[test]
+fn load_mut_writes_in_place() {
+    let capacity = Capacity {bids : 10, winners : 3};
+    let mut data = get_state_data(&capacity);
+    data.extend_from_slice(&[0u8; 16]);
+
+    let mut state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.get_capacity(), capacity);
+    assert_eq!((state.bid_keys.len(), state.bid_amounts.len(), state.bid_times.len(), state.winners.len()), (10, 10, 10, 3));
+
+    state.bid_amounts[3].set(u64::MAX - 1);
+    state.winners[2] = Pubkey::new_from_array([7; 32]);
+    state.charity_data.record_donation(0, 5, 7).unwrap();
+
+    let bid_offset = get_state_header_size() + 10 * 32 + 3 * 8;
+    assert_eq!(data[bid_offset..bid_offset + 8], (u64::MAX - 1).to_le_bytes());
+
+    let winner_offset = get_state_size(&capacity) - 32;
+    assert_eq!(data[winner_offset..winner_offset + 32], [7; 32]);
+
+    let charity_offset = offset_of!(StateHeader, charity_data);
+    let mut expected = CharityData::zeroed();
+    expected.record_donation(0, 5, 7).unwrap();
+    assert_eq!(data[charity_offset..charity_offset + get_charity_size()], expected.try_to_vec().unwrap());
+}
+
+#[test]
+fn load_rejects_short_or_unset_data() {
+    let capacity = Capacity {bids : 10, winners : 3};
+    let mut data = get_state_data(&capacity);
+    data.pop();
+    assert_eq!(State::load_mut(&mut data).unwrap_err(), ProgramError::AccountDataTooSmall);
+
+    // an account whose capacity hasn't been set up can't be used
+    let mut data = vec![0u8; get_state_size(&capacity)];
+    assert_eq!(State::load_mut(&mut data).unwrap_err(), ProgramError::UninitializedAccount);
+
+    assert_eq!(State::load_mut(&mut data[..1]).unwrap_err(), ProgramError::UninitializedAccount);
+}
+
+#[test]
+fn layout_version_is_read_from_the_header() {
+    let capacity = Capacity {bids : 10, winners : 3};
+    let mut data = get_state_data(&capacity);
+    assert_eq!(get_state_layout_version(&data), Some(STATE_LAYOUT_VERSION));
+
+    // a layout the program doesn't know can't be read
+    data[8] = STATE_LAYOUT_VERSION + 1;
+    assert_eq!(get_state_layout_version(&data), Some(STATE_LAYOUT_VERSION + 1));
+    assert_eq!(State::load_mut(&mut data).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedStateLayout));
+
+    // accounts without the header are legacy accounts if they are large enough, as long as CreateDataAccount
+    // has set them up. the dump is a v0 account captured after a couple of rounds, see game_bid_index.rs
+    let mut data = vec![0u8; get_legacy_state_size()];
+    assert_eq!(get_state_layout_version(&data), None);
+
+    let mut data_v0 = include_bytes!("fixtures/legacy_state_v0.bin").to_vec();
+    assert_eq!(data_v0.len(), get_legacy_state_size());
+    assert_eq!(get_state_layout_version(&data_v0), Some(LEGACY_LAYOUT_VERSION));
+    assert_eq!(get_state_layout_version(&data_v0[..get_legacy_state_size() - 1]), None);
+    assert_eq!(State::load_mut(&mut data_v0).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedStateLayout));
+
+    data.extend_from_slice(&[0u8; 10240]);
+    assert_eq!(State::load_mut(&mut data).unwrap_err(), ProgramError::UninitializedAccount);
+}
+
+#[test]
+fn remove_bid_keeps_totals_consistent() {
+    let mut data = get_state_data(&Capacity {bids : 8, winners : 1});
+    let mut state = State::load_mut(&mut data).unwrap();
+    for (i, amount) in [(3, 100), (7, 250)] {
+        state.bid_keys[i] = Pubkey::new_unique();
+        state.bid_amounts[i].set(amount);
+        state.bid_times[i].set(1_650_000_000);
+    }
+    state.n_bidders.set(2);
+    state.total_bid_amount.set(350);
+
+    assert_eq!(state.remove_bid(3), Some(100));
+    assert_eq!(state.n_bidders.get(), 1);
+    assert_eq!(state.total_bid_amount.get(), 250);
+    assert_eq!(state.bid_keys[3], Pubkey::default());
+    assert_eq!(state.bid_amounts[3].get(), 0);
+    assert_eq!(state.bid_times[3].get(), 0);
+
+    // the remaining bid is untouched
+    assert_eq!(state.bid_amounts[7].get(), 250);
+}
+
+#[test]
+fn bid_accounting_at_u64_boundaries() {
+    let mut data = get_state_data(&Capacity {bids : 2, winners : 1});
+    let key = Pubkey::new_unique();
+
+    {
+        let mut state = State::load_mut(&mut data).unwrap();
+        state.insert_bid(0, key, u64::MAX - 1, 10).unwrap();
+        assert_eq!(state.add_to_bid(0, 1, 11), Some(u64::MAX));
+    }
+
+    // a bid or total that would pass u64::MAX is rejected and leaves the state untouched
+    let before = data.clone();
+    {
+        let mut state = State::load_mut(&mut data).unwrap();
+        assert_eq!(state.add_to_bid(0, 1, 12), None);
+        assert_eq!(state.insert_bid(1, Pubkey::new_unique(), 1, 12), None);
+    }
+    assert_eq!(data, before);
+
+    let mut state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.get_bid_totals(i64::MAX), Some((1, u64::MAX)));
+    assert_eq!(state.remove_bid(0), Some(u64::MAX));
+    assert_eq!((state.n_bidders.get(), state.total_bid_amount.get()), (0, 0));
+
+    // two bids that sum past u64::MAX can't be totalled
+    state.bid_amounts[0].set(u64::MAX);
+    state.bid_amounts[1].set(1);
+    state.bid_times[0].set(1);
+    state.bid_times[1].set(1);
+    assert_eq!(state.get_bid_totals(i64::MAX), None);
+}
+
+#[test]
+fn bid_accounting_at_u16_boundaries() {
+    let mut data = get_state_data(&Capacity {bids : 1, winners : 1});
+    let mut state = State::load_mut(&mut data).unwrap();
+
+    // the bidder count can't wrap past u16::MAX
+    state.n_bidders.set(u16::MAX);
+    assert_eq!(state.insert_bid(0, Pubkey::new_unique(), 1, 10), None);
+
+    // and removing a bid when the counts are already zero means the state is out of sync
+    state.n_bidders.set(0);
+    state.bid_amounts[0].set(5);
+    assert_eq!(state.remove_bid(0), None);
+    assert_eq!(state.bid_amounts[0].get(), 5);
+}
+
+#[test]
+fn bidder_data_reads_legacy_accounts() {
+    // accounts created before evictions were recorded only hold the index
+    let mut legacy = 513u16.to_le_bytes().to_vec();
+    assert_eq!(legacy.len(), LEGACY_BIDDER_DATA_SIZE);
+
+    let mut bidder_data = BidderData::load(&legacy).unwrap();
+    assert_eq!(bidder_data, BidderData {index: 513, ..BidderData::default()});
+
+    // storing into a legacy account only updates the index
+    bidder_data.index = 7;
+    bidder_data.record_eviction(100).unwrap();
+    bidder_data.store(&mut legacy).unwrap();
+    assert_eq!(legacy, 7u16.to_le_bytes());
+
+    // accounts created before prizes were credited keep the eviction record
+    let mut evictions = vec![0u8; 14];
+    bidder_data.store(&mut evictions).unwrap();
+    assert_eq!(BidderData::load(&evictions).unwrap(), BidderData {index: 7, n_evictions: 1, lamports_evicted: 100, ..BidderData::default()});
+
+    // but can't be credited with a prize until they are resized
+    bidder_data.record_win(50).unwrap();
+    assert_eq!(bidder_data.store(&mut evictions).unwrap_err(), ProgramError::AccountDataTooSmall);
+
+    // once resized the full record is kept
+    assert_eq!(get_bid_status_size(), 26);
+    let mut data = vec![0u8; get_bid_status_size()];
+    bidder_data.store(&mut data).unwrap();
+    assert_eq!(BidderData::load(&data).unwrap(), BidderData {index: 7, n_evictions: 1, lamports_evicted: 100, n_wins: 1, claimable: 50});
+
+    assert!(BidderData::load(&[1]).is_err());
+
+    let mut saturated = BidderData {n_evictions: u32::MAX, ..BidderData::default()};
+    assert_eq!(saturated.record_eviction(1), None);
+    saturated.n_evictions = 0;
+    saturated.lamports_evicted = u64::MAX;
+    assert_eq!(saturated.record_eviction(1), None);
+    assert_eq!(saturated, BidderData {lamports_evicted: u64::MAX, ..BidderData::default()});
+
+    let mut saturated = BidderData {claimable: u64::MAX, ..BidderData::default()};
+    assert_eq!(saturated.record_win(1), None);
+    assert_eq!(saturated, BidderData {claimable: u64::MAX, ..BidderData::default()});
+}
+
+#[test]
+fn config_validation() {
+    let config = get_mainnet_config(&dp_common::game::id());
+    assert!(config.is_valid());
+    assert_eq!(get_layout_header_size() + config.try_to_vec().unwrap().len(), get_config_size());
+
+    let mut too_many_winners = config.clone();
+    too_many_winners.max_winners = MAX_WINNERS as u8 + 1;
+    assert!(!too_many_winners.is_valid());
+
+    let mut no_reward = config.clone();
+    no_reward.tokens_won = 0;
+    assert!(!no_reward.is_valid());
+
+    let mut no_min_bid = config.clone();
+    no_min_bid.min_bid = 0;
+    assert!(!no_min_bid.is_valid());
+
+    let mut no_oracle_age = config.clone();
+    no_oracle_age.max_oracle_slot_age = 0;
+    assert!(!no_oracle_age.is_valid());
+
+    // the commit's slot hash has to still be in SlotHashes when it is revealed
+    let mut long_reveal = config.clone();
+    long_reveal.reveal_timeout_slots = MAX_REVEAL_TIMEOUT_SLOTS + 1;
+    assert!(!long_reveal.is_valid());
+
+    // an input window has to fit in the vote tally
+    let mut long_window = config.clone();
+    long_window.input_window_slots = MAX_INPUT_WINDOW_SLOTS + 1;
+    assert!(!long_window.is_valid());
+    long_window.input_window_slots = 0;
+    assert!(!long_window.is_valid());
+
+    let mut no_mode_ballot = config.clone();
+    no_mode_ballot.mode_vote_slots = 0;
+    assert!(!no_mode_ballot.is_valid());
+
+    // at most every token of a press can be burned or sent to the treasury
+    let mut push_split = config;
+    push_split.push_burn_bps = MAX_BASIS_POINTS;
+    assert!(push_split.is_valid());
+    push_split.push_treasury_bps = 1;
+    assert!(!push_split.is_valid());
+}
+
+#[test]
+fn config_is_stored_behind_a_layout_header() {
+    let config = get_mainnet_config(&dp_common::game::id());
+
+    let mut data = vec![0u8; get_config_size()];
+    config.store(&mut data).unwrap();
+
+    assert_eq!(data[..8], CONFIG_DISCRIMINATOR);
+    assert_eq!(get_config_layout_version(&data), Some(CONFIG_LAYOUT_VERSION));
+    assert_eq!(Config::load(&data).unwrap(), config);
+    assert_eq!(Config::load_keys(&data).unwrap(), (config.authority, config.data_account));
+
+    // it has to fit the whole config
+    let mut short = vec![0u8; get_config_size() - 1];
+    assert_eq!(config.store(&mut short).unwrap_err(), ProgramError::AccountDataTooSmall);
+}
+
+#[test]
+fn legacy_configs_only_give_up_their_keys() {
+    let config = get_mainnet_config(&dp_common::game::id());
+
+    // a config written before the header, and before the push split was added to it
+    let mut legacy = config.try_to_vec().unwrap();
+    legacy.truncate(legacy.len() - 4);
+
+    assert_eq!(get_config_layout_version(&legacy), Some(LEGACY_LAYOUT_VERSION));
+    assert_eq!(Config::load(&legacy).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedConfigLayout));
+    assert_eq!(Config::load_keys(&legacy).unwrap(), (config.authority, config.data_account));
+
+    // too small to even hold the keys
+    assert_eq!(get_config_layout_version(&legacy[..63]), None);
+    assert_eq!(Config::load_keys(&legacy[..63]).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedConfigLayout));
+
+    // a config written with the first header, before the cancel refund was added to it
+    let mut data = vec![0u8; get_config_size()];
+    config.store(&mut data).unwrap();
+    data[8] = 1;
+    data.truncate(get_config_size() - 8);
+    assert_eq!(Config::load(&data).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedConfigLayout));
+    assert_eq!(Config::load_keys(&data).unwrap(), (config.authority, config.data_account));
+
+    // a version from the future
+    let mut data = vec![0u8; get_config_size()];
+    config.store(&mut data).unwrap();
+    data[8] = CONFIG_LAYOUT_VERSION + 1;
+    assert_eq!(Config::load(&data).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedConfigLayout));
+    assert_eq!(Config::load_keys(&data).unwrap_err(), ProgramError::from(DaoPlaysError::UnsupportedConfigLayout));
+}
+
+#[test]
+fn cancel_refund_is_rounded_down() {
+    let mut config = get_mainnet_config(&dp_common::game::id());
+
+    config.cancel_refund_tokens_per_sol = 1000;
+    assert_eq!(config.get_cancel_refund(LAMPORTS_PER_SOL / 4), Some(250));
+    assert_eq!(config.get_cancel_refund(LAMPORTS_PER_SOL / 1000 - 1), Some(0));
+
+    config.cancel_refund_tokens_per_sol = 0;
+    assert_eq!(config.get_cancel_refund(u64::MAX), Some(0));
+
+    config.cancel_refund_tokens_per_sol = u64::MAX;
+    assert_eq!(config.get_cancel_refund(LAMPORTS_PER_SOL), Some(u64::MAX));
+    assert_eq!(config.get_cancel_refund(LAMPORTS_PER_SOL + 1), None);
+}
+
+#[test]
+fn push_split_and_stats() {
+    let mut config = get_mainnet_config(&dp_common::game::id());
+
+    // by default every token pushed is recycled into the prize pool
+    assert_eq!(config.get_push_split(7), PushSplit {burn : 0, treasury : 0, prizes : 7});
+
+    // and the prize pool gets whatever is lost to rounding
+    config.push_burn_bps = 2500;
+    config.push_treasury_bps = 5000;
+    assert_eq!(config.get_push_split(100), PushSplit {burn : 25, treasury : 50, prizes : 25});
+    assert_eq!(config.get_push_split(3), PushSplit {burn : 0, treasury : 1, prizes : 2});
+    assert_eq!(config.get_push_split(u64::MAX), PushSplit {burn : u64::MAX / 4, treasury : u64::MAX / 2, prizes : u64::MAX - u64::MAX / 4 - u64::MAX / 2});
+
+    let mut stats = PushStats::default();
+    assert_eq!(stats.try_to_vec().unwrap().len(), get_push_stats_size());
+
+    stats.record(&config.get_push_split(100)).unwrap();
+    stats.record(&config.get_push_split(3)).unwrap();
+    assert_eq!(stats, PushStats {n_presses : 2, tokens_pushed : 103, tokens_burned : 25, tokens_to_treasury : 51, tokens_to_prizes : 27});
+
+    stats.tokens_pushed = u64::MAX;
+    let before = stats.clone();
+    assert!(stats.record(&config.get_push_split(100)).is_none());
+    assert_eq!(stats, before);
+}
+
+#[test]
+fn test_commit() {
+    let commit = Commit::default();
+    assert!(!commit.is_active());
+    assert_eq!(commit.try_to_vec().unwrap().len(), get_commit_size());
+
+    let commit = Commit {committer : Pubkey::new_unique(), commit_slot : 100, ..Commit::default()};
+    assert!(commit.is_active());
+    assert_eq!(commit.get_reveal_deadline(150), 250);
+
+    let commit = Commit {commit_slot : u64::MAX, ..commit};
+    assert_eq!(commit.get_reveal_deadline(150), u64::MAX);
+}
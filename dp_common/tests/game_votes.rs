@@ -0,0 +1,94 @@
+use dp_common::game::inputs::NO_BUTTON;
+use dp_common::game::instruction::Button;
+use dp_common::game::votes::{VoteTally, VOTE_HISTORY_SLOTS, VOTE_TALLY_DISCRIMINATOR, get_vote_tally_size};
+use solana_program::program_error::ProgramError;
+
+fn new_tally_data() -> Vec<u8> {
+    let mut data = vec![0u8; get_vote_tally_size()];
+    VoteTally::initialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn tally_fits_in_an_account_the_program_can_create() {
+    assert!(get_vote_tally_size() <= 10240);
+}
+
+#[test]
+fn tally_must_be_initialized() {
+    let mut data = vec![0u8; get_vote_tally_size()];
+    assert_eq!(VoteTally::load(&data).unwrap_err(), ProgramError::UninitializedAccount);
+    assert_eq!(VoteTally::load(&data[1..]).unwrap_err(), ProgramError::AccountDataTooSmall);
+
+    VoteTally::initialize(&mut data).unwrap();
+    assert_eq!(&data[..8], &VOTE_TALLY_DISCRIMINATOR);
+    assert_eq!(VoteTally::load(&data).unwrap().iter_recent().count(), 0);
+}
+
+#[test]
+fn votes_in_a_slot_are_added_together() {
+    let mut data = new_tally_data();
+    let tally = VoteTally::load_mut(&mut data).unwrap();
+
+    tally.record_vote(10, &Button::A, 5).unwrap();
+    tally.record_vote(10, &Button::A, 7).unwrap();
+    tally.record_vote(10, &Button::Start, 1).unwrap();
+    tally.record_vote(11, &Button::B, 3).unwrap();
+
+    let votes = tally.get_slot_votes(10).unwrap();
+    assert_eq!(votes.n_presses.get(), 3);
+    assert_eq!(votes.get_tally(&Button::A), 12);
+    assert_eq!(votes.get_tally(&Button::Start), 1);
+    assert_eq!(votes.get_tally(&Button::B), 0);
+    assert_eq!(votes.get_total(), Some(13));
+
+    assert_eq!(tally.get_slot_votes(11).unwrap().get_tally(&Button::B), 3);
+    assert!(tally.get_slot_votes(12).is_none());
+
+    // an overflowing vote is refused
+    assert!(tally.record_vote(11, &Button::B, u64::MAX).is_none());
+}
+
+#[test]
+fn largest_press_is_the_top_bid() {
+    let mut data = new_tally_data();
+    let tally = VoteTally::load_mut(&mut data).unwrap();
+
+    tally.record_vote(10, &Button::A, 5).unwrap();
+    tally.record_vote(10, &Button::B, 7).unwrap();
+    tally.record_vote(10, &Button::Up, 7).unwrap();
+    tally.record_vote(12, &Button::Down, 7).unwrap();
+
+    // the first of two equal presses keeps the top bid, in a slot and across a window
+    let votes = tally.get_slot_votes(10).unwrap();
+    assert_eq!((votes.top_bid.get(), votes.top_button), (7, Button::B as u8));
+
+    let window_votes = tally.get_window_votes(10, 13).unwrap();
+    assert_eq!((window_votes.top_bid, window_votes.top_button), (7, Button::B as u8));
+
+    let window_votes = tally.get_window_votes(11, 13).unwrap();
+    assert_eq!((window_votes.top_bid, window_votes.top_button), (7, Button::Down as u8));
+
+    let window_votes = tally.get_window_votes(13, 20).unwrap();
+    assert_eq!((window_votes.top_bid, window_votes.top_button), (0, NO_BUTTON));
+}
+
+#[test]
+fn ring_keeps_the_most_recent_slots() {
+    let mut data = new_tally_data();
+    let tally = VoteTally::load_mut(&mut data).unwrap();
+
+    let n_slots = VOTE_HISTORY_SLOTS as u64 + 5;
+    for slot in 1..=n_slots {
+        tally.record_vote(slot, &Button::Up, slot).unwrap();
+    }
+
+    // the oldest slots have been overwritten
+    assert!(tally.get_slot_votes(5).is_none());
+    assert_eq!(tally.get_slot_votes(6).unwrap().get_tally(&Button::Up), 6);
+
+    let recent : Vec<u64> = tally.iter_recent().map(|votes| votes.slot.get()).collect();
+    assert_eq!(recent.len(), VOTE_HISTORY_SLOTS);
+    assert_eq!(recent[0], n_slots);
+    assert_eq!(recent[VOTE_HISTORY_SLOTS - 1], 6);
+}
@@ -0,0 +1,44 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::Zeroable;
+use dp_common::layout::LEGACY_LAYOUT_VERSION;
+use dp_common::token_launch::error::RNGError;
+use dp_common::token_launch::state::{TokenLaunchData, TokenLaunchState, LAUNCH_LAYOUT_VERSION, get_launch_layout_version, get_legacy_state_size, get_state_size, load_launch_data, migrate_legacy_launch_data, store_launch_data};
+use solana_program::program_error::ProgramError;
+
+#[test]
+fn launch_state_starts_with_layout_header() {
+    assert_eq!(get_legacy_state_size(), 80);
+    assert_eq!(get_state_size(), 89);
+
+    let mut launch_data = TokenLaunchData::zeroed();
+    launch_data.record_donation(3, 1000, 1500).unwrap();
+
+    let mut data = vec![0u8; get_state_size()];
+    store_launch_data(&launch_data, &mut data).unwrap();
+    assert_eq!(data, TokenLaunchState::new(launch_data).try_to_vec().unwrap());
+    assert_eq!(get_launch_layout_version(&data), Some(LAUNCH_LAYOUT_VERSION));
+    assert_eq!(load_launch_data(&data).unwrap(), launch_data);
+
+    data[8] = LAUNCH_LAYOUT_VERSION + 1;
+    assert!(matches!(load_launch_data(&data), Err(ProgramError::Custom(code)) if code == RNGError::UnsupportedStateLayout as u32));
+}
+
+#[test]
+fn migrate_legacy_launch_account() {
+    // the launch account as it was captured from mainnet, which holds just the launch data
+    let mut legacy = TokenLaunchData::zeroed();
+    legacy.record_donation(0, 250_000, 300_000).unwrap();
+    legacy.record_donation(6, 90_000, 100_000).unwrap();
+    let mut data = legacy.try_to_vec().unwrap();
+
+    assert_eq!(get_launch_layout_version(&data), Some(LEGACY_LAYOUT_VERSION));
+    assert_eq!(get_launch_layout_version(&data[..79]), None);
+    assert!(load_launch_data(&data).is_err());
+    assert_eq!(migrate_legacy_launch_data(&mut data).unwrap_err(), ProgramError::AccountDataTooSmall);
+
+    data.resize(get_state_size(), 0);
+    migrate_legacy_launch_data(&mut data).unwrap();
+    assert_eq!(load_launch_data(&data).unwrap(), legacy);
+    assert_eq!(TokenLaunchState::try_from_slice(&data).unwrap(), TokenLaunchState::new(legacy));
+    assert_eq!(migrate_legacy_launch_data(&mut data).unwrap_err(), ProgramError::InvalidAccountData);
+}
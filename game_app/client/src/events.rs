@@ -0,0 +1,26 @@
+use dp_common::game::events::GameEvent;
+
+// the prefix the runtime gives to data written with sol_log_data
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+// pick the game's events out of a transaction's log messages
+pub fn decode_events(log_messages: &[String]) -> Vec<GameEvent> {
+
+    let mut events = Vec::new();
+    for log in log_messages {
+
+        let fields = match log.strip_prefix(PROGRAM_DATA_PREFIX) {
+            Some(fields) => fields,
+            None => continue
+        };
+
+        // each slice passed to sol_log_data is logged as a separate base64 field
+        for field in fields.split_whitespace() {
+            if let Some(event) = base64::decode(field).ok().and_then(|data| GameEvent::unpack(&data)) {
+                events.push(event);
+            }
+        }
+    }
+
+    events
+}
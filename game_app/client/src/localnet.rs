@@ -0,0 +1,51 @@
+use crate::state::{Error, Result};
+use dp_common::game::state::Config;
+use dp_common::mock_pyth::{self, MockPrice};
+use solana_program::{pubkey::Pubkey, rent};
+use std::fs;
+use std::path::Path;
+
+// helpers for running the game against solana-test-validator, where the mainnet pyth oracles don't exist.
+// the oracle accounts are written out as json files that the validator loads with
+//   solana-test-validator --account <key> <file> ...
+// and the config is pointed at them when the data account is created
+
+// the account in the format used by `solana account --output json`
+fn account_json(key : &Pubkey, mock_price : &MockPrice) -> String {
+    let data = mock_price.pack();
+    let lamports = rent::Rent::default().minimum_balance(data.len());
+
+    format!(
+        "{{\"pubkey\":\"{}\",\"account\":{{\"lamports\":{},\"data\":[\"{}\",\"base64\"],\"owner\":\"{}\",\"executable\":false,\"rentEpoch\":0,\"space\":{}}}}}",
+        key, lamports, base64::encode(&data), mock_pyth::get_pyth_program_key(), data.len()
+    )
+}
+
+pub fn get_mock_oracle_keys() -> [Pubkey; 3] {
+    [mock_pyth::get_mock_btc_key(), mock_pyth::get_mock_eth_key(), mock_pyth::get_mock_sol_key()]
+}
+
+// point the config at the mock oracles
+pub fn use_mock_oracles(config : &mut Config) {
+    let [btc_oracle, eth_oracle, sol_oracle] = get_mock_oracle_keys();
+    config.btc_oracle = btc_oracle;
+    config.eth_oracle = eth_oracle;
+    config.sol_oracle = sol_oracle;
+}
+
+// write an account file for each of the mock oracles to dir, and print the validator arguments to load them
+pub fn write_mock_oracles(dir : &str) -> Result<()> {
+    fs::create_dir_all(dir).map_err(Error::FileWriteError)?;
+
+    let mut validator_args : Vec<String> = Vec::new();
+    for (name, (key, mock_price)) in ["btc", "eth", "sol"].iter().zip(get_mock_oracle_keys().iter().zip(mock_pyth::get_default_mock_prices().iter())) {
+        let path = Path::new(dir).join(format!("{}_oracle.json", name));
+        fs::write(&path, account_json(key, mock_price)).map_err(Error::FileWriteError)?;
+
+        validator_args.push(format!("--account {} {}", key, path.display()));
+    }
+
+    println!("solana-test-validator {}", validator_args.join(" "));
+
+    Ok(())
+}
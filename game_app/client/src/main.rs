@@ -1,55 +1,59 @@
-pub mod state;
+// the client error type wraps ClientError directly so `?` works on rpc calls
+#![allow(clippy::result_large_err)]
 
-use crate::state::{Result, DPPInstruction, InitData, Charity, BidData, State, MAX_WINNERS};
+pub mod events;
+pub mod state;
+pub mod verify;
+#[cfg(feature = "localnet")]
+pub mod localnet;
+
+use crate::state::Result;
+use dp_common::charity::{CharityRegistry, AddCharityData, DEFAULT_CHARITIES, get_expected_charity_registry_key};
+use dp_common::game::instruction::{DPPInstruction, InitData, BidData, Button, ButtonData, ModeVoteData, ResizeData, SequenceData, SequenceStep};
+use dp_common::game::state::{State, Capacity, Config, BidderData, PushStats, STATE_LAYOUT_VERSION, get_state_layout_version};
+use dp_common::game::bid_index::{load_ladder_mut, get_data_account_size};
+use dp_common::game::accounts;
+use dp_common::game::votes::VoteTally;
+use dp_common::game::inputs::{InputLog, get_input_random, resolve_button};
+use dp_common::game::modes::{InputMode, ModeBallot};
+use dp_common::game::randomness::{get_commitment, OraclePrice};
 
 use std::env;
 use std::str::FromStr;
 use solana_client::rpc_client::RpcClient;
-use solana_program::{pubkey::Pubkey, rent, native_token::LAMPORTS_PER_SOL, system_program};
+use solana_program::{pubkey::Pubkey, native_token::LAMPORTS_PER_SOL, system_program, program_pack::Pack, hash::hash, sysvar};
 use solana_sdk::{
-    signer::Signer,
+    signer::Signer, signature::Signature, signer::keypair::Keypair,
     instruction::{AccountMeta, Instruction},
     transaction::Transaction, signer::keypair::read_keypair_file, commitment_config::{CommitmentConfig, CommitmentLevel}
 };
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshDeserialize;
 use solana_transaction_status::UiTransactionEncoding;
 use spl_associated_token_account::{get_associated_token_address};
-use enum_map::{enum_map, Enum};
 
 use std::time::Instant;
 
 
+#[cfg(not(feature = "localnet"))]
 const URL: &str = "https://api.mainnet-beta.solana.com";
+#[cfg(feature = "localnet")]
+const URL: &str = "http://127.0.0.1:8899";
 
-fn match_charity(index :  u8) ->  Charity 
-{
-    match index {
-        0 => Charity::EvidenceAction,
-        1 => Charity::GirlsWhoCode,
-        2 => Charity::OneTreePlanted,
-        3 => Charity::OutrightActionInt,
-        4 => Charity::TheLifeYouCanSave,
-        5 => Charity::UkraineERF,
-        6 => Charity::WaterOrg,
-        _ => Charity::InvalidCharity
-    }
-}
-
-fn get_button_from_int(index: u64) -> state::Button {
+fn get_button_from_int(index: u64) -> Button {
     if index == 0 {
-        return state::Button::A;
+        Button::A
     } else if index == 1 {
-        return state::Button::B;
+        Button::B
     } else if index == 2 {
-        return state::Button::Up;
+        Button::Up
     } else if index == 3 {
-        return state::Button::Down;
+        Button::Down
     } else if index == 4 {
-        return state::Button::Left;
+        Button::Left
     } else if index == 5 {
-        return state::Button::Right;
+        Button::Right
     } else {
-        return state::Button::Select;
+        Button::Select
     }
 
 }
@@ -66,8 +70,7 @@ fn main() {
         let amount: u64 = amount_arg.parse().unwrap();
 
         if let Err(err) = create_accounts(key_file, amount) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
+            exit_with_error(err);
         }
     }
     else if function == "place_bid" {
@@ -76,28 +79,167 @@ fn main() {
         let amount_dao_arg = &args[5];
 
         let charity_index : u8 = charity_arg.parse().unwrap();
-        let charity = match_charity(charity_index);
         let amount_charity: u64 = amount_charity_arg.parse().unwrap();
         let amount_dao: u64 = amount_dao_arg.parse().unwrap();
 
-        if let Err(err) = place_bid(key_file, charity, amount_charity, amount_dao) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
+        if let Err(err) = place_bid(key_file, charity_index, amount_charity, amount_dao) {
+            exit_with_error(err);
+        }
+
+    }
+
+    else if function == "show_events" {
+        let signature_arg = &args[3];
+
+        if let Err(err) = show_events(signature_arg) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "show_votes" {
+        // show a single slot if one is given, otherwise every slot in the tally
+        let slot = args.get(3).map(|slot_arg| slot_arg.parse::<u64>().unwrap());
+
+        if let Err(err) = show_votes(slot) {
+            exit_with_error(err);
         }
+    }
 
+    else if function == "show_push_stats" {
+        if let Err(err) = show_push_stats() {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "resolve_input" {
+        if let Err(err) = resolve_input(key_file) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "show_inputs" {
+        if let Err(err) = show_inputs() {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "vote_mode" {
+        // the mode as the index of the InputMode variant, and the number of tokens to vote with
+        let mode = InputMode::from_u8(args[3].parse().unwrap()).unwrap();
+        let amount : u64 = args[4].parse().unwrap();
+
+        if let Err(err) = vote_input_mode(key_file, mode, amount) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "verify_draw" {
+        let signature_arg = &args[3];
+        let state_file_arg = &args[4];
+
+        // optionally the btc, eth and sol prices as price and conf pairs, with "none none" for an unavailable oracle
+        let supplied_prices = (args.len() >= 11).then(|| {
+            let mut prices : [Option<OraclePrice>; 3] = [None; 3];
+            for (idx, price) in prices.iter_mut().enumerate() {
+                let price_arg = &args[5 + 2 * idx];
+                let conf_arg = &args[6 + 2 * idx];
+                if price_arg != "none" {
+                    *price = Some(OraclePrice {price : price_arg.parse().unwrap(), conf : conf_arg.parse().unwrap(), expo : 0});
+                }
+            }
+            prices
+        });
+
+        if let Err(err) = verify_draw(signature_arg, state_file_arg, supplied_prices) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "cancel_bid" {
+        if let Err(err) = cancel_bid(key_file) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "init_charities" {
+        if let Err(err) = init_charities(key_file) {
+            exit_with_error(err);
+        }
     }
 
     else if function == "monitor_data" {
         if let Err(err) = monitor_data(key_file) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
+            exit_with_error(err);
         }
     }
 
     else if function == "select_winners" {
         if let Err(err) = select_winners(key_file) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "credit_winners" {
+        if let Err(err) = credit_winners(key_file) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "crank" {
+        if let Err(err) = crank(key_file) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "claim_prize" {
+        // claim for another bidder if their wallet is given, otherwise for the key file's wallet
+        let bidder = match args.get(3) {
+            Some(bidder_arg) => Pubkey::from_str(bidder_arg).unwrap(),
+            None => read_keypair_file(key_file).unwrap().pubkey()
+        };
+
+        if let Err(err) = claim_prize(key_file, &bidder) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "commit_seed" {
+        let passphrase_arg = &args[3];
+
+        if let Err(err) = commit_seed(key_file, passphrase_arg) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "reveal_seed" {
+        let passphrase_arg = &args[3];
+
+        if let Err(err) = reveal_seed(key_file, passphrase_arg) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "resize_state" {
+        let bid_capacity_arg = &args[3];
+        let winner_capacity_arg = &args[4];
+
+        let bid_capacity: u16 = bid_capacity_arg.parse().unwrap();
+        let winner_capacity: u8 = winner_capacity_arg.parse().unwrap();
+
+        if let Err(err) = resize_state(key_file, bid_capacity, winner_capacity) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "migrate_state" {
+        if let Err(err) = migrate_state(key_file) {
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "slash_commit" {
+        if let Err(err) = slash_commit(key_file) {
+            exit_with_error(err);
         }
     }
 
@@ -107,106 +249,505 @@ fn main() {
         let index: u64 = index_arg.parse().unwrap();
         let pressed_button = get_button_from_int(index);
         if let Err(err) = press_button(key_file, pressed_button) {
-            eprintln!("{:?}", err);
-            std::process::exit(1);
+            exit_with_error(err);
+        }
+    }
+
+    else if function == "push_sequence" {
+        // each step as <button index>:<hold frames>, paying a token per step
+        let steps : Vec<SequenceStep> = args[3..].iter().map(|step_arg| {
+            let (index_arg, hold_frames_arg) = step_arg.split_once(':').unwrap();
+            SequenceStep {button : get_button_from_int(index_arg.parse().unwrap()), hold_frames : hold_frames_arg.parse().unwrap()}
+        }).collect();
+
+        if let Err(err) = push_sequence(key_file, steps) {
+            exit_with_error(err);
         }
     }
 
+    // only available when built for a local validator
+    #[cfg(feature = "localnet")]
+    if function == "write_mock_oracles" {
+        let dir_arg = &args[3];
+
+        if let Err(err) = localnet::write_mock_oracles(dir_arg) {
+            exit_with_error(err);
+        }
+    }
 
 
 }
 
 
-fn create_accounts(key_file: &String, amount : u64) ->Result<()> {
+// print the error, naming the program's error if the transaction failed with one, and exit
+fn exit_with_error(err: state::Error) -> ! {
+    if let state::Error::ClientError(client_error) = &err {
+        if let Some(program_error) = state::get_program_error(client_error) {
+            eprintln!("program error {:?}: {}", program_error, program_error);
+        }
+    }
+
+    eprintln!("{:?}", err);
+    std::process::exit(1);
+}
+
+// read the program's config account, which holds the keys and parameters for this deployment
+fn get_config(connection: &RpcClient, program: &Pubkey) -> Result<Config> {
+
+    let (config_account, _bump_seed) = accounts::get_expected_config_key(program);
+    let config_data = connection.get_account_data(&config_account)?;
+
+    Config::load(&config_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))
+}
+
+// look up the address of an active charity in the program's charity registry
+fn get_charity_address(connection: &RpcClient, program: &Pubkey, charity : u8) -> Result<Pubkey> {
+
+    let (charity_registry_account, _bump_seed) = get_expected_charity_registry_key(program);
+    let registry_data = connection.get_account_data(&charity_registry_account)?;
+    let registry = CharityRegistry::load(&registry_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    match registry.get_charity(charity) {
+        Some(entry) if entry.is_active() => Ok(entry.address),
+        _ => Err(state::Error::InvalidConfig(format!("charity {} is not an active charity", charity)))
+    }
+}
+
+// add the charities that were supported before the registry, so they keep their original indices
+fn init_charities(key_file: &String) -> Result<()> {
 
-    // (2) Create a new Keypair for the new account
     let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
 
-    // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    let program = Pubkey::from_str("GRxdexptfCKuXfGpTGREEjtwTrZPTwZSfdSXiWDC11me").unwrap();
-  
-    let (expected_pda, bump_seed) = Pubkey::find_program_address(&[b"token_account"], &program);
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (charity_registry_account, _registry_bump_seed) = get_expected_charity_registry_key(&program);
+
+    let mut instructions = vec![];
+    for (name, address) in DEFAULT_CHARITIES {
+        let charity_data = AddCharityData{name : name.to_string(), address : Pubkey::from_str(address).unwrap()};
+
+        instructions.push(Instruction::new_with_borsh(
+            program,
+            &DPPInstruction::AddCharity { charity_data },
+            vec![
+                AccountMeta::new(wallet.pubkey(), true),
+                AccountMeta::new_readonly(config_account, false),
+                AccountMeta::new(charity_registry_account, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ],
+        ));
+    }
+
+    let signers = [&wallet];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
     );
 
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    println!("Success!");
+    Ok(())
+}
+
+// print the game events logged by a transaction
+fn show_events(signature_arg: &str) -> Result<()> {
+
+    let connection = RpcClient::new(URL);
+
+    let signature = Signature::from_str(signature_arg).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+    let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
+
+    let log_messages = response.transaction.meta.and_then(|meta| Option::<Vec<String>>::from(meta.log_messages)).unwrap_or_default();
+
+    for event in events::decode_events(&log_messages) {
+        println!("{:?}", event);
+    }
+
+    Ok(())
+}
+
+// check that a SelectWinners or RevealSeed transaction chose the winners its seed and the bid ladder in
+// state_file, the data account from before the transaction, say it should have. RPC nodes can't serve the account
+// as it was then, see verify.rs for where the snapshot can come from
+fn verify_draw(signature_arg: &str, state_file_arg: &str, supplied_prices: Option<[Option<OraclePrice>; 3]>) -> Result<()> {
+
+    let connection = RpcClient::new(URL);
+
+    let signature = Signature::from_str(signature_arg).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+    let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
+
+    let log_messages = response.transaction.meta.and_then(|meta| Option::<Vec<String>>::from(meta.log_messages)).unwrap_or_default();
+
+    let state_data = std::fs::read(state_file_arg).map_err(state::Error::FileReadError)?;
+
+    println!("draw in transaction {} at slot {}", signature, response.slot);
+
+    // draws made before the game events were added are checked against their msg! output
+    let mismatches = if verify::is_legacy_draw(&log_messages) {
+        let report = verify::verify_legacy_draw(&state_data, &log_messages, response.block_time, supplied_prices)?;
+        println!("{}", report);
+        report.mismatches.len()
+    }
+    else {
+        let report = verify::verify_draw(&state_data, &log_messages, supplied_prices)?;
+        println!("{}", report);
+        report.mismatches.len()
+    };
+
+    if mismatches > 0 {
+        return Err(state::Error::DrawNotVerified(format!("{} mismatches", mismatches)));
+    }
+
+    Ok(())
+}
+
+// remove this wallet's bid from the ladder
+fn cancel_bid(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+
     let my_token_address = get_associated_token_address(
         &wallet.pubkey(), 
-        &mint_address
+        &config.token_mint
     );
 
-    // in this function we need to create the data account for the program
-    // we need to create the data account off chain because it is too big  (45kb)
-   
-    let data_account = Pubkey::create_with_seed(
-                            &wallet.pubkey(),
-                            "data_account",
-                            &program,
-                        )?;
+    let (expected_bidder_pda, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(&program, &wallet.pubkey());
+    let (commit_account, _commit_bump_seed) = accounts::get_expected_commit_key(&program);
+    let (program_derived_account, _pda_bump_seed) = accounts::get_expected_program_address_key(&program);
+    let program_token_account = accounts::get_expected_program_token_key(&program, &config.token_mint);
 
-    println!("pda: {} {}", expected_pda, bump_seed);
-    println!("data account {}", data_account);
-    println!("token_address: {} {}", program_token_address, my_token_address);
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &DPPInstruction::CancelBid,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(my_token_address, false),
+            AccountMeta::new(expected_bidder_pda, false),
+            AccountMeta::new(config.data_account, false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new_readonly(commit_account, false),
+            AccountMeta::new_readonly(program_derived_account, false),
+            AccountMeta::new(program_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false)
+        ],
+    );
 
-    //return Ok(());
-    let data_account_balance = connection.get_balance(&data_account)?;
-    println!("data account balance: {}", data_account_balance);
-    // we need to create the data account off chain because it is too big
-    // Check if the account has already been initialized
-    if data_account_balance > 0 {
-        println!("data account is already initialized. skipping");
-        
-    }
-    else {
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
 
-        println!("Creating programs data account");
-        
-        let data_size: usize = 49379;
-        let space : u64 = data_size.try_into().unwrap();
-        let lamports = rent::Rent::default().minimum_balance(data_size);
-   
-        println!("Require {} lamports for {} size data", (lamports as f64) / (LAMPORTS_PER_SOL as f64), data_size); 
-        let instruction = solana_sdk::system_instruction::create_account_with_seed(
-            &wallet.pubkey(),
-            &data_account,
-            &wallet.pubkey(),
-            "data_account",
-            lamports,
-            space,
-            &program,
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    println!("Success!");
+    Ok(())
+}
+
+// in commit-reveal mode the secret is the hash of a passphrase chosen by the cranker, which should be long and random
+fn get_secret(passphrase: &str) -> [u8; 32] {
+    hash(passphrase.as_bytes()).to_bytes()
+}
+
+fn send_instruction(connection: &RpcClient, wallet: &Keypair, instruction: Instruction) -> Result<Signature> {
+
+    let signers = [wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(signature)
+}
+
+// commit to the secret for the next draw, putting up the bond from the config
+fn commit_seed(key_file: &String, passphrase: &str) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (commit_account, _commit_bump_seed) = accounts::get_expected_commit_key(&program);
+
+    let instruction = Instruction::new_with_bytes(
+        program,
+        &DPPInstruction::CommitSeed { commitment : get_commitment(&get_secret(passphrase)) }.pack(),
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(commit_account, false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    send_instruction(&connection, &wallet, instruction)?;
+
+    println!("committed, reveal with the same passphrase within {} slots to get the bond of {} SOL back", config.reveal_timeout_slots, config.commit_bond as f64 / LAMPORTS_PER_SOL as f64);
+    Ok(())
+}
+
+// reveal the secret, which selects the winners
+fn reveal_seed(key_file: &String, passphrase: &str) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (commit_account, _commit_bump_seed) = accounts::get_expected_commit_key(&program);
+    let program_token_address = accounts::get_expected_program_token_key(&program, &config.token_mint);
+
+    let instruction = Instruction::new_with_bytes(
+        program,
+        &DPPInstruction::RevealSeed { secret : get_secret(passphrase) }.pack(),
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(commit_account, false),
+            AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+            AccountMeta::new(config.data_account, false),
+            AccountMeta::new_readonly(program_token_address, false),
+            AccountMeta::new_readonly(config_account, false)
+        ],
+    );
+
+    send_instruction(&connection, &wallet, instruction)?;
+
+    println!("Success!");
+    Ok(())
+}
+
+// slash a commitment that wasn't revealed in time, sending its bond to the authority
+fn slash_commit(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (commit_account, _commit_bump_seed) = accounts::get_expected_commit_key(&program);
+
+    let instruction = Instruction::new_with_bytes(
+        program,
+        &DPPInstruction::SlashCommit.pack(),
+        vec![
+            AccountMeta::new(commit_account, false),
+            AccountMeta::new(config.authority, false),
+            AccountMeta::new_readonly(config_account, false)
+        ],
+    );
+
+    send_instruction(&connection, &wallet, instruction)?;
+
+    println!("Success!");
+    Ok(())
+}
+
+// grow the data account so the ladder holds bid_capacity bids and winner_capacity winners. the program can only grow
+// the account by 10KB in each instruction, so ResizeState is sent until the account reports the new capacity
+fn resize_state(key_file: &String, bid_capacity: u16, winner_capacity: u8) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+
+    let capacity = Capacity {bids : bid_capacity as usize, winners : winner_capacity as usize};
+    println!("resizing data account {} to {:?}, {} bytes", config.data_account, capacity, get_data_account_size(&capacity));
+
+    loop {
+        let mut state_data = connection.get_account_data(&config.data_account)?;
+        let current_capacity = State::load_mut(&mut state_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?.get_capacity();
+
+        println!("data account has {:?} in {} bytes", current_capacity, state_data.len());
+        if current_capacity == capacity {
+            break;
+        }
+
+        let instruction = Instruction::new_with_bytes(
+            program,
+            &DPPInstruction::ResizeState {resize_data : ResizeData {bid_capacity, winner_capacity}}.pack(),
+            vec![
+                AccountMeta::new(wallet.pubkey(), true),
+                AccountMeta::new(config.data_account, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config_account, false)
+            ],
         );
 
-        let signers = [&wallet];
-        let instructions = vec![instruction];
-        let recent_hash = connection.get_latest_blockhash()?;
-    
-        let txn = Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&wallet.pubkey()),
-            &signers,
-            recent_hash,
+        send_instruction(&connection, &wallet, instruction)?;
+    }
+
+    println!("Success!");
+    Ok(())
+}
+
+fn migrate_state(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+
+    println!("migrating data account {} to layout version {}", config.data_account, STATE_LAYOUT_VERSION);
+
+    loop {
+        let state_data = connection.get_account_data(&config.data_account)?;
+        let layout_version = get_state_layout_version(&state_data);
+
+        println!("data account has layout version {:?} in {} bytes", layout_version, state_data.len());
+        if layout_version == Some(STATE_LAYOUT_VERSION) {
+            break;
+        }
+
+        let instruction = Instruction::new_with_bytes(
+            program,
+            &DPPInstruction::MigrateState.pack(),
+            vec![
+                AccountMeta::new(wallet.pubkey(), true),
+                AccountMeta::new(config.data_account, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config_account, false)
+            ],
         );
-    
-    
-        let signature = connection.send_and_confirm_transaction(&txn)?;
-        println!("signature: {}", signature);
-        let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
-        println!("result: {:#?}", response); 
 
+        send_instruction(&connection, &wallet, instruction)?;
+    }
+
+    println!("Success!");
+    Ok(())
+}
+
+// if this bid will replace the oldest bid in a full ladder, find the token account and data account of
+// that bidder so the program can record the eviction
+fn get_evicted_bidder_accounts(connection: &RpcClient, program: &Pubkey, config: &Config, bidder_token_address: &Pubkey, bidder_data_account: &Pubkey) -> Result<Option<(Pubkey, Pubkey)>> {
+
+    let mut state_data = connection.get_account_data(&config.data_account)?;
+    let (state, bid_index) = load_ladder_mut(&mut state_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    // an existing bid is just added to, so nothing is evicted
+    if let Ok(bidder_data) = connection.get_account_data(bidder_data_account) {
+        let bidder_data = BidderData::load(&bidder_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+        if state.bid_keys.get(bidder_data.index as usize) == Some(bidder_token_address) {
+            return Ok(None);
+        }
     }
-        
 
-    let init_data =  InitData{amount : amount};
+    // use the program's bid index, which only replaces the oldest bid when there are no empty slots
+    if bid_index.n_free.get() > 0 {
+        return Ok(None);
+    }
+
+    let oldest_bid_index = match bid_index.get_oldest() {
+        Some(oldest_bid_index) => oldest_bid_index,
+        None => return Ok(None)
+    };
+
+    let evicted_token_address = state.bid_keys[oldest_bid_index];
+
+    // the data account is derived from the owner of the evicted token account, if it still exists
+    let evicted_owner = match connection.get_account_data(&evicted_token_address) {
+        Ok(token_data) => match spl_token::state::Account::unpack(&token_data) {
+            Ok(token_account) => token_account.owner,
+            Err(_) => Pubkey::default()
+        },
+        Err(_) => Pubkey::default()
+    };
+
+    let (evicted_bidder_data_account, _bump_seed) = accounts::get_expected_bidder_data_key(program, &evicted_owner);
+
+    println!("bid will replace the oldest bid at position {} from {}", oldest_bid_index, evicted_token_address);
+
+    Ok(Some((evicted_token_address, evicted_bidder_data_account)))
+}
+
+fn create_accounts(key_file: &String, amount : u64) ->Result<()> {
+
+    // (2) Create a new Keypair for the new account
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    // (3) Create RPC client to be used to talk to Solana cluster
+    let connection = RpcClient::new(URL);
+
+    let program = dp_common::game::id();
+  
+    // start from the mainnet config, with this wallet as the authority
+    let mut config = accounts::get_mainnet_config(&program);
+    config.authority = wallet.pubkey();
+    config.data_account = accounts::get_expected_data_account_key(&wallet.pubkey(), &program);
+
+    // on a local validator the oracles are the mock accounts from write_mock_oracles
+    #[cfg(feature = "localnet")]
+    localnet::use_mock_oracles(&mut config);
+
+    let (expected_pda, bump_seed) = accounts::get_expected_program_address_key(&program);
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (vote_tally_account, _vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(&program);
+    let (input_log_account, _input_log_bump_seed) = accounts::get_expected_input_log_key(&program);
+    let (mode_ballot_account, _mode_ballot_bump_seed) = accounts::get_expected_mode_ballot_key(&program);
+    let (push_stats_account, _push_stats_bump_seed) = accounts::get_expected_push_stats_key(&program);
+    let mint_address = config.token_mint;
+    let program_token_address = accounts::get_expected_program_token_key(&program, &mint_address);
+
+    let my_token_address = get_associated_token_address(
+        &wallet.pubkey(), 
+        &mint_address
+    );
+
+    // the program creates the data account with room for a small ladder, use resize_state to grow it
+    let data_account = config.data_account;
+
+    println!("pda: {} {}", expected_pda, bump_seed);
+    println!("data account {}", data_account);
+    println!("token_address: {} {}", program_token_address, my_token_address);
+
+    let init_data =  InitData{amount, config};
 
     let instruction = Instruction::new_with_borsh(
         program,
-        &DPPInstruction::CreateDataAccount {init_data : init_data},
+        &DPPInstruction::CreateDataAccount {init_data},
         vec![
-            AccountMeta::new_readonly(wallet.pubkey(), true),
+            AccountMeta::new(wallet.pubkey(), true),
             AccountMeta::new(expected_pda, false),
             AccountMeta::new(data_account, false),
 
@@ -216,7 +757,14 @@ fn create_accounts(key_file: &String, amount : u64) ->Result<()> {
 
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_associated_token_account::id(), false),
-            AccountMeta::new(solana_sdk::system_program::id(), false)
+            AccountMeta::new(solana_sdk::system_program::id(), false),
+
+            AccountMeta::new(config_account, false),
+            AccountMeta::new(vote_tally_account, false),
+            AccountMeta::new(input_log_account, false),
+            AccountMeta::new(mode_ballot_account, false),
+            AccountMeta::new(push_stats_account, false),
+            AccountMeta::new_readonly(accounts::get_expected_program_data_key(&program), false)
         ],
     );
 
@@ -238,79 +786,64 @@ fn create_accounts(key_file: &String, amount : u64) ->Result<()> {
     println!("result: {:#?}", response); 
 
 
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
 }
 
 
-fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
+fn place_bid(key_file: &String, charity : u8, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
 
     println!("In place_bid");
-    let charity_key_map = enum_map!{
-        Charity::EvidenceAction => "9fF5EQV6FVy7V5SaHBXfAaTUBvuyimQ9X3jarc2mRHzi",
-        Charity::GirlsWhoCode => "5qrmDeRFhBTnEkqJsRKJAkTJzrZnyC9bWmRhL6RZqWt1",
-        Charity::OneTreePlanted => "GeCaNYhRswBFoTxtNaf9wKYJEBZoxHa9Fao6aQKzDDo2",
-        Charity::OutrightActionInt => "AiY4t79umvBqGvR43f5rL8jR8F2JZwG87mB55adAF2cf",
-        Charity::TheLifeYouCanSave => "8qQpHYjLkNiKvLtFzrjzgFZfveNJZ9AnQuBUoQj1t3DB",
-        Charity::UkraineERF  => "E6TPLh77cx9b5aWsmxM8geit2PBLVEBVAvF6ye9Qe4ZQ",
-        Charity::WaterOrg => "5UNSVwtiSdfsCbJokL4fHtzV28mVNi8fQkMjPQw6v7Xd",
-        Charity::InvalidCharity => "NULL"
-    };
    
     let wallet = read_keypair_file(key_file).unwrap();
-    let program = Pubkey::from_str("GRxdexptfCKuXfGpTGREEjtwTrZPTwZSfdSXiWDC11me").unwrap();
-    let daoplays  = Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap();
+    let program = dp_common::game::id();
 
-    let program_data_account = Pubkey::create_with_seed(
-        &daoplays,
-        "data_account",
-        &program,
-    )?;
+    // (3) Create RPC client to be used to talk to Solana cluster
+    let connection = RpcClient::new(URL);
 
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
 
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"token_account"], &program);
+    let daoplays  = config.authority;
 
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
-    );
+    let program_data_account = config.data_account;
+
+    let mint_address = config.token_mint;
+    let program_token_address = accounts::get_expected_program_token_key(&program, &mint_address);
     let my_token_address = get_associated_token_address(
         &wallet.pubkey(), 
         &mint_address
     );
 
-    let (expected_bidder_pda, _bidder_bump_seed) = Pubkey::find_program_address(&[&wallet.pubkey().to_bytes()], &program);
-
-    if charity == Charity::InvalidCharity {
-        return Ok(println!("InvalidCharity!"));
-    }
+    let (expected_bidder_pda, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(&program, &wallet.pubkey());
 
-    let charity_key = Pubkey::from_str(charity_key_map[charity]).unwrap();
+    let charity_key = get_charity_address(&connection, &program, charity)?;
+    let (charity_registry_account, _registry_bump_seed) = get_expected_charity_registry_key(&program);
+    let (commit_account, _commit_bump_seed) = accounts::get_expected_commit_key(&program);
 
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let connection = RpcClient::new(URL);
+    println!("wallet {}", wallet.pubkey()); 
+    println!("my_token_address {}", my_token_address);
+    println!("expected_bidder_pda  {}\n", expected_bidder_pda);
 
-    println!("wallet {}", wallet.pubkey().to_string()); 
-    println!("my_token_address {}", my_token_address.to_string());
-    println!("expected_bidder_pda  {}\n", expected_bidder_pda.to_string());
+    println!("daoplays {}", daoplays);
+    println!("charity_key  {}", charity_key);
 
-    println!("daoplays {}", daoplays.to_string());
-    println!("charity_key  {}", charity_key.to_string());
+    println!("data account {}\n", program_data_account);
+    println!("program_token_address {}", program_token_address);
 
-    println!("data account {}\n", program_data_account.to_string());
-    println!("program_token_address {}", program_token_address.to_string());
+    println!("mint_address {}", mint_address);
 
-    println!("mint_address {}", mint_address.to_string());
 
+    let evicted_accounts = get_evicted_bidder_accounts(&connection, &program, &config, &my_token_address, &expected_bidder_pda)?;
 
     let lpm : f64 = LAMPORTS_PER_SOL as f64;
     println!("total bid {} + {} = {}", (amount_charity as f64) / lpm, (amount_dao as f64) / lpm, ((amount_charity + amount_dao) as f64) / lpm);
 
-    let bid_data =  BidData{charity : charity, amount_charity : amount_charity,  amount_dao : amount_dao};
+    let bid_data =  BidData{charity, amount_charity,  amount_dao};
     
-    let instruction = Instruction::new_with_borsh(
+    let mut instruction = Instruction::new_with_borsh(
         program,
-        &DPPInstruction::PlaceBid { bid_data : bid_data},
+        &DPPInstruction::PlaceBid { bid_data},
         vec![
             AccountMeta::new(wallet.pubkey(), true),
             AccountMeta::new(my_token_address, false),
@@ -328,10 +861,19 @@ fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount
 
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_associated_token_account::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false)
+            AccountMeta::new_readonly(system_program::id(), false),
+
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new(charity_registry_account, false),
+            AccountMeta::new_readonly(commit_account, false)
         ],
     );
 
+    if let Some((evicted_token_address, evicted_bidder_data_account)) = evicted_accounts {
+        instruction.accounts.push(AccountMeta::new_readonly(evicted_token_address, false));
+        instruction.accounts.push(AccountMeta::new(evicted_bidder_data_account, false));
+    }
+
     let signers = [&wallet];
     let instructions = vec![instruction];
     let recent_hash = connection.get_latest_blockhash()?;
@@ -349,43 +891,39 @@ fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount
     let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
     println!("result: {:#?}", response);
 
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
 }
 
 
 pub fn monitor_data(_key_file: &String) -> Result<()> {
 
     // (2) Create a new Keypair for the new account
-    let program = Pubkey::from_str("GRxdexptfCKuXfGpTGREEjtwTrZPTwZSfdSXiWDC11me").unwrap();
-    let daoplays  = Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap();
-
-    let program_data_account = Pubkey::create_with_seed(
-        &daoplays,
-        "data_account",
-        &program,
-    )?;
+    let program = dp_common::game::id();
 
     // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    let response = connection.get_account_data(&program_data_account)?;
+    let program_data_account = get_config(&connection, &program)?.data_account;
+
+    let mut response = connection.get_account_data(&program_data_account)?;
     //println!("data in account: {}", data_pubkey);
     //println!("{:#?}", response);
 
-    let current_state = State::try_from_slice(&response[..]).unwrap();
+    let current_state = State::load_mut(&mut response).unwrap();
 
-    println!("data: n_bidders {} bid_amount: {}", current_state.n_bidders, (current_state.total_bid_amount as f64) / (LAMPORTS_PER_SOL as f64));
+    println!("data: n_bidders {} bid_amount: {} capacity: {:?}", current_state.n_bidders, (current_state.total_bid_amount.get() as f64) / (LAMPORTS_PER_SOL as f64), current_state.get_capacity());
 
-    for i in 0..1024 {
+    for i in 0..current_state.bid_keys.len() {
         println!("bidders: {} {} {} {}", i, current_state.bid_keys[i], current_state.bid_amounts[i],  current_state.bid_times[i]);
     }
 
     let n_winners = current_state.n_winners;
-    let winners = current_state.winners;
+    let winners = &current_state.winners;
 
     println!("\n\nn_winners:  {}", n_winners);
-    for i in 0..MAX_WINNERS {
-        println!("winner: {} {}", i, winners[i]);
+    for (i, winner) in winners.iter().enumerate() {
+        println!("winner: {} {}", i, winner);
     }
 
     Ok(())
@@ -400,34 +938,25 @@ fn select_winners(key_file: &String) ->Result<()> {
     // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    let program = Pubkey::from_str("GRxdexptfCKuXfGpTGREEjtwTrZPTwZSfdSXiWDC11me").unwrap();
-    let daoplays  = Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap();
+    let program = dp_common::game::id();
 
-    // in this function we need to create the data account for the program
-    // we need to create the data account off chain because it is too big  (45kb)
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"token_account"], &program);
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
-    );
-    let data_account = Pubkey::create_with_seed(
-                            &daoplays,
-                            "data_account",
-                            &program,
-                        )?;
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+
+    let program_token_address = accounts::get_expected_program_token_key(&program, &config.token_mint);
+    let data_account = config.data_account;
 
     // we will use 3 streams, BTC,  ETH and SOL
-    let btc_key =   Pubkey::from_str("GVXRSBjFk6e6J3NbVPXohDJetcTjaeeuykUpbQF8UoMU").unwrap();
-    let eth_key =   Pubkey::from_str("JBu1AL4obBcCMqKBBxhpWCNUt136ijcuMZLFvTP7iWdB").unwrap();
-    let sol_key =   Pubkey::from_str("H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG").unwrap();
+    let btc_key =   config.btc_oracle;
+    let eth_key =   config.eth_oracle;
+    let sol_key =   config.sol_oracle;
 
-    println!("wallet {}",  wallet.pubkey().to_string());
-    println!("btc_key {}",  btc_key.to_string());
-    println!("eth_key {}",  eth_key.to_string());
-    println!("sol_key {}",  sol_key.to_string());
-    println!("data_account {}",  data_account.to_string());
-    println!("program_token_address {}",  program_token_address.to_string());
+    println!("wallet {}",  wallet.pubkey());
+    println!("btc_key {}",  btc_key);
+    println!("eth_key {}",  eth_key);
+    println!("sol_key {}",  sol_key);
+    println!("data_account {}",  data_account);
+    println!("program_token_address {}",  program_token_address);
 
 
     let instruction = Instruction::new_with_borsh(
@@ -439,7 +968,8 @@ fn select_winners(key_file: &String) ->Result<()> {
             AccountMeta::new(eth_key, false),
             AccountMeta::new(sol_key, false),
             AccountMeta::new(data_account, false),
-            AccountMeta::new(program_token_address, false)
+            AccountMeta::new(program_token_address, false),
+            AccountMeta::new_readonly(config_account, false)
         ],
     );
  
@@ -454,89 +984,460 @@ fn select_winners(key_file: &String) ->Result<()> {
         recent_hash,
     );
 
+    // keep a copy of the ladder the draw will be made from, so it can be checked later with verify_draw
+    let state_file = format!("draw_{}.state", txn.signatures[0]);
+    std::fs::write(&state_file, connection.get_account_data(&data_account)?).map_err(state::Error::FileWriteError)?;
+    println!("saved the bid ladder to {}", state_file);
 
     let signature = connection.send_and_confirm_transaction(&txn)?;
     println!("signature: {}", signature);
     let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
     println!("result: {:#?}", response); 
 
-    let data_response = connection.get_account_data(&data_account)?;
-    let current_state = State::try_from_slice(&data_response[..]).unwrap();
+    let mut data_response = connection.get_account_data(&data_account)?;
+    let current_state = State::load_mut(&mut data_response).unwrap();
 
 
     let n_winners = current_state.n_winners;
-    let winners = current_state.winners;
+    let winners = &current_state.winners;
 
     println!("\n\nn_winners:  {}", n_winners);
-    for i in 0..MAX_WINNERS {
-        println!("winner: {} {}", i, winners[i]);
+    for (i, winner) in winners.iter().enumerate() {
+        println!("winner: {} {}", i, winner);
     }
- 
+
+    // credit the winners so they can claim their prizes
+    send_credit_winners(&connection, &wallet, &program, &config)?;
+
+  
+    println!("Success!");
+    Ok(())
+}
+
+// the wallet and bidder data account of each winner waiting to be credited. the program needs the wallet of each
+// winner, which is the owner of their token account, so a winner whose token account has been closed is left for
+// when it can be found
+fn get_winner_accounts(connection: &RpcClient, program: &Pubkey, config: &Config) -> Result<Vec<AccountMeta>> {
+
+    let mut state_data = connection.get_account_data(&config.data_account)?;
+    let state = State::load_mut(&mut state_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
     let mut accounts : Vec<AccountMeta> = Vec::new();
-    accounts.push(AccountMeta::new_readonly(wallet.pubkey(), true));
-    accounts.push(AccountMeta::new(expected_pda, false));
-    accounts.push(AccountMeta::new(program_token_address, false));
-    accounts.push(AccountMeta::new(data_account, false));
-    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
-    for i in 0..(n_winners as usize) {
-        accounts.push(AccountMeta::new(winners[i], false));
+    for winner in state.winners.iter().take(state.n_winners as usize) {
+        if *winner == system_program::id() {
+            continue;
+        }
+
+        let token_account = connection.get_account_data(winner).ok().and_then(|data| spl_token::state::Account::unpack(&data).ok());
+        match token_account {
+            Some(token_account) => {
+                let (bidder_data_account, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(program, &token_account.owner);
+                accounts.push(AccountMeta::new_readonly(token_account.owner, false));
+                accounts.push(AccountMeta::new(bidder_data_account, false));
+            },
+            None => println!("can't find the owner of winner {}, skipping", winner)
+        }
     }
 
+    Ok(accounts)
+}
+
+// add the prizes of the winners waiting to be credited to their bidder data accounts
+fn send_credit_winners(connection: &RpcClient, wallet: &Keypair, program: &Pubkey, config: &Config) -> Result<()> {
+
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(program);
+
+    let winner_accounts = get_winner_accounts(connection, program, config)?;
+    if winner_accounts.is_empty() {
+        println!("no winners to credit");
+        return Ok(());
+    }
+
+    let mut accounts : Vec<AccountMeta> = vec![
+        AccountMeta::new(wallet.pubkey(), true),
+        AccountMeta::new(config.data_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(config_account, false)
+    ];
+    accounts.extend(winner_accounts);
+
+    let instruction = Instruction::new_with_borsh(*program, &DPPInstruction::CreditWinners, accounts);
+    send_instruction(connection, wallet, instruction)?;
+
+    Ok(())
+}
+
+fn credit_winners(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    send_credit_winners(&connection, &wallet, &program, &config)?;
+
+    println!("Success!");
+    Ok(())
+}
+
+// the most bidders a crank is sent, so the transaction stays within its account limit. winners beyond these are
+// credited by the next crank
+const MAX_CRANK_BIDDERS : usize = 10;
+
+// the wallet and bidder data account of the winners waiting to be credited, followed by the largest bidders in the
+// ladder so that the crank can credit whoever it draws
+fn get_crank_bidder_accounts(connection: &RpcClient, program: &Pubkey, config: &Config) -> Result<Vec<AccountMeta>> {
+
+    let mut accounts = get_winner_accounts(connection, program, config)?;
+
+    let mut state_data = connection.get_account_data(&config.data_account)?;
+    let state = State::load_mut(&mut state_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
 
-    let send_instruction = Instruction::new_with_borsh(
+    let mut bids : Vec<(Pubkey, u64)> = state.bid_keys.iter().zip(state.bid_amounts.iter())
+        .filter(|(key, _)| **key != Pubkey::default())
+        .map(|(key, amount)| (*key, amount.get()))
+        .collect();
+    bids.sort_by_key(|bid| std::cmp::Reverse(bid.1));
+
+    for (bid_key, _amount) in bids {
+        if accounts.len() >= 2 * MAX_CRANK_BIDDERS {
+            break;
+        }
+
+        let token_account = connection.get_account_data(&bid_key).ok().and_then(|data| spl_token::state::Account::unpack(&data).ok());
+        if let Some(token_account) = token_account {
+            if accounts.iter().any(|account| account.pubkey == token_account.owner) {
+                continue;
+            }
+            let (bidder_data_account, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(program, &token_account.owner);
+            accounts.push(AccountMeta::new_readonly(token_account.owner, false));
+            accounts.push(AccountMeta::new(bidder_data_account, false));
+        }
+    }
+
+    Ok(accounts)
+}
+
+// credit the winners of the last draw, select new ones and credit them, for the tip set in the config
+fn crank(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (crank_tip_address, _crank_tip_bump_seed) = accounts::get_expected_crank_tip_key(&program);
+    let crank_tip_token_address = accounts::get_expected_crank_tip_token_key(&program, &config.token_mint);
+    let program_token_address = accounts::get_expected_program_token_key(&program, &config.token_mint);
+
+    // the token tip is sent to the wallet's associated token account, so make sure it has one
+    let wallet_token_address = get_associated_token_address(&wallet.pubkey(), &config.token_mint);
+    if config.crank_tip_tokens > 0 && connection.get_account_data(&wallet_token_address).is_err() {
+        println!("creating token account {} for the tip", wallet_token_address);
+        let instruction = spl_associated_token_account::instruction::create_associated_token_account(&wallet.pubkey(), &wallet.pubkey(), &config.token_mint, &spl_token::id());
+        send_instruction(&connection, &wallet, instruction)?;
+    }
+
+    let mut accounts : Vec<AccountMeta> = vec![
+        AccountMeta::new(wallet.pubkey(), true),
+        AccountMeta::new(wallet_token_address, false),
+        AccountMeta::new_readonly(config.btc_oracle, false),
+        AccountMeta::new_readonly(config.eth_oracle, false),
+        AccountMeta::new_readonly(config.sol_oracle, false),
+        AccountMeta::new(config.data_account, false),
+        AccountMeta::new(crank_tip_address, false),
+        AccountMeta::new(crank_tip_token_address, false),
+        AccountMeta::new(program_token_address, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(config_account, false)
+    ];
+    accounts.extend(get_crank_bidder_accounts(&connection, &program, &config)?);
+
+    let instruction = Instruction::new_with_borsh(program, &DPPInstruction::Crank, accounts);
+    send_instruction(&connection, &wallet, instruction)?;
+
+    println!("Success!");
+    Ok(())
+}
+
+// send the prizes credited to a bidder to their token account, which anyone can do on the bidder's behalf
+fn claim_prize(key_file: &String, bidder: &Pubkey) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (expected_pda, _bump_seed) = accounts::get_expected_program_address_key(&program);
+    let program_token_address = accounts::get_expected_program_token_key(&program, &config.token_mint);
+    let (bidder_data_account, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(&program, bidder);
+
+    let bidder_data = BidderData::load(&connection.get_account_data(&bidder_data_account)?).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+    println!("bidder {} has won {} times and has {} tokens to claim", bidder, bidder_data.n_wins, bidder_data.claimable);
+
+    let instruction = Instruction::new_with_borsh(
         program,
-        &DPPInstruction::SendTokens,
-        accounts
+        &DPPInstruction::ClaimPrize,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new_readonly(*bidder, false),
+            AccountMeta::new(get_associated_token_address(bidder, &config.token_mint), false),
+            AccountMeta::new(bidder_data_account, false),
+            AccountMeta::new_readonly(expected_pda, false),
+            AccountMeta::new(program_token_address, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new(config.data_account, false)
+        ],
     );
 
+    send_instruction(&connection, &wallet, instruction)?;
 
-    let send_signers = [&wallet];
-    let send_instructions = vec![send_instruction];
-    let send_recent_hash = connection.get_latest_blockhash()?;
+    println!("Success!");
+    Ok(())
+}
 
-    let send_txn = Transaction::new_signed_with_payer(
-        &send_instructions,
-        Some(&wallet.pubkey()),
-        &send_signers,
-        send_recent_hash,
+// print the token weighted button votes from the vote tally account
+fn show_votes(slot: Option<u64>) -> Result<()> {
+
+    let program = dp_common::game::id();
+    let connection = RpcClient::new(URL);
+
+    let (vote_tally_account, _vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(&program);
+    let vote_tally_data = connection.get_account_data(&vote_tally_account)?;
+    let vote_tally = VoteTally::load(&vote_tally_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    let buttons = [Button::A, Button::B, Button::Up, Button::Down, Button::Left, Button::Right, Button::Start, Button::Select];
+
+    for slot_votes in vote_tally.iter_recent().filter(|slot_votes| slot.is_none_or(|slot| slot_votes.slot.get() == slot)) {
+        println!("slot {}: {} presses", slot_votes.slot, slot_votes.n_presses);
+        for button in buttons.iter() {
+            println!("    {:?} {}", button, slot_votes.get_tally(button));
+        }
+    }
+
+    Ok(())
+}
+
+// print how the config splits each press and where the tokens pushed so far have gone
+fn show_push_stats() -> Result<()> {
+
+    let program = dp_common::game::id();
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (push_stats_account, _push_stats_bump_seed) = accounts::get_expected_push_stats_key(&program);
+    let push_stats_data = connection.get_account_data(&push_stats_account)?;
+    let push_stats = PushStats::try_from_slice(&push_stats_data).map_err(state::Error::SerializationError)?;
+
+    println!("each press burns {} bps and sends {} bps to the treasury {}, the rest goes to the prize pool", config.push_burn_bps, config.push_treasury_bps, config.treasury);
+    println!("{} presses pushed {} tokens: {} burned, {} to the treasury, {} to the prize pool", push_stats.n_presses, push_stats.tokens_pushed, push_stats.tokens_burned, push_stats.tokens_to_treasury, push_stats.tokens_to_prizes);
+
+    Ok(())
+}
+
+// resolve the next closed window of button votes into the input log
+fn resolve_input(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (vote_tally_account, _vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(&program);
+    let (input_log_account, _input_log_bump_seed) = accounts::get_expected_input_log_key(&program);
+    let (mode_ballot_account, _mode_ballot_bump_seed) = accounts::get_expected_mode_ballot_key(&program);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &DPPInstruction::ResolveInput,
+        vec![
+            AccountMeta::new_readonly(wallet.pubkey(), true),
+            AccountMeta::new_readonly(vote_tally_account, false),
+            AccountMeta::new(input_log_account, false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new(mode_ballot_account, false)
+        ],
     );
 
-    let send_signature = connection.send_and_confirm_transaction(&send_txn)?;
-    println!("signature: {}", send_signature);
-    let send_response = connection.get_transaction(&send_signature, UiTransactionEncoding::Json)?;
-    println!("result: {:#?}", send_response); 
+    send_instruction(&connection, &wallet, instruction)?;
 
-  
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
+}
+
+// print the resolved inputs, checking each one against the vote tally while it still has the window's votes
+fn show_inputs() -> Result<()> {
+
+    let program = dp_common::game::id();
+    let connection = RpcClient::new(URL);
+
+    let (input_log_account, _input_log_bump_seed) = accounts::get_expected_input_log_key(&program);
+    let input_log_data = connection.get_account_data(&input_log_account)?;
+    let input_log = InputLog::load(&input_log_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    let (vote_tally_account, _vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(&program);
+    let vote_tally_data = connection.get_account_data(&vote_tally_account)?;
+    let vote_tally = VoteTally::load(&vote_tally_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    let (mode_ballot_account, _mode_ballot_bump_seed) = accounts::get_expected_mode_ballot_key(&program);
+    let mode_ballot_data = connection.get_account_data(&mode_ballot_account)?;
+    let mode_ballot = ModeBallot::load(&mode_ballot_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    let buttons = [Button::A, Button::B, Button::Up, Button::Down, Button::Left, Button::Right, Button::Start, Button::Select];
+
+    println!("input mode {:?} since slot {}", mode_ballot.get_mode().map_err(|e| state::Error::InvalidConfig(e.to_string()))?, mode_ballot.mode_start_slot);
+    if mode_ballot.ballot_end_slot.get() != 0 {
+        println!("ballot closing at slot {} with {} votes: {:?}", mode_ballot.ballot_end_slot, mode_ballot.n_votes, mode_ballot.votes);
+    }
+
+    println!("{} windows resolved, next window starts at slot {}", input_log.n_resolved, input_log.next_slot);
+
+    for input in input_log.iter_recent() {
+        let (start_slot, end_slot) = (input.start_slot.get(), input.end_slot.get());
+        let button = match buttons.get(input.button as usize) {
+            Some(button) => format!("{:?}", button),
+            None => "none".to_string()
+        };
+
+        let mode = InputMode::from_u8(input.mode);
+
+        let check = match (vote_tally.get_window_votes(start_slot, end_slot), mode) {
+            (Some(votes), Some(mode)) if votes.complete && input.complete == 1 => {
+                let random = get_input_random(start_slot, end_slot, &votes.tallies);
+                let expected_button = resolve_button(mode, &votes, random);
+                if random == input.random.get() && expected_button == input.button { "verified" } else { "MISMATCH" }
+            },
+            _ => "votes no longer in the tally"
+        };
+
+        println!("slots {} to {} ({:?}): {} from {} tokens ({})", start_slot, end_slot, mode, button, input.total, check);
+
+        // the buttons the game plays for the window, which are whole presses in anarchy and auction
+        match input_log.get_window_inputs(input) {
+            Some(inputs) => {
+                let inputs : Vec<String> = inputs.iter().map(|(button, hold_frames)| match buttons.get(*button as usize) {
+                    Some(button) => format!("{:?}:{}", button, hold_frames),
+                    None => "none".to_string()
+                }).collect();
+                println!("    plays: {}", inputs.join(" "));
+            },
+            None => println!("    presses no longer in the log")
+        }
+    }
+
+    Ok(())
 }
 
-fn press_button(key_file: &String, pressed_button: state::Button) -> Result<()> {
+// spend tokens on a vote to change the input mode
+fn vote_input_mode(key_file: &String, mode: InputMode, amount: u64) -> Result<()> {
 
     let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
 
     let connection = RpcClient::new(URL);
 
-    let program = Pubkey::from_str("GRxdexptfCKuXfGpTGREEjtwTrZPTwZSfdSXiWDC11me").unwrap();
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (mode_ballot_account, _mode_ballot_bump_seed) = accounts::get_expected_mode_ballot_key(&program);
+    let program_token_address = accounts::get_expected_program_token_key(&program, &config.token_mint);
 
-    // devnet version:
-    //let mint_address = Pubkey::from_str("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h").unwrap();
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &DPPInstruction::VoteInputMode {mode_vote_data : ModeVoteData {mode, amount}},
+        vec![
+            AccountMeta::new_readonly(wallet.pubkey(), true),
+            AccountMeta::new(get_associated_token_address(&wallet.pubkey(), &config.token_mint), false),
+            AccountMeta::new(program_token_address, false),
+            AccountMeta::new(config.token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new(mode_ballot_account, false),
+            AccountMeta::new(get_associated_token_address(&config.treasury, &config.token_mint), false)
+        ],
+    );
 
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"token_account"], &program);
+    send_instruction(&connection, &wallet, instruction)?;
 
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
+    println!("Success!");
+    Ok(())
+}
+
+fn push_sequence(key_file: &String, steps: Vec<SequenceStep>) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::game::id();
+
+    let connection = RpcClient::new(URL);
+
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (vote_tally_account, _vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(&program);
+    let (push_stats_account, _push_stats_bump_seed) = accounts::get_expected_push_stats_key(&program);
+    let (input_log_account, _input_log_bump_seed) = accounts::get_expected_input_log_key(&program);
+    let program_token_address = accounts::get_expected_program_token_key(&program, &config.token_mint);
+
+    let amount = steps.len() as u64;
+    let sequence_data = SequenceData {steps, amount};
+
+    // the same accounts as PushButton
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &DPPInstruction::PushSequence {sequence_data},
+        vec![
+            AccountMeta::new_readonly(wallet.pubkey(), true),
+            AccountMeta::new(get_associated_token_address(&wallet.pubkey(), &config.token_mint), false),
+            AccountMeta::new(program_token_address, false),
+            AccountMeta::new(config.token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new(vote_tally_account, false),
+            AccountMeta::new(get_associated_token_address(&config.treasury, &config.token_mint), false),
+            AccountMeta::new(push_stats_account, false),
+            AccountMeta::new(input_log_account, false)
+        ],
     );
+
+    send_instruction(&connection, &wallet, instruction)?;
+
+    println!("Success!");
+    Ok(())
+}
+
+fn press_button(key_file: &String, pressed_button: Button) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = dp_common::game::id();
+
+    // the mint is read from the config, so this works on any cluster the program is deployed to
+    let config = get_config(&connection, &program)?;
+    let (config_account, _config_bump_seed) = accounts::get_expected_config_key(&program);
+    let (vote_tally_account, _vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(&program);
+    let (push_stats_account, _push_stats_bump_seed) = accounts::get_expected_push_stats_key(&program);
+    let (input_log_account, _input_log_bump_seed) = accounts::get_expected_input_log_key(&program);
+    let treasury_token_address = get_associated_token_address(&config.treasury, &config.token_mint);
+    let mint_address = config.token_mint;
+
+    let program_token_address = accounts::get_expected_program_token_key(&program, &mint_address);
     let my_token_address = get_associated_token_address(
         &wallet.pubkey(), 
         &mint_address
     );
 
-    let button: state::Button = pressed_button;
+    let button: Button = pressed_button;
     let amount: u64 = 1;
 
-    let button_data =  state::ButtonData{button: button, amount: amount};
+    let button_data =  ButtonData{button, amount};
 
     println!("wallet {}",wallet.pubkey());
     println!("my_token_address {}",my_token_address);
@@ -552,8 +1453,13 @@ fn press_button(key_file: &String, pressed_button: state::Button) -> Result<()>
             AccountMeta::new_readonly(wallet.pubkey(), true),
             AccountMeta::new(my_token_address, false),
             AccountMeta::new(program_token_address, false),
-            AccountMeta::new_readonly(mint_address, false),
+            AccountMeta::new(mint_address, false),
             AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(config_account, false),
+            AccountMeta::new(vote_tally_account, false),
+            AccountMeta::new(treasury_token_address, false),
+            AccountMeta::new(push_stats_account, false),
+            AccountMeta::new(input_log_account, false),
         ],
     );
 
@@ -568,7 +1474,7 @@ fn press_button(key_file: &String, pressed_button: state::Button) -> Result<()>
         recent_hash,
     );
 
-    let commitment_config = CommitmentConfig{commitment : CommitmentLevel::Confirmed};
+    let _commitment_config = CommitmentConfig{commitment : CommitmentLevel::Confirmed};
 
     let start = Instant::now();
     println!("sending transaction..");
@@ -579,7 +1485,8 @@ fn press_button(key_file: &String, pressed_button: state::Button) -> Result<()>
    // let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
     //println!("result: {:#?}", response);
 
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
 
     
 }
@@ -1,10 +1,7 @@
 use thiserror::Error;
-use borsh::{BorshDeserialize, BorshSerialize};
-use enum_map::{enum_map, Enum};
-use solana_program::{pubkey::Pubkey};
-
-pub const MAX_BIDDERS : usize = 1024;
-pub const MAX_WINNERS : usize = 4;
+use dp_common::game::error::DaoPlaysError;
+use solana_client::client_error::ClientError;
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
 
 
 #[derive(Error, Debug)]
@@ -18,6 +15,15 @@ pub enum Error {
     #[error("serialization error: ({0})")]
     SerializationError(std::io::Error),
 
+    #[error("failed to write file: ({0})")]
+    FileWriteError(std::io::Error),
+
+    #[error("failed to read file: ({0})")]
+    FileReadError(std::io::Error),
+
+    #[error("draw could not be verified: ({0})")]
+    DrawNotVerified(String),
+
     #[error("solana client error: ({0})")]
     ClientError(#[from] solana_client::client_error::ClientError),
 
@@ -27,112 +33,10 @@ pub enum Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub enum Button {
-    A,
-    B,
-    Up,
-    Down,
-    Left,
-    Right,
-    Start,
-    Select,
+// map a transaction that failed with Custom(n) back to the program's error
+pub fn get_program_error(err: &ClientError) -> Option<DaoPlaysError> {
+    match err.get_transaction_error()? {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => DaoPlaysError::from_code(code),
+        _ => None
+    }
 }
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct ButtonData {
-    pub button: Button,
-    pub amount: u64
-}
-
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct BidData {
-    // the amount in lamports that will be donated to charity
-    pub amount_charity : u64,
-    // the amount in lamports being paid to the developers
-    pub amount_dao : u64,
-    // the chosen charity
-    pub charity : Charity
-}
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct InitData {
-    // the amount of DPTTs to be sent to the program
-    pub amount : u64
-}
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub enum DPPInstruction {
-
-    CreateDataAccount {
-        init_data : InitData
-    },
-
-    PushButton {
-        button_data: ButtonData
-    },
-
-    PlaceBid {
-        bid_data: BidData
-    },
-
-    SelectWinners,
-
-    SendTokens
-}
-
-
-
-// enum that lists the supported charities for this token launch
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Enum, Copy)]
-pub enum Charity {
-
-    EvidenceAction,
-    GirlsWhoCode,
-    OneTreePlanted,
-    OutrightActionInt,
-    TheLifeYouCanSave,
-    UkraineERF,
-    WaterOrg,
-    InvalidCharity
-}
-
-// on chain data that saves summary stats of the token launch
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct CharityData {
-    // the total donated to each charity
-    pub charity_totals : [u64 ; 7],
-    // the total donated overall
-    pub donated_total : u64,
-    // the total paid overall
-    pub paid_total : u64,
-    // the number of participating accounts
-    pub n_donations : u64
-}
-
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct State {
-
-    // this is the last time we actually chose winners, and decides how soon in the future will we choose again
-    pub prev_choose_winners_time: i64,
-
-    // the number of active bids in the system up to MAX_BIDDERS
-    pub n_bidders: u16,
-    // the sum of all the current bids
-    pub total_bid_amount : u64,
-
-    // for each bid we track the key, amount and time
-    pub bid_keys : [Pubkey; MAX_BIDDERS],
-    pub bid_amounts: [u64; MAX_BIDDERS],
-    pub bid_times: [i64; MAX_BIDDERS],
-
-    // the number of winners to be chosen, up to MAX_WINNERS
-    pub n_winners : u8,
-    pub winners: [Pubkey; MAX_WINNERS],
-
-    // summary of the charity stats for the auction
-    pub charity_data : CharityData
-}
\ No newline at end of file
@@ -0,0 +1,389 @@
+use crate::events;
+use crate::state::{Error, Result};
+use dp_common::game::bid_index::load_ladder_mut;
+use dp_common::game::draw::{self, DrawnWinner};
+use dp_common::game::events::{GameEvent, SeedRevealed, WinnerSelected};
+use dp_common::game::randomness::{get_oracle_seed, get_revealed_seed, OraclePrice};
+use dp_common::game::state::LegacyState;
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use std::fmt;
+
+// reproduce a draw off chain. given the data account as it was before a SelectWinners or RevealSeed transaction
+// and the transaction's logs, the seed is recomputed from the logged oracle prices (or the revealed secret), the
+// shared draw is run over the ladder, and the winners it picks are compared with the WinnerSelected events.
+// draws made before the events were added only left msg! output, which verify_legacy_draw checks against the
+// block walk SelectWinners made then
+//
+// RPC nodes only serve the current state of an account, so the data account from before the transaction can't be
+// fetched from its signature. the snapshot has to come from a copy saved before the draw, which the client's
+// select_winners command writes to draw_<signature>.state, from an archive of account states such as an indexer or
+// a geyser plugin, or from replaying the ledger up to the slot before the transaction
+
+// the prefix the runtime gives to msg! output
+const PROGRAM_LOG_PREFIX: &str = "Program log: ";
+
+const ORACLE_NAMES: [&str; 3] = ["btc", "eth", "sol"];
+
+// where the seed for the draw came from
+pub enum SeedSource {
+    // the prices read by SelectWinners, from the logs or supplied by the user
+    Oracles { prices: [Option<OraclePrice>; 3], supplied: bool },
+    // the secret revealed by RevealSeed
+    Revealed(SeedRevealed)
+}
+
+pub struct DrawReport {
+    pub source: SeedSource,
+    pub seed: u64,
+    pub threshold_time: i64,
+    pub n_bidders: u16,
+    pub total_bid: u64,
+    pub expected: Vec<DrawnWinner>,
+    pub recorded: Vec<WinnerSelected>,
+    pub mismatches: Vec<String>
+}
+
+impl DrawReport {
+    pub fn is_verified(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+// parse "btc price: (price +/- conf) x 10^expo"
+fn parse_oracle_price(message: &str) -> Option<OraclePrice> {
+    let (value, expo) = message.strip_prefix('(')?.split_once(") x 10^")?;
+    let (price, conf) = value.split_once(" +/- ")?;
+
+    Some(OraclePrice {
+        price: price.parse().ok()?,
+        conf: conf.parse().ok()?,
+        expo: expo.parse().ok()?
+    })
+}
+
+// the oracle prices that SelectWinners logged, or None if it didn't log any. oracles that were logged as
+// unavailable were left out of the seed, so are None
+pub fn get_logged_prices(log_messages: &[String]) -> Option<[Option<OraclePrice>; 3]> {
+
+    let mut prices: [Option<OraclePrice>; 3] = [None; 3];
+    let mut found = false;
+
+    for log in log_messages {
+        let message = match log.strip_prefix(PROGRAM_LOG_PREFIX) {
+            Some(message) => message,
+            None => continue
+        };
+
+        for (idx, name) in ORACLE_NAMES.iter().enumerate() {
+            if let Some(price) = message.strip_prefix(name).and_then(|rest| rest.strip_prefix(" price: ")) {
+                prices[idx] = parse_oracle_price(price);
+                found = true;
+            }
+            else if message.starts_with(&format!("{} oracle is unavailable", name)) {
+                found = true;
+            }
+        }
+    }
+
+    found.then_some(prices)
+}
+
+// the oracle prices the seed is made from, supplied by the user or else logged by SelectWinners
+fn get_seed_prices(log_messages: &[String], supplied_prices: Option<[Option<OraclePrice>; 3]>) -> Result<([Option<OraclePrice>; 3], bool)> {
+    match supplied_prices {
+        Some(prices) => Ok((prices, true)),
+        None => match get_logged_prices(log_messages) {
+            Some(prices) => Ok((prices, false)),
+            None => Err(Error::DrawNotVerified("the transaction didn't log its oracle prices, supply them instead".to_string()))
+        }
+    }
+}
+
+// recompute the draw made by the transaction with log_messages over pre_state_data, the data account before the
+// transaction. supplied_prices are used in place of the logged oracle prices if given
+pub fn verify_draw(pre_state_data: &[u8], log_messages: &[String], supplied_prices: Option<[Option<OraclePrice>; 3]>) -> Result<DrawReport> {
+
+    let game_events = events::decode_events(log_messages);
+
+    let recorded: Vec<WinnerSelected> = game_events.iter().filter_map(|event| match event {
+        GameEvent::WinnerSelected(winner) => Some(winner.clone()),
+        _ => None
+    }).collect();
+
+    let revealed = game_events.iter().find_map(|event| match event {
+        GameEvent::SeedRevealed(revealed) => Some(revealed.clone()),
+        _ => None
+    });
+
+    // every winner is selected with the same clock
+    let draw_time = match recorded.first() {
+        Some(winner) => winner.time,
+        None => return Err(Error::DrawNotVerified("the transaction didn't select any winners".to_string()))
+    };
+
+    let (source, seed, threshold_time) = match revealed {
+        Some(revealed) => {
+            let seed = get_revealed_seed(&revealed.secret, &revealed.slot_hash);
+            let threshold_time = (draw_time - 2).min(revealed.commit_time);
+            (SeedSource::Revealed(revealed), seed, threshold_time)
+        },
+        None => {
+            let (prices, supplied) = get_seed_prices(log_messages, supplied_prices)?;
+
+            let seed = get_oracle_seed(&prices).ok_or_else(|| Error::DrawNotVerified("none of the oracles have a price".to_string()))?;
+            (SeedSource::Oracles { prices, supplied }, seed, draw_time - 2)
+        }
+    };
+
+    // the draw is made on a copy of the snapshot
+    let mut data = pre_state_data.to_vec();
+    let (mut state, mut bid_index) = load_ladder_mut(&mut data).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+
+    let (n_bidders, total_bid) = state.get_bid_totals(threshold_time).ok_or_else(|| Error::DrawNotVerified("the bid totals overflow".to_string()))?;
+
+    // the index is rebuilt from the snapshot, as the draw only depends on the ladder
+    bid_index.rebuild(&state);
+
+    let expected = draw::draw_winners(&mut state, &mut bid_index, recorded.len() as u8, threshold_time, seed)
+        .map_err(|err| Error::DrawNotVerified(err.to_string()))?;
+
+    let mut mismatches: Vec<String> = Vec::new();
+
+    if let SeedSource::Revealed(revealed) = &source {
+        if revealed.seed != seed {
+            mismatches.push(format!("the logged seed {} doesn't match the seed {} from the revealed secret", revealed.seed, seed));
+        }
+    }
+
+    if expected.len() != recorded.len() {
+        mismatches.push(format!("the draw selects {} winners but {} were recorded", expected.len(), recorded.len()));
+    }
+
+    for (current_winner, (expected_winner, recorded_winner)) in expected.iter().zip(recorded.iter()).enumerate() {
+        if expected_winner.total_bid != recorded_winner.total_bid {
+            mismatches.push(format!("winner {}: total bid in the draw {} was recorded as {}, the state snapshot doesn't match the ladder the program saw", current_winner, expected_winner.total_bid, recorded_winner.total_bid));
+        }
+        if expected_winner.index != recorded_winner.index as usize || expected_winner.winner_key != recorded_winner.winner_key {
+            mismatches.push(format!("winner {}: expected {} at index {} but {} at index {} was recorded", current_winner, expected_winner.winner_key, expected_winner.index, recorded_winner.winner_key, recorded_winner.index));
+        }
+        if expected_winner.bid_amount != recorded_winner.bid_amount {
+            mismatches.push(format!("winner {}: expected a bid of {} but {} was recorded", current_winner, expected_winner.bid_amount, recorded_winner.bid_amount));
+        }
+    }
+
+    Ok(DrawReport {source, seed, threshold_time, n_bidders, total_bid, expected, recorded, mismatches})
+}
+
+// a winner as the legacy SelectWinners logged it, "Have winner {}: idx {}, random = {},  {} > {}, bid {}"
+pub struct LoggedWinner {
+    pub winner: u8,
+    pub index: usize,
+    pub random: f64
+}
+
+// the bids in the draw as the legacy SelectWinners logged them, "check bids : {} {}, bid totals {} {} winners {}",
+// with the totals in SOL
+pub struct LoggedBids {
+    pub n_bidders: u16,
+    pub total_bid_sol: f64,
+    pub n_winners: u8
+}
+
+pub struct LegacyDrawReport {
+    pub prices: [Option<OraclePrice>; 3],
+    pub supplied: bool,
+    pub seed: u64,
+    pub threshold_time: i64,
+    pub logged_bids: LoggedBids,
+    pub n_bidders: u16,
+    pub total_bid: u64,
+    pub expected: Vec<DrawnWinner>,
+    pub recorded: Vec<LoggedWinner>,
+    pub mismatches: Vec<String>
+}
+
+impl LegacyDrawReport {
+    pub fn is_verified(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+fn parse_logged_winner(message: &str) -> Option<LoggedWinner> {
+    let (winner, rest) = message.strip_prefix("Have winner ")?.split_once(": idx ")?;
+    let (index, rest) = rest.split_once(", random = ")?;
+    let (random, _) = rest.split_once(",  ")?;
+
+    Some(LoggedWinner {
+        winner: winner.parse().ok()?,
+        index: index.parse().ok()?,
+        random: random.parse().ok()?
+    })
+}
+
+fn parse_logged_bids(message: &str) -> Option<LoggedBids> {
+    let (bidders, rest) = message.strip_prefix("check bids : ")?.split_once(", bid totals ")?;
+    let (totals, n_winners) = rest.split_once(" winners ")?;
+    let (n_bidders, _) = bidders.split_once(' ')?;
+    let (total_bid_sol, _) = totals.split_once(' ')?;
+
+    Some(LoggedBids {
+        n_bidders: n_bidders.parse().ok()?,
+        total_bid_sol: total_bid_sol.parse().ok()?,
+        n_winners: n_winners.parse().ok()?
+    })
+}
+
+fn get_program_logs(log_messages: &[String]) -> impl Iterator<Item = &str> {
+    log_messages.iter().filter_map(|log| log.strip_prefix(PROGRAM_LOG_PREFIX))
+}
+
+// whether the draw was made by the legacy SelectWinners, which logged its winners with msg! rather than events
+pub fn is_legacy_draw(log_messages: &[String]) -> bool {
+    let has_events = events::decode_events(log_messages).iter().any(|event| matches!(event, GameEvent::WinnerSelected(_)));
+    !has_events && get_program_logs(log_messages).any(|message| parse_logged_bids(message).is_some())
+}
+
+// recompute a draw made by the legacy SelectWinners over pre_state_data, the data account in the legacy layout
+// before the transaction. the program took its threshold from the clock, which is the block time of the
+// transaction's slot, so that has to be given too
+pub fn verify_legacy_draw(pre_state_data: &[u8], log_messages: &[String], block_time: Option<i64>, supplied_prices: Option<[Option<OraclePrice>; 3]>) -> Result<LegacyDrawReport> {
+
+    let logged_bids = get_program_logs(log_messages).find_map(parse_logged_bids)
+        .ok_or_else(|| Error::DrawNotVerified("the transaction didn't log the bids in its draw".to_string()))?;
+    let recorded: Vec<LoggedWinner> = get_program_logs(log_messages).filter_map(parse_logged_winner).collect();
+
+    if recorded.is_empty() {
+        return Err(Error::DrawNotVerified("the transaction didn't select any winners".to_string()));
+    }
+
+    let block_time = block_time.ok_or_else(|| Error::DrawNotVerified("the transaction has no block time to take the threshold from".to_string()))?;
+    let threshold_time = block_time - 2;
+
+    let (prices, supplied) = get_seed_prices(log_messages, supplied_prices)?;
+    let seed = get_oracle_seed(&prices).ok_or_else(|| Error::DrawNotVerified("none of the oracles have a price".to_string()))?;
+
+    let state = LegacyState::load(pre_state_data).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+
+    // the bids in the draw, as the program counted them
+    let mut n_bidders: u16 = 0;
+    let mut total_bid: u64 = 0;
+    for (amount, time) in state.bid_amounts.iter().zip(state.bid_times.iter()) {
+        if time.get() < threshold_time && amount.get() > 0 {
+            n_bidders = n_bidders.wrapping_add(1);
+            total_bid = total_bid.wrapping_add(amount.get());
+        }
+    }
+
+    let expected = draw::draw_legacy_winners(state, logged_bids.n_winners, threshold_time, seed);
+
+    let mut mismatches: Vec<String> = Vec::new();
+
+    if n_bidders != logged_bids.n_bidders || to_sol(total_bid) != logged_bids.total_bid_sol {
+        mismatches.push(format!("{} bids totalling {} SOL were logged in the draw but the state snapshot has {} totalling {} SOL, it doesn't match the ladder the program saw", logged_bids.n_bidders, logged_bids.total_bid_sol, n_bidders, to_sol(total_bid)));
+    }
+
+    if expected.len() != recorded.len() {
+        mismatches.push(format!("the draw selects {} winners but {} were logged", expected.len(), recorded.len()));
+    }
+
+    // the winners are logged in the order the walk finds them, which is the order the draw returns them in
+    for (expected_winner, recorded_winner) in expected.iter().zip(recorded.iter()) {
+        if expected_winner.index != recorded_winner.index {
+            mismatches.push(format!("winner {}: expected index {} but index {} was logged", recorded_winner.winner, expected_winner.index, recorded_winner.index));
+        }
+        if expected_winner.random != recorded_winner.random {
+            mismatches.push(format!("winner {}: expected random {} but {} was logged, the seed doesn't match", recorded_winner.winner, expected_winner.random, recorded_winner.random));
+        }
+    }
+
+    Ok(LegacyDrawReport {prices, supplied, seed, threshold_time, logged_bids, n_bidders, total_bid, expected, recorded, mismatches})
+}
+
+// as the legacy program logged amounts
+fn to_sol(value: u64) -> f64 {
+    (value as f64) / (LAMPORTS_PER_SOL as f64)
+}
+
+fn write_prices(f: &mut fmt::Formatter, prices: &[Option<OraclePrice>; 3], supplied: bool) -> fmt::Result {
+    writeln!(f, "  oracle prices {}", if supplied { "(supplied)" } else { "(from the transaction logs)" })?;
+    for (name, price) in ORACLE_NAMES.iter().zip(prices.iter()) {
+        match price {
+            Some(price) => writeln!(f, "    {}: ({} +/- {}) x 10^{}", name, price.price, price.conf, price.expo)?,
+            None => writeln!(f, "    {}: unavailable, left out of the seed", name)?
+        }
+    }
+    Ok(())
+}
+
+fn write_mismatches(f: &mut fmt::Formatter, mismatches: &[String]) -> fmt::Result {
+    if mismatches.is_empty() {
+        write!(f, "VERIFIED: the draw reproduces the recorded winners")
+    }
+    else {
+        writeln!(f, "NOT VERIFIED")?;
+        for mismatch in mismatches.iter() {
+            writeln!(f, "  {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LegacyDrawReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        writeln!(f, "seed")?;
+        write_prices(f, &self.prices, self.supplied)?;
+        writeln!(f, "  seed: {}", self.seed)?;
+
+        writeln!(f, "legacy draw")?;
+        writeln!(f, "  bids made before {} are in the draw: {} bids totalling {}", self.threshold_time, self.n_bidders, self.total_bid)?;
+
+        for winner in self.expected.iter() {
+            writeln!(f, "  random {} x total {} = {}", winner.random, winner.total_bid, winner.threshold)?;
+            writeln!(f, "    the block walk passes it at index {} ({} > {}), bid {} by {}", winner.index, winner.cumulative_bid, winner.threshold, winner.bid_amount, winner.winner_key)?;
+        }
+
+        writeln!(f, "logged")?;
+        writeln!(f, "  {} bids totalling {} SOL, {} winners", self.logged_bids.n_bidders, self.logged_bids.total_bid_sol, self.logged_bids.n_winners)?;
+        for winner in self.recorded.iter() {
+            writeln!(f, "  winner {}: index {}, random {}", winner.winner, winner.index, winner.random)?;
+        }
+
+        write_mismatches(f, &self.mismatches)
+    }
+}
+
+impl fmt::Display for DrawReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        writeln!(f, "seed")?;
+        match &self.source {
+            SeedSource::Oracles { prices, supplied } => write_prices(f, prices, *supplied)?,
+            SeedSource::Revealed(revealed) => {
+                writeln!(f, "  revealed by {}", revealed.committer)?;
+                writeln!(f, "    secret:    {}", to_hex(&revealed.secret))?;
+                writeln!(f, "    slot hash: {} (slot {})", to_hex(&revealed.slot_hash), revealed.commit_slot)?;
+            }
+        }
+        writeln!(f, "  seed: {}", self.seed)?;
+
+        writeln!(f, "draw")?;
+        writeln!(f, "  bids made before {} are in the draw: {} bids totalling {}", self.threshold_time, self.n_bidders, self.total_bid)?;
+
+        for (current_winner, winner) in self.expected.iter().enumerate() {
+            writeln!(f, "  winner {}: random {} x total {} = {}", current_winner, winner.random, winner.total_bid, winner.threshold)?;
+            writeln!(f, "    cumulative bids pass it at index {} ({} > {}), bid {} by {}", winner.index, winner.cumulative_bid, winner.threshold, winner.bid_amount, winner.winner_key)?;
+        }
+
+        writeln!(f, "recorded")?;
+        for (current_winner, winner) in self.recorded.iter().enumerate() {
+            writeln!(f, "  winner {}: index {}, bid {} of {} by {}", current_winner, winner.index, winner.bid_amount, winner.total_bid, winner.winner_key)?;
+        }
+
+        write_mismatches(f, &self.mismatches)
+    }
+}
+
+fn to_hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -1,19 +1,2 @@
-use thiserror::Error;
-use solana_program::program_error::ProgramError;
-
-
-#[derive(Error, Debug, Copy, Clone)]
-pub enum DaoPlaysError {
-    /// Invalid instruction
-    #[error("Invalid Instruction")]
-    InvalidInstruction,
-
-    #[error("Invalid bid amount for button press")]
-    InvalidButtonBid
-}
-
-impl From<DaoPlaysError> for ProgramError {
-    fn from(e: DaoPlaysError) -> Self {
-        ProgramError::Custom(e as u32)
-    }
-}
\ No newline at end of file
+// the error codes are shared with the clients so they can be mapped back to the variant names
+pub use dp_common::game::error::*;
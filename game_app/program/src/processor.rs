@@ -1,12 +1,15 @@
-use std::str::FromStr;
-use crate::state::{StateEnum, get_state_index, Charity, CharityData, BidderData, BidValues, MAX_WINNERS, TOKENS_WON, WinnersKeys, BID_BLOCK, N_BID_BLOCKS, BidTimes};
-use crate::instruction::{DPPInstruction, InitData, BidData, ButtonData};
+use crate::state::{State, VoteTally, InputLog, ResolvedInput, ModeBallot, Capacity, Config, BidderData, CharityRegistry, Commit, RandomnessSource, INITIAL_BID_CAPACITY, LEGACY_CAPACITY, LEGACY_LAYOUT_VERSION, STATE_LAYOUT_VERSION, load_ladder_mut, initialize_ladder, resize_ladder, migrate_legacy_ladder, get_state_layout_version, get_data_account_size, get_config_size, get_charity_registry_size, get_commit_size, get_push_stats_size, get_vote_tally_size, get_input_log_size, get_mode_ballot_size};
+use crate::instruction::{DPPInstruction, InitData, BidData, ButtonData, ResizeData, ModeVoteData, SequenceData, SequenceStep, DEFAULT_HOLD_FRAMES};
 use crate::error::{DaoPlaysError};
-use borsh::{BorshDeserialize, BorshSerialize};
+use dp_common::game::events::{GameEvent, DataAccountCreated, ButtonPressed, BidPlaced, BidEvicted, BidCancelled, WinnerSelected, TokensSent, SeedCommitted, SeedRevealed, CommitSlashed, StateResized, StateMigrated, PrizeCredited, Cranked, InputResolved, ModeVoted, ModeBallotSettled, SequencePushed};
+use dp_common::game::randomness::{get_commitment, get_revealed_seed};
+use dp_common::game::inputs::{get_input_random, resolve_button};
+use dp_common::game::draw;
+use borsh::BorshSerialize;
 use solana_program::native_token::LAMPORTS_PER_SOL;
 use crate::accounts;
 use crate::utils;
-use enum_map::{enum_map, EnumMap};
+use dp_common::charity::{AddCharityData, UpdateCharityData, CHARITY_REGISTRY_SEED, get_expected_charity_registry_key, check_charity_registry};
 use crate::randoms;
 
 
@@ -17,9 +20,9 @@ use solana_program::{
     msg,
     program_pack::Pack,
     pubkey::Pubkey,
-    program::{invoke},
+    program::{invoke, invoke_signed},
     system_instruction,
-    clock::Clock, sysvar::Sysvar
+    clock::Clock, sysvar::Sysvar, rent::Rent
 };
 use spl_associated_token_account::get_associated_token_address;
 
@@ -27,7 +30,7 @@ pub struct Processor;
 impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
 
-        let instruction = DPPInstruction::try_from_slice(&instruction_data[..])?;
+        let instruction = DPPInstruction::unpack(instruction_data)?;
 
         match instruction {
             DPPInstruction::CreateDataAccount {init_data} => {
@@ -47,8 +50,69 @@ impl Processor {
                 Self::select_winners(accounts, program_id)
             },
             DPPInstruction::SendTokens => {
-                msg!("Instruction: SendTokens");
-                Self::send_tokens(accounts, program_id)
+                // unpack already rejects tag 4, but borsh can still build the variant
+                msg!("SendTokens has been retired, winners are paid with CreditWinners and ClaimPrize");
+                Err(ProgramError::InvalidInstructionData)
+            },
+            DPPInstruction::CreditWinners => {
+                msg!("Instruction: CreditWinners");
+                Self::credit_winners(accounts, program_id)
+            },
+            DPPInstruction::UpdateConfig {config} => {
+                msg!("Instruction: UpdateConfig");
+                Self::update_config(accounts, config, program_id)
+            },
+            DPPInstruction::AddCharity {charity_data} => {
+                msg!("Instruction: AddCharity");
+                Self::add_charity(accounts, charity_data, program_id)
+            },
+            DPPInstruction::UpdateCharity {charity_data} => {
+                msg!("Instruction: UpdateCharity");
+                Self::update_charity(accounts, charity_data, program_id)
+            },
+            DPPInstruction::CancelBid => {
+                msg!("Instruction: CancelBid");
+                Self::cancel_bid(accounts, program_id)
+            },
+            DPPInstruction::CommitSeed {commitment} => {
+                msg!("Instruction: CommitSeed");
+                Self::commit_seed(accounts, commitment, program_id)
+            },
+            DPPInstruction::RevealSeed {secret} => {
+                msg!("Instruction: RevealSeed");
+                Self::reveal_seed(accounts, secret, program_id)
+            },
+            DPPInstruction::SlashCommit => {
+                msg!("Instruction: SlashCommit");
+                Self::slash_commit(accounts, program_id)
+            },
+            DPPInstruction::ResizeState {resize_data} => {
+                msg!("Instruction: ResizeState");
+                Self::resize_state(accounts, resize_data, program_id)
+            },
+            DPPInstruction::MigrateState => {
+                msg!("Instruction: MigrateState");
+                Self::migrate_state(accounts, program_id)
+            },
+            DPPInstruction::ClaimPrize => {
+                msg!("Instruction: ClaimPrize");
+                Self::claim_prize(accounts, program_id)
+            },
+            DPPInstruction::Crank => {
+                msg!("Instruction: Crank");
+                Self::crank(accounts, program_id)
+            },
+            DPPInstruction::ResolveInput => {
+                msg!("Instruction: ResolveInput");
+                Self::resolve_input(accounts, program_id)
+            },
+            DPPInstruction::VoteInputMode {mode_vote_data} => {
+                msg!("Instruction: VoteInputMode");
+                Self::vote_input_mode(accounts, mode_vote_data, program_id)
+            },
+            DPPInstruction::PushSequence {sequence_data} => {
+                msg!("Instruction: PushSequence");
+                Self::process_push_sequence(accounts, sequence_data, program_id)
             }
         }
     } 
@@ -64,7 +128,7 @@ impl Processor {
 
         let account_info_iter = &mut accounts.iter();
 
-        // This function expects to be passed eight accounts, get them all first and then check their value is as expected
+        // This function expects to be passed fifteen accounts, get them all first and then check their value is as expected
         let funding_account_info = next_account_info(account_info_iter)?;
 
         let program_derived_account_info = next_account_info(account_info_iter)?;
@@ -78,16 +142,61 @@ impl Processor {
         let associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
+        let config_account_info = next_account_info(account_info_iter)?;
+        let vote_tally_account_info = next_account_info(account_info_iter)?;
+        let input_log_account_info = next_account_info(account_info_iter)?;
+        let mode_ballot_account_info = next_account_info(account_info_iter)?;
+        let push_stats_account_info = next_account_info(account_info_iter)?;
+        let loader_program_data_account_info = next_account_info(account_info_iter)?;
+
         // the first account should be the funding account and should be a signer
         if !funding_account_info.is_signer {
             msg!("expected first account as signer");
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // only we should be able to call this function
-        if funding_account_info.key != &accounts::get_expected_daoplays_key() {
-            msg!("expected first account to be a daoplays account  {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
+        // the tenth account is the config account, which we create the first time this is called
+        let (expected_config_key, config_bump_seed) = accounts::get_expected_config_key(program_id);
+
+        if config_account_info.key != &expected_config_key {
+            msg!("expected tenth account to be the config account {}", expected_config_key);
+            return Err(DaoPlaysError::InvalidConfigAccount.into());
+        }
+
+        if **config_account_info.try_borrow_lamports()? == 0 {
+
+            // only the program's upgrade authority can create the config, so nobody watching the deploy can send
+            // CreateDataAccount first and take it over. the fifteenth account is the loader's program data account
+            utils::check_upgrade_authority(program_id, loader_program_data_account_info, funding_account_info.key)?;
+
+            // the creator becomes the config authority, and the data account is derived from their key
+            let config = init_data.config;
+            if !config.is_valid() ||
+                config.authority != *funding_account_info.key ||
+                config.data_account != accounts::get_expected_data_account_key(funding_account_info.key, program_id)
+            {
+                msg!("initial config is invalid");
+                return Err(DaoPlaysError::InvalidConfig.into());
+            }
+
+            utils::create_pda_account(
+                funding_account_info,
+                config_account_info,
+                program_id,
+                get_config_size(),
+                accounts::CONFIG_SEED,
+                config_bump_seed
+            )?;
+
+            config.store(&mut config_account_info.data.borrow_mut())?;
+        }
+
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // only the config authority should be able to call this function
+        if funding_account_info.key != &config.authority {
+            msg!("expected first account to be the config authority  {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
         }
 
         // the second account is the program derived address which we can verify with find_program_address
@@ -95,49 +204,139 @@ impl Processor {
          
         if program_derived_account_info.key != &expected_pda_key {
             msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
+            return Err(DaoPlaysError::InvalidProgramAddress.into());
         }
 
-        // the next account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected second account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
+        // the third account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected third account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
         }
   
-        // the third account is the source of the tokens which we can verify with get_associated_token_address
-        if token_source_account_info.key != &accounts::get_expected_daoplays_token_key() {
-            msg!("expected third account to be the funder's token account {}", accounts::get_expected_daoplays_token_key());
-            return Err(ProgramError::InvalidAccountData);
+        // the fourth account is the source of the tokens which we can verify with get_associated_token_address
+        let expected_source_token_key = get_associated_token_address(
+            funding_account_info.key,
+            &config.token_mint
+        );
+
+        if token_source_account_info.key != &expected_source_token_key {
+            msg!("expected fourth account to be the funder's token account {}", expected_source_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
         }
 
-        // the fourth account is the program's token account
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id) {
-            msg!("expected fourth account to be the program's token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
+        // the fifth account is the program's token account
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key {
+            msg!("expected fifth account to be the program's token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
         }
 
-        // the fifth account is the mint address for the token
-        if token_mint_account_info.key != &accounts::get_expected_token_mint_key() {
-            msg!("expected fifth account to be the token's mint account {}", accounts::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
+        // the sixth account is the mint address for the token
+        if token_mint_account_info.key != &config.token_mint {
+            msg!("expected sixth account to be the token's mint account {}", config.token_mint);
+            return Err(DaoPlaysError::InvalidTokenMint.into());
         }
 
-        // the ninth account is the token_program
+        // the seventh account is the token_program
         if token_program_account_info.key != &spl_token::id() {
-            msg!("expected ninth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected seventh account to be the token program {}", spl_token::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
         }
 
-        // the tenth account is the associated_token_program
+        // the eighth account is the associated_token_program
         if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected tenth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected eighth account to be the associated token program {}", spl_associated_token_account::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
         }
         
-        // the eleventh and final account is the system_program
+        // the ninth account is the system_program
         if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected ninth account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        // the eleventh to fourteenth accounts are the vote tally, the input log, the mode ballot and the push stats,
+        // which are created here so PushButton, ResolveInput and VoteInputMode never have to pay for them. deployments
+        // from before they existed get them by sending CreateDataAccount again
+        let (expected_vote_tally_key, vote_tally_bump_seed) = accounts::get_expected_vote_tally_key(program_id);
+
+        if vote_tally_account_info.key != &expected_vote_tally_key {
+            msg!("expected eleventh account to be the vote tally account {}", expected_vote_tally_key);
+            return Err(DaoPlaysError::InvalidVoteTallyAccount.into());
+        }
+
+        let (expected_input_log_key, input_log_bump_seed) = accounts::get_expected_input_log_key(program_id);
+
+        if input_log_account_info.key != &expected_input_log_key {
+            msg!("expected twelfth account to be the input log account {}", expected_input_log_key);
+            return Err(DaoPlaysError::InvalidInputLogAccount.into());
+        }
+
+        let (expected_mode_ballot_key, mode_ballot_bump_seed) = accounts::get_expected_mode_ballot_key(program_id);
+
+        if mode_ballot_account_info.key != &expected_mode_ballot_key {
+            msg!("expected thirteenth account to be the mode ballot account {}", expected_mode_ballot_key);
+            return Err(DaoPlaysError::InvalidModeBallotAccount.into());
+        }
+
+        let (expected_push_stats_key, push_stats_bump_seed) = accounts::get_expected_push_stats_key(program_id);
+
+        if push_stats_account_info.key != &expected_push_stats_key {
+            msg!("expected fourteenth account to be the push stats account {}", expected_push_stats_key);
+            return Err(DaoPlaysError::InvalidPushStatsAccount.into());
+        }
+
+        if **vote_tally_account_info.try_borrow_lamports()? == 0 {
+            utils::create_pda_account(
+                funding_account_info,
+                vote_tally_account_info,
+                program_id,
+                get_vote_tally_size(),
+                accounts::VOTE_TALLY_SEED,
+                vote_tally_bump_seed
+            )?;
+
+            VoteTally::initialize(&mut vote_tally_account_info.data.borrow_mut())?;
+        }
+
+        // the first input window starts when the log is created
+        if **input_log_account_info.try_borrow_lamports()? == 0 {
+            utils::create_pda_account(
+                funding_account_info,
+                input_log_account_info,
+                program_id,
+                get_input_log_size(),
+                accounts::INPUT_LOG_SEED,
+                input_log_bump_seed
+            )?;
+
+            InputLog::initialize(&mut input_log_account_info.data.borrow_mut(), Clock::get()?.slot)?;
+        }
+
+        if **mode_ballot_account_info.try_borrow_lamports()? == 0 {
+            utils::create_pda_account(
+                funding_account_info,
+                mode_ballot_account_info,
+                program_id,
+                get_mode_ballot_size(),
+                accounts::MODE_BALLOT_SEED,
+                mode_ballot_bump_seed
+            )?;
+
+            ModeBallot::initialize(&mut mode_ballot_account_info.data.borrow_mut())?;
+        }
+
+        // the totals all start at zero, which is what the new account holds
+        if **push_stats_account_info.try_borrow_lamports()? == 0 {
+            utils::create_pda_account(
+                funding_account_info,
+                push_stats_account_info,
+                program_id,
+                get_push_stats_size(),
+                accounts::PUSH_STATS_SEED,
+                push_stats_bump_seed
+            )?;
         }
         
 
@@ -153,7 +352,8 @@ impl Processor {
             program_derived_account_info,
             token_mint_account_info,
             program_token_account_info,
-            token_program_account_info
+            token_program_account_info,
+            system_program_account_info
         )?;
 
         if init_data.amount > 0 {
@@ -171,18 +371,44 @@ impl Processor {
         // now just initialise the prev_selected_time field of the state to clock now
         
 
-        let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
+        // the data account is created here with room for a small ladder, and can be grown with ResizeState
+        let capacity = Capacity {bids : INITIAL_BID_CAPACITY, winners : config.max_winners as usize};
+        if **program_data_account_info.try_borrow_lamports()? == 0 {
+            utils::create_data_account(
+                funding_account_info,
+                program_data_account_info,
+                program_id,
+                get_data_account_size(&capacity)
+            )?;
+        }
+
+        let mut program_data = program_data_account_info.data.borrow_mut();
+
+        // a legacy account keeps its bids, and is upgraded with MigrateState rather than set up again
+        if get_state_layout_version(&program_data) == Some(LEGACY_LAYOUT_VERSION) {
+            msg!("data account has the legacy layout, upgrade it with MigrateState");
+        }
+        else {
+            // the capacity is set and the bid index that follows the state is built the first time through
+            initialize_ladder(&mut program_data, &capacity)?;
 
-        // check if the time is uninitialized and set it to the current time if so
-        let prev_time_selected = i64::try_from_slice(&program_data_account_info.data.borrow()[prev_time_idx.0..prev_time_idx.1])?;
+            let mut state = State::load_mut(&mut program_data)?;
 
-        if prev_time_selected == 0 {
+            // check if the time is uninitialized and set it to the current time if so
+            if state.prev_choose_winners_time.get() == 0 {
 
-            let clock = Clock::get()?;
-            let current_time = clock.unix_timestamp;
-            current_time.serialize(&mut &mut program_data_account_info.data.borrow_mut()[prev_time_idx.0..prev_time_idx.1])?;  
+                let clock = Clock::get()?;
+                let current_time = clock.unix_timestamp;
+                state.prev_choose_winners_time.set(current_time);
+            }
         }
 
+        GameEvent::DataAccountCreated(DataAccountCreated {
+            authority : *funding_account_info.key,
+            data_account : *program_data_account_info.key,
+            amount : init_data.amount
+        }).emit();
+
         Ok(())
 
 
@@ -194,9 +420,55 @@ impl Processor {
         program_id: &Pubkey,
     ) -> ProgramResult {
 
+        let steps = [SequenceStep {button : button_data.button.clone(), hold_frames : DEFAULT_HOLD_FRAMES}];
+        let (player, time) = Self::push_tokens(accounts, &steps, button_data.amount, program_id)?;
+
+        GameEvent::ButtonPressed(ButtonPressed {
+            player,
+            button : button_data.button,
+            amount : button_data.amount,
+            time
+        }).emit();
+
+        Ok(())
+    }
+
+    // a sequence is tallied as a vote for its first button, so the input modes that pick a single button count it
+    // towards where it starts, and every step is added to the press log for the modes that play whole presses
+    fn process_push_sequence(
+        accounts: &[AccountInfo],
+        sequence_data: SequenceData,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        sequence_data.validate()?;
+
+        let (player, time) = Self::push_tokens(accounts, &sequence_data.steps, sequence_data.amount, program_id)?;
+        msg!("pushed a sequence of {} steps for {} tokens", sequence_data.steps.len(), sequence_data.amount);
+
+        GameEvent::SequencePushed(SequencePushed {
+            player,
+            steps : sequence_data.steps,
+            amount : sequence_data.amount,
+            time
+        }).emit();
+
+        Ok(())
+    }
+
+    // check the accounts for PushButton or PushSequence, split the tokens between burning, the treasury and the prize
+    // pool as the config says, add them to the votes for the first step's button and add the steps to the press log,
+    // returning the player and the time of the press
+    fn push_tokens(
+        accounts: &[AccountInfo],
+        steps: &[SequenceStep],
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> Result<(Pubkey, i64), ProgramError> {
+
         let account_info_iter = &mut accounts.iter();
 
-        // This function expects to be passed five accounts, get them all first and then check their value is as expected
+        // This function expects to be passed ten accounts, get them all first and then check their value is as expected
         let player_account_info = next_account_info(account_info_iter)?;
         let player_token_account_info = next_account_info(account_info_iter)?;
 
@@ -205,6 +477,13 @@ impl Processor {
         let token_mint_account_info = next_account_info(account_info_iter)?;
         let token_program_account_info = next_account_info(account_info_iter)?;
 
+        let config_account_info = next_account_info(account_info_iter)?;
+        let vote_tally_account_info = next_account_info(account_info_iter)?;
+
+        let treasury_token_account_info = next_account_info(account_info_iter)?;
+        let push_stats_account_info = next_account_info(account_info_iter)?;
+        let input_log_account_info = next_account_info(account_info_iter)?;
+
 
         // check the accounts
         // the first account is the player, who should be signing the transaction
@@ -212,44 +491,70 @@ impl Processor {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
+        // the sixth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
         // the second account should be the players associated token account
         let expected_player_token_key = get_associated_token_address(
-            &player_account_info.key, 
-            &token_mint_account_info.key
+            player_account_info.key, 
+            token_mint_account_info.key
         );
 
         if player_token_account_info.key != &expected_player_token_key
         { 
             msg!("expected second account to be the player's associated token account {}", expected_player_token_key);
-            return Err(ProgramError::InvalidAccountData); 
+            return Err(DaoPlaysError::InvalidTokenAccount.into()); 
         }
 
 
         // the third account should be the programs token address
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id)
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key
         { 
-            msg!("expected third account to be the programs token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData); 
+            msg!("expected third account to be the programs token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into()); 
         }
 
         // the fourth account is the mint address for the token
-        if token_mint_account_info.key != &accounts::get_expected_token_mint_key()
+        if token_mint_account_info.key != &config.token_mint
         {
-            msg!("expected fourth account to be the token mint address {}", accounts::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected fourth account to be the token mint address {}", config.token_mint);
+            return Err(DaoPlaysError::InvalidTokenMint.into());
         }
 
         // the fifth  account is the token_program
         if token_program_account_info.key != &spl_token::id() {
             msg!("expected fifth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        // the seventh account is the vote tally
+        utils::check_vote_tally(program_id, vote_tally_account_info)?;
+
+        // the eighth account is the treasury's token account
+        let expected_treasury_token_key = get_associated_token_address(
+            &config.treasury,
+            &config.token_mint
+        );
+
+        if treasury_token_account_info.key != &expected_treasury_token_key
+        {
+            msg!("expected eighth account to be the treasury's token account {}", expected_treasury_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
         }
 
+        // the ninth account is the push stats
+        let mut push_stats = utils::get_push_stats(program_id, push_stats_account_info)?;
+
+        // the tenth account is the input log
+        utils::check_input_log(program_id, input_log_account_info)?;
+
         // perform some sanity checks:
         // bid amount should be greater than zero
         // the players token account should exist and should have enough tokens
 
-        if button_data.amount <= 0 {
+        if amount == 0 {
             msg!("Bid amount must be greater than zero");
             return Err(DaoPlaysError::InvalidButtonBid.into());
         }
@@ -261,31 +566,51 @@ impl Processor {
 
             let token_balance = player_token_account.amount;
 
-            if token_balance < button_data.amount {
-                msg!("insufficient balance in token account: {} < {}", token_balance, button_data.amount);
-                return Err(ProgramError::InvalidAccountData);
+            if token_balance < amount {
+                msg!("insufficient balance in token account: {} < {}", token_balance, amount);
+                return Err(DaoPlaysError::InsufficientTokens.into());
             }
         }
 
         else {
 
             msg!("player's token account hasn't been initialised");
-            return Err(ProgramError::InvalidAccountData);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
         }
 
 
-        // finally transfer the tokens to the program
-        utils::transfer_tokens(
-            button_data.amount,
+        // finally burn the tokens or move them to the treasury and the program, where they top up the prize pool
+        let split = config.get_push_split(amount);
+
+        utils::spend_tokens(
+            &split,
             player_token_account_info,
+            token_mint_account_info,
+            treasury_token_account_info,
             program_token_account_info,
             player_account_info,
-            token_program_account_info,
-            accounts::get_pda_bump()
-    
+            token_program_account_info
         )?;
 
-        Ok(())
+        push_stats.record(&split).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        push_stats.serialize(&mut &mut push_stats_account_info.data.borrow_mut()[..])?;
+
+        // add the press to the votes for this slot, so the outcome can be read from the tally
+        let clock = Clock::get()?;
+
+        let mut vote_tally_data = vote_tally_account_info.data.borrow_mut();
+        let vote_tally = VoteTally::load_mut(&mut vote_tally_data)?;
+
+        let slot_votes = vote_tally.record_vote(clock.slot, &steps[0].button, amount).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        msg!("slot {} has {} presses totalling {} tokens", clock.slot, slot_votes.n_presses, slot_votes.get_total().unwrap_or(u64::MAX));
+
+        // and to the press log, which holds the inputs of windows resolved in anarchy
+        let mut input_log_data = input_log_account_info.data.borrow_mut();
+        let input_log = InputLog::load_mut(&mut input_log_data)?;
+
+        input_log.log_press(clock.slot, steps, amount).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+        Ok((*player_account_info.key, clock.unix_timestamp))
     }
 
     fn process_place_bid(
@@ -294,27 +619,6 @@ impl Processor {
         program_id: &Pubkey,
     ) ->ProgramResult {
 
-        let charity_key_map = enum_map!{
-            Charity::EvidenceAction => "9fF5EQV6FVy7V5SaHBXfAaTUBvuyimQ9X3jarc2mRHzi",
-            Charity::GirlsWhoCode => "5qrmDeRFhBTnEkqJsRKJAkTJzrZnyC9bWmRhL6RZqWt1",
-            Charity::OneTreePlanted => "GeCaNYhRswBFoTxtNaf9wKYJEBZoxHa9Fao6aQKzDDo2",
-            Charity::OutrightActionInt => "AiY4t79umvBqGvR43f5rL8jR8F2JZwG87mB55adAF2cf",
-            Charity::TheLifeYouCanSave => "8qQpHYjLkNiKvLtFzrjzgFZfveNJZ9AnQuBUoQj1t3DB",
-            Charity::UkraineERF  => "E6TPLh77cx9b5aWsmxM8geit2PBLVEBVAvF6ye9Qe4ZQ",
-            Charity::WaterOrg => "5UNSVwtiSdfsCbJokL4fHtzV28mVNi8fQkMjPQw6v7Xd"
-        };
-
-        let charity_index_map: EnumMap<Charity, usize> = enum_map!{
-            Charity::EvidenceAction => 0,
-            Charity::GirlsWhoCode => 1,
-            Charity::OneTreePlanted => 2,
-            Charity::OutrightActionInt => 3,
-            Charity::TheLifeYouCanSave => 4,
-            Charity::UkraineERF => 5,
-            Charity::WaterOrg => 6
-        };
-
-
         let account_info_iter = &mut accounts.iter();
 
         let bidder_account_info = next_account_info(account_info_iter)?;
@@ -335,85 +639,113 @@ impl Processor {
         let associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
+        let config_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+
+        // when the ladder is full the new bid replaces the oldest one, in which case the token account and
+        // data account of that bidder are passed last so that the eviction can be recorded
+        let evicted_token_account_info = account_info_iter.next();
+        let evicted_bidder_data_account_info = account_info_iter.next();
+
 
         if !bidder_account_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
+        // the twelfth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the thirteenth account is the charity registry
+        check_charity_registry(program_id, charity_registry_account_info, DaoPlaysError::InvalidCharityRegistry.into())?;
+
+        // the fourteenth account is the commit account. while a commitment is waiting to be revealed the bids already
+        // in the draw can't be topped up or evicted, as the committer may already know the seed
+        let pending_commit = utils::get_pending_commit(program_id, commit_account_info, &config)?;
+
         // the second account should be the bidders associated token account
         let expected_bidder_token_key = get_associated_token_address(
-            &bidder_account_info.key, 
-            &token_mint_account_info.key
+            bidder_account_info.key, 
+            token_mint_account_info.key
         );
 
         if bidder_token_account_info.key != &expected_bidder_token_key
         { 
             msg!("expected second account to be the player's associated token account {}", expected_bidder_token_key);
-            return Err(ProgramError::InvalidAccountData); 
+            return Err(DaoPlaysError::InvalidTokenAccount.into()); 
         }
 
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
+        // the fourth account is the daoplays SOL address, which is the config authority
+        if dao_plays_account_info.key != &config.authority
         {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected fourth account to be the daoplays address {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
         }
 
-        // the seventh account is the charity SOL address, which we can check with the map
-        let expected_charity_key = Pubkey::from_str(charity_key_map[bid_data.charity]).unwrap();
+        // the fifth account is the charity SOL address, which we can check with the charity registry
+        let expected_charity_key = match CharityRegistry::load_mut(&mut charity_registry_account_info.data.borrow_mut())?.get_active_charity_mut(bid_data.charity) {
+            Some(charity) => charity.address,
+            None => {
+                msg!("charity {} is not an active charity in the registry", bid_data.charity);
+                return Err(DaoPlaysError::InvalidCharity.into());
+            }
+        };
 
         if charity_account_info.key != &expected_charity_key
         {
             msg!("expected fifth account to be the chosen charities address {}", expected_charity_key);
-            return Err(ProgramError::InvalidAccountData);
+            return Err(DaoPlaysError::InvalidCharityAccount.into());
         }
 
 
-        // the next account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected second account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
+        // the sixth account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected sixth account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
         }
 
-        let (expected_bidder_data_key, bidder_bump_seed) = Pubkey::find_program_address(&[&bidder_account_info.key.to_bytes()], &program_id);
+        // the third account is the bidders data account
+        let (expected_bidder_data_key, bidder_bump_seed) = accounts::get_expected_bidder_data_key(program_id, bidder_account_info.key);
         
         if bidder_data_account_info.key != &expected_bidder_data_key
         { 
-            msg!("expected fifth account to be the bidders data account {}", expected_bidder_data_key);
-            return Err(ProgramError::InvalidAccountData); 
+            msg!("expected third account to be the bidders data account {}", expected_bidder_data_key);
+            return Err(DaoPlaysError::InvalidBidderDataAccount.into()); 
         }
 
-        // the fourth account should be the programs token address
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id)
+        // the seventh account should be the programs token address
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key
         { 
-            msg!("expected fifth account to be the programs token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData); 
+            msg!("expected seventh account to be the programs token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into()); 
         }
 
 
-        // the fifth account is the mint address for the token
-        if token_mint_account_info.key != &accounts::get_expected_token_mint_key()
+        // the eighth account is the mint address for the token
+        if token_mint_account_info.key != &config.token_mint
         {
-            msg!("expected fifth account to be the token mint address {}", accounts::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected eighth account to be the token mint address {}", config.token_mint);
+            return Err(DaoPlaysError::InvalidTokenMint.into());
         }
 
-        // the sixth  account is the token_program
+        // the ninth account is the token_program
         if token_program_account_info.key != &spl_token::id() {
-            msg!("expected sixth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected ninth account to be the token program {}", spl_token::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
         }
 
-        // the seventh account is the associated_token_program
+        // the tenth account is the associated_token_program
         if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected tenth account to be the associated token program {}", spl_associated_token_account::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
         }
 
-        // the eighth and final account is the system_program
+        // the eleventh account is the system_program
         if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eighth account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
         }
 
         // create the bidders token account if necessary
@@ -422,15 +754,17 @@ impl Processor {
             bidder_account_info,
             token_mint_account_info,
             bidder_token_account_info,
-            token_program_account_info
+            token_program_account_info,
+            system_program_account_info
         )?;
         
         
         // transfer the SOL to the required accounts
-        let min_amount : u64 = 100000;
-        if bid_data.amount_charity + bid_data.amount_dao < min_amount {
-            msg!("Amount bid is less than the minimum of 0.0001 SOL");
-            return Err(ProgramError::InvalidArgument);
+        let total_paid = bid_data.amount_charity.checked_add(bid_data.amount_dao).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+        if total_paid < config.min_bid {
+            msg!("Amount bid is less than the minimum of {} SOL", utils::to_sol(config.min_bid));
+            return Err(DaoPlaysError::BidBelowMinimum.into());
         }
 
         // if we have made it this far in the transaction we can try transferring the SOL
@@ -445,24 +779,6 @@ impl Processor {
         )?;
 
 
-        // update the charity stats data
-        let charity_data_idx = get_state_index(StateEnum::CharityData);
-        //msg!("get charity data {} {} {} {}", charity_data_idx.0, charity_data_idx.1, charity_data_idx.1 - charity_data_idx.0, get_charity_size());
-        let mut current_state = CharityData::try_from_slice(&program_data_account_info.data.borrow()[charity_data_idx.0..charity_data_idx.1])?;
-
-        // calculate the current average to see if this individual has paid more
-        let total_paid = bid_data.amount_charity + bid_data.amount_dao;
-
-        let charity_index = charity_index_map[bid_data.charity];
-
-        current_state.charity_totals[charity_index] += bid_data.amount_charity;
-        current_state.donated_total += bid_data.amount_charity;
-        current_state.paid_total += total_paid;
-        current_state.n_donations += 1;
-
-        current_state.serialize(&mut &mut program_data_account_info.data.borrow_mut()[charity_data_idx.0..charity_data_idx.1])?;
-        
-
         // create the bidders data account if we need it
         utils::create_bidder_data_account(
             bidder_account_info,
@@ -471,18 +787,20 @@ impl Processor {
             bidder_bump_seed
         )?;
 
-        // we will need to update n_bidders and total_bid so get them now
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
+        // accounts created before evictions were recorded need to be resized to hold the full record
+        utils::resize_bidder_data_account(bidder_account_info, bidder_data_account_info)?;
 
-        let mut n_bidders =  u16::try_from_slice(&program_data_account_info.data.borrow()[n_bidders_idx.0..n_bidders_idx.1])?;
-        let mut total_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[total_bid_idx.0..total_bid_idx.1])?;
+        let mut program_data = program_data_account_info.data.borrow_mut();
+        let (mut state, mut bid_index) = load_ladder_mut(&mut program_data)?;
 
+        // update the charity stats data
+        state.charity_data.record_donation(bid_data.charity, bid_data.amount_charity, total_paid).ok_or(DaoPlaysError::ArithmeticOverflow)?;
 
-        let mut new_bid = total_paid;
-
-        // update total_bid with new_bid
-        total_bid += new_bid;
+        // and the running total for the chosen charity in the registry
+        let mut charity_registry_data = charity_registry_account_info.data.borrow_mut();
+        if let Some(charity) = CharityRegistry::load_mut(&mut charity_registry_data)?.get_charity_mut(bid_data.charity) {
+            charity.add_donation(bid_data.amount_charity).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        }
 
         
         // get the current time as a point of comparison for finding the oldest bid, and for the bids time
@@ -490,7 +808,7 @@ impl Processor {
         let current_time = clock.unix_timestamp;
 
         // get the bid index from the bidders account
-        let bidder_data = BidderData::try_from_slice(&bidder_data_account_info.data.borrow()[..])?;
+        let mut bidder_data = BidderData::load(&bidder_data_account_info.data.borrow())?;
 
 
         // when adding the bid to the program state we have three possibilities:
@@ -500,10 +818,10 @@ impl Processor {
 
         // start by checking if a bid exists
         let mut bidders_index = bidder_data.index as usize;
+        let new_bid;
 
         // check the public key that is present in the data account at bid_index
-        let key_idx = get_state_index(StateEnum::BidKeys{index: bidders_index});
-        let key = Pubkey::try_from_slice(&program_data_account_info.data.borrow()[key_idx.0..key_idx.1])?;
+        let key = state.bid_keys.get(bidders_index).copied().unwrap_or_default();
 
         msg!("compare keys {} {} as position  {}", key, bidder_token_account_info.key, bidder_data.index);
         
@@ -512,101 +830,246 @@ impl Processor {
         if key == *bidder_token_account_info.key {
 
             msg!("Existing bid found, accumulating amount");
+
+            // topping up moves the bid's time past the commitment, which would take it out of the draw
+            if let Some(commit) = &pending_commit {
+                msg!("bids can't be topped up while the commitment from {} is waiting to be revealed", commit.committer);
+                return Err(DaoPlaysError::CommitPending.into());
+            }
+
             // get the old bid
-            let old_bid_idx = get_state_index(StateEnum::BidAmounts{index: bidders_index});
-            let old_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[old_bid_idx.0..old_bid_idx.1])?;
+            let old_bid = state.bid_amounts[bidders_index].get();
+
+            new_bid = state.add_to_bid(bidders_index, total_paid, current_time).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            bid_index.add_to_bid(bidders_index, total_paid).ok_or(DaoPlaysError::ArithmeticOverflow)?;
                             
-            msg!("have old bid {} + {} -> {}", old_bid, new_bid, new_bid + old_bid);
-            new_bid += old_bid;
+            msg!("have old bid {} + {} -> {}", old_bid, total_paid, new_bid);
  
         }
 
         else {
 
-            // if they were a new bidder add their bid to the ladder, first just try and find the first open spot
+            // if they were a new bidder add their bid to the ladder, first just try and take an open spot
             msg!("Have new bidder");
-            let mut found_space = false;
-             
-            // if there isn't a space we will want to replace the oldest bid
-            // so we find  that in the same loop
-            let mut oldest_bid_index : usize = 0;
-            let mut oldest_time = i64::MAX;
-            for i in 0..N_BID_BLOCKS {
+            let free_slot = bid_index.take_free_slot();
 
+            // use the open spot if there was one, otherwise we overwrite the oldest bid
+            if let Some(free_slot) = free_slot {
+                bidders_index = free_slot;
+            }
+            else {
 
-                let time_idx = get_state_index(StateEnum::BidTimes {index: i * BID_BLOCK});
-                let times = BidTimes::try_from_slice(&program_data_account_info.data.borrow()[time_idx.0..time_idx.0 + BID_BLOCK * 8])?; 
-        
-                for j in 0..BID_BLOCK {
-
-                    let total_index = i * BID_BLOCK + j;
-
-                    // if the bid time is zero that indicates we have found an empty slot, so break out of the loop
-                    if times.bid_times[j] == 0 {
-                        bidders_index = total_index;
-                        found_space = true;
-                        break
-                    }
-
-                    // otherwise check if this is older than the oldest known bid so far
-                    if times.bid_times[j] < oldest_time {
-                        oldest_bid_index = total_index;
-                        oldest_time = times.bid_times[j];
-                    }
+                if let Some(commit) = &pending_commit {
+                    msg!("bids can't be evicted while the commitment from {} is waiting to be revealed", commit.committer);
+                    return Err(DaoPlaysError::CommitPending.into());
                 }
 
-                if found_space {
-                    break;
+                bidders_index = bid_index.get_oldest().ok_or(DaoPlaysError::StateOutOfSync)?;
+                msg!("using oldest bid position in {}", bidders_index);
+
+                // if we are overwriting we need to subtract bid_amount and reduce n_bidders by one
+                let existing_bid = state.bid_amounts[bidders_index].get();
+
+                // the config can protect the oldest bid from being replaced by a smaller one
+                if config.protect_larger_bids && total_paid < existing_bid {
+                    msg!("new bid {} is smaller than the oldest bid {} so can't replace it", utils::to_sol(total_paid), utils::to_sol(existing_bid));
+                    return Err(DaoPlaysError::BidTooSmallToEvict.into());
                 }
-            }
 
-            
-  
-            // if there was no open spot we overwrite the oldest bid
-            if !found_space {
+                let evicted_key = state.bid_keys[bidders_index];
 
-                bidders_index = oldest_bid_index;
-                msg!("using oldest bid position in {}", bidders_index);
+                utils::record_eviction(
+                    bidder_account_info,
+                    evicted_token_account_info,
+                    evicted_bidder_data_account_info,
+                    &evicted_key,
+                    existing_bid,
+                    program_id
+                )?;
 
-                // if we are overwriting we need to subtract bid_amount and reduce n_bidders by one
-                let existing_bid_idx = get_state_index(StateEnum::BidAmounts{index: bidders_index});
-                let existing_bid = u64::try_from_slice(&program_data_account_info.data.borrow()[existing_bid_idx.0..existing_bid_idx.1])?;
-                total_bid -= existing_bid;
-                n_bidders -=1;
+                GameEvent::BidEvicted(BidEvicted {
+                    evicted_key,
+                    new_key : *bidder_token_account_info.key,
+                    index : bidders_index as u16,
+                    amount : existing_bid,
+                    bid_time : state.bid_times[bidders_index].get()
+                }).emit();
+
+                state.remove_bid(bidders_index).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+                bid_index.remove_bid(bidders_index, existing_bid).ok_or(DaoPlaysError::StateOutOfSync)?;
+
+                // which frees the slot for the new bid
+                bidders_index = bid_index.take_free_slot().ok_or(DaoPlaysError::StateOutOfSync)?;
 
             }
 
-            // for a new bid we need to add the public key
-            let bidder_token_pubkey = *bidder_token_account_info.key;
-            let new_key_idx = get_state_index(StateEnum::BidKeys{index: bidders_index});
-        
-            // serialise the new account
-            bidder_token_pubkey.serialize(&mut &mut program_data_account_info.data.borrow_mut()[new_key_idx.0..new_key_idx.1])?;  
+            msg!("insert new bid at position {}", bidders_index);
 
-            // update their bid data
-            let new_bidder_data = BidderData {index: bidders_index as u16};
-            new_bidder_data.serialize(&mut &mut bidder_data_account_info.data.borrow_mut()[..])?;
+            // for a new bid we need to add the public key, as well as the amount and time
+            state.insert_bid(bidders_index, *bidder_token_account_info.key, total_paid, current_time).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            bid_index.insert_bid(bidders_index, total_paid).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            new_bid = total_paid;
 
-            // update n_bidders
-            n_bidders += 1;
+            // update their bid data
+            bidder_data.index = bidders_index as u16;
+            bidder_data.store(&mut bidder_data_account_info.data.borrow_mut())?;
      
         }
 
-        msg!("update bid details for position {}", bidders_index);
+        GameEvent::BidPlaced(BidPlaced {
+            bidder_key : *bidder_token_account_info.key,
+            charity : bid_data.charity,
+            amount_charity : bid_data.amount_charity,
+            amount_dao : bid_data.amount_dao,
+            index : bidders_index as u16,
+            bid_amount : new_bid,
+            time : current_time
+        }).emit();
+
+
+        Ok(())
+    }
+
+
+    fn cancel_bid(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let bidder_token_account_info = next_account_info(account_info_iter)?;
+        let bidder_data_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+
+        let program_derived_account_info = next_account_info(account_info_iter)?;
+        let program_token_account_info = next_account_info(account_info_iter)?;
+        let token_program_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the bidder and should be a signer
+        if !bidder_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the fifth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the second account should be the bidders associated token account, which is the key stored with their bid
+        let expected_bidder_token_key = get_associated_token_address(
+            bidder_account_info.key, 
+            &config.token_mint
+        );
+
+        if bidder_token_account_info.key != &expected_bidder_token_key
+        { 
+            msg!("expected second account to be the player's associated token account {}", expected_bidder_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into()); 
+        }
+
+        // the third account is the bidders data account, which holds the index of their bid
+        let (expected_bidder_data_key, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(program_id, bidder_account_info.key);
+        
+        if bidder_data_account_info.key != &expected_bidder_data_key
+        { 
+            msg!("expected third account to be the bidders data account {}", expected_bidder_data_key);
+            return Err(DaoPlaysError::InvalidBidderDataAccount.into()); 
+        }
+
+        if bidder_data_account_info.owner != program_id {
+            msg!("bidder has never placed a bid");
+            return Err(DaoPlaysError::NoActiveBid.into());
+        }
+
+        // the fourth account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected fourth account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
+        }
+
+        // the sixth account is the commit account. a bid can't be taken out of the draw while a commitment is waiting
+        // to be revealed, as the committer may already know the seed
+        if let Some(commit) = utils::get_pending_commit(program_id, commit_account_info, &config)? {
+            msg!("bids can't be cancelled while the commitment from {} is waiting to be revealed", commit.committer);
+            return Err(DaoPlaysError::CommitPending.into());
+        }
+
+        // the seventh account is the program derived address which we can verify with find_program_address
+        let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
+
+        if program_derived_account_info.key != &expected_pda_key {
+            msg!("expected seventh account to be PDA {}", expected_pda_key);
+            return Err(DaoPlaysError::InvalidProgramAddress.into());
+        }
+
+        // the eighth account is the program's token account, which the refund is paid from
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key {
+            msg!("expected eighth account to be the program's token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
+
+        // the ninth account is the token_program
+        if token_program_account_info.key != &spl_token::id() {
+            msg!("expected ninth account to be the token program {}", spl_token::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        let mut bidder_data = BidderData::load(&bidder_data_account_info.data.borrow())?;
+        let bidders_index = bidder_data.index as usize;
+
+        let mut program_data = program_data_account_info.data.borrow_mut();
+        let (mut state, mut bid_index) = load_ladder_mut(&mut program_data)?;
+
+        // the bid may already have been removed, either because they won or because it was the oldest bid
+        // and was replaced, in which case the slot will now be empty or belong to someone else
+        if bidders_index >= state.bid_keys.len() || state.bid_keys[bidders_index] != *bidder_token_account_info.key {
+            msg!("no active bid found at position {}", bidders_index);
+            return Err(DaoPlaysError::NoActiveBid.into());
+        }
+
+        let removed_bid = state.remove_bid(bidders_index).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        bid_index.remove_bid(bidders_index, removed_bid).ok_or(DaoPlaysError::StateOutOfSync)?;
+
+        msg!("cancelled bid of {} at position {}, {} bidders remaining", utils::to_sol(removed_bid), bidders_index, state.n_bidders.get());
+
+        // the bid is refunded in tokens at the config's rate. prizes that have been credited but not claimed are still
+        // in the program's token account and aren't available to refund
+        let refund = config.get_cancel_refund(removed_bid).ok_or(DaoPlaysError::ArithmeticOverflow)?;
 
-        // insert the new bid and time into the program data
-        let new_bid_idx = get_state_index(StateEnum::BidAmounts{index: bidders_index});
-        let new_time_idx = get_state_index(StateEnum::BidTimes{index: bidders_index});
+        if refund > 0 {
+            let program_token_account = spl_token::state::Account::unpack_unchecked(&program_token_account_info.try_borrow_data()?)?;
+            let token_balance = program_token_account.amount.saturating_sub(state.total_claimable.get());
 
-        current_time.serialize(&mut &mut program_data_account_info.data.borrow_mut()[new_time_idx.0..new_time_idx.1])?;  
-        new_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[new_bid_idx.0..new_bid_idx.1])?; 
+            if token_balance < refund {
+                msg!("insufficient tokens in program account to refund the bid: {} < {}", token_balance, refund);
+                return Err(DaoPlaysError::InsufficientTokens.into());
+            }
 
-        // update total bid
-        total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?; 
+            utils::transfer_tokens(
+                refund,
+                program_token_account_info,
+                bidder_token_account_info,
+                program_derived_account_info,
+                token_program_account_info,
+                bump_seed
+            )?;
+        }
 
-        //  update n_bidders
-        n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?; 
+        GameEvent::BidCancelled(BidCancelled {
+            bidder_key : *bidder_token_account_info.key,
+            index : bidders_index as u16,
+            amount : removed_bid,
+            refund
+        }).emit();
 
+        // reset the bidders data so it no longer points at the slot
+        bidder_data.index = 0;
+        bidder_data.store(&mut bidder_data_account_info.data.borrow_mut())?;
 
         Ok(())
     }
@@ -625,7 +1088,7 @@ impl Processor {
         let funding_account_info = next_account_info(account_info_iter)?;
 
 
-        // first accounts are the pyth oracles
+        // the second to fourth accounts are the pyth oracles
         let btc_account_info = next_account_info(account_info_iter)?;
         let eth_account_info = next_account_info(account_info_iter)?;
         let sol_account_info = next_account_info(account_info_iter)?;
@@ -633,6 +1096,8 @@ impl Processor {
         let program_data_account_info = next_account_info(account_info_iter)?;
         let program_token_account_info = next_account_info(account_info_iter)?;
 
+        let config_account_info = next_account_info(account_info_iter)?;
+
 
         // the first account should be the funding account and should be a signer
         if !funding_account_info.is_signer {
@@ -640,61 +1105,100 @@ impl Processor {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
+        // the seventh and final account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
         // check the accounts match what we expect
-        if  btc_account_info.key != &accounts::get_expected_btc_key() || 
-            eth_account_info.key != &accounts::get_expected_eth_key() ||
-            sol_account_info.key != &accounts::get_expected_sol_key() 
+        if  btc_account_info.key != &config.btc_oracle || 
+            eth_account_info.key != &config.eth_oracle ||
+            sol_account_info.key != &config.sol_oracle 
         {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(DaoPlaysError::InvalidOracle.into());
         }
 
-        // the next account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected fifth account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
+        // the fifth account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected fifth account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
         }
 
-        // the last account should be the programs token address
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id)
+        // the sixth account should be the programs token address
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key
         { 
-            msg!("expected sixth account to be the programs token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData); 
+            msg!("expected sixth account to be the programs token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into()); 
         }
 
-        // first check we should actually be here
-        // if we have already chosen winners then we don't need to do anything
-
-        let n_winners_idx = get_state_index(StateEnum::NWinners);
-        let mut n_winners = u8::try_from_slice(&program_data_account_info.data.borrow()[n_winners_idx.0..n_winners_idx.1])?;
+        // winners are only selected here when the config uses the oracles for randomness
+        if config.randomness_source != RandomnessSource::Oracles {
+            msg!("the config uses commit-reveal randomness, winners are selected with RevealSeed");
+            return Err(DaoPlaysError::RandomnessSourceMismatch.into());
+        }
+
+        // for selecting winners we only include bids that were made up to a couple of seconds ago
+        let clock = Clock::get()?;
+        let threshold_time = clock.unix_timestamp.checked_sub(2).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+        Self::draw_winners(
+            program_data_account_info,
+            program_token_account_info,
+            &config,
+            threshold_time,
+            || randoms::generate_seed(
+                btc_account_info,
+                eth_account_info,
+                sol_account_info,
+                clock.slot,
+                config.max_oracle_slot_age
+            )
+        )
+    }
+
+    // select the winners from the bids made before threshold_time, weighted by the size of their bid.
+    // the seed is only generated once we know there are winners to select
+    fn draw_winners<F>(
+        program_data_account_info : &AccountInfo,
+        program_token_account_info : &AccountInfo,
+        config : &Config,
+        threshold_time : i64,
+        generate_seed : F
+    ) -> ProgramResult
+    where F : FnOnce() -> Result<u64, ProgramError>
+    {
+        // first check we should actually be here
+        // if we have already chosen winners then we don't need to do anything
+
+        let mut program_data = program_data_account_info.data.borrow_mut();
+        let (mut state, mut bid_index) = load_ladder_mut(&mut program_data)?;
+
+        let mut n_winners = state.n_winners;
 
         if n_winners != 0 {
             msg!("winners have already been selected");
-            return Ok(());
+            return Err(DaoPlaysError::WinnersAlreadySelected.into());
         }
 
         // update the prev_selected_time field of the state to clock now
         let clock = Clock::get()?;
         let current_time = clock.unix_timestamp;
-        let threshold_time = current_time - 2;
-
-        // get the current total bid and n_bidders so we can update this later
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
 
-        let mut n_bidders =  u16::try_from_slice(&program_data_account_info.data.borrow()[n_bidders_idx.0..n_bidders_idx.1])?;
-        let mut total_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[total_bid_idx.0..total_bid_idx.1])?;
+        // get the current total bid and n_bidders to compare against
+        let n_bidders = state.n_bidders.get();
+        let total_bid = state.total_bid_amount.get();
         
-        // for selecting winners we only include bids that were made up to a couple of seconds ago
-        // and so want to find the total bid amount of just those
-        let update = utils::get_bid_state(threshold_time, program_data_account_info)?;
-        let mut valid_n_bidders = update.0;
-        let mut valid_total_bid = update.1;
+        // find the total bid amount of just the bids in the draw
+        let update = bid_index.get_bid_totals(&state, threshold_time).ok_or(DaoPlaysError::StateOutOfSync)?;
+        let valid_n_bidders = update.0;
+        let valid_total_bid = update.1;
 
    
         // check to see if now is a good time to choose winners
         n_winners = utils::check_winners_state(
             valid_n_bidders, 
-            program_data_account_info,
+            &state,
+            config,
             program_token_account_info
         )?;
         
@@ -707,302 +1211,1303 @@ impl Processor {
         }
 
         // update n_winners
-        n_winners.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_winners_idx.0..n_winners_idx.1])?;
+        state.n_winners = n_winners;
 
         // generate the seed for selecting winners
-        let mut pyth_random = randoms::generate_seed(
-            btc_account_info,
-            eth_account_info,
-            sol_account_info
-        );
+        let random_seed = generate_seed()?;
 
-        let mut ran_vec : Vec<f64> = Vec::new();
-        for _winner in 0..n_winners {
-            pyth_random = randoms::shift_seed(pyth_random);
-            let random_f64 = randoms::generate_random(pyth_random);
+        let winners = draw::draw_winners(&mut state, &mut bid_index, n_winners, threshold_time, random_seed)?;
+        for (current_winner, winner) in winners.iter().enumerate() {
 
-            ran_vec.push(random_f64);
-            //msg!("random {} : {}", winner, random_f64);
+            msg!("Have winner {}: idx {}, random = {},  {} > {}, bid {}", current_winner, winner.index, winner.random, (winner.cumulative_bid as f64) / (LAMPORTS_PER_SOL as f64), (winner.threshold as f64) / (LAMPORTS_PER_SOL as f64), (winner.bid_amount as f64) / (LAMPORTS_PER_SOL as f64));
 
+            GameEvent::WinnerSelected(WinnerSelected {
+                winner_key : winner.winner_key,
+                index : winner.index as u16,
+                bid_amount : winner.bid_amount,
+                total_bid : winner.total_bid,
+                time : current_time
+            }).emit();
         }
 
-        ran_vec.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        state.prev_choose_winners_time.set(current_time);
 
+     
+        Ok(())
+    }
+    
+    fn credit_winners(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
 
-        // get the starting state
-        let mut cumulative_total : u64 = 0;
-        let mut winners_found : [bool; MAX_WINNERS] = [false; MAX_WINNERS];
+        let account_info_iter = &mut accounts.iter();
 
-        for idx in 0..N_BID_BLOCKS {
+        // first load and check all the non-winner accounts
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
 
-            let bid_idx = get_state_index(StateEnum::BidAmounts {index: idx*BID_BLOCK});
-            let mut bids = BidValues::try_from_slice(&program_data_account_info.data.borrow()[bid_idx.0..bid_idx.0 + BID_BLOCK*8])?; 
 
-            let time_idx = get_state_index(StateEnum::BidTimes {index: idx*BID_BLOCK});
-            let times = BidTimes::try_from_slice(&program_data_account_info.data.borrow()[time_idx.0..time_idx.0 + BID_BLOCK*8])?; 
-    
-            for current_winner in 0..n_winners {
+        // the first account should be the funding account and should be a signer, as it pays to resize old bidder data accounts
+        if !funding_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
-                if winners_found[current_winner as usize] {
-                    continue;
-                }
+        // the fourth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
 
-                // update the threshold
-                let random_f64 = ran_vec[current_winner as usize];
-                let threshold = ((valid_total_bid as f64) * random_f64) as u64;
+        // the second account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected second account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
+        }
 
-                //msg!("check for winner {} block {} total {} threshold {} current total {}", current_winner, idx,utils::to_sol(valid_total_bid), utils::to_sol(threshold),  utils::to_sol(cumulative_total));
+        // the third account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected third account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
 
-                let mut sub_total : u64 = cumulative_total;
-                for bid_index in 0..BID_BLOCK {
+        Self::credit_winner_accounts(
+            funding_account_info,
+            program_data_account_info,
+            &config,
+            program_id,
+            account_info_iter.as_slice(),
+            false
+        )?;
 
-                    // check if this is within the time threshold
-                    if times.bid_times[bid_index] >= threshold_time {
-                        continue;
-                    }
-                    
-                    let current_bid =  bids.bid_amounts[bid_index];
-                    sub_total += current_bid;
-        
-                    if sub_total > threshold {
+        Ok(())
+    }
 
-                        winners_found[current_winner as usize] = true;
-        
-                        let winner_index = idx * BID_BLOCK + bid_index;
-        
-                        msg!("Have winner {}: idx {}, random = {},  {} > {}, bid {}", current_winner, winner_index, random_f64, (sub_total as f64) / (LAMPORTS_PER_SOL as f64), (threshold as f64) / (LAMPORTS_PER_SOL as f64), (current_bid as f64) / (LAMPORTS_PER_SOL as f64));
+    // credit the winners whose wallet and bidder data account are passed in winner_accounts, returning the number
+    // credited. n_winners is reset once every winner has been credited so new winners can be selected. pairs that
+    // aren't waiting to be credited are an error unless skip_non_winners is set
+    fn credit_winner_accounts<'a>(
+        funding_account_info : &AccountInfo<'a>,
+        program_data_account_info : &AccountInfo<'a>,
+        config : &Config,
+        program_id : &Pubkey,
+        winner_accounts : &[AccountInfo<'a>],
+        skip_non_winners : bool
+    ) -> Result<u8, ProgramError>
+    {
+        if !winner_accounts.chunks_exact(2).remainder().is_empty() {
+            msg!("expected a bidder data account after each winner's wallet");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
 
-                        // get the winners key from the program data account
-                        let key_idx = get_state_index(StateEnum::BidKeys{index: winner_index});
-                        let winners_key = Pubkey::try_from_slice(&program_data_account_info.data.borrow()[key_idx.0..key_idx.1])?; 
-        
-                        // and insert it into the winners array
-                        let winner_idx = get_state_index(StateEnum::Winners{index: current_winner as usize});
-                        winners_key.serialize(&mut &mut program_data_account_info.data.borrow_mut()[winner_idx.0..winner_idx.1])?; 
+        let mut program_data = program_data_account_info.data.borrow_mut();
+        let (mut state, mut bid_index) = load_ladder_mut(&mut program_data)?;
 
-    
-                        // now clear the winners data in the program data account
-                        // start by zero'ing their bid
-                        let win_bid_idx = get_state_index(StateEnum::BidAmounts {index: winner_index});
-                        0u64.serialize(&mut &mut program_data_account_info.data.borrow_mut()[win_bid_idx.0..win_bid_idx.1])?;  
+        let n_winners = state.n_winners as usize;
 
-                        // then the bid time
-                        let win_time_idx = get_state_index(StateEnum::BidTimes{index: winner_index});
-                        0i64.serialize(&mut &mut program_data_account_info.data.borrow_mut()[win_time_idx.0..win_time_idx.1])?;  
+        if n_winners == 0 {
+            msg!("No winners selected, exiting credit_winners");
+            return Ok(0);
+        }
 
-                        // and then clear their key
-                        solana_program::system_program::id().serialize(&mut &mut program_data_account_info.data.borrow_mut()[key_idx.0..key_idx.1])?;
-     
-                        // as a sanity check make sure current bid is less than total_bid
-                        if current_bid > valid_total_bid {
-                            msg!("Current bid is greater than total bid, this shouldn't happen {} > {}", current_bid, valid_total_bid);
-                            return Ok(());
-                        }
+        let mut n_credited : u8 = 0;
+
+        // the accounts are the wallet and bidder data account of each winner to credit. winners can be credited in
+        // any order and across several transactions, and a winner is cleared from the winners array once their
+        // prize has been added to their bidder data account
+        for winner_pair in winner_accounts.chunks_exact(2) {
+            let bidder_account_info = &winner_pair[0];
+            let bidder_data_account_info = &winner_pair[1];
+
+            // bids are always made from the bidder's associated token account, so the winner's key follows from the
+            // wallet even if the token account has since been closed
+            let winner_key = get_associated_token_address(bidder_account_info.key, &config.token_mint);
+
+            let w_idx = match state.winners[..n_winners].iter().position(|winner| *winner == winner_key) {
+                Some(w_idx) => w_idx,
+                None if skip_non_winners => continue,
+                None => {
+                    msg!("{} is not a winner waiting to be credited", winner_key);
+                    return Err(DaoPlaysError::WinnersAccountMismatch.into());
+                }
+            };
 
-                        // finally decrement the number of bidders, and the total bid amount
-                        valid_n_bidders -= 1;
-                        valid_total_bid -= current_bid;
+            let (expected_bidder_data_key, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(program_id, bidder_account_info.key);
 
-                        n_bidders -= 1;
-                        total_bid -= current_bid;
+            if bidder_data_account_info.key != &expected_bidder_data_key || bidder_data_account_info.owner != program_id {
+                msg!("expected winner {} to have the bidder data account {}", w_idx, expected_bidder_data_key);
+                return Err(DaoPlaysError::InvalidBidderDataAccount.into());
+            }
 
-                        bids.bid_amounts[bid_index] = 0;
+            utils::resize_bidder_data_account(funding_account_info, bidder_data_account_info)?;
 
-                        break;
+            let mut bidder_data = BidderData::load(&bidder_data_account_info.data.borrow())?;
+            bidder_data.record_win(config.tokens_won).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            bidder_data.store(&mut bidder_data_account_info.data.borrow_mut())?;
 
-                    }
-                }
+            // the prize stays in the program's token account until it is claimed, so set it aside from the pool
+            let total_claimable = state.total_claimable.get().checked_add(config.tokens_won).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            state.total_claimable.set(total_claimable);
 
-                // if this winner wasn't found in this block, move onto the next block
-                if winners_found[current_winner as usize] == false {
+            state.winners[w_idx] = solana_program::system_program::id();
+            n_credited += 1;
 
-                    cumulative_total = sub_total;
-                   
-                    break;
-                }
-            }
+            GameEvent::PrizeCredited(PrizeCredited {
+                winner_key,
+                bidder : *bidder_account_info.key,
+                amount : config.tokens_won,
+                claimable : bidder_data.claimable
+            }).emit();
+        }
+
+        let n_remaining = state.winners[..n_winners].iter().filter(|winner| **winner != solana_program::system_program::id()).count();
+        if n_remaining > 0 {
+            msg!("{} of {} winners still to be credited", n_remaining, n_winners);
+            return Ok(n_credited);
         }
 
-        // update number of bidders
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?;
+        // finally just reset the n_winners value to zero so we can select new winners again
+        state.n_winners = 0;
 
-        // update total_bid_amount
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
-        total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?;
+        // as a sanity check make sure the totals still agree with the bid index
+        let n_bidders = (bid_index.get_capacity() - bid_index.n_free.get() as usize) as u16;
+        let total_bid = bid_index.get_total();
 
+        let current_n_bidders = state.n_bidders.get();
+        let current_total_bid = state.total_bid_amount.get();
 
-        let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
-        current_time.serialize(&mut &mut program_data_account_info.data.borrow_mut()[prev_time_idx.0..prev_time_idx.1])?;  
+        // if they don't, recount the ladder and rebuild the index from it
+
+        if n_bidders != current_n_bidders || total_bid != current_total_bid {
+
+            msg!("bid data is out of sync: {} {} {} {}", n_bidders, current_n_bidders, total_bid, current_total_bid);
+
+            utils::update_bid_state(&mut state)?;
+            bid_index.rebuild(&state);
+        }
 
-     
-        Ok(())
-    }
     
-    fn send_tokens(
+        Ok(n_credited)
+    }
+
+    // a permissionless SelectWinners that also credits the winners. the wallets and bidder data accounts passed
+    // after the config are used to credit any winners left from the last draw, and then, once they are all
+    // credited, to credit the new winners as soon as they are drawn. passing every bidder in the ladder lets one
+    // crank finish the round, and winners that weren't passed are left for the next crank or CreditWinners. the
+    // cranker is tipped from the crank tip address and its token account if the crank did anything
+    fn crank(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
     ) ->ProgramResult {
 
-        msg!("in send_tokens");
+        let account_info_iter = &mut accounts.iter();
 
-        let account_info_iter = &mut accounts.iter().peekable();
+        let cranker_account_info = next_account_info(account_info_iter)?;
+        let cranker_token_account_info = next_account_info(account_info_iter)?;
+
+        let btc_account_info = next_account_info(account_info_iter)?;
+        let eth_account_info = next_account_info(account_info_iter)?;
+        let sol_account_info = next_account_info(account_info_iter)?;
 
-        // first load and check all the non-winner accounts
-        let funding_account_info = next_account_info(account_info_iter)?;
-        let program_derived_account_info = next_account_info(account_info_iter)?;
-        let program_token_account_info = next_account_info(account_info_iter)?;
         let program_data_account_info = next_account_info(account_info_iter)?;
-        let token_program_account_info = next_account_info(account_info_iter)?;
+        let crank_tip_account_info = next_account_info(account_info_iter)?;
+        let crank_tip_token_account_info = next_account_info(account_info_iter)?;
+        let program_token_account_info = next_account_info(account_info_iter)?;
 
+        let token_program_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !funding_account_info.is_signer {
+        // the first account is the cranker, who pays to resize old bidder data accounts and receives the tip
+        if !cranker_account_info.is_signer {
             msg!("expected first account as signer");
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // the second account is the program derived address which we can verify with find_program_address
-        let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
-         
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
+        // the twelfth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the second account should be the cranker's associated token account
+        let expected_cranker_token_key = get_associated_token_address(cranker_account_info.key, &config.token_mint);
+
+        if cranker_token_account_info.key != &expected_cranker_token_key {
+            msg!("expected second account to be the cranker's associated token account {}", expected_cranker_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
         }
 
-        // the third account is the program's token account
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id) {
-            msg!("expected third account to be the program's token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
+        // the third to fifth accounts are the pyth oracles
+        if  btc_account_info.key != &config.btc_oracle ||
+            eth_account_info.key != &config.eth_oracle ||
+            sol_account_info.key != &config.sol_oracle
+        {
+            return Err(DaoPlaysError::InvalidOracle.into());
         }
 
-        // the fifth account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected fifth account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
+        // the sixth account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected sixth account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
         }
 
-        // the sixth account is the token_program
+        // the seventh account is the crank tip address, which holds the lamports for the tip
+        let (expected_crank_tip_key, crank_tip_bump_seed) = accounts::get_expected_crank_tip_key(program_id);
+
+        if crank_tip_account_info.key != &expected_crank_tip_key {
+            msg!("expected seventh account to be the crank tip address {}", expected_crank_tip_key);
+            return Err(DaoPlaysError::InvalidProgramAddress.into());
+        }
+
+        // the eighth account is the crank tip address's token account, which holds the tokens for the tip
+        let expected_crank_tip_token_key = accounts::get_expected_crank_tip_token_key(program_id, &config.token_mint);
+
+        if crank_tip_token_account_info.key != &expected_crank_tip_token_key {
+            msg!("expected eighth account to be the crank tip token account {}", expected_crank_tip_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
+
+        // the ninth account is the program's token account, which holds the prize pool
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key {
+            msg!("expected ninth account to be the program's token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
+
+        // the tenth account is the token_program
         if token_program_account_info.key != &spl_token::id() {
-            msg!("expected sixth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
+            msg!("expected tenth account to be the token program {}", spl_token::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
         }
 
-        
-        // now check how many winners we expect and make sure the keys match the program data
-        let n_winners_idx = get_state_index(StateEnum::NWinners);
-        let n_winners = u8::try_from_slice(&program_data_account_info.data.borrow()[n_winners_idx.0..n_winners_idx.1])?;
+        // the eleventh account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
 
-        if n_winners == 0 {
-            msg!("No winners selected, exiting send_tokens");
-            return Ok(());
+        // the crank selects winners with the oracles, in commit-reveal mode the cranker uses RevealSeed instead
+        if config.randomness_source != RandomnessSource::Oracles {
+            msg!("the config uses commit-reveal randomness, winners are selected with RevealSeed");
+            return Err(DaoPlaysError::RandomnessSourceMismatch.into());
         }
 
-        msg!("have {} winners to send tokens to", n_winners);
+        // the rest of the accounts are the wallet and bidder data account of each bidder the cranker wants credited
+        // if they win, so they don't all have to be winners
+        let bidder_accounts = account_info_iter.as_slice();
+
+        let mut n_credited = Self::credit_winner_accounts(
+            cranker_account_info,
+            program_data_account_info,
+            &config,
+            program_id,
+            bidder_accounts,
+            true
+        )?;
 
-        // get the winner's account info
-        let mut winners_account_info : Vec<&AccountInfo> = Vec::new();
-        for _w_idx in 0..n_winners {
+        // new winners can only be drawn once all of the last draw's winners have been credited
+        let mut n_selected : u8 = 0;
+        if State::load_mut(&mut program_data_account_info.data.borrow_mut())?.n_winners == 0 {
 
-            if account_info_iter.peek().is_some() {
-                winners_account_info.push(next_account_info(account_info_iter)?);
-            }
-            else {
-                msg!("n_winners {} exceeds the number of accounts passed", n_winners);
-                return Ok(());
+            let clock = Clock::get()?;
+            let threshold_time = clock.unix_timestamp.checked_sub(2).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+            Self::draw_winners(
+                program_data_account_info,
+                program_token_account_info,
+                &config,
+                threshold_time,
+                || randoms::generate_seed(
+                    btc_account_info,
+                    eth_account_info,
+                    sol_account_info,
+                    clock.slot,
+                    config.max_oracle_slot_age
+                )
+            )?;
+
+            n_selected = State::load_mut(&mut program_data_account_info.data.borrow_mut())?.n_winners;
+
+            // credit the new winners straight away from the same accounts
+            if n_selected > 0 {
+                let n_new_credited = Self::credit_winner_accounts(
+                    cranker_account_info,
+                    program_data_account_info,
+                    &config,
+                    program_id,
+                    bidder_accounts,
+                    true
+                )?;
+                n_credited = n_credited.checked_add(n_new_credited).ok_or(DaoPlaysError::ArithmeticOverflow)?;
             }
         }
 
-        // check that was the last account
-        if account_info_iter.peek().is_some() {
-            msg!("n_winners {} is less than the number of accounts passed", n_winners);
-            return Ok(());
+        // only pay for cranks that moved the game on
+        if n_credited == 0 && n_selected == 0 {
+            msg!("no winners to credit or select");
+            return Err(DaoPlaysError::NothingToCrank.into());
         }
 
-        let winners_key_idx = get_state_index(StateEnum::Winners { index: 0 });
-        let expected_winners = WinnersKeys::try_from_slice(&program_data_account_info.data.borrow()[winners_key_idx.0..winners_key_idx.0 + 32 * MAX_WINNERS])?;
+        // the token tip is paid from the crank tip token account, so it never touches the prize pool. a tip account
+        // that hasn't been created or funded just means no token tip
+        let tip_token_balance = spl_token::state::Account::unpack(&crank_tip_token_account_info.try_borrow_data()?)
+            .map(|tip_token_account| tip_token_account.amount)
+            .unwrap_or(0);
 
-        // check the winners sent are what we expect
-        // the front end may end up sending multiple requests to send tokens and we don't want the whole
-        // instruction chain to fail just because the program state has moved on
-        for w_idx in 0..(n_winners as usize) {
-            msg!("winner {} : {}", w_idx, expected_winners.keys[w_idx].to_string());
+        let mut tip_tokens = config.crank_tip_tokens;
+        if tip_tokens > 0 && tip_token_balance < tip_tokens {
+            msg!("insufficient tokens in the crank tip token account to pay the tip: {} < {}", tip_token_balance, tip_tokens);
+            tip_tokens = 0;
+        }
 
-            if expected_winners.keys[w_idx as usize] != *winners_account_info[w_idx].key {
-                msg!("expected winner {} to have key {}", w_idx, winners_account_info[w_idx].key);
-                return Ok(());
-            }
+        if tip_tokens > 0 {
+            utils::transfer_tokens_with_seed(
+                tip_tokens,
+                crank_tip_token_account_info,
+                cranker_token_account_info,
+                crank_tip_account_info,
+                token_program_account_info,
+                accounts::CRANK_TIP_SEED,
+                crank_tip_bump_seed
+            )?;
+        }
 
-            // also check none of the winners are the system program which would indicate we have arrived here too early
-            if *winners_account_info[w_idx].key == solana_program::system_program::id() {
-                msg!("winner {} has system program key {}", w_idx, winners_account_info[w_idx].key);
-                return Ok(());
-            }
+        // the lamport tip comes from the balance of the crank tip address above its rent exemption. the address is
+        // a system account, so the lamports are moved by the system program with the address signing
+        let rent_exempt_lamports = Rent::get()?.minimum_balance(0);
+        let spare_lamports = crank_tip_account_info.lamports().saturating_sub(rent_exempt_lamports);
+
+        let mut tip_lamports = config.crank_tip_lamports;
+        if tip_lamports > 0 && spare_lamports < tip_lamports {
+            msg!("insufficient lamports in the crank tip address to pay the tip: {} < {}", spare_lamports, tip_lamports);
+            tip_lamports = 0;
         }
 
-        // finally check that the remaining entries in the winners data vec are the system program id
-        for w_idx in (n_winners as usize)..MAX_WINNERS {
-            msg!("winner {} : {}", w_idx, expected_winners.keys[w_idx as usize].to_string());
+        if tip_lamports > 0 {
+            invoke_signed(
+                &system_instruction::transfer(crank_tip_account_info.key, cranker_account_info.key, tip_lamports),
+                &[crank_tip_account_info.clone(), cranker_account_info.clone(), system_program_account_info.clone()],
+                &[&[accounts::CRANK_TIP_SEED, &[crank_tip_bump_seed]]]
+            )?;
+        }
 
-            if expected_winners.keys[w_idx] != solana_program::system_program::id() {
-                msg!("expected winner {} to have key {}", w_idx, solana_program::system_program::id());
-                return Ok(());
+        GameEvent::Cranked(Cranked {
+            cranker : *cranker_account_info.key,
+            n_credited,
+            n_selected,
+            tip_lamports,
+            tip_tokens
+        }).emit();
+
+        Ok(())
+    }
+
+    // turn the votes of the next closed window of slots into the game's input, see dp_common::game::inputs.
+    // anyone can resolve a window, and as windows are resolved in order the result doesn't depend on who does
+    fn resolve_input(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let resolver_account_info = next_account_info(account_info_iter)?;
+        let vote_tally_account_info = next_account_info(account_info_iter)?;
+        let input_log_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let mode_ballot_account_info = next_account_info(account_info_iter)?;
+
+        if !resolver_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the fourth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the second account is the vote tally
+        utils::check_vote_tally(program_id, vote_tally_account_info)?;
+
+        // the third account is the input log
+        utils::check_input_log(program_id, input_log_account_info)?;
+
+        // the fifth account is the mode ballot
+        utils::check_mode_ballot(program_id, mode_ballot_account_info)?;
+
+        let vote_tally_data = vote_tally_account_info.data.borrow();
+        let vote_tally = VoteTally::load(&vote_tally_data)?;
+
+        let mut input_log_data = input_log_account_info.data.borrow_mut();
+        let input_log = InputLog::load_mut(&mut input_log_data)?;
+
+        let clock = Clock::get()?;
+
+        let start_slot = input_log.next_slot.get();
+        let mut end_slot = start_slot.checked_add(config.input_window_slots).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+        if clock.slot < end_slot {
+            msg!("the window from slot {} closes at slot {}, the current slot is {}", start_slot, end_slot, clock.slot);
+            return Err(DaoPlaysError::InputWindowOpen.into());
+        }
+
+        // a mode ballot that closed before this window started decides the mode it is resolved with
+        let mut mode_ballot_data = mode_ballot_account_info.data.borrow_mut();
+        let mode_ballot = ModeBallot::load_mut(&mut mode_ballot_data)?;
+
+        let n_votes = mode_ballot.n_votes.get();
+        if let Some(previous_mode) = mode_ballot.settle(start_slot)? {
+            msg!("mode ballot settled with {} votes, the input mode is now {:?}", n_votes, mode_ballot.get_mode()?);
+
+            GameEvent::ModeBallotSettled(ModeBallotSettled {
+                previous_mode,
+                mode : mode_ballot.get_mode()?,
+                start_slot,
+                n_votes
+            }).emit();
+        }
+
+        let mode = mode_ballot.get_mode()?;
+
+        let mut votes = vote_tally.get_window_votes(start_slot, end_slot).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+        // a run of windows without any votes is resolved in one go, up to the next window that has a vote or the
+        // last closed window, so the log can catch up after a quiet spell
+        if votes.n_presses == 0 {
+            let mut n_windows = (clock.slot - start_slot) / config.input_window_slots;
+            if let Some(next_voted_slot) = vote_tally.get_next_voted_slot(start_slot) {
+                n_windows = n_windows.min((next_voted_slot - start_slot) / config.input_window_slots);
             }
+
+            let n_slots = config.input_window_slots.checked_mul(n_windows.max(1)).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            end_slot = start_slot.checked_add(n_slots).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+            votes = vote_tally.get_window_votes(start_slot, end_slot).ok_or(DaoPlaysError::ArithmeticOverflow)?;
         }
 
+        let total = votes.get_total().ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        let random = get_input_random(start_slot, end_slot, &votes.tallies);
+        let button = resolve_button(mode, &votes, random);
+
+        // the presses made in the window, in order, are the inputs of an anarchy window
+        let (first_press, n_presses, presses_complete) = input_log.take_window_presses(end_slot);
+        let complete = votes.complete && presses_complete;
+
+        input_log.record(ResolvedInput {
+            start_slot : start_slot.into(),
+            end_slot : end_slot.into(),
+            button,
+            mode : mode as u8,
+            complete : complete as u8,
+            total : total.into(),
+            random : random.into(),
+            first_press : first_press.into(),
+            n_presses : n_presses.into()
+        }).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+        msg!("resolved slots {} to {} in {:?} mode: button {} from {} presses totalling {}", start_slot, end_slot, mode, button, votes.n_presses, total);
+
+        GameEvent::InputResolved(InputResolved {
+            start_slot,
+            end_slot,
+            button,
+            total,
+            random,
+            complete
+        }).emit();
 
-        // now we can transfer the tokens
+        Ok(())
+    }
 
-        for w_idx in 0..(n_winners as usize) {
+    // spend tokens on a vote to change the input mode, see dp_common::game::modes
+    fn vote_input_mode(
+        accounts: &[AccountInfo],
+        mode_vote_data: ModeVoteData,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
 
-            utils::transfer_tokens(
-                TOKENS_WON,
-                program_token_account_info,
-                winners_account_info[w_idx],
-                program_derived_account_info,
-                token_program_account_info,
-                bump_seed
-        
-            )?;
+        let account_info_iter = &mut accounts.iter();
+
+        // This function expects to be passed eight accounts, the first eight of PushButton with the mode ballot in place
+        // of the vote tally
+        let voter_account_info = next_account_info(account_info_iter)?;
+        let voter_token_account_info = next_account_info(account_info_iter)?;
+        let program_token_account_info = next_account_info(account_info_iter)?;
+        let token_mint_account_info = next_account_info(account_info_iter)?;
+        let token_program_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let mode_ballot_account_info = next_account_info(account_info_iter)?;
+        let treasury_token_account_info = next_account_info(account_info_iter)?;
+
+        // the first account is the voter, who should be signing the transaction
+        if !voter_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // finally just reset the n_winners value to zero so we can select new winners again
-        // and reset all the winners keys to their default
-        for current_winner in 0..MAX_WINNERS {
+        // the sixth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the second account should be the voter's associated token account
+        let expected_voter_token_key = get_associated_token_address(voter_account_info.key, &config.token_mint);
+
+        if voter_token_account_info.key != &expected_voter_token_key {
+            msg!("expected second account to be the voter's associated token account {}", expected_voter_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
 
-            let winner_idx = get_state_index(StateEnum::Winners{index: current_winner});
-            solana_program::system_program::id().serialize(&mut &mut program_data_account_info.data.borrow_mut()[winner_idx.0..winner_idx.1])?; 
+        // the third account should be the programs token address
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
 
+        if program_token_account_info.key != &expected_program_token_key {
+            msg!("expected third account to be the programs token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
         }
 
-        0u8.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_winners_idx.0..n_winners_idx.1])?;
+        // the fourth account is the mint address for the token
+        if token_mint_account_info.key != &config.token_mint {
+            msg!("expected fourth account to be the token mint address {}", config.token_mint);
+            return Err(DaoPlaysError::InvalidTokenMint.into());
+        }
 
-        // as a sanity check  make sure the bidder data is still correct
+        // the fifth account is the token_program
+        if token_program_account_info.key != &spl_token::id() {
+            msg!("expected fifth account to be the token program {}", spl_token::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
 
-        // calculate the total bid amount and number of bidders at this time
-        let update = utils::get_bid_state(i64::MAX, program_data_account_info)?;
-        let n_bidders = update.0;
-        let total_bid = update.1;
+        // the seventh account is the mode ballot
+        utils::check_mode_ballot(program_id, mode_ballot_account_info)?;
 
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
+        // the eighth account is the treasury's token account
+        let expected_treasury_token_key = get_associated_token_address(&config.treasury, &config.token_mint);
 
-        let current_n_bidders =  u16::try_from_slice(&program_data_account_info.data.borrow()[n_bidders_idx.0..n_bidders_idx.1])?;
-        let current_total_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[total_bid_idx.0..total_bid_idx.1])?;
+        if treasury_token_account_info.key != &expected_treasury_token_key {
+            msg!("expected eighth account to be the treasury's token account {}", expected_treasury_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
 
-        // check these agree
+        if mode_vote_data.amount == 0 {
+            msg!("mode vote amount must be greater than zero");
+            return Err(DaoPlaysError::InvalidModeVote.into());
+        }
 
-        if n_bidders != current_n_bidders || total_bid != current_total_bid {
+        let clock = Clock::get()?;
 
-            msg!("bid data is out of sync: {} {} {} {}", n_bidders, current_n_bidders, total_bid, current_total_bid);
-            
-            n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?;
+        let mut mode_ballot_data = mode_ballot_account_info.data.borrow_mut();
+        let mode_ballot = ModeBallot::load_mut(&mut mode_ballot_data)?;
 
-            total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?;
+        // a closed ballot is settled by ResolveInput, and a new one can be opened once it has been
+        if mode_ballot.is_closed(clock.slot) {
+            msg!("the mode ballot closed at slot {}, send ResolveInput to settle it", mode_ballot.ballot_end_slot);
+            return Err(DaoPlaysError::ModeBallotClosed.into());
         }
 
-    
+        // the tokens are spent in the same way as a button press, split between burning, the treasury and the prize pool
+        let split = config.get_push_split(mode_vote_data.amount);
+
+        utils::spend_tokens(
+            &split,
+            voter_token_account_info,
+            token_mint_account_info,
+            treasury_token_account_info,
+            program_token_account_info,
+            voter_account_info,
+            token_program_account_info
+        )?;
+
+        mode_ballot.add_vote(clock.slot, mode_vote_data.mode, mode_vote_data.amount, config.mode_vote_slots).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        msg!("{:?} has {} votes in the ballot closing at slot {}", mode_vote_data.mode, mode_ballot.votes[mode_vote_data.mode as usize], mode_ballot.ballot_end_slot);
+
+        GameEvent::ModeVoted(ModeVoted {
+            voter : *voter_account_info.key,
+            mode : mode_vote_data.mode,
+            amount : mode_vote_data.amount,
+            ballot_end_slot : mode_ballot.ballot_end_slot.get()
+        }).emit();
+
+        Ok(())
+    }
+
+    fn claim_prize(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let bidder_token_account_info = next_account_info(account_info_iter)?;
+        let bidder_data_account_info = next_account_info(account_info_iter)?;
+
+        let program_derived_account_info = next_account_info(account_info_iter)?;
+        let program_token_account_info = next_account_info(account_info_iter)?;
+
+        let token_program_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the funding account and should be a signer. anyone can send a winner their
+        // prize, as it can only go to the winner's own token account
+        if !funding_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the eighth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the third account should be the bidder's associated token account
+        let expected_bidder_token_key = get_associated_token_address(bidder_account_info.key, &config.token_mint);
+
+        if bidder_token_account_info.key != &expected_bidder_token_key {
+            msg!("expected third account to be the bidder's associated token account {}", expected_bidder_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
+
+        // the fourth account should be the bidder's data account
+        let (expected_bidder_data_key, _bidder_bump_seed) = accounts::get_expected_bidder_data_key(program_id, bidder_account_info.key);
+
+        if bidder_data_account_info.key != &expected_bidder_data_key || bidder_data_account_info.owner != program_id {
+            msg!("expected fourth account to be the bidders data account {}", expected_bidder_data_key);
+            return Err(DaoPlaysError::InvalidBidderDataAccount.into());
+        }
+
+        // the fifth account is the program derived address which we can verify with find_program_address
+        let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
+
+        if program_derived_account_info.key != &expected_pda_key {
+            msg!("expected fifth account to be PDA {}", expected_pda_key);
+            return Err(DaoPlaysError::InvalidProgramAddress.into());
+        }
+
+        // the sixth account is the program's token account
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key {
+            msg!("expected sixth account to be the program's token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
+
+        // the seventh account is the token_program
+        if token_program_account_info.key != &spl_token::id() {
+            msg!("expected seventh account to be the token program {}", spl_token::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        // the ninth account is the programs data account, which keeps the total of the unclaimed prizes
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected ninth account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
+        }
+
+        let mut bidder_data = BidderData::load(&bidder_data_account_info.data.borrow())?;
+        let amount = bidder_data.claimable;
+
+        if amount == 0 {
+            msg!("bidder {} has no prize to claim", bidder_account_info.key);
+            return Err(DaoPlaysError::NothingToClaim.into());
+        }
+
+        // the prize stays credited to the bidder if it can't be sent, so it can be claimed later
+        let program_token_account = spl_token::state::Account::unpack_unchecked(&program_token_account_info.try_borrow_data()?)?;
+        if program_token_account.amount < amount {
+            msg!("insufficient tokens in program account to pay the prize: {} < {}", program_token_account.amount, amount);
+            return Err(DaoPlaysError::InsufficientTokens.into());
+        }
+
+        utils::transfer_tokens(
+            amount,
+            program_token_account_info,
+            bidder_token_account_info,
+            program_derived_account_info,
+            token_program_account_info,
+            bump_seed
+        )?;
+
+        bidder_data.claimable = 0;
+        bidder_data.store(&mut bidder_data_account_info.data.borrow_mut())?;
+
+        let mut program_data = program_data_account_info.data.borrow_mut();
+        let mut state = State::load_mut(&mut program_data)?;
+        let total_claimable = state.total_claimable.get().checked_sub(amount).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+        state.total_claimable.set(total_claimable);
+
+        GameEvent::TokensSent(TokensSent {
+            winner_key : *bidder_token_account_info.key,
+            amount
+        }).emit();
+
+        Ok(())
+    }
+
+    fn update_config(
+        accounts: &[AccountInfo],
+        new_config: Config,
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let authority_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the config authority and should be a signer
+        if !authority_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the second account is the config account. a config written in an older layout can't be read in full, but
+        // it always starts with the authority and the data account, which is all that is needed to replace it
+        let (expected_config_key, _config_bump_seed) = accounts::get_expected_config_key(program_id);
+
+        if config_account_info.key != &expected_config_key {
+            msg!("expected second account to be the config account {}", expected_config_key);
+            return Err(DaoPlaysError::InvalidConfigAccount.into());
+        }
+
+        if config_account_info.owner != program_id {
+            msg!("config account has not been initialised");
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let (authority, data_account) = Config::load_keys(&config_account_info.data.borrow())?;
+
+        if authority_account_info.key != &authority {
+            msg!("expected first account to be the config authority {}", authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
+        }
+
+        // the data account is fixed when the config is created as the state can't be moved
+        if !new_config.is_valid() || new_config.data_account != data_account {
+            msg!("new config is invalid");
+            return Err(DaoPlaysError::InvalidConfig.into());
+        }
+
+        // the third account is the system program, which tops up the rent of an older config that has to grow
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected third account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        utils::resize_config_account(authority_account_info, config_account_info)?;
+
+        new_config.store(&mut config_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn add_charity(
+        accounts: &[AccountInfo],
+        charity_data: AddCharityData,
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let authority_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the config authority and should be a signer
+        if !authority_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the second account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        if authority_account_info.key != &config.authority {
+            msg!("expected first account to be the config authority {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
+        }
+
+        // the third account is the charity registry, which we create the first time a charity is added
+        let (expected_registry_key, registry_bump_seed) = get_expected_charity_registry_key(program_id);
+
+        if charity_registry_account_info.key != &expected_registry_key {
+            msg!("expected third account to be the charity registry {}", expected_registry_key);
+            return Err(DaoPlaysError::InvalidCharityRegistry.into());
+        }
+
+        // the fourth and final account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected fourth account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        if **charity_registry_account_info.try_borrow_lamports()? == 0 {
+            utils::create_pda_account(
+                authority_account_info,
+                charity_registry_account_info,
+                program_id,
+                get_charity_registry_size(),
+                CHARITY_REGISTRY_SEED,
+                registry_bump_seed
+            )?;
+        }
+
+        check_charity_registry(program_id, charity_registry_account_info, DaoPlaysError::InvalidCharityRegistry.into())?;
+
+        let mut charity_registry_data = charity_registry_account_info.data.borrow_mut();
+        let charity_registry = CharityRegistry::load_mut(&mut charity_registry_data)?;
+
+        match charity_registry.add_charity(&charity_data.name, charity_data.address) {
+            Some(index) => {
+                msg!("added charity {} at index {} with address {}", charity_data.name, index, charity_data.address);
+            },
+            None => {
+                msg!("unable to add charity {}, the registry may be full", charity_data.name);
+                return Err(DaoPlaysError::InvalidCharity.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_charity(
+        accounts: &[AccountInfo],
+        charity_data: UpdateCharityData,
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let authority_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the config authority and should be a signer
+        if !authority_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the second account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        if authority_account_info.key != &config.authority {
+            msg!("expected first account to be the config authority {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
+        }
+
+        // the third account is the charity registry
+        check_charity_registry(program_id, charity_registry_account_info, DaoPlaysError::InvalidCharityRegistry.into())?;
+
+        let mut charity_registry_data = charity_registry_account_info.data.borrow_mut();
+        let charity_registry = CharityRegistry::load_mut(&mut charity_registry_data)?;
+
+        let charity = match charity_registry.get_charity_mut(charity_data.index) {
+            Some(charity) => charity,
+            None => {
+                msg!("no charity at index {}", charity_data.index);
+                return Err(DaoPlaysError::InvalidCharity.into());
+            }
+        };
+
+        charity.address = charity_data.address;
+        charity.active = charity_data.active as u8;
+
+        msg!("updated charity {} : {} active {}", charity.get_name(), charity.address, charity_data.active);
+
+        Ok(())
+    }
+
+    fn commit_seed(
+        accounts: &[AccountInfo],
+        commitment : [u8; 32],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let committer_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the first account is the committer, who puts up the bond and should be a signer
+        if !committer_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the third account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        if config.randomness_source != RandomnessSource::CommitReveal {
+            msg!("the config doesn't use commit-reveal randomness");
+            return Err(DaoPlaysError::RandomnessSourceMismatch.into());
+        }
+
+        // the second account is the commit account, which we create the first time a commitment is made
+        let (expected_commit_key, commit_bump_seed) = accounts::get_expected_commit_key(program_id);
+
+        if commit_account_info.key != &expected_commit_key {
+            msg!("expected second account to be the commit account {}", expected_commit_key);
+            return Err(DaoPlaysError::InvalidCommitAccount.into());
+        }
+
+        // the fourth and final account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected fourth account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        if **commit_account_info.try_borrow_lamports()? == 0 {
+            utils::create_pda_account(
+                committer_account_info,
+                commit_account_info,
+                program_id,
+                get_commit_size(),
+                accounts::COMMIT_SEED,
+                commit_bump_seed
+            )?;
+        }
+
+        // only one commitment can be waiting to be revealed at a time
+        let commit = utils::get_commit(program_id, commit_account_info)?;
+
+        if commit.is_active() {
+            msg!("commitment from {} is waiting to be revealed until slot {}", commit.committer, commit.get_reveal_deadline(config.reveal_timeout_slots));
+            return Err(DaoPlaysError::CommitPending.into());
+        }
+
+        // the bond is held in the commit account until the commitment is revealed or slashed
+        if config.commit_bond > 0 {
+            invoke(
+                &system_instruction::transfer(committer_account_info.key, commit_account_info.key, config.commit_bond),
+                &[committer_account_info.clone(), commit_account_info.clone()],
+            )?;
+        }
+
+        let clock = Clock::get()?;
+        let commit = Commit {
+            committer : *committer_account_info.key,
+            commitment,
+            commit_slot : clock.slot,
+            commit_time : clock.unix_timestamp,
+            bond : config.commit_bond
+        };
+
+        commit.serialize(&mut &mut commit_account_info.data.borrow_mut()[..])?;
+
+        GameEvent::SeedCommitted(SeedCommitted {
+            committer : commit.committer,
+            commitment,
+            commit_slot : commit.commit_slot,
+            bond : commit.bond
+        }).emit();
+
+        Ok(())
+    }
+
+    fn reveal_seed(
+        accounts: &[AccountInfo],
+        secret : [u8; 32],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let committer_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+        let slot_hashes_account_info = next_account_info(account_info_iter)?;
+
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let program_token_account_info = next_account_info(account_info_iter)?;
+
+        let config_account_info = next_account_info(account_info_iter)?;
+
+        // the first account is the committer, who should be a signer
+        if !committer_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the sixth and final account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        if config.randomness_source != RandomnessSource::CommitReveal {
+            msg!("the config doesn't use commit-reveal randomness");
+            return Err(DaoPlaysError::RandomnessSourceMismatch.into());
+        }
+
+        // the second account is the commit account, which should hold this committer's commitment
+        let commit = utils::get_commit(program_id, commit_account_info)?;
+
+        if !commit.is_active() {
+            msg!("there is no commitment to reveal");
+            return Err(DaoPlaysError::NoActiveCommit.into());
+        }
+
+        if committer_account_info.key != &commit.committer {
+            msg!("expected first account to be the committer {}", commit.committer);
+            return Err(DaoPlaysError::InvalidCommitter.into());
+        }
+
+        if get_commitment(&secret) != commit.commitment {
+            msg!("secret doesn't match the commitment");
+            return Err(DaoPlaysError::InvalidReveal.into());
+        }
+
+        // the secret can only be revealed once the slot hash of the commit's slot is known, and before it times out
+        let clock = Clock::get()?;
+        let reveal_deadline = commit.get_reveal_deadline(config.reveal_timeout_slots);
+
+        if clock.slot <= commit.commit_slot {
+            msg!("commitment can't be revealed until after slot {}", commit.commit_slot);
+            return Err(DaoPlaysError::RevealTooEarly.into());
+        }
+
+        if clock.slot > reveal_deadline {
+            msg!("commitment timed out at slot {}", reveal_deadline);
+            return Err(DaoPlaysError::RevealTimedOut.into());
+        }
+
+        // the fourth account is the programs data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected fourth account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
+        }
+
+        // the fifth account should be the programs token address
+        let expected_program_token_key = accounts::get_expected_program_token_key(program_id, &config.token_mint);
+
+        if program_token_account_info.key != &expected_program_token_key
+        {
+            msg!("expected fifth account to be the programs token account {}", expected_program_token_key);
+            return Err(DaoPlaysError::InvalidTokenAccount.into());
+        }
+
+        // the third account is the slot hashes sysvar
+        let slot_hash = randoms::get_slot_hash(slot_hashes_account_info, commit.commit_slot)?;
+        let seed = get_revealed_seed(&secret, &slot_hash);
+
+        GameEvent::SeedRevealed(SeedRevealed {
+            committer : commit.committer,
+            secret,
+            commit_slot : commit.commit_slot,
+            slot_hash,
+            seed,
+            commit_time : commit.commit_time
+        }).emit();
+
+        // return the bond and clear the commitment
+        utils::transfer_program_lamports(commit_account_info, committer_account_info, commit.bond)?;
+        Commit::default().serialize(&mut &mut commit_account_info.data.borrow_mut()[..])?;
+
+        // only bids made before the commitment are in the draw, as the committer knows the seed before anyone else
+        let threshold_time = clock.unix_timestamp.checked_sub(2).ok_or(DaoPlaysError::ArithmeticOverflow)?.min(commit.commit_time);
+
+        Self::draw_winners(
+            program_data_account_info,
+            program_token_account_info,
+            &config,
+            threshold_time,
+            || Ok(seed)
+        )
+    }
+
+    fn slash_commit(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let commit_account_info = next_account_info(account_info_iter)?;
+        let dao_plays_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+
+        // the third account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        // the first account is the commit account
+        let commit = utils::get_commit(program_id, commit_account_info)?;
+
+        if !commit.is_active() {
+            msg!("there is no commitment to slash");
+            return Err(DaoPlaysError::NoActiveCommit.into());
+        }
+
+        // anyone can slash a commitment once it has timed out without being revealed
+        let clock = Clock::get()?;
+        let reveal_deadline = commit.get_reveal_deadline(config.reveal_timeout_slots);
+
+        if clock.slot <= reveal_deadline {
+            msg!("commitment can still be revealed until slot {}", reveal_deadline);
+            return Err(DaoPlaysError::CommitNotExpired.into());
+        }
+
+        // the second account is the daoplays SOL address, which is the config authority and receives the bond
+        if dao_plays_account_info.key != &config.authority {
+            msg!("expected second account to be the daoplays address {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
+        }
+
+        utils::transfer_program_lamports(commit_account_info, dao_plays_account_info, commit.bond)?;
+        Commit::default().serialize(&mut &mut commit_account_info.data.borrow_mut()[..])?;
+
+        msg!("slashed {} lamports from {} for not revealing the commitment from slot {}", commit.bond, commit.committer, commit.commit_slot);
+
+        GameEvent::CommitSlashed(CommitSlashed {
+            committer : commit.committer,
+            commit_slot : commit.commit_slot,
+            bond : commit.bond
+        }).emit();
+
+        Ok(())
+    }
+
+    fn resize_state(
+        accounts: &[AccountInfo],
+        resize_data : ResizeData,
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let authority_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the config authority and should be a signer, as it pays the extra rent
+        if !authority_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the fourth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        if authority_account_info.key != &config.authority {
+            msg!("expected first account to be the config authority {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
+        }
+
+        // the second account is the program's data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected second account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
+        }
+
+        // the third account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected third account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        let current_capacity = State::load_mut(&mut program_data_account_info.data.borrow_mut())?.get_capacity();
+        let capacity = Capacity {bids : resize_data.bid_capacity as usize, winners : resize_data.winner_capacity as usize};
+
+        // the ladder can only grow
+        if !capacity.is_valid() || !capacity.contains(&current_capacity) {
+            msg!("can't resize the data account from {:?} to {:?}", current_capacity, capacity);
+            return Err(DaoPlaysError::InvalidCapacity.into());
+        }
+
+        if capacity == current_capacity {
+            msg!("data account already has capacity {:?}", capacity);
+            return Ok(());
+        }
+
+        // large resizes take several instructions, and the ladder is only moved once the account is large enough
+        let data_size = get_data_account_size(&capacity);
+        if !utils::grow_data_account(authority_account_info, program_data_account_info, data_size)? {
+            msg!("data account has grown to {} of {} bytes, call ResizeState again to continue", program_data_account_info.data_len(), data_size);
+            return Ok(());
+        }
+
+        resize_ladder(&mut program_data_account_info.data.borrow_mut(), &capacity)?;
+
+        msg!("resized the data account to {:?}", capacity);
+
+        GameEvent::StateResized(StateResized {
+            data_account : *program_data_account_info.key,
+            bid_capacity : resize_data.bid_capacity,
+            winner_capacity : resize_data.winner_capacity,
+            data_size : data_size as u64
+        }).emit();
+
+        Ok(())
+    }
+
+
+    fn migrate_state(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let authority_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
+
+        // the first account should be the config authority and should be a signer, as it pays the extra rent
+        if !authority_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the fourth account is the config account
+        let config = utils::get_config(program_id, config_account_info)?;
+
+        if authority_account_info.key != &config.authority {
+            msg!("expected first account to be the config authority {}", config.authority);
+            return Err(DaoPlaysError::InvalidAuthority.into());
+        }
+
+        // the second account is the program's data account
+        if program_data_account_info.key != &config.data_account {
+            msg!("expected second account to be program data account {}", config.data_account);
+            return Err(DaoPlaysError::InvalidDataAccount.into());
+        }
+
+        // the third account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected third account to be the system program {}", solana_program::system_program::id());
+            return Err(DaoPlaysError::InvalidProgramAccount.into());
+        }
+
+        let layout_version = get_state_layout_version(&program_data_account_info.data.borrow());
+        match layout_version {
+            Some(STATE_LAYOUT_VERSION) => {
+                msg!("data account already has layout version {}", STATE_LAYOUT_VERSION);
+                return Ok(());
+            },
+            Some(LEGACY_LAYOUT_VERSION) => {},
+            _ => {
+                msg!("can't migrate a data account with layout version {:?}", layout_version);
+                return Err(DaoPlaysError::UnsupportedStateLayout.into());
+            }
+        }
+
+        // the legacy account needs to grow to fit the bid index, which takes several instructions, and the ladder
+        // is only moved once the account is large enough
+        let data_size = get_data_account_size(&LEGACY_CAPACITY);
+        if !utils::grow_data_account(authority_account_info, program_data_account_info, data_size)? {
+            msg!("data account has grown to {} of {} bytes, call MigrateState again to continue", program_data_account_info.data_len(), data_size);
+            return Ok(());
+        }
+
+        migrate_legacy_ladder(&mut program_data_account_info.data.borrow_mut())?;
+
+        msg!("migrated the data account to layout version {}", STATE_LAYOUT_VERSION);
+
+        GameEvent::StateMigrated(StateMigrated {
+            data_account : *program_data_account_info.key,
+            from_version : LEGACY_LAYOUT_VERSION,
+            to_version : STATE_LAYOUT_VERSION,
+            data_size : data_size as u64
+        }).emit();
+
         Ok(())
     }
 
-}
\ No newline at end of file
+}
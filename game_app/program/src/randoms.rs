@@ -1,98 +1,100 @@
-use crate::state::SeedStruct;
-use std::mem;
+use crate::error::DaoPlaysError;
 use solana_program::{
     account_info::AccountInfo,
-    msg
+    program_error::ProgramError,
+    msg, sysvar
 };
-use pyth_sdk_solana::{load_price_feed_from_account_info};
-use murmur3::murmur3_x64_128;
+use arrayref::array_ref;
+use pyth_sdk_solana::state::{load_price_account, PriceStatus};
 
+// the seed and draw math is shared with the off chain verifier via dp_common
+pub use dp_common::game::randomness::{shift_seed, generate_random, get_oracle_seed, OraclePrice};
 
+// read the aggregate price from a pyth price account, checking that it is trading, was published within
+// max_slot_age slots of current_slot, and isn't negative
+pub fn get_oracle_price(
+    price_account_info : &AccountInfo,
+    current_slot : u64,
+    max_slot_age : u64
+    ) -> Result<OraclePrice, DaoPlaysError> {
 
-// A xorshift* generator as suggested by Marsaglia.
-// The following 64-bit generator with 64 bits of state has a maximal period of 2^64−1
-// and fails only the MatrixRank test of BigCrush
-// see https://en.wikipedia.org/wiki/Xorshift
-pub fn shift_seed(mut seed: u64) -> u64 {
-    seed ^= seed >> 12;
-    seed ^= seed << 25;
-    seed ^= seed >> 27;
-    seed *= 0x2545F4914F6CDD1D;
+    let data = price_account_info.try_borrow_data().map_err(|_| DaoPlaysError::OracleDataInvalid)?;
+    let price_account = load_price_account(&data).map_err(|_| DaoPlaysError::OracleDataInvalid)?;
 
-    return seed;
+    if !matches!(price_account.agg.status, PriceStatus::Trading) {
+        return Err(DaoPlaysError::OraclePriceUnavailable);
+    }
 
-}
-
-pub fn generate_random(seed: u64) -> f64 {
-
-    let tmp = 0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF);
-    let result: f64 = unsafe { mem::transmute(tmp) };
-    
-
-    return result - 1.0;
-}
+    if current_slot.saturating_sub(price_account.agg.pub_slot) > max_slot_age {
+        return Err(DaoPlaysError::OraclePriceStale);
+    }
 
+    let price = u64::try_from(price_account.agg.price).map_err(|_| DaoPlaysError::OraclePriceNegative)?;
 
-unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
-    ::std::slice::from_raw_parts(
-        (p as *const T) as *const u8,
-        ::std::mem::size_of::<T>(),
-    )
+    Ok(OraclePrice {price, conf : price_account.agg.conf, expo : price_account.expo})
 }
 
+// hash the btc, eth and sol prices into a seed. any oracle without a usable price is left out of the seed,
+// so winners can still be selected while at least one of them is healthy
 pub fn generate_seed<'a>(
     btc_account_info : &AccountInfo<'a>,
     eth_account_info : &AccountInfo<'a>,
     sol_account_info : &AccountInfo<'a>,
-    ) ->u64 {
-
-    let btc_price_feed = load_price_feed_from_account_info( &btc_account_info ).unwrap();
-    let eth_price_feed = load_price_feed_from_account_info( &eth_account_info ).unwrap();
-    let sol_price_feed = load_price_feed_from_account_info( &sol_account_info ).unwrap();
-
-    let btc_price_struct = btc_price_feed.get_current_price().unwrap();
-    let eth_price_struct = eth_price_feed.get_current_price().unwrap();
-    let sol_price_struct = sol_price_feed.get_current_price().unwrap();
-
-    let btc_price_value = u64::try_from(btc_price_struct.price).unwrap();
-    let btc_price_error = btc_price_struct.conf;
-
-    let eth_price_value = u64::try_from(eth_price_struct.price).unwrap();
-    let eth_price_error = eth_price_struct.conf;
-
-    let sol_price_value = u64::try_from(sol_price_struct.price).unwrap();
-    let sol_price_error = sol_price_struct.conf;
-
-    msg!("btc price: ({} +/- {}) x 10^{}", btc_price_value, btc_price_error, btc_price_struct.expo);
-    msg!("eth price: ({} +/- {}) x 10^{}", eth_price_value, eth_price_error, eth_price_struct.expo);
-    msg!("sol price: ({} +/- {}) x 10^{}", sol_price_value, sol_price_error, sol_price_struct.expo);
-
-    let mut seed_values = SeedStruct { seed_prices : [0; 9] };
-
-   
-    //msg!("Generating seed");    
-
-    seed_values.seed_prices[0] = shift_seed(shift_seed(btc_price_value + btc_price_error));
-    seed_values.seed_prices[1] = shift_seed(shift_seed(btc_price_value));
-    seed_values.seed_prices[2] = shift_seed(shift_seed(btc_price_value - btc_price_error));
-
-    seed_values.seed_prices[3] = shift_seed(shift_seed(eth_price_value + eth_price_error));
-    seed_values.seed_prices[4] = shift_seed(shift_seed(eth_price_value));
-    seed_values.seed_prices[5] = shift_seed(shift_seed(eth_price_value - eth_price_error));
-
-    seed_values.seed_prices[6] = shift_seed(shift_seed(sol_price_value + sol_price_error));
-    seed_values.seed_prices[7] = shift_seed(shift_seed(sol_price_value));
-    seed_values.seed_prices[8] = shift_seed(shift_seed(sol_price_value - sol_price_error));
-
-    let mut vec_to_hash = unsafe{any_as_u8_slice(&seed_values)};
-    let h = murmur3_x64_128(&mut vec_to_hash, 0).unwrap();
-
-    // we can take our 128bit number and get two 64bit values
-    let lower  = u64::try_from(h & 0xFFFFFFFFFFFFFFFF).unwrap();
-    let upper  = u64::try_from((h >> 64) & 0xFFFFFFFFFFFFFFFF).unwrap();
-
-    let seed = lower ^ upper;
-        
+    current_slot : u64,
+    max_slot_age : u64
+    ) -> Result<u64, ProgramError> {
+
+    let mut prices : [Option<OraclePrice>; 3] = [None; 3];
+
+    let oracles = [("btc", btc_account_info), ("eth", eth_account_info), ("sol", sol_account_info)];
+    for (idx, (name, price_account_info)) in oracles.iter().enumerate() {
+
+        let oracle_price = match get_oracle_price(price_account_info, current_slot, max_slot_age) {
+            Ok(oracle_price) => oracle_price,
+            Err(err) => {
+                msg!("{} oracle is unavailable: {}", name, err);
+                continue;
+            }
+        };
+
+        msg!("{} price: ({} +/- {}) x 10^{}", name, oracle_price.price, oracle_price.conf, oracle_price.expo);
+
+        prices[idx] = Some(oracle_price);
+    }
+
+    match get_oracle_seed(&prices) {
+        Some(seed) => Ok(seed),
+        None => {
+            msg!("none of the oracles have a usable price");
+            Err(DaoPlaysError::NoHealthyOracles.into())
+        }
+    }
+}
 
-    return seed;
-}
\ No newline at end of file
+// look up the hash of slot in the SlotHashes sysvar, which holds the hashes of the most recent 512 slots.
+// the sysvar is too large to deserialize on chain, so we scan the bincode encoded Vec<(Slot, Hash)> in place
+pub fn get_slot_hash(
+    slot_hashes_account_info : &AccountInfo,
+    slot : u64
+    ) -> Result<[u8; 32], ProgramError> {
+
+    if slot_hashes_account_info.key != &sysvar::slot_hashes::id() {
+        msg!("expected the slot hashes sysvar {}", sysvar::slot_hashes::id());
+        return Err(DaoPlaysError::InvalidProgramAccount.into());
+    }
+
+    let data = slot_hashes_account_info.try_borrow_data()?;
+    if data.len() < 8 {
+        return Err(DaoPlaysError::SlotHashUnavailable.into());
+    }
+
+    let n_entries = u64::from_le_bytes(*array_ref![data, 0, 8]) as usize;
+    for entry in data[8..].chunks_exact(40).take(n_entries) {
+        if u64::from_le_bytes(*array_ref![entry, 0, 8]) == slot {
+            return Ok(*array_ref![entry, 8, 32]);
+        }
+    }
+
+    msg!("slot {} is not in the slot hashes sysvar", slot);
+    Err(DaoPlaysError::SlotHashUnavailable.into())
+}
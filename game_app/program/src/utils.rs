@@ -1,14 +1,20 @@
-use crate::state::{get_state_index, StateEnum, get_bid_status_size, TOKENS_WON, MAX_WINNERS, BID_BLOCK, N_BID_BLOCKS, BidValues, BidTimes};
+use crate::state::{State, Commit, Config, BidderData, PushSplit, PushStats, get_bid_status_size, get_config_size};
+use crate::accounts;
+use crate::error::DaoPlaysError;
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
     msg,
     program::{invoke, invoke_signed},
+    system_instruction,
     program_pack::Pack, pubkey::Pubkey, rent, clock::Clock, sysvar::Sysvar,
-    program_error::ProgramError, native_token::LAMPORTS_PER_SOL
+    program_error::ProgramError, native_token::LAMPORTS_PER_SOL,
+    entrypoint::MAX_PERMITTED_DATA_INCREASE
 };
-use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_program::program_utils::limited_deserialize;
 use spl_associated_token_account::instruction::create_associated_token_account;
+use borsh::BorshDeserialize;
 
 pub fn create_bidder_data_account<'a>(
     funding_account: &AccountInfo<'a>,
@@ -51,6 +57,419 @@ pub fn create_bidder_data_account<'a>(
     Ok(())
 }
 
+// bidder data accounts created before evictions were recorded are too small for the full record, so top up
+// their rent from the funding account and grow them in place
+pub fn resize_bidder_data_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    data_account: &AccountInfo<'a>
+) -> ProgramResult
+{
+    let data_size = get_bid_status_size();
+    if data_account.data_len() >= data_size {
+        return Ok(());
+    }
+
+    msg!("Resizing bidders data account {} to {}", data_account.key, data_size);
+
+    resize_account(funding_account, data_account, data_size)
+}
+
+// configs written before the config had a layout header, or before its later fields were added, are smaller than
+// the current config, so top up their rent from the funding account and grow them in place
+pub fn resize_config_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    config_account: &AccountInfo<'a>
+) -> ProgramResult
+{
+    let config_size = get_config_size();
+    if config_account.data_len() >= config_size {
+        return Ok(());
+    }
+
+    msg!("Resizing config account {} to {}", config_account.key, config_size);
+
+    resize_account(funding_account, config_account, config_size)
+}
+
+fn resize_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    data_size : usize
+) -> ProgramResult
+{
+    let lamports = rent::Rent::default().minimum_balance(data_size);
+    let top_up = lamports.saturating_sub(account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(funding_account.key, account.key, top_up),
+            &[funding_account.clone(), account.clone()],
+        )?;
+    }
+
+    account.realloc(data_size, true)?;
+
+    Ok(())
+}
+
+// create the program's data account, whose address is derived from the authority's key with create_with_seed
+pub fn create_data_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    data_account: &AccountInfo<'a>,
+    program_id :  &Pubkey,
+    data_size : usize
+) -> ProgramResult
+{
+    msg!("Creating program data account {}", data_account.key);
+
+    let space : u64 = data_size.try_into().unwrap();
+    let lamports = rent::Rent::default().minimum_balance(data_size);
+
+    msg!("Require {} lamports for {} size data", lamports, data_size);
+    let ix = system_instruction::create_account_with_seed(
+        funding_account.key,
+        data_account.key,
+        funding_account.key,
+        accounts::DATA_ACCOUNT_SEED,
+        lamports,
+        space,
+        program_id,
+    );
+
+    invoke(
+        &ix,
+        &[funding_account.clone(), data_account.clone()]
+    )?;
+
+    Ok(())
+}
+
+// an account can only grow by MAX_PERMITTED_DATA_INCREASE bytes in each instruction, so grow the data account by
+// at most that much towards data_size, topping up its rent from the funding account. returns true once the
+// account is large enough
+pub fn grow_data_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    data_account: &AccountInfo<'a>,
+    data_size : usize
+) -> Result<bool, ProgramError>
+{
+    let current_size = data_account.data_len();
+    if current_size >= data_size {
+        return Ok(true);
+    }
+
+    let new_size = data_size.min(current_size + MAX_PERMITTED_DATA_INCREASE);
+    msg!("Resizing program data account {} from {} to {}", data_account.key, current_size, new_size);
+
+    let lamports = rent::Rent::default().minimum_balance(new_size);
+    let top_up = lamports.saturating_sub(data_account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(funding_account.key, data_account.key, top_up),
+            &[funding_account.clone(), data_account.clone()],
+        )?;
+    }
+
+    data_account.realloc(new_size, true)?;
+
+    Ok(new_size >= data_size)
+}
+
+// add an eviction to the record of the bidder whose bid was replaced
+pub fn record_eviction<'a>(
+    funding_account: &AccountInfo<'a>,
+    evicted_token_account_info : Option<&AccountInfo<'a>>,
+    evicted_bidder_data_account_info : Option<&AccountInfo<'a>>,
+    evicted_key : &Pubkey,
+    amount : u64,
+    program_id :  &Pubkey
+) -> ProgramResult
+{
+    let (token_account_info, bidder_data_account_info) = match (evicted_token_account_info, evicted_bidder_data_account_info) {
+        (Some(token_account_info), Some(bidder_data_account_info)) => (token_account_info, bidder_data_account_info),
+        _ => {
+            msg!("expected the evicted bidder's token account and data account as the final accounts");
+            return Err(DaoPlaysError::InvalidEvictedBidder.into());
+        }
+    };
+
+    if token_account_info.key != evicted_key {
+        msg!("expected the evicted bidder's token account {}", evicted_key);
+        return Err(DaoPlaysError::InvalidEvictedBidder.into());
+    }
+
+    // the data account is derived from the owner of the token account that was stored with the bid.  If that
+    // account has since been closed there is no one left to record the eviction against, but that mustn't
+    // stop the slot from being reused
+    let token_account = match spl_token::state::Account::unpack(&token_account_info.try_borrow_data()?) {
+        Ok(token_account) => token_account,
+        Err(_) => {
+            msg!("evicted token account {} is closed, not recording eviction", evicted_key);
+            return Ok(());
+        }
+    };
+
+    let (expected_bidder_data_key, _bump_seed) = accounts::get_expected_bidder_data_key(program_id, &token_account.owner);
+
+    if bidder_data_account_info.key != &expected_bidder_data_key {
+        msg!("expected the evicted bidder's data account {}", expected_bidder_data_key);
+        return Err(DaoPlaysError::InvalidEvictedBidder.into());
+    }
+
+    if bidder_data_account_info.owner != program_id {
+        msg!("evicted bidder has no data account, not recording eviction");
+        return Ok(());
+    }
+
+    resize_bidder_data_account(funding_account, bidder_data_account_info)?;
+
+    let mut bidder_data = BidderData::load(&bidder_data_account_info.data.borrow())?;
+    bidder_data.record_eviction(amount).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+    bidder_data.store(&mut bidder_data_account_info.data.borrow_mut())?;
+
+    msg!("recorded eviction of {} for {}, {} evictions totalling {}", to_sol(amount), token_account.owner, bidder_data.n_evictions, to_sol(bidder_data.lamports_evicted));
+
+    Ok(())
+}
+
+// create an account owned by the program at the address derived from seed, used for the config and the charity registry
+pub fn create_pda_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    new_account: &AccountInfo<'a>,
+    program_id :  &Pubkey,
+    data_size : usize,
+    seed : &[u8],
+    bump_seed : u8
+) -> ProgramResult
+{
+
+    msg!("Creating program derived account {}", new_account.key);
+
+    let space : u64 = data_size.try_into().unwrap();
+    let lamports = rent::Rent::default().minimum_balance(data_size);
+
+    msg!("Require {} lamports for {} size data", lamports, data_size);
+    let ix = solana_program::system_instruction::create_account(
+        funding_account.key,
+        new_account.key,
+        lamports,
+        space,
+        program_id,
+    );
+
+    // Sign and submit transaction
+    invoke_signed(
+        &ix,
+        &[funding_account.clone(), new_account.clone()],
+        &[&[seed, &[bump_seed]]]
+    )?;
+
+    Ok(())
+}
+
+// check that authority is the program's upgrade authority, as recorded in the loader's program data account
+pub fn check_upgrade_authority(
+    program_id :  &Pubkey,
+    program_data_account_info : &AccountInfo,
+    authority : &Pubkey
+) -> ProgramResult
+{
+    let expected_program_data_key = accounts::get_expected_program_data_key(program_id);
+
+    if program_data_account_info.key != &expected_program_data_key || program_data_account_info.owner != &bpf_loader_upgradeable::id() {
+        msg!("expected program data account {}", expected_program_data_key);
+        return Err(DaoPlaysError::InvalidProgramAccount.into());
+    }
+
+    let data = program_data_account_info.data.borrow();
+    let metadata = data.get(..UpgradeableLoaderState::size_of_programdata_metadata()).ok_or(ProgramError::AccountDataTooSmall)?;
+
+    match limited_deserialize(metadata, UpgradeableLoaderState::size_of_programdata_metadata() as u64) {
+        Ok(UpgradeableLoaderState::ProgramData {upgrade_authority_address : Some(upgrade_authority), ..}) if &upgrade_authority == authority => Ok(()),
+        _ => {
+            msg!("{} is not the program's upgrade authority", authority);
+            Err(DaoPlaysError::InvalidAuthority.into())
+        }
+    }
+}
+
+// read the commitment from the commit account, checking that it is the one belonging to this program
+pub fn get_commit(
+    program_id :  &Pubkey,
+    commit_account_info : &AccountInfo
+) -> Result<Commit, ProgramError>
+{
+    let (expected_commit_key, _bump_seed) = accounts::get_expected_commit_key(program_id);
+
+    if commit_account_info.key != &expected_commit_key {
+        msg!("expected commit account {}", expected_commit_key);
+        return Err(DaoPlaysError::InvalidCommitAccount.into());
+    }
+
+    if commit_account_info.owner != program_id {
+        msg!("commit account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let commit = Commit::try_from_slice(&commit_account_info.data.borrow()[..])?;
+
+    Ok(commit)
+}
+
+// the commitment waiting to be revealed, if there is one that can still be revealed. the committer knows the seed as
+// soon as the commit's slot hash exists, so while this is Some the bids already in the draw must not change. the
+// commit account is only created by the first CommitSeed, so until then there is nothing pending
+pub fn get_pending_commit(
+    program_id :  &Pubkey,
+    commit_account_info : &AccountInfo,
+    config : &Config
+) -> Result<Option<Commit>, ProgramError>
+{
+    let (expected_commit_key, _bump_seed) = accounts::get_expected_commit_key(program_id);
+
+    if commit_account_info.key != &expected_commit_key {
+        msg!("expected commit account {}", expected_commit_key);
+        return Err(DaoPlaysError::InvalidCommitAccount.into());
+    }
+
+    if commit_account_info.owner != program_id {
+        return Ok(None);
+    }
+
+    let commit = Commit::try_from_slice(&commit_account_info.data.borrow()[..])?;
+
+    // a commitment that has timed out can no longer be revealed, only slashed
+    if !commit.is_active() || Clock::get()?.slot > commit.get_reveal_deadline(config.reveal_timeout_slots) {
+        return Ok(None);
+    }
+
+    Ok(Some(commit))
+}
+
+// read the push stats from the push stats account, checking that it is the one belonging to this program
+pub fn get_push_stats(
+    program_id :  &Pubkey,
+    push_stats_account_info : &AccountInfo
+) -> Result<PushStats, ProgramError>
+{
+    let (expected_push_stats_key, _bump_seed) = accounts::get_expected_push_stats_key(program_id);
+
+    if push_stats_account_info.key != &expected_push_stats_key {
+        msg!("expected push stats account {}", expected_push_stats_key);
+        return Err(DaoPlaysError::InvalidPushStatsAccount.into());
+    }
+
+    if push_stats_account_info.owner != program_id {
+        msg!("push stats account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let push_stats = PushStats::try_from_slice(&push_stats_account_info.data.borrow()[..])?;
+
+    Ok(push_stats)
+}
+
+// check the vote tally account is the one belonging to this program before it is loaded
+pub fn check_vote_tally(
+    program_id :  &Pubkey,
+    vote_tally_account_info : &AccountInfo
+) -> ProgramResult
+{
+    let (expected_vote_tally_key, _bump_seed) = accounts::get_expected_vote_tally_key(program_id);
+
+    if vote_tally_account_info.key != &expected_vote_tally_key {
+        msg!("expected vote tally account {}", expected_vote_tally_key);
+        return Err(DaoPlaysError::InvalidVoteTallyAccount.into());
+    }
+
+    if vote_tally_account_info.owner != program_id {
+        msg!("vote tally account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(())
+}
+
+// check the input log account is the one belonging to this program before it is loaded
+pub fn check_input_log(
+    program_id :  &Pubkey,
+    input_log_account_info : &AccountInfo
+) -> ProgramResult
+{
+    let (expected_input_log_key, _bump_seed) = accounts::get_expected_input_log_key(program_id);
+
+    if input_log_account_info.key != &expected_input_log_key {
+        msg!("expected input log account {}", expected_input_log_key);
+        return Err(DaoPlaysError::InvalidInputLogAccount.into());
+    }
+
+    if input_log_account_info.owner != program_id {
+        msg!("input log account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(())
+}
+
+// check the mode ballot account is the one belonging to this program before it is loaded
+pub fn check_mode_ballot(
+    program_id :  &Pubkey,
+    mode_ballot_account_info : &AccountInfo
+) -> ProgramResult
+{
+    let (expected_mode_ballot_key, _bump_seed) = accounts::get_expected_mode_ballot_key(program_id);
+
+    if mode_ballot_account_info.key != &expected_mode_ballot_key {
+        msg!("expected mode ballot account {}", expected_mode_ballot_key);
+        return Err(DaoPlaysError::InvalidModeBallotAccount.into());
+    }
+
+    if mode_ballot_account_info.owner != program_id {
+        msg!("mode ballot account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(())
+}
+
+// move lamports out of an account owned by the program
+pub fn transfer_program_lamports(
+    source_account : &AccountInfo,
+    dest_account : &AccountInfo,
+    amount : u64
+) -> ProgramResult
+{
+    let source_lamports = source_account.lamports().checked_sub(amount).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+    let dest_lamports = dest_account.lamports().checked_add(amount).ok_or(DaoPlaysError::ArithmeticOverflow)?;
+
+    **source_account.try_borrow_mut_lamports()? = source_lamports;
+    **dest_account.try_borrow_mut_lamports()? = dest_lamports;
+
+    Ok(())
+}
+
+// read the config from the config account, checking that it is the one belonging to this program
+pub fn get_config(
+    program_id :  &Pubkey,
+    config_account_info : &AccountInfo
+) -> Result<Config, ProgramError>
+{
+    let (expected_config_key, _bump_seed) = accounts::get_expected_config_key(program_id);
+
+    if config_account_info.key != &expected_config_key {
+        msg!("expected config account {}", expected_config_key);
+        return Err(DaoPlaysError::InvalidConfigAccount.into());
+    }
+
+    if config_account_info.owner != program_id {
+        msg!("config account has not been initialised");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let config = Config::load(&config_account_info.data.borrow())?;
+
+    Ok(config)
+}
+
 pub fn create_program_account<'a>(
     funding_account: &AccountInfo<'a>,
     pda : &AccountInfo<'a>,
@@ -99,6 +518,21 @@ pub fn transfer_tokens<'a>(
     token_program_account : &AccountInfo<'a>,
     bump_seed : u8
 
+) -> ProgramResult
+{
+    transfer_tokens_with_seed(amount, token_source_account, token_dest_account, authority_account, token_program_account, accounts::PDA_SEED, bump_seed)
+}
+
+// transfer tokens from an account owned by the program derived address with the given seed
+pub fn transfer_tokens_with_seed<'a>(
+    amount : u64,
+    token_source_account : &AccountInfo<'a>,
+    token_dest_account : &AccountInfo<'a>,
+    authority_account : &AccountInfo<'a>,
+    token_program_account : &AccountInfo<'a>,
+    seed : &[u8],
+    bump_seed : u8
+
 ) -> ProgramResult
 {
     let ix = spl_token::instruction::transfer(
@@ -113,7 +547,64 @@ pub fn transfer_tokens<'a>(
     invoke_signed(
         &ix,
         &[token_source_account.clone(), token_dest_account.clone(), authority_account.clone(), token_program_account.clone()],
-        &[&[b"token_account", &[bump_seed]]]
+        &[&[seed, &[bump_seed]]]
+    )?;
+
+    Ok(())
+}
+
+// spend tokens from an account the authority, who must sign the transaction, owns, as split says: burning some,
+// sending some to the treasury and the rest to the program's token account, where they top up the prize pool
+pub fn spend_tokens<'a>(
+    split : &PushSplit,
+    token_source_account : &AccountInfo<'a>,
+    token_mint_account : &AccountInfo<'a>,
+    treasury_token_account : &AccountInfo<'a>,
+    program_token_account : &AccountInfo<'a>,
+    authority_account : &AccountInfo<'a>,
+    token_program_account : &AccountInfo<'a>
+
+) -> ProgramResult
+{
+    if split.burn > 0 {
+        burn_tokens(split.burn, token_source_account, token_mint_account, authority_account, token_program_account)?;
+    }
+
+    if split.treasury > 0 {
+        transfer_tokens(split.treasury, token_source_account, treasury_token_account, authority_account, token_program_account, accounts::get_pda_bump())?;
+    }
+
+    if split.prizes > 0 {
+        transfer_tokens(split.prizes, token_source_account, program_token_account, authority_account, token_program_account, accounts::get_pda_bump())?;
+    }
+
+    msg!("burned {} tokens, sent {} to the treasury and {} to the prize pool", split.burn, split.treasury, split.prizes);
+
+    Ok(())
+}
+
+// burn tokens from an account the authority, who must sign the transaction, owns
+pub fn burn_tokens<'a>(
+    amount : u64,
+    token_source_account : &AccountInfo<'a>,
+    token_mint_account : &AccountInfo<'a>,
+    authority_account : &AccountInfo<'a>,
+    token_program_account : &AccountInfo<'a>
+
+) -> ProgramResult
+{
+    let ix = spl_token::instruction::burn(
+        token_program_account.key,
+        token_source_account.key,
+        token_mint_account.key,
+        authority_account.key,
+        &[],
+        amount,
+    )?;
+
+    invoke(
+        &ix,
+        &[token_source_account.clone(), token_mint_account.clone(), authority_account.clone(), token_program_account.clone()]
     )?;
 
     Ok(())
@@ -124,7 +615,8 @@ pub fn create_token_account<'a>(
     wallet_account : &AccountInfo<'a>,
     token_mint_account : &AccountInfo<'a>,
     new_token_account : &AccountInfo<'a>,
-    token_program_account : &AccountInfo<'a>
+    token_program_account : &AccountInfo<'a>,
+    system_program_account : &AccountInfo<'a>
 
 ) -> ProgramResult
 {
@@ -135,48 +627,30 @@ pub fn create_token_account<'a>(
     }
 
     msg!("creating Token account");
-    let create_ata_idx = create_associated_token_account(&funding_account.key, &wallet_account.key,&token_mint_account.key);
+    let create_ata_idx = create_associated_token_account(funding_account.key, wallet_account.key,token_mint_account.key);
 
     invoke(
         &create_ata_idx,
-        &[funding_account.clone(), new_token_account.clone(), wallet_account.clone(), token_mint_account.clone(), token_program_account.clone()],
+        &[funding_account.clone(), new_token_account.clone(), wallet_account.clone(), token_mint_account.clone(), system_program_account.clone(), token_program_account.clone()],
     )?;
 
     Ok(())
 }
 
-pub fn get_bid_state(max_time : i64, program_data_account_info : &AccountInfo) ->  Result<(u16, u64), ProgramError> {
-
+pub fn get_bid_state(max_time : i64, state : &State) ->  Result<(u16, u64), ProgramError> {
 
     // calculate the total bid amount and number of bidders at this time
-    let mut total_bid : u64 = 0;
-    let mut n_bidders : u16 = 0;
-    for idx in 0..N_BID_BLOCKS {
-        let bid_idx = get_state_index(StateEnum::BidAmounts {index: idx*BID_BLOCK});
-        let time_idx = get_state_index(StateEnum::BidTimes {index: idx*BID_BLOCK});
-
-
-        let bids = BidValues::try_from_slice(&program_data_account_info.data.borrow()[bid_idx.0..bid_idx.0 + BID_BLOCK*8])?; 
-        let times = BidTimes::try_from_slice(&program_data_account_info.data.borrow()[time_idx.0..time_idx.0 + BID_BLOCK*8])?; 
-
+    let bid_state = state.get_bid_totals(max_time).ok_or(DaoPlaysError::ArithmeticOverflow)?;
 
-        for jdx in 0..BID_BLOCK {
-            if times.bid_times[jdx] < max_time && bids.bid_amounts[jdx] > 0 {
-                total_bid += bids.bid_amounts[jdx];
-                n_bidders += 1;
-            }
-
-        }
-    }
-
-    Ok((n_bidders, total_bid))
+    Ok(bid_state)
     
 }
 
-pub fn check_winners_state<'a>(
+pub fn check_winners_state(
     n_bidders : u16, 
-    program_data_account_info : &AccountInfo<'a>,
-    program_token_account_info : &AccountInfo<'a>
+    state : &State,
+    config : &Config,
+    program_token_account_info : &AccountInfo
 ) ->  Result<u8, ProgramError> {
 
 
@@ -189,47 +663,52 @@ pub fn check_winners_state<'a>(
     }
 
 
-    // if there aren't enough tokens available then we can't choose winners
-    let min_tokens: u64 = TOKENS_WON;
+    // if there aren't enough tokens available then we can't choose winners. prizes that have been credited but not
+    // claimed are still in the program's token account, and aren't available
+    let min_tokens: u64 = config.tokens_won;
     let program_token_account = spl_token::state::Account::unpack_unchecked(&program_token_account_info.try_borrow_data()?)?;
 
-    let token_balance = program_token_account.amount;
+    let token_balance = program_token_account.amount.saturating_sub(state.total_claimable.get());
     if token_balance < min_tokens {
         msg!("insufficient tokens in program account to select new winners: {} < {}", token_balance, min_tokens);
         return Ok(0);
     }
 
-    let max_token_blocks = token_balance / TOKENS_WON;
+    let max_token_blocks = token_balance / config.tokens_won;
 
 
     // set the number of winners to the max and check if we should decrease from there
-    let mut n_winners = MAX_WINNERS as u8;
+    let mut n_winners = config.max_winners;
 
     // check if we have enough token blocks for this many
     if n_winners as u64 > max_token_blocks {
         n_winners = max_token_blocks as u8;
     }
 
+    // and that the data account has room for them, as the config can be updated before the account is resized
+    if n_winners > state.winner_capacity {
+        n_winners = state.winner_capacity;
+    }
+
     // finally check if we have enough bidders for this
     let max_winners_from_bidders = n_bidders / 64 + 1;
     if n_winners as u16 > max_winners_from_bidders {
         n_winners = max_winners_from_bidders as u8;
     }
 
-    let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
-    let prev_time_selected = i64::try_from_slice(&program_data_account_info.data.borrow()[prev_time_idx.0..prev_time_idx.1])?;
+    let prev_time_selected = state.prev_choose_winners_time.get();
 
     let clock = Clock::get()?;
     let current_time = clock.unix_timestamp;
-    let time_passed = (current_time - prev_time_selected) as f64;
+    let time_passed = current_time.checked_sub(prev_time_selected).ok_or(DaoPlaysError::ArithmeticOverflow)? as f64;
 
     
-    // on average we expect a single bidder to wait 5 minutes before being selected
+    // on average we expect a single bidder to wait selection_time seconds before being selected
     // we therefore calculate time_per_bidder based on the number of bidders, and number of winners being selected
     // if this is below 3 seconds we just allow new winners to be selected so that there is less friction with large
     // numbers of bidders
 
-    let time_per_bidder = (5.0 * 60.0) / ((n_bidders as f64) / (n_winners as f64));
+    let time_per_bidder = (config.selection_time as f64) / ((n_bidders as f64) / (n_winners as f64));
     
     msg!("time_per_bidder {} time_passed: {} n_bidders {} token_balance {} max_blocks {}", time_per_bidder, time_passed, n_bidders, token_balance, max_token_blocks);
 
@@ -243,24 +722,22 @@ pub fn check_winners_state<'a>(
     Ok(n_winners)
 }
 
-pub fn update_bid_state<'a>(
-    program_data_account_info : &AccountInfo<'a>
+pub fn update_bid_state(
+    state : &mut State
 ) -> ProgramResult {
 
 
     // calculate the total bid amount and number of bidders at this time
-    let update = get_bid_state(i64::MAX, program_data_account_info)?;
+    let update = get_bid_state(i64::MAX, state)?;
     let n_bidders = update.0;
     let total_bid = update.1;
 
     // update number of bidders
-    let n_bidders_idx = get_state_index(StateEnum::NBidders);
-    n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?;
+    state.n_bidders.set(n_bidders);
 
     // update total_bid_amount
-    let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
-    total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?;
-    
+    state.total_bid_amount.set(total_bid);
+
     Ok(())
 }
 
@@ -1,36 +1,1825 @@
-#![cfg(feature = "test-bpf")]
-
-use {
-    assert_matches::*,
-    solana_program::{
-        instruction::{AccountMeta, Instruction},
-        pubkey::Pubkey,
-    },
-    solana_sdk::{signature::Signer, transaction::Transaction},
-    solana_validator::test_validator::*,
+// runs the game program in process with solana-program-test, seeding the token mint and mock pyth price accounts in
+// the genesis so that a full round can be played from CreateDataAccount through to PushButton
+use borsh::{BorshDeserialize, BorshSerialize};
+use dp_common::charity::{AddCharityData, get_expected_charity_registry_key};
+use dp_common::game::accounts;
+use dp_common::game::error::DaoPlaysError;
+use dp_common::game::instruction::{DPPInstruction, InitData, BidData, ButtonData, Button, ModeVoteData, ResizeData, SequenceData, SequenceStep, MAX_SEQUENCE_STEPS};
+use dp_common::game::bid_index::{load_ladder_mut, get_data_account_size};
+use dp_common::game::state::{BidderData, Capacity, Commit, Config, LegacyState, PushStats, RandomnessSource, State, StateHeader, INITIAL_BID_CAPACITY, LEGACY_CAPACITY, STATE_LAYOUT_VERSION, get_state_header_size, get_state_layout_version, get_config_size};
+use dp_common::game::draw;
+use dp_common::game::votes::VoteTally;
+use dp_common::game::inputs::{InputLog, NO_BUTTON, get_input_random, pick_button};
+use dp_common::game::modes::{InputMode, ModeBallot};
+use dp_common::game::randomness::{get_commitment, get_oracle_seed, OraclePrice};
+use dp_v01::processor::Processor;
+use dp_common::mock_pyth::{self, MockPrice};
+use pyth_sdk_solana::state::PriceStatus;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    system_program,
+    sysvar,
+    transaction::{Transaction, TransactionError},
 };
+use spl_associated_token_account::get_associated_token_address;
 
-#[test]
-fn test_validator_transaction() {
-    solana_logger::setup_with_default("solana_program_runtime=debug");
-    let program_id = Pubkey::new_unique();
-
-    let (test_validator, payer) = TestValidatorGenesis::default()
-        .add_program("bpf_program_template", program_id)
-        .start();
-    let rpc_client = test_validator.get_rpc_client();
-
-    let blockhash = rpc_client.get_latest_blockhash().unwrap();
-
-    let mut transaction = Transaction::new_with_payer(
-        &[Instruction {
-            program_id,
-            accounts: vec![AccountMeta::new(payer.pubkey(), false)],
-            data: vec![1, 2, 3],
-        }],
-        Some(&payer.pubkey()),
+const TOKEN_SUPPLY : u64 = 1_000_000;
+const TOKENS_SENT_TO_PROGRAM : u64 = 10_000;
+const TOKENS_WON : u64 = 100;
+
+struct Game {
+    context : ProgramTestContext,
+    program_id : Pubkey,
+    authority : Keypair,
+    bidder : Keypair,
+    charity : Pubkey,
+    config : Config
+}
+
+fn price_account(mock_price : &MockPrice) -> Account {
+    let data = mock_price.pack();
+    Account {
+        lamports : Rent::default().minimum_balance(data.len()),
+        data,
+        owner : mock_pyth::get_pyth_program_key(),
+        executable : false,
+        rent_epoch : 0
+    }
+}
+
+fn packed_account<T : Pack>(state : T, owner : &Pubkey) -> Account {
+    let mut data = vec![0; T::LEN];
+    T::pack(state, &mut data).unwrap();
+
+    Account {
+        lamports : Rent::default().minimum_balance(T::LEN),
+        data,
+        owner : *owner,
+        executable : false,
+        rent_epoch : 0
+    }
+}
+
+fn token_account(mint : &Pubkey, owner : &Pubkey, amount : u64) -> Account {
+    packed_account(
+        spl_token::state::Account {
+            mint : *mint,
+            owner : *owner,
+            amount,
+            state : spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+        &spl_token::id()
+    )
+}
+
+async fn setup() -> Game {
+    setup_with_prices(mock_pyth::get_default_mock_prices()).await
+}
+
+async fn setup_with_prices(mock_prices : [MockPrice; 3]) -> Game {
+    // the program's token account is derived with a fixed bump seed, so the program must be deployed at its real address
+    let program_id = dp_common::game::id();
+    let mut program_test = ProgramTest::new("dp_v01", program_id, processor!(Processor::process));
+
+    let authority = Keypair::new();
+    let bidder = Keypair::new();
+    let charity = Pubkey::new_unique();
+    let token_mint = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let (btc_oracle, eth_oracle, sol_oracle) = (mock_pyth::get_mock_btc_key(), mock_pyth::get_mock_eth_key(), mock_pyth::get_mock_sol_key());
+
+    for wallet in [authority.pubkey(), bidder.pubkey(), charity] {
+        program_test.add_account(wallet, Account::new(100 * LAMPORTS_PER_SOL, 0, &system_program::id()));
+    }
+
+    program_test.add_account(
+        token_mint,
+        packed_account(
+            spl_token::state::Mint {
+                mint_authority : COption::None,
+                supply : TOKEN_SUPPLY,
+                decimals : 0,
+                is_initialized : true,
+                freeze_authority : COption::None
+            },
+            &spl_token::id()
+        )
+    );
+    program_test.add_account(
+        get_associated_token_address(&authority.pubkey(), &token_mint),
+        token_account(&token_mint, &authority.pubkey(), TOKEN_SUPPLY)
+    );
+    program_test.add_account(
+        get_associated_token_address(&treasury, &token_mint),
+        token_account(&token_mint, &treasury, 0)
+    );
+
+    for (oracle, mock_price) in [btc_oracle, eth_oracle, sol_oracle].iter().zip(mock_prices.iter()) {
+        program_test.add_account(*oracle, price_account(mock_price));
+    }
+
+    // the authority deployed the program, so the loader records them as its upgrade authority
+    program_test.add_account(
+        accounts::get_expected_program_data_key(&program_id),
+        Account::new_data(
+            LAMPORTS_PER_SOL,
+            &UpgradeableLoaderState::ProgramData {slot : 0, upgrade_authority_address : Some(authority.pubkey())},
+            &bpf_loader_upgradeable::id()
+        ).unwrap()
+    );
+
+    // CreateDataAccount creates the data account with a seed from the authority's key
+    let data_account = accounts::get_expected_data_account_key(&authority.pubkey(), &program_id);
+
+    let config = Config {
+        authority : authority.pubkey(),
+        data_account,
+        token_mint,
+        btc_oracle,
+        eth_oracle,
+        sol_oracle,
+        tokens_won : TOKENS_WON,
+        max_winners : 4,
+        min_bid : LAMPORTS_PER_SOL / 10,
+        selection_time : 0,
+        protect_larger_bids : false,
+        max_oracle_slot_age : 25,
+        randomness_source : RandomnessSource::Oracles,
+        reveal_timeout_slots : 10,
+        commit_bond : LAMPORTS_PER_SOL / 10,
+        crank_tip_lamports : LAMPORTS_PER_SOL / 100,
+        crank_tip_tokens : 5,
+        input_window_slots : 4,
+        mode_vote_slots : 8,
+        treasury,
+        push_burn_bps : 0,
+        push_treasury_bps : 0,
+        cancel_refund_tokens_per_sol : 0
+    };
+
+    let context = program_test.start_with_context().await;
+
+    Game {context, program_id, authority, bidder, charity, config}
+}
+
+impl Game {
+
+    async fn send(&mut self, instruction : Instruction, signer : &Keypair) -> Result<(), BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash
+        );
+
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    // change the ladder in the data account directly, rebuilding the bid index from it afterwards
+    async fn modify_ladder<F : FnOnce(&mut State)>(&mut self, modify : F) {
+        let mut account = self.context.banks_client.get_account(self.config.data_account).await.unwrap().unwrap();
+        let (mut state, mut bid_index) = load_ladder_mut(&mut account.data).unwrap();
+        modify(&mut state);
+        bid_index.rebuild(&state);
+        self.context.set_account(&self.config.data_account, &account.into());
+    }
+
+    // the data account, to be viewed with State::load_mut or load_ladder_mut
+    async fn get_data(&mut self) -> Vec<u8> {
+        self.context.banks_client.get_account(self.config.data_account).await.unwrap().unwrap().data
+    }
+
+    async fn get_header(&mut self) -> StateHeader {
+        let data = self.get_data().await;
+        *bytemuck::from_bytes(&data[..get_state_header_size()])
+    }
+
+    async fn get_token_balance(&mut self, key : Pubkey) -> u64 {
+        let account = self.context.banks_client.get_account(key).await.unwrap().unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    async fn get_lamports(&mut self, key : Pubkey) -> u64 {
+        self.context.banks_client.get_balance(key).await.unwrap()
+    }
+
+    // move the clock on so that the bids that have been placed are old enough to be included in the draw
+    async fn advance_clock(&mut self, seconds : i64) {
+        let mut clock : Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    // move on by n_slots, keeping the clock's time where it was
+    async fn warp_slots(&mut self, n_slots : u64) {
+        let clock : Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        self.context.warp_to_slot(clock.slot + n_slots).unwrap();
+
+        let mut warped_clock : Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        warped_clock.unix_timestamp = warped_clock.unix_timestamp.max(clock.unix_timestamp);
+        self.context.set_sysvar(&warped_clock);
+    }
+
+    async fn get_commit(&mut self) -> Commit {
+        let (commit_key, _) = accounts::get_expected_commit_key(&self.program_id);
+        let account = self.context.banks_client.get_account(commit_key).await.unwrap().unwrap();
+        Commit::try_from_slice(&account.data).unwrap()
+    }
+
+    fn program_token_key(&self) -> Pubkey {
+        accounts::get_expected_program_token_key(&self.program_id, &self.config.token_mint)
+    }
+
+    fn token_key(&self, wallet : &Pubkey) -> Pubkey {
+        get_associated_token_address(wallet, &self.config.token_mint)
+    }
+
+    fn create_data_account_instruction(&self, token_mint : Pubkey) -> Instruction {
+        let authority = self.authority.pubkey();
+        let (pda, _) = accounts::get_expected_program_address_key(&self.program_id);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (vote_tally_key, _) = accounts::get_expected_vote_tally_key(&self.program_id);
+        let (input_log_key, _) = accounts::get_expected_input_log_key(&self.program_id);
+        let (mode_ballot_key, _) = accounts::get_expected_mode_ballot_key(&self.program_id);
+        let (push_stats_key, _) = accounts::get_expected_push_stats_key(&self.program_id);
+
+        let instruction = DPPInstruction::CreateDataAccount {
+            init_data : InitData {amount : TOKENS_SENT_TO_PROGRAM, config : self.config.clone()}
+        };
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &instruction.pack(),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(pda, false),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new(self.token_key(&authority), false),
+                AccountMeta::new(self.program_token_key(), false),
+                AccountMeta::new_readonly(token_mint, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(config_key, false),
+                AccountMeta::new(vote_tally_key, false),
+                AccountMeta::new(input_log_key, false),
+                AccountMeta::new(mode_ballot_key, false),
+                AccountMeta::new(push_stats_key, false),
+                AccountMeta::new_readonly(accounts::get_expected_program_data_key(&self.program_id), false)
+            ]
+        )
+    }
+
+    fn add_charity_instruction(&self) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (registry_key, _) = get_expected_charity_registry_key(&self.program_id);
+
+        let instruction = DPPInstruction::AddCharity {
+            charity_data : AddCharityData {name : "test charity".to_string(), address : self.charity}
+        };
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &instruction.pack(),
+            vec![
+                AccountMeta::new(self.authority.pubkey(), true),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(registry_key, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ]
+        )
+    }
+
+    fn place_bid_instruction(&self, charity : Pubkey, amount_charity : u64, amount_dao : u64) -> Instruction {
+        let bidder = self.bidder.pubkey();
+        let (bidder_data_key, _) = accounts::get_expected_bidder_data_key(&self.program_id, &bidder);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (registry_key, _) = get_expected_charity_registry_key(&self.program_id);
+        let (commit_key, _) = accounts::get_expected_commit_key(&self.program_id);
+
+        let instruction = DPPInstruction::PlaceBid {
+            bid_data : BidData {amount_charity, amount_dao, charity : 0}
+        };
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &instruction.pack(),
+            vec![
+                AccountMeta::new(bidder, true),
+                AccountMeta::new(self.token_key(&bidder), false),
+                AccountMeta::new(bidder_data_key, false),
+                AccountMeta::new(self.authority.pubkey(), false),
+                AccountMeta::new(charity, false),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new(self.program_token_key(), false),
+                AccountMeta::new_readonly(self.config.token_mint, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(registry_key, false),
+                AccountMeta::new_readonly(commit_key, false)
+            ]
+        )
+    }
+
+    fn cancel_bid_instruction(&self) -> Instruction {
+        let bidder = self.bidder.pubkey();
+        let (bidder_data_key, _) = accounts::get_expected_bidder_data_key(&self.program_id, &bidder);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (commit_key, _) = accounts::get_expected_commit_key(&self.program_id);
+        let (pda, _) = accounts::get_expected_program_address_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::CancelBid.pack(),
+            vec![
+                AccountMeta::new(bidder, true),
+                AccountMeta::new(self.token_key(&bidder), false),
+                AccountMeta::new(bidder_data_key, false),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new_readonly(commit_key, false),
+                AccountMeta::new_readonly(pda, false),
+                AccountMeta::new(self.program_token_key(), false),
+                AccountMeta::new_readonly(spl_token::id(), false)
+            ]
+        )
+    }
+
+    fn select_winners_instruction(&self, btc_oracle : Pubkey) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::SelectWinners.pack(),
+            vec![
+                AccountMeta::new(self.authority.pubkey(), true),
+                AccountMeta::new_readonly(btc_oracle, false),
+                AccountMeta::new_readonly(self.config.eth_oracle, false),
+                AccountMeta::new_readonly(self.config.sol_oracle, false),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new_readonly(self.program_token_key(), false),
+                AccountMeta::new_readonly(config_key, false)
+            ]
+        )
+    }
+
+    fn credit_winners_instruction(&self, bidders : &[Pubkey]) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        let mut account_metas = vec![
+            AccountMeta::new(self.authority.pubkey(), true),
+            AccountMeta::new(self.config.data_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_key, false)
+        ];
+        for bidder in bidders {
+            let (bidder_data_key, _) = accounts::get_expected_bidder_data_key(&self.program_id, bidder);
+            account_metas.push(AccountMeta::new_readonly(*bidder, false));
+            account_metas.push(AccountMeta::new(bidder_data_key, false));
+        }
+
+        Instruction::new_with_bytes(self.program_id, &DPPInstruction::CreditWinners.pack(), account_metas)
+    }
+
+    fn claim_prize_instruction(&self, funder : &Pubkey, bidder : &Pubkey) -> Instruction {
+        let (pda, _) = accounts::get_expected_program_address_key(&self.program_id);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (bidder_data_key, _) = accounts::get_expected_bidder_data_key(&self.program_id, bidder);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::ClaimPrize.pack(),
+            vec![
+                AccountMeta::new(*funder, true),
+                AccountMeta::new_readonly(*bidder, false),
+                AccountMeta::new(self.token_key(bidder), false),
+                AccountMeta::new(bidder_data_key, false),
+                AccountMeta::new_readonly(pda, false),
+                AccountMeta::new(self.program_token_key(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(self.config.data_account, false)
+            ]
+        )
+    }
+
+    fn crank_instruction(&self, cranker : &Pubkey, bidders : &[Pubkey]) -> Instruction {
+        let (crank_tip_key, _) = accounts::get_expected_crank_tip_key(&self.program_id);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        let mut account_metas = vec![
+            AccountMeta::new(*cranker, true),
+            AccountMeta::new(self.token_key(cranker), false),
+            AccountMeta::new_readonly(self.config.btc_oracle, false),
+            AccountMeta::new_readonly(self.config.eth_oracle, false),
+            AccountMeta::new_readonly(self.config.sol_oracle, false),
+            AccountMeta::new(self.config.data_account, false),
+            AccountMeta::new(crank_tip_key, false),
+            AccountMeta::new(accounts::get_expected_crank_tip_token_key(&self.program_id, &self.config.token_mint), false),
+            AccountMeta::new(self.program_token_key(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_key, false)
+        ];
+        for bidder in bidders {
+            let (bidder_data_key, _) = accounts::get_expected_bidder_data_key(&self.program_id, bidder);
+            account_metas.push(AccountMeta::new_readonly(*bidder, false));
+            account_metas.push(AccountMeta::new(bidder_data_key, false));
+        }
+
+        Instruction::new_with_bytes(self.program_id, &DPPInstruction::Crank.pack(), account_metas)
+    }
+
+    async fn get_bidder_data(&mut self, bidder : &Pubkey) -> BidderData {
+        let (bidder_data_key, _) = accounts::get_expected_bidder_data_key(&self.program_id, bidder);
+        let account = self.context.banks_client.get_account(bidder_data_key).await.unwrap().unwrap();
+        BidderData::load(&account.data).unwrap()
+    }
+
+    fn push_button_instruction(&self, player : &Pubkey, button : Button, amount : u64) -> Instruction {
+        self.push_instruction(player, DPPInstruction::PushButton {
+            button_data : ButtonData {button, amount}
+        })
+    }
+
+    fn push_sequence_instruction(&self, player : &Pubkey, steps : Vec<SequenceStep>, amount : u64) -> Instruction {
+        self.push_instruction(player, DPPInstruction::PushSequence {
+            sequence_data : SequenceData {steps, amount}
+        })
+    }
+
+    // PushButton and PushSequence take the same accounts
+    fn push_instruction(&self, player : &Pubkey, instruction : DPPInstruction) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (vote_tally_key, _) = accounts::get_expected_vote_tally_key(&self.program_id);
+        let (push_stats_key, _) = accounts::get_expected_push_stats_key(&self.program_id);
+        let (input_log_key, _) = accounts::get_expected_input_log_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &instruction.pack(),
+            vec![
+                AccountMeta::new_readonly(*player, true),
+                AccountMeta::new(self.token_key(player), false),
+                AccountMeta::new(self.program_token_key(), false),
+                AccountMeta::new(self.config.token_mint, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(vote_tally_key, false),
+                AccountMeta::new(self.token_key(&self.config.treasury), false),
+                AccountMeta::new(push_stats_key, false),
+                AccountMeta::new(input_log_key, false)
+            ]
+        )
+    }
+
+    async fn get_push_stats(&mut self) -> PushStats {
+        let (push_stats_key, _) = accounts::get_expected_push_stats_key(&self.program_id);
+        let account = self.context.banks_client.get_account(push_stats_key).await.unwrap().unwrap();
+        PushStats::try_from_slice(&account.data).unwrap()
+    }
+
+    async fn get_token_supply(&mut self) -> u64 {
+        let account = self.context.banks_client.get_account(self.config.token_mint).await.unwrap().unwrap();
+        spl_token::state::Mint::unpack(&account.data).unwrap().supply
+    }
+
+    async fn get_vote_tally(&mut self) -> VoteTally {
+        let (vote_tally_key, _) = accounts::get_expected_vote_tally_key(&self.program_id);
+        let account = self.context.banks_client.get_account(vote_tally_key).await.unwrap().unwrap();
+        *VoteTally::load(&account.data).unwrap()
+    }
+
+    fn resolve_input_instruction(&self, resolver : &Pubkey) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (vote_tally_key, _) = accounts::get_expected_vote_tally_key(&self.program_id);
+        let (input_log_key, _) = accounts::get_expected_input_log_key(&self.program_id);
+        let (mode_ballot_key, _) = accounts::get_expected_mode_ballot_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::ResolveInput.pack(),
+            vec![
+                AccountMeta::new_readonly(*resolver, true),
+                AccountMeta::new_readonly(vote_tally_key, false),
+                AccountMeta::new(input_log_key, false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(mode_ballot_key, false)
+            ]
+        )
+    }
+
+    fn vote_input_mode_instruction(&self, voter : &Pubkey, mode : InputMode, amount : u64) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+        let (mode_ballot_key, _) = accounts::get_expected_mode_ballot_key(&self.program_id);
+
+        let instruction = DPPInstruction::VoteInputMode {
+            mode_vote_data : ModeVoteData {mode, amount}
+        };
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &instruction.pack(),
+            vec![
+                AccountMeta::new_readonly(*voter, true),
+                AccountMeta::new(self.token_key(voter), false),
+                AccountMeta::new(self.program_token_key(), false),
+                AccountMeta::new(self.config.token_mint, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(mode_ballot_key, false),
+                AccountMeta::new(self.token_key(&self.config.treasury), false)
+            ]
+        )
+    }
+
+    async fn get_mode_ballot(&mut self) -> ModeBallot {
+        let (mode_ballot_key, _) = accounts::get_expected_mode_ballot_key(&self.program_id);
+        let account = self.context.banks_client.get_account(mode_ballot_key).await.unwrap().unwrap();
+        *ModeBallot::load(&account.data).unwrap()
+    }
+
+    async fn get_input_log(&mut self) -> InputLog {
+        let (input_log_key, _) = accounts::get_expected_input_log_key(&self.program_id);
+        let account = self.context.banks_client.get_account(input_log_key).await.unwrap().unwrap();
+        *InputLog::load(&account.data).unwrap()
+    }
+
+    fn commit_seed_instruction(&self, committer : &Pubkey, secret : &[u8; 32]) -> Instruction {
+        let (commit_key, _) = accounts::get_expected_commit_key(&self.program_id);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::CommitSeed {commitment : get_commitment(secret)}.pack(),
+            vec![
+                AccountMeta::new(*committer, true),
+                AccountMeta::new(commit_key, false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ]
+        )
+    }
+
+    fn reveal_seed_instruction(&self, committer : &Pubkey, secret : &[u8; 32]) -> Instruction {
+        let (commit_key, _) = accounts::get_expected_commit_key(&self.program_id);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::RevealSeed {secret : *secret}.pack(),
+            vec![
+                AccountMeta::new(*committer, true),
+                AccountMeta::new(commit_key, false),
+                AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new_readonly(self.program_token_key(), false),
+                AccountMeta::new_readonly(config_key, false)
+            ]
+        )
+    }
+
+    fn slash_commit_instruction(&self) -> Instruction {
+        let (commit_key, _) = accounts::get_expected_commit_key(&self.program_id);
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::SlashCommit.pack(),
+            vec![
+                AccountMeta::new(commit_key, false),
+                AccountMeta::new(self.authority.pubkey(), false),
+                AccountMeta::new_readonly(config_key, false)
+            ]
+        )
+    }
+
+    fn resize_state_instruction(&self, authority : &Pubkey, bid_capacity : u16, winner_capacity : u8) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::ResizeState {resize_data : ResizeData {bid_capacity, winner_capacity}}.pack(),
+            vec![
+                AccountMeta::new(*authority, true),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config_key, false)
+            ]
+        )
+    }
+
+    // send ResizeState until the data account has grown to the new capacity, returning the number of instructions it took
+    async fn resize_state(&mut self, bid_capacity : u16, winner_capacity : u8) -> usize {
+        let authority = self.authority.insecure_clone();
+        let capacity = Capacity {bids : bid_capacity as usize, winners : winner_capacity as usize};
+
+        let mut n_instructions = 0;
+        while self.get_header().await.get_capacity() != capacity {
+            self.send(self.resize_state_instruction(&authority.pubkey(), bid_capacity, winner_capacity), &authority).await.unwrap();
+            n_instructions += 1;
+        }
+
+        n_instructions
+    }
+
+    fn migrate_state_instruction(&self, authority : &Pubkey) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::MigrateState.pack(),
+            vec![
+                AccountMeta::new(*authority, true),
+                AccountMeta::new(self.config.data_account, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config_key, false)
+            ]
+        )
+    }
+
+    // send MigrateState until the data account has the current layout, returning the number of instructions it took
+    async fn migrate_state(&mut self) -> usize {
+        let authority = self.authority.insecure_clone();
+
+        let mut n_instructions = 0;
+        while get_state_layout_version(&self.get_data().await) != Some(STATE_LAYOUT_VERSION) {
+            self.send(self.migrate_state_instruction(&authority.pubkey()), &authority).await.unwrap();
+            n_instructions += 1;
+        }
+
+        n_instructions
+    }
+
+    fn update_config_instruction(&self, authority : &Pubkey) -> Instruction {
+        let (config_key, _) = accounts::get_expected_config_key(&self.program_id);
+
+        Instruction::new_with_bytes(
+            self.program_id,
+            &DPPInstruction::UpdateConfig {config : self.config.clone()}.pack(),
+            vec![
+                AccountMeta::new(*authority, true),
+                AccountMeta::new(config_key, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ]
+        )
+    }
+
+    // create the data account and the charity registry, and place a bid that is old enough to be drawn
+    async fn start_round(&mut self) {
+        let authority = self.authority.insecure_clone();
+        let bidder = self.bidder.insecure_clone();
+
+        self.send(self.create_data_account_instruction(self.config.token_mint), &authority).await.unwrap();
+        self.send(self.add_charity_instruction(), &authority).await.unwrap();
+        self.send(self.place_bid_instruction(self.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await.unwrap();
+
+        self.advance_clock(10).await;
+    }
+}
+
+fn assert_game_error(result : Result<(), BanksClientError>, error : DaoPlaysError) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(0, InstructionError::Custom(code)) => assert_eq!(code, error as u32, "expected {:?}", error),
+        other => panic!("expected {:?}, got {:?}", error, other)
+    }
+}
+
+#[tokio::test]
+async fn test_full_round() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    let bidder_token_key = game.token_key(&bidder.pubkey());
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, TOKENS_SENT_TO_PROGRAM);
+    assert_ne!(game.get_header().await.prev_choose_winners_time.get(), 0);
+
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+
+    // the bid is split between the charity and the authority
+    let charity_lamports = game.get_lamports(game.charity).await;
+    let authority_lamports = game.get_lamports(authority.pubkey()).await;
+
+    game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 5), &bidder).await.unwrap();
+
+    assert_eq!(game.get_lamports(game.charity).await, charity_lamports + LAMPORTS_PER_SOL / 10);
+    assert_eq!(game.get_lamports(authority.pubkey()).await, authority_lamports + LAMPORTS_PER_SOL / 5);
+
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.n_bidders.get(), 1);
+    assert_eq!(state.total_bid_amount.get(), 3 * LAMPORTS_PER_SOL / 10);
+    assert_eq!(state.bid_keys[0], bidder_token_key);
+    assert_eq!(game.get_token_balance(bidder_token_key).await, 0);
+
+    // with a single bidder they are the only possible winner
+    game.advance_clock(10).await;
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.n_winners, 1);
+    assert_eq!(state.winners[0], bidder_token_key);
+    assert_eq!(state.n_bidders.get(), 0);
+    assert_eq!(state.total_bid_amount.get(), 0);
+
+    // the prize is credited to the winner's bidder data account, and then claimed
+    game.send(game.credit_winners_instruction(&[bidder.pubkey()]), &authority).await.unwrap();
+
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.n_winners, 0);
+    assert_eq!(state.winners[0], system_program::id());
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.claimable, TOKENS_WON);
+    assert_eq!(game.get_token_balance(bidder_token_key).await, 0);
+
+    game.send(game.claim_prize_instruction(&bidder.pubkey(), &bidder.pubkey()), &bidder).await.unwrap();
+
+    let bidder_data = game.get_bidder_data(&bidder.pubkey()).await;
+    assert_eq!((bidder_data.n_wins, bidder_data.claimable), (1, 0));
+    assert_eq!(game.get_token_balance(bidder_token_key).await, TOKENS_WON);
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, TOKENS_SENT_TO_PROGRAM - TOKENS_WON);
+
+    let result = game.send(game.claim_prize_instruction(&bidder.pubkey(), &bidder.pubkey()), &bidder).await;
+    assert_game_error(result, DaoPlaysError::NothingToClaim);
+
+    // the winner can now push the tokens they won back into the game
+    game.send(game.push_button_instruction(&bidder.pubkey(), Button::A, 1), &bidder).await.unwrap();
+
+    assert_eq!(game.get_token_balance(bidder_token_key).await, TOKENS_WON - 1);
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, TOKENS_SENT_TO_PROGRAM - TOKENS_WON + 1);
+}
+
+#[tokio::test]
+async fn test_push_button_tallies_votes_per_slot() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    // presses in the same slot are added to that slot's votes
+    let blockhash = game.context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            game.push_button_instruction(&authority.pubkey(), Button::A, 5),
+            game.push_button_instruction(&authority.pubkey(), Button::B, 3),
+            game.push_button_instruction(&authority.pubkey(), Button::A, 2)
+        ],
+        Some(&authority.pubkey()),
+        &[&authority],
+        blockhash
+    );
+    game.context.banks_client.process_transaction(transaction).await.unwrap();
+
+    let tally = game.get_vote_tally().await;
+    let first_slot = tally.iter_recent().next().unwrap().slot.get();
+    let votes = tally.get_slot_votes(first_slot).unwrap();
+    assert_eq!(votes.n_presses.get(), 3);
+    assert_eq!((votes.get_tally(&Button::A), votes.get_tally(&Button::B), votes.get_tally(&Button::Up)), (7, 3, 0));
+
+    // and a press in a later slot starts a new record, keeping the earlier one
+    game.warp_slots(5).await;
+    game.send(game.push_button_instruction(&authority.pubkey(), Button::Up, 4), &authority).await.unwrap();
+
+    let tally = game.get_vote_tally().await;
+    let recent : Vec<_> = tally.iter_recent().collect();
+    assert_eq!(recent.len(), 2);
+    assert!(recent[0].slot.get() > first_slot);
+    assert_eq!((recent[0].get_tally(&Button::Up), recent[0].get_total()), (4, Some(4)));
+    assert_eq!(recent[1].get_total(), Some(10));
+}
+
+#[tokio::test]
+async fn test_push_button_splits_tokens_between_burn_treasury_and_prizes() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    // by default every token pushed tops up the prize pool
+    let program_balance = game.get_token_balance(game.program_token_key()).await;
+    game.send(game.push_button_instruction(&authority.pubkey(), Button::A, 4), &authority).await.unwrap();
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, program_balance + 4);
+
+    // a split that adds up to more than every token is rejected
+    game.config.push_burn_bps = 6000;
+    game.config.push_treasury_bps = 5000;
+    let result = game.send(game.update_config_instruction(&authority.pubkey()), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidConfig);
+
+    game.config.push_burn_bps = 2000;
+    game.config.push_treasury_bps = 3000;
+    game.send(game.update_config_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    let authority_token_key = game.token_key(&authority.pubkey());
+    let treasury_token_key = game.token_key(&game.config.treasury);
+    let balance = game.get_token_balance(authority_token_key).await;
+    let supply = game.get_token_supply().await;
+
+    game.send(game.push_button_instruction(&authority.pubkey(), Button::B, 10), &authority).await.unwrap();
+
+    assert_eq!(game.get_token_balance(authority_token_key).await, balance - 10);
+    assert_eq!(game.get_token_supply().await, supply - 2);
+    assert_eq!(game.get_token_balance(treasury_token_key).await, 3);
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, program_balance + 4 + 5);
+
+    let stats = game.get_push_stats().await;
+    assert_eq!(stats, PushStats {n_presses : 2, tokens_pushed : 14, tokens_burned : 2, tokens_to_treasury : 3, tokens_to_prizes : 9});
+
+    // the treasury's share can only go to the treasury
+    let mut instruction = game.push_button_instruction(&authority.pubkey(), Button::B, 10);
+    instruction.accounts[7].pubkey = authority_token_key;
+    let result = game.send(instruction, &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidTokenAccount);
+
+    // tokens spent on a mode vote are split in the same way, but aren't counted as presses
+    game.send(game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Democracy, 10), &authority).await.unwrap();
+
+    assert_eq!(game.get_token_balance(authority_token_key).await, balance - 20);
+    assert_eq!(game.get_token_supply().await, supply - 4);
+    assert_eq!(game.get_token_balance(treasury_token_key).await, 6);
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, program_balance + 4 + 5 + 5);
+    assert_eq!(game.get_push_stats().await.n_presses, 2);
+
+    let mut instruction = game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Democracy, 10);
+    instruction.accounts[7].pubkey = authority_token_key;
+    let result = game.send(instruction, &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidTokenAccount);
+}
+
+#[tokio::test]
+async fn test_push_sequence_tallies_its_first_button_and_logs_every_step() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    let authority_token_key = game.token_key(&authority.pubkey());
+    let balance = game.get_token_balance(authority_token_key).await;
+
+    // walking up two tiles and pressing A is a single vote for Up, paid for with the whole amount
+    let steps = vec![
+        SequenceStep {button : Button::Up, hold_frames : 16},
+        SequenceStep {button : Button::Up, hold_frames : 16},
+        SequenceStep {button : Button::A, hold_frames : 5}
+    ];
+    game.send(game.push_sequence_instruction(&authority.pubkey(), steps, 6), &authority).await.unwrap();
+
+    assert_eq!(game.get_token_balance(authority_token_key).await, balance - 6);
+
+    let tally = game.get_vote_tally().await;
+    let votes = tally.iter_recent().next().unwrap();
+    assert_eq!(votes.n_presses.get(), 1);
+    assert_eq!((votes.get_tally(&Button::Up), votes.get_tally(&Button::A)), (6, 0));
+    assert_eq!((votes.top_bid.get(), votes.top_button), (6, Button::Up as u8));
+
+    // the whole sequence is kept in the press log, so the modes that play whole presses can play it back
+    let log = game.get_input_log().await;
+    let steps : Vec<(u8, u8, u8, u64)> = log.presses[..log.n_logged.get() as usize].iter().map(|press| (press.button, press.hold_frames, press.n_steps, press.amount.get())).collect();
+    assert_eq!(steps, vec![(Button::Up as u8, 16, 3, 6), (Button::Up as u8, 16, 0, 0), (Button::A as u8, 5, 0, 0)]);
+
+    // a sequence has to cost at least a token a step, and can't be longer than MAX_SEQUENCE_STEPS
+    let steps = vec![SequenceStep {button : Button::B, hold_frames : 5}; 3];
+    let result = game.send(game.push_sequence_instruction(&authority.pubkey(), steps, 2), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidButtonBid);
+
+    let steps = vec![SequenceStep {button : Button::B, hold_frames : 5}; MAX_SEQUENCE_STEPS + 1];
+    let result = game.send(game.push_sequence_instruction(&authority.pubkey(), steps, 100), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidSequence);
+
+    assert_eq!(game.get_token_balance(authority_token_key).await, balance - 6);
+    assert_eq!(game.get_input_log().await.n_logged.get(), 3);
+}
+
+#[tokio::test]
+async fn test_resolve_input_picks_the_button_for_each_closed_window() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    let window_slots = game.config.input_window_slots;
+    let first_slot = game.get_input_log().await.next_slot.get();
+
+    // the first window is still open when the votes are made in it
+    let result = game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await;
+    assert_game_error(result, DaoPlaysError::InputWindowOpen);
+
+    game.send(game.push_button_instruction(&authority.pubkey(), Button::A, 5), &authority).await.unwrap();
+    game.send(game.push_button_instruction(&authority.pubkey(), Button::B, 3), &authority).await.unwrap();
+
+    let tally = game.get_vote_tally().await;
+    let vote_slot = tally.iter_recent().next().unwrap().slot.get();
+    assert!(vote_slot < first_slot + window_slots);
+
+    // once it has closed the button is the one anyone can work out from the window's tallies
+    game.warp_slots(3 * window_slots).await;
+    game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    let mut tallies = [0; 8];
+    tallies[Button::A as usize] = 5;
+    tallies[Button::B as usize] = 3;
+    let random = get_input_random(first_slot, first_slot + window_slots, &tallies);
+
+    let log = game.get_input_log().await;
+    let input = log.get_input(vote_slot).unwrap();
+    assert_eq!((input.start_slot.get(), input.end_slot.get()), (first_slot, first_slot + window_slots));
+    assert_eq!((input.button, input.total.get(), input.random.get(), input.complete), (pick_button(&tallies, random).unwrap(), 8, random, 1));
+
+    // the empty windows since then are caught up in one go, up to the last closed window
+    game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    let clock : Clock = game.context.banks_client.get_sysvar().await.unwrap();
+    let last_closed_slot = first_slot + (clock.slot - first_slot) / window_slots * window_slots;
+
+    let log = game.get_input_log().await;
+    let input = log.iter_recent().next().unwrap();
+    assert_eq!((input.start_slot.get(), input.end_slot.get()), (first_slot + window_slots, last_closed_slot));
+    assert_eq!((input.button, input.total.get()), (NO_BUTTON, 0));
+    assert_eq!((log.n_resolved.get(), log.next_slot.get()), (2, last_closed_slot));
+
+    // leaving only the window that is still open
+    let result = game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await;
+    assert_game_error(result, DaoPlaysError::InputWindowOpen);
+}
+
+#[tokio::test]
+async fn test_anarchy_window_inputs_are_its_presses_in_order() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    let window_slots = game.config.input_window_slots;
+
+    // switch to anarchy, which takes effect from the first window after the ballot closes
+    game.send(game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Anarchy, 5), &authority).await.unwrap();
+    game.warp_slots(game.config.mode_vote_slots + window_slots).await;
+    game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+    while game.get_input_log().await.next_slot.get() + window_slots <= game.context.banks_client.get_sysvar::<Clock>().await.unwrap().slot {
+        game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+    }
+
+    let blockhash = game.context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            game.push_button_instruction(&authority.pubkey(), Button::Up, 5),
+            game.push_button_instruction(&authority.pubkey(), Button::A, 1),
+            game.push_button_instruction(&authority.pubkey(), Button::Up, 2)
+        ],
+        Some(&authority.pubkey()),
+        &[&authority],
+        blockhash
     );
-    transaction.sign(&[&payer], blockhash);
+    game.context.banks_client.process_transaction(transaction).await.unwrap();
+
+    game.warp_slots(2 * window_slots).await;
+    game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    // every press in the window is an input, in the order it was made
+    let log = game.get_input_log().await;
+    let input = log.iter_recent().next().unwrap();
+    assert_eq!((input.mode, input.button, input.n_presses.get(), input.complete), (InputMode::Anarchy as u8, NO_BUTTON, 3, 1));
+
+    let presses : Vec<(u8, u64)> = log.get_presses(input).unwrap().map(|press| (press.button, press.amount.get())).collect();
+    assert_eq!(presses, vec![(Button::Up as u8, 5), (Button::A as u8, 1), (Button::Up as u8, 2)]);
+}
+
+#[tokio::test]
+async fn test_mode_vote_switches_to_auction_between_windows() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    let window_slots = game.config.input_window_slots;
+    let token_balance = game.get_token_balance(game.token_key(&authority.pubkey())).await;
+
+    // the votes are spent like button presses
+    game.send(game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Auction, 5), &authority).await.unwrap();
+    game.send(game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Democracy, 3), &authority).await.unwrap();
+    assert_eq!(game.get_token_balance(game.token_key(&authority.pubkey())).await, token_balance - 8);
+
+    let ballot = game.get_mode_ballot().await;
+    assert_eq!((ballot.votes[InputMode::Auction as usize].get(), ballot.votes[InputMode::Democracy as usize].get(), ballot.n_votes.get()), (5, 3, 2));
+    let ballot_end_slot = ballot.ballot_end_slot.get();
+
+    // once it has closed the ballot takes no more votes until it is settled
+    game.warp_slots(game.config.mode_vote_slots + window_slots).await;
+    let result = game.send(game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Lottery, 10), &authority).await;
+    assert_game_error(result, DaoPlaysError::ModeBallotClosed);
+
+    // the windows that started before it closed are still resolved as a lottery
+    game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+    let log = game.get_input_log().await;
+    assert_eq!(log.iter_recent().next().unwrap().mode, InputMode::Lottery as u8);
+    assert_eq!(game.get_mode_ballot().await.get_mode(), Ok(InputMode::Lottery));
+
+    // A has the most tokens but B has the largest single press
+    let blockhash = game.context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            game.push_button_instruction(&authority.pubkey(), Button::A, 5),
+            game.push_button_instruction(&authority.pubkey(), Button::B, 6),
+            game.push_button_instruction(&authority.pubkey(), Button::A, 5)
+        ],
+        Some(&authority.pubkey()),
+        &[&authority],
+        blockhash
+    );
+    game.context.banks_client.process_transaction(transaction).await.unwrap();
+
+    game.warp_slots(2 * window_slots).await;
+    game.send(game.resolve_input_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    let log = game.get_input_log().await;
+    let input = log.iter_recent().next().unwrap();
+    assert!(input.start_slot.get() >= ballot_end_slot);
+    assert_eq!((input.mode, input.button, input.total.get()), (InputMode::Auction as u8, Button::B as u8, 16));
+
+    let ballot = game.get_mode_ballot().await;
+    assert_eq!(ballot.get_mode(), Ok(InputMode::Auction));
+    assert_eq!((ballot.mode_start_slot.get(), ballot.ballot_end_slot.get(), ballot.n_votes.get()), (input.start_slot.get(), 0, 0));
+
+    // and a new ballot can be opened
+    game.send(game.vote_input_mode_instruction(&authority.pubkey(), InputMode::Lottery, 1), &authority).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_create_data_account_rejects_a_config_from_anyone_but_the_upgrade_authority() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    // someone watching the deploy tries to create the config with themselves as its authority
+    let config = game.config.clone();
+    game.config.authority = bidder.pubkey();
+    game.config.data_account = accounts::get_expected_data_account_key(&bidder.pubkey(), &game.program_id);
+    game.authority = bidder.insecure_clone();
+
+    let result = game.send(game.create_data_account_instruction(config.token_mint), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidAuthority);
+
+    let (config_key, _) = accounts::get_expected_config_key(&game.program_id);
+    assert!(game.context.banks_client.get_account(config_key).await.unwrap().is_none());
+
+    // while the upgrade authority can
+    game.config = config;
+    game.authority = authority.insecure_clone();
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    assert!(game.context.banks_client.get_account(config_key).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_create_data_account_rejects_wrong_mint() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+
+    let result = game.send(game.create_data_account_instruction(Pubkey::new_unique()), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidTokenMint);
+}
+
+#[tokio::test]
+async fn test_place_bid_rejects_wrong_charity_account() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+
+    let result = game.send(game.place_bid_instruction(Pubkey::new_unique(), LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidCharityAccount);
+}
+
+#[tokio::test]
+async fn test_place_bid_rejects_wrong_or_uninitialised_charity_registry() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+
+    // the registry is only created by the first AddCharity
+    let result = game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidCharityRegistry);
+
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+
+    let mut instruction = game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10);
+    instruction.accounts[12].pubkey = Pubkey::new_unique();
+    let result = game.send(instruction, &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidCharityRegistry);
+}
+
+#[tokio::test]
+async fn test_place_bid_rejects_bid_below_minimum() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+
+    let result = game.send(game.place_bid_instruction(game.charity, 1, 1), &bidder).await;
+    assert_game_error(result, DaoPlaysError::BidBelowMinimum);
+}
+
+#[tokio::test]
+async fn test_select_winners_rejects_wrong_oracle() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    let result = game.send(game.select_winners_instruction(game.config.eth_oracle), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidOracle);
+
+    assert_eq!(game.get_header().await.n_winners, 0);
+}
+
+#[tokio::test]
+async fn test_select_winners_with_one_oracle_halted() {
+    let mut mock_prices = mock_pyth::get_default_mock_prices();
+    mock_prices[0].status = PriceStatus::Halted;
+
+    let mut game = setup_with_prices(mock_prices).await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+    assert_eq!(game.get_header().await.n_winners, 1);
+}
+
+#[tokio::test]
+async fn test_select_winners_rejects_no_healthy_oracles() {
+    let mut mock_prices = mock_pyth::get_default_mock_prices();
+    mock_prices[0].status = PriceStatus::Halted;
+    mock_prices[1].price = -1;
+    mock_prices[2].pub_slot = 0;
+
+    let mut game = setup_with_prices(mock_prices).await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    // move far enough past the sol price for it to be stale
+    game.context.warp_to_slot(100).unwrap();
+
+    let result = game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await;
+    assert_game_error(result, DaoPlaysError::NoHealthyOracles);
+
+    assert_eq!(game.get_header().await.n_winners, 0);
+}
+
+#[tokio::test]
+async fn test_credit_winners_rejects_wrong_winner() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+
+    let result = game.send(game.credit_winners_instruction(&[authority.pubkey()]), &authority).await;
+    assert_game_error(result, DaoPlaysError::WinnersAccountMismatch);
+
+    // a winner can only be credited once
+    let winner = game.bidder.pubkey();
+    let result = game.send(game.credit_winners_instruction(&[winner, winner]), &authority).await;
+    assert_game_error(result, DaoPlaysError::WinnersAccountMismatch);
+
+    assert_eq!(game.get_header().await.n_winners, 1);
+    assert_eq!(game.get_bidder_data(&winner).await.claimable, 0);
+}
+
+#[tokio::test]
+async fn test_prize_survives_a_failed_claim() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    let bidder_token_key = game.token_key(&bidder.pubkey());
+    game.start_round().await;
+
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+
+    // crediting doesn't need the winner's token account, so closing it doesn't hold up the game
+    game.context.set_account(&bidder_token_key, &AccountSharedData::new(0, 0, &system_program::id()));
+    game.send(game.credit_winners_instruction(&[bidder.pubkey()]), &authority).await.unwrap();
+
+    let result = game.send(game.claim_prize_instruction(&authority.pubkey(), &bidder.pubkey()), &authority).await;
+    assert!(result.is_err());
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.claimable, TOKENS_WON);
+
+    // the next round goes ahead, and PlaceBid opens a new token account for the bidder
+    game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await.unwrap();
+    game.advance_clock(10).await;
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+    game.send(game.credit_winners_instruction(&[bidder.pubkey()]), &authority).await.unwrap();
+
+    let bidder_data = game.get_bidder_data(&bidder.pubkey()).await;
+    assert_eq!((bidder_data.n_wins, bidder_data.claimable), (2, 2 * TOKENS_WON));
+
+    // and anyone can send the bidder everything they are owed
+    game.send(game.claim_prize_instruction(&authority.pubkey(), &bidder.pubkey()), &authority).await.unwrap();
+    assert_eq!(game.get_token_balance(bidder_token_key).await, 2 * TOKENS_WON);
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.claimable, 0);
+}
+
+#[tokio::test]
+async fn test_unclaimed_prizes_are_kept_out_of_later_draws() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    let (pda, _) = accounts::get_expected_program_address_key(&game.program_id);
+    let program_token_key = game.program_token_key();
+    game.start_round().await;
+
+    // the program only holds enough tokens for one and a half prizes
+    game.context.set_account(&program_token_key, &token_account(&game.config.token_mint, &pda, TOKENS_WON * 3 / 2).into());
+
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+    game.send(game.credit_winners_instruction(&[bidder.pubkey()]), &authority).await.unwrap();
+    assert_eq!(game.get_header().await.total_claimable.get(), TOKENS_WON);
+
+    // the authority bids in the next round
+    game.bidder = authority.insecure_clone();
+    game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &authority).await.unwrap();
+    game.advance_clock(10).await;
+
+    // the first prize hasn't been claimed yet, so what is left isn't enough for a second
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+    assert_eq!(game.get_header().await.n_winners, 0);
+
+    // once the pool has been topped up there is
+    game.context.set_account(&program_token_key, &token_account(&game.config.token_mint, &pda, TOKENS_WON * 2).into());
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+    game.send(game.credit_winners_instruction(&[authority.pubkey()]), &authority).await.unwrap();
+    assert_eq!(game.get_header().await.total_claimable.get(), 2 * TOKENS_WON);
+
+    // and both winners can claim their prizes
+    let authority_tokens = game.get_token_balance(game.token_key(&authority.pubkey())).await;
+    game.send(game.claim_prize_instruction(&bidder.pubkey(), &bidder.pubkey()), &bidder).await.unwrap();
+    game.send(game.claim_prize_instruction(&authority.pubkey(), &authority.pubkey()), &authority).await.unwrap();
+
+    assert_eq!(game.get_token_balance(game.token_key(&bidder.pubkey())).await, TOKENS_WON);
+    assert_eq!(game.get_token_balance(game.token_key(&authority.pubkey())).await, authority_tokens + TOKENS_WON);
+    assert_eq!(game.get_token_balance(program_token_key).await, 0);
+    assert_eq!(game.get_header().await.total_claimable.get(), 0);
+}
+
+#[tokio::test]
+async fn test_crank_credits_and_selects_for_a_tip() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    // anyone can crank, and is tipped into their own token account
+    let cranker = Keypair::new();
+    let cranker_token_key = game.token_key(&cranker.pubkey());
+    game.context.set_account(&cranker.pubkey(), &Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()).into());
+    game.context.set_account(&cranker_token_key, &token_account(&game.config.token_mint, &cranker.pubkey(), 0).into());
+
+    // the tip is paid from the crank tip address and its token account rather than the prize pool
+    let (crank_tip_key, _) = accounts::get_expected_crank_tip_key(&game.program_id);
+    let crank_tip_token_key = accounts::get_expected_crank_tip_token_key(&game.program_id, &game.config.token_mint);
+    game.send(system_instruction::transfer(&authority.pubkey(), &crank_tip_key, LAMPORTS_PER_SOL), &authority).await.unwrap();
+    game.context.set_account(&crank_tip_token_key, &token_account(&game.config.token_mint, &crank_tip_key, 100).into());
+    let program_tokens = game.get_token_balance(game.program_token_key()).await;
+
+    // a crank passed the bidders selects the winners and credits them in the same instruction
+    game.send(game.crank_instruction(&cranker.pubkey(), &[bidder.pubkey()]), &cranker).await.unwrap();
+    assert_eq!(game.get_header().await.n_winners, 0);
+    assert_eq!(game.get_header().await.total_claimable.get(), TOKENS_WON);
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.claimable, TOKENS_WON);
+    assert_eq!(game.get_lamports(crank_tip_key).await, LAMPORTS_PER_SOL - game.config.crank_tip_lamports);
+    assert_eq!(game.get_token_balance(crank_tip_token_key).await, 100 - game.config.crank_tip_tokens);
+    assert_eq!(game.get_token_balance(cranker_token_key).await, game.config.crank_tip_tokens);
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, program_tokens);
+
+    // nothing is paid for a crank that doesn't move the game on
+    let result = game.send(game.crank_instruction(&cranker.pubkey(), &[bidder.pubkey()]), &cranker).await;
+    assert_game_error(result, DaoPlaysError::NothingToCrank);
+}
+
+#[tokio::test]
+async fn test_crank_leaves_winners_it_is_not_passed() {
+    let mut game = setup().await;
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    let cranker = Keypair::new();
+    game.context.set_account(&cranker.pubkey(), &Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()).into());
+    game.context.set_account(&game.token_key(&cranker.pubkey()), &token_account(&game.config.token_mint, &cranker.pubkey(), 0).into());
+
+    // without the winner's accounts the crank only selects, and an unfunded tip account just means no tip
+    game.send(game.crank_instruction(&cranker.pubkey(), &[]), &cranker).await.unwrap();
+    assert_eq!(game.get_header().await.n_winners, 1);
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.claimable, 0);
+    assert_eq!(game.get_token_balance(game.token_key(&cranker.pubkey())).await, 0);
+
+    // the next crank credits the winner, leaving the ladder empty for the next draw
+    game.send(game.crank_instruction(&cranker.pubkey(), &[bidder.pubkey()]), &cranker).await.unwrap();
+    assert_eq!(game.get_header().await.n_winners, 0);
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.claimable, TOKENS_WON);
+}
+
+#[tokio::test]
+async fn test_update_config_upgrades_a_legacy_config() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    // a config written before it had a layout header, and before the push split was added to it
+    let (config_key, _) = accounts::get_expected_config_key(&game.program_id);
+    let mut legacy = game.config.try_to_vec().unwrap();
+    legacy.truncate(legacy.len() - 4);
+    let lamports = game.context.banks_client.get_rent().await.unwrap().minimum_balance(legacy.len());
+    game.context.set_account(&config_key, &Account {lamports, data : legacy, owner : game.program_id, executable : false, rent_epoch : 0}.into());
+
+    let result = game.send(game.push_button_instruction(&authority.pubkey(), Button::A, 1), &authority).await;
+    assert_game_error(result, DaoPlaysError::UnsupportedConfigLayout);
+
+    // only the authority the legacy config names can replace it
+    let result = game.send(game.update_config_instruction(&bidder.pubkey()), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidAuthority);
+
+    game.config.push_burn_bps = 1000;
+    game.send(game.update_config_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    let account = game.context.banks_client.get_account(config_key).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), get_config_size());
+    assert_eq!(Config::load(&account.data).unwrap(), game.config);
+
+    game.send(game.push_button_instruction(&authority.pubkey(), Button::A, 10), &authority).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_update_config_rejects_non_authority() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+
+    let result = game.send(game.update_config_instruction(&bidder.pubkey()), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidAuthority);
+}
+
+#[tokio::test]
+async fn test_commit_reveal_round() {
+    let mut game = setup().await;
+    game.config.randomness_source = RandomnessSource::CommitReveal;
+
+    let authority = game.authority.insecure_clone();
+    let committer = Keypair::new();
+    game.send(
+        solana_sdk::system_instruction::transfer(&authority.pubkey(), &committer.pubkey(), LAMPORTS_PER_SOL),
+        &authority
+    ).await.unwrap();
+
+    game.start_round().await;
+
+    // winners can't be selected from the oracles in commit-reveal mode
+    let result = game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await;
+    assert_game_error(result, DaoPlaysError::RandomnessSourceMismatch);
+
+    let secret = [42; 32];
+    let committer_lamports = game.get_lamports(committer.pubkey()).await;
+    game.send(game.commit_seed_instruction(&committer.pubkey(), &secret), &committer).await.unwrap();
+
+    let commit = game.get_commit().await;
+    assert_eq!(commit.committer, committer.pubkey());
+    assert_eq!(commit.bond, game.config.commit_bond);
+    assert!(game.get_lamports(committer.pubkey()).await < committer_lamports - game.config.commit_bond);
+
+    // only one commitment at a time
+    let result = game.send(game.commit_seed_instruction(&authority.pubkey(), &[1; 32]), &authority).await;
+    assert_game_error(result, DaoPlaysError::CommitPending);
+
+    game.warp_slots(2).await;
+
+    let result = game.send(game.reveal_seed_instruction(&committer.pubkey(), &[43; 32]), &committer).await;
+    assert_game_error(result, DaoPlaysError::InvalidReveal);
+
+    let result = game.send(game.reveal_seed_instruction(&authority.pubkey(), &secret), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidCommitter);
+
+    let committer_lamports = game.get_lamports(committer.pubkey()).await;
+    game.send(game.reveal_seed_instruction(&committer.pubkey(), &secret), &committer).await.unwrap();
+
+    // the bond comes back, less the transaction fee, and the bid made before the commitment wins
+    assert!(game.get_lamports(committer.pubkey()).await > committer_lamports);
+    assert!(!game.get_commit().await.is_active());
+
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.n_winners, 1);
+    assert_eq!(state.winners[0], game.token_key(&game.bidder.pubkey()));
+}
+
+#[tokio::test]
+async fn test_bids_in_the_draw_are_frozen_while_a_commit_is_pending() {
+    let mut game = setup().await;
+    game.config.randomness_source = RandomnessSource::CommitReveal;
+
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    game.send(game.commit_seed_instruction(&authority.pubkey(), &[3; 32]), &authority).await.unwrap();
+
+    // the committer may already know the seed, so the bidder can't leave the draw or top up and be moved out of it
+    let result = game.send(game.cancel_bid_instruction(), &bidder).await;
+    assert_game_error(result, DaoPlaysError::CommitPending);
+
+    let result = game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await;
+    assert_game_error(result, DaoPlaysError::CommitPending);
+
+    // once the commitment has timed out it can't be revealed, so the bid is free again
+    game.warp_slots(game.config.reveal_timeout_slots + 2).await;
+    game.send(game.cancel_bid_instruction(), &bidder).await.unwrap();
+    assert_eq!(game.get_header().await.n_bidders.get(), 0);
+}
+
+#[tokio::test]
+async fn test_cancel_bid_refunds_tokens_from_the_prize_pool() {
+    let mut game = setup().await;
+    // 0.2 SOL was bid, so 100 tokens are refunded
+    game.config.cancel_refund_tokens_per_sol = 500;
+
+    let bidder = game.bidder.insecure_clone();
+    let bidder_token_key = game.token_key(&bidder.pubkey());
+    game.start_round().await;
+
+    game.send(game.cancel_bid_instruction(), &bidder).await.unwrap();
+
+    assert_eq!(game.get_header().await.n_bidders.get(), 0);
+    assert_eq!(game.get_bidder_data(&bidder.pubkey()).await.index, 0);
+    assert_eq!(game.get_token_balance(bidder_token_key).await, 100);
+    assert_eq!(game.get_token_balance(game.program_token_key()).await, TOKENS_SENT_TO_PROGRAM - 100);
+
+    // there is nothing left to cancel
+    let result = game.send(game.cancel_bid_instruction(), &bidder).await;
+    assert_game_error(result, DaoPlaysError::NoActiveBid);
+}
+
+#[tokio::test]
+async fn test_cancel_bid_rejects_a_refund_larger_than_the_prize_pool() {
+    let mut game = setup().await;
+    game.config.cancel_refund_tokens_per_sol = TOKENS_SENT_TO_PROGRAM * 10;
+
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    // the bid stays in the ladder until the pool can pay its refund
+    let result = game.send(game.cancel_bid_instruction(), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InsufficientTokens);
+    assert_eq!(game.get_header().await.n_bidders.get(), 1);
+}
+
+#[tokio::test]
+async fn test_slash_unrevealed_commit() {
+    let mut game = setup().await;
+    game.config.randomness_source = RandomnessSource::CommitReveal;
+
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    let secret = [7; 32];
+    game.send(game.commit_seed_instruction(&bidder.pubkey(), &secret), &bidder).await.unwrap();
+
+    let result = game.send(game.slash_commit_instruction(), &authority).await;
+    assert_game_error(result, DaoPlaysError::CommitNotExpired);
+
+    game.warp_slots(game.config.reveal_timeout_slots + 2).await;
+
+    let result = game.send(game.reveal_seed_instruction(&bidder.pubkey(), &secret), &bidder).await;
+    assert_game_error(result, DaoPlaysError::RevealTimedOut);
+
+    // anyone can slash the commitment, and the bond goes to the authority
+    let slasher = Keypair::new();
+    game.send(
+        solana_sdk::system_instruction::transfer(&authority.pubkey(), &slasher.pubkey(), LAMPORTS_PER_SOL),
+        &authority
+    ).await.unwrap();
+
+    let authority_lamports = game.get_lamports(authority.pubkey()).await;
+    game.send(game.slash_commit_instruction(), &slasher).await.unwrap();
+
+    assert_eq!(game.get_lamports(authority.pubkey()).await, authority_lamports + game.config.commit_bond);
+    assert!(!game.get_commit().await.is_active());
+    assert_eq!(game.get_header().await.n_winners, 0);
+
+    // and a new commitment can be made
+    game.send(game.commit_seed_instruction(&authority.pubkey(), &secret), &authority).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_commit_seed_rejects_oracle_mode() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    let result = game.send(game.commit_seed_instruction(&authority.pubkey(), &[1; 32]), &authority).await;
+    assert_game_error(result, DaoPlaysError::RandomnessSourceMismatch);
+}
+
+#[tokio::test]
+async fn test_select_winners_matches_off_chain_draw() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    // four winners need at least 192 bidders, which is more than a new data account holds
+    game.resize_state(256, 4).await;
+
+    // fill the ladder so that several winners are drawn from more than one block, with a few bids too recent to be in the draw
+    let clock : Clock = game.context.banks_client.get_sysvar().await.unwrap();
+    game.modify_ladder(|state| {
+        for index in 1..200 {
+            let time = if index % 50 == 0 { clock.unix_timestamp } else { clock.unix_timestamp - 100 };
+            state.insert_bid(index, Pubkey::new_unique(), (index as u64 % 7 + 1) * LAMPORTS_PER_SOL / 10, time).unwrap();
+        }
+    }).await;
+
+    let mut pre_data = game.get_data().await;
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+
+    // recompute the draw from the oracle prices and the ladder before the transaction
+    let prices = mock_pyth::get_default_mock_prices().map(|mock_price| Some(OraclePrice {
+        price : mock_price.price as u64,
+        conf : mock_price.conf,
+        expo : mock_price.expo
+    }));
+    let seed = get_oracle_seed(&prices).unwrap();
+
+    let (mut expected_state, mut expected_bid_index) = load_ladder_mut(&mut pre_data).unwrap();
+    let expected = draw::draw_winners(&mut expected_state, &mut expected_bid_index, 4, clock.unix_timestamp - 2, seed).unwrap();
+    assert_eq!(expected.len(), 4);
+
+    // the recent bids can't win
+    assert!(expected.iter().all(|winner| winner.index % 50 != 0));
+
+    let mut data = game.get_data().await;
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.n_winners, 4);
+    assert_eq!(state.winners, expected_state.winners);
+    assert_eq!(state.bid_amounts, expected_state.bid_amounts);
+    assert_eq!(state.n_bidders, expected_state.n_bidders);
+    assert_eq!(state.total_bid_amount, expected_state.total_bid_amount);
+    assert_eq!(bid_index.amount_tree, expected_bid_index.amount_tree);
+    assert_eq!(bid_index.free_slots, expected_bid_index.free_slots);
+    assert_eq!((bid_index.oldest, bid_index.newest), (expected_bid_index.oldest, expected_bid_index.newest));
+}
+
+#[tokio::test]
+async fn test_place_bid_evicts_oldest_bid_from_full_ladder() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+
+    // fill the ladder, with the oldest bid part way along it
+    let clock : Clock = game.context.banks_client.get_sysvar().await.unwrap();
+    game.modify_ladder(|state| {
+        for index in 0..INITIAL_BID_CAPACITY {
+            let time = if index == 70 { clock.unix_timestamp - 5000 } else { clock.unix_timestamp - 1000 + index as i64 };
+            state.insert_bid(index, Pubkey::new_unique(), LAMPORTS_PER_SOL, time).unwrap();
+        }
+    }).await;
+
+    // the evicted bidder's token account doesn't exist, so the eviction isn't recorded against them
+    let mut data = game.get_data().await;
+    let evicted_key = State::load_mut(&mut data).unwrap().bid_keys[70];
+    let mut instruction = game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10);
+    instruction.accounts.push(AccountMeta::new(evicted_key, false));
+    instruction.accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+    game.send(instruction, &bidder).await.unwrap();
+
+    let mut data = game.get_data().await;
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.bid_keys[70], game.token_key(&bidder.pubkey()));
+    assert_eq!(state.n_bidders.get() as usize, INITIAL_BID_CAPACITY);
+    assert_eq!(state.total_bid_amount.get(), (INITIAL_BID_CAPACITY as u64 - 1) * LAMPORTS_PER_SOL + LAMPORTS_PER_SOL / 5);
+
+    // the new bid is now the newest, and the next oldest bid is first in line to be replaced
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+    assert_eq!(bid_index.newest.get(), 70);
+    assert_eq!(bid_index.get_oldest(), Some(0));
+}
+
+#[tokio::test]
+async fn test_place_bid_rejects_a_bid_past_the_u64_boundary() {
+    let mut game = setup().await;
+    let bidder = game.bidder.insecure_clone();
+    let bidder_token_key = game.token_key(&bidder.pubkey());
+    game.start_round().await;
+
+    // push the bidder's existing bid, and so the ladder's total, up to just below u64::MAX
+    game.modify_ladder(|state| {
+        let index = state.bid_keys.iter().position(|key| *key == bidder_token_key).unwrap();
+        state.bid_amounts[index].set(u64::MAX - 1);
+        state.total_bid_amount.set(u64::MAX - 1);
+    }).await;
+
+    let result = game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await;
+    assert_game_error(result, DaoPlaysError::ArithmeticOverflow);
+
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!((state.n_bidders.get(), state.total_bid_amount.get()), (1, u64::MAX - 1));
+}
+
+#[tokio::test]
+async fn test_place_bid_rejects_a_new_bidder_past_the_u16_boundary() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+
+    // the ladder has free slots, but the count of bidders is already at u16::MAX
+    game.modify_ladder(|state| state.n_bidders.set(u16::MAX)).await;
+
+    let result = game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await;
+    assert_game_error(result, DaoPlaysError::ArithmeticOverflow);
+
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!((state.n_bidders.get(), state.total_bid_amount.get()), (u16::MAX, 0));
+}
+
+#[tokio::test]
+async fn test_select_winners_at_the_u16_and_u64_boundaries() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    // a draw with n_bidders at u16::MAX and the whole of u64::MAX bid goes ahead
+    game.modify_ladder(|state| {
+        let index = state.bid_amounts.iter().position(|amount| amount.get() > 0).unwrap();
+        state.bid_amounts[index].set(u64::MAX);
+        state.total_bid_amount.set(u64::MAX);
+        state.n_bidders.set(u16::MAX);
+    }).await;
+
+    game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await.unwrap();
+
+    let bidder_token_key = game.token_key(&game.bidder.pubkey());
+    let mut data = game.get_data().await;
+    let state = State::load_mut(&mut data).unwrap();
+    assert_eq!(state.winners[0], bidder_token_key);
+    assert_eq!((state.n_bidders.get(), state.total_bid_amount.get()), (u16::MAX - 1, 0));
+}
+
+#[tokio::test]
+async fn test_select_winners_rejects_a_total_bid_below_zero() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    game.start_round().await;
+
+    // removing the winning bid would take the ladder's total below zero
+    game.modify_ladder(|state| {
+        state.total_bid_amount.set(0);
+    }).await;
+
+    let result = game.send(game.select_winners_instruction(game.config.btc_oracle), &authority).await;
+    assert_game_error(result, DaoPlaysError::ArithmeticOverflow);
+    assert_eq!(game.get_header().await.n_winners, 0);
+}
+
+#[tokio::test]
+async fn test_resize_state_grows_the_ladder_in_steps() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+    game.start_round().await;
+
+    // CreateDataAccount makes an account with the initial capacity
+    let initial_capacity = Capacity {bids : INITIAL_BID_CAPACITY, winners : 4};
+    assert_eq!(game.get_header().await.get_capacity(), initial_capacity);
+    assert_eq!(game.get_data().await.len(), get_data_account_size(&initial_capacity));
+
+    // only the authority can resize it, and it can't shrink
+    let result = game.send(game.resize_state_instruction(&bidder.pubkey(), 1024, 8), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidAuthority);
+
+    let result = game.send(game.resize_state_instruction(&authority.pubkey(), 64, 8), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidCapacity);
+
+    let result = game.send(game.resize_state_instruction(&authority.pubkey(), 1024, 2), &authority).await;
+    assert_game_error(result, DaoPlaysError::InvalidCapacity);
+
+    // an account can only grow by 10KB in each instruction, so this takes several
+    let capacity = Capacity {bids : 1024, winners : 8};
+    let n_instructions = game.resize_state(1024, 8).await;
+    assert_eq!(n_instructions, (get_data_account_size(&capacity) - get_data_account_size(&initial_capacity)).div_ceil(10240));
+
+    let data_account = game.context.banks_client.get_account(game.config.data_account).await.unwrap().unwrap();
+    assert_eq!(data_account.data.len(), get_data_account_size(&capacity));
+    assert!(data_account.lamports >= Rent::default().minimum_balance(data_account.data.len()));
+
+    // the bid from before the resize is where it was, and the rest of the ladder is free
+    let bidder_token_key = game.token_key(&bidder.pubkey());
+    let mut data = game.get_data().await;
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.get_capacity(), capacity);
+    assert_eq!(state.bid_keys[0], bidder_token_key);
+    assert_eq!(state.n_bidders.get(), 1);
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+    assert_eq!(bid_index.n_free.get(), 1023);
+
+    // resizing to the same capacity does nothing
+    game.send(game.resize_state_instruction(&authority.pubkey(), 1024, 8), &authority).await.unwrap();
+
+    // and bids can be placed in the new slots
+    game.modify_ladder(|state| {
+        for index in INITIAL_BID_CAPACITY..1000 {
+            state.insert_bid(index, Pubkey::new_unique(), LAMPORTS_PER_SOL, 1).unwrap();
+        }
+    }).await;
+    game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await.unwrap();
+
+    let mut data = game.get_data().await;
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.bid_amounts[0].get(), 2 * LAMPORTS_PER_SOL / 5);
+    assert_eq!(state.n_bidders.get() as usize, 1000 - INITIAL_BID_CAPACITY + 1);
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+}
+
+#[tokio::test]
+async fn test_migrate_state_upgrades_a_legacy_account() {
+    let mut game = setup().await;
+    let authority = game.authority.insecure_clone();
+    let bidder = game.bidder.insecure_clone();
+
+    // a data account in the legacy layout, captured from the v0 program after a couple of rounds, with one winner
+    // still waiting for their tokens. see dp_common/tests/game_bid_index.rs for how it was made
+    let legacy_data = include_bytes!("../../../dp_common/tests/fixtures/legacy_state_v0.bin").to_vec();
+    let legacy = *LegacyState::load(&legacy_data).unwrap();
+    let mut legacy_account = AccountSharedData::new(Rent::default().minimum_balance(legacy_data.len()), legacy_data.len(), &game.program_id);
+    legacy_account.set_data_from_slice(&legacy_data);
+    game.context.set_account(&game.config.data_account, &legacy_account);
+
+    // CreateDataAccount leaves the legacy account alone, and it can't be used until it has been migrated
+    game.send(game.create_data_account_instruction(game.config.token_mint), &authority).await.unwrap();
+    game.send(game.add_charity_instruction(), &authority).await.unwrap();
+    assert_eq!(game.get_data().await, legacy_data);
+
+    let result = game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await;
+    assert_game_error(result, DaoPlaysError::UnsupportedStateLayout);
+
+    let result = game.send(game.migrate_state_instruction(&bidder.pubkey()), &bidder).await;
+    assert_game_error(result, DaoPlaysError::InvalidAuthority);
+
+    // the account can only grow by 10KB in each instruction, so this takes several
+    let n_instructions = game.migrate_state().await;
+    let data_size = get_data_account_size(&LEGACY_CAPACITY);
+    assert_eq!(n_instructions, (data_size - legacy_data.len()).div_ceil(10240));
+    assert!(n_instructions > 1);
+
+    let data_account = game.context.banks_client.get_account(game.config.data_account).await.unwrap().unwrap();
+    assert_eq!(data_account.data.len(), data_size);
+    assert!(data_account.lamports >= Rent::default().minimum_balance(data_size));
+
+    // migrating again does nothing
+    game.send(game.migrate_state_instruction(&authority.pubkey()), &authority).await.unwrap();
+
+    let mut data = game.get_data().await;
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.get_capacity(), LEGACY_CAPACITY);
+    assert_eq!(state.prev_choose_winners_time, legacy.prev_choose_winners_time);
+    assert_eq!((state.n_bidders.get(), state.total_bid_amount.get()), (10, 2_080_000_000));
+    assert_eq!(state.bid_keys, &legacy.bid_keys[..]);
+    assert_eq!(state.bid_amounts, &legacy.bid_amounts[..]);
+    assert_eq!(state.bid_times, &legacy.bid_times[..]);
+    assert_eq!((state.n_winners, state.winners[0]), (1, legacy.winners[0]));
+    assert_eq!(state.charity_data, legacy.charity_data);
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
+    assert_eq!(bid_index.n_free.get(), 1014);
+    assert_eq!(bid_index.get_oldest(), Some(0));
+
+    // and the game carries on from where it was
+    game.send(game.place_bid_instruction(game.charity, LAMPORTS_PER_SOL / 10, LAMPORTS_PER_SOL / 10), &bidder).await.unwrap();
 
-    assert_matches!(rpc_client.send_and_confirm_transaction(&transaction), Ok(_));
+    let mut data = game.get_data().await;
+    let (state, bid_index) = load_ladder_mut(&mut data).unwrap();
+    assert_eq!(state.n_bidders.get(), 11);
+    assert_eq!(state.bid_keys[8], game.token_key(&bidder.pubkey()));
+    assert_eq!(bid_index.get_total(), state.total_bid_amount.get());
 }
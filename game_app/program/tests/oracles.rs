@@ -0,0 +1,116 @@
+use dp_common::game::error::DaoPlaysError;
+use dp_common::mock_pyth::{self, MockPrice};
+use dp_v01::randoms;
+use pyth_sdk_solana::{load_price_feed_from_account, state::PriceStatus};
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solana_sdk::account::Account;
+
+const CURRENT_SLOT : u64 = 1000;
+const MAX_SLOT_AGE : u64 = 25;
+
+fn mock_account(mock_price : &MockPrice) -> Account {
+    Account {
+        lamports : 1,
+        data : mock_price.pack(),
+        owner : mock_pyth::get_pyth_program_key(),
+        executable : false,
+        rent_epoch : 0
+    }
+}
+
+fn oracle_price(mock_price : &MockPrice) -> Result<randoms::OraclePrice, DaoPlaysError> {
+    let key = Pubkey::new_unique();
+    let mut account = mock_account(mock_price);
+    let account_info = AccountInfo::new(&key, false, false, &mut account.lamports, &mut account.data, &account.owner, false, 0);
+
+    randoms::get_oracle_price(&account_info, CURRENT_SLOT, MAX_SLOT_AGE)
+}
+
+// generate the seed from the three mock prices, as SelectWinners would from the oracles in the config
+fn seed_from_prices(prices : &[MockPrice; 3]) -> Result<u64, ProgramError> {
+    let keys = [mock_pyth::get_mock_btc_key(), mock_pyth::get_mock_eth_key(), mock_pyth::get_mock_sol_key()];
+    let mut accounts : Vec<Account> = prices.iter().map(mock_account).collect();
+
+    let account_infos : Vec<AccountInfo> = keys.iter().zip(accounts.iter_mut()).map(|(key, account)| {
+        AccountInfo::new(key, false, false, &mut account.lamports, &mut account.data, &account.owner, false, 0)
+    }).collect();
+
+    randoms::generate_seed(&account_infos[0], &account_infos[1], &account_infos[2], CURRENT_SLOT, MAX_SLOT_AGE)
+}
+
+#[test]
+fn test_mock_price_loads_as_pyth_price() {
+    let mock_price = MockPrice::trading(2_000_000_000_000, 1_000_000_000, -8);
+    let mut account = mock_account(&mock_price);
+
+    let price_feed = load_price_feed_from_account(&Pubkey::new_unique(), &mut account).unwrap();
+    let price = price_feed.get_current_price().unwrap();
+
+    assert_eq!(price.price, mock_price.price);
+    assert_eq!(price.conf, mock_price.conf);
+    assert_eq!(price.expo, mock_price.expo);
+}
+
+#[test]
+fn test_oracle_price() {
+    let mock_price = MockPrice::trading(2_000_000_000_000, 1_000_000_000, -8);
+    assert_eq!(oracle_price(&mock_price), Ok(randoms::OraclePrice {price : 2_000_000_000_000, conf : 1_000_000_000, expo : -8}));
+
+    // the price is still usable when published exactly max_slot_age slots ago
+    let oldest_price = MockPrice {pub_slot : CURRENT_SLOT - MAX_SLOT_AGE, ..mock_price};
+    assert!(oracle_price(&oldest_price).is_ok());
+}
+
+#[test]
+fn test_oracle_price_failures() {
+    let mock_price = MockPrice::trading(100, 1, -2);
+
+    let halted = MockPrice {status : PriceStatus::Halted, ..mock_price};
+    assert_eq!(oracle_price(&halted), Err(DaoPlaysError::OraclePriceUnavailable));
+
+    let stale = MockPrice {pub_slot : CURRENT_SLOT - MAX_SLOT_AGE - 1, ..mock_price};
+    assert_eq!(oracle_price(&stale), Err(DaoPlaysError::OraclePriceStale));
+
+    let negative = MockPrice {price : -1, ..mock_price};
+    assert_eq!(oracle_price(&negative), Err(DaoPlaysError::OraclePriceNegative));
+
+    // an account that isn't a pyth price account
+    let key = Pubkey::new_unique();
+    let (mut lamports, mut data, owner) = (1, vec![0; 64], Pubkey::new_unique());
+    let account_info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+    assert_eq!(randoms::get_oracle_price(&account_info, CURRENT_SLOT, MAX_SLOT_AGE), Err(DaoPlaysError::OracleDataInvalid));
+}
+
+#[test]
+fn test_seed_is_deterministic() {
+    let prices = mock_pyth::get_default_mock_prices();
+    assert_eq!(seed_from_prices(&prices), seed_from_prices(&prices));
+
+    let mut moved_prices = prices;
+    moved_prices[2].price += 1;
+    assert_ne!(seed_from_prices(&prices), seed_from_prices(&moved_prices));
+}
+
+#[test]
+fn test_seed_falls_back_to_healthy_oracles() {
+    let prices = mock_pyth::get_default_mock_prices();
+
+    // a conf larger than the price no longer underflows
+    let mut wide_conf = prices;
+    wide_conf[0].conf = wide_conf[0].price as u64 + 1;
+    assert!(seed_from_prices(&wide_conf).is_ok());
+
+    let mut one_halted = prices;
+    one_halted[1].status = PriceStatus::Halted;
+    assert!(seed_from_prices(&one_halted).is_ok());
+    assert_ne!(seed_from_prices(&one_halted), seed_from_prices(&prices));
+
+    let mut one_healthy = prices;
+    one_healthy[0].price = -1;
+    one_healthy[1].pub_slot = 0;
+    assert!(seed_from_prices(&one_healthy).is_ok());
+
+    let mut none_healthy = one_healthy;
+    none_healthy[2].status = PriceStatus::Unknown;
+    assert_eq!(seed_from_prices(&none_healthy), Err(DaoPlaysError::NoHealthyOracles.into()));
+}
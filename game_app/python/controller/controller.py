@@ -5,7 +5,9 @@ from solana.publickey import PublicKey
 from solana.transaction import Transaction, TransactionInstruction, AccountMeta
 from solana.rpc.types import TxOpts
 from solana.rpc.api import Client
-from borsh_construct import Enum, CStruct, U64, U8
+from borsh_construct import Enum, CStruct, U64, U16, U8, I64, Bool
+from construct import Bytes
+import base64
 import spl.token.instructions as spl_token_instructions
 
 import solana as sol
@@ -33,15 +35,36 @@ button_type = Enum(
     enum_name = "Button"
 )
 
-charity_type = Enum(
-    "EvidenceAction",
-    "GirlsWhoCode",
-    "OneTreePlanted",
-    "OutrightActionInt",
-    "TheLifeYouCanSave",
-    "UkraineERF",
-    "WaterOrg",
-    enum_name = "Charity"
+# charities are referred to by their index in the program's charity registry
+charity_type = U8
+
+# the program's config account, a layout header followed by the config
+config_account_type = CStruct(
+    "discriminator" / Bytes(8),
+    "layout_version" / U8,
+    "authority" / Bytes(32),
+    "data_account" / Bytes(32),
+    "token_mint" / Bytes(32),
+    "btc_oracle" / Bytes(32),
+    "eth_oracle" / Bytes(32),
+    "sol_oracle" / Bytes(32),
+    "tokens_won" / U64,
+    "max_winners" / U8,
+    "min_bid" / U64,
+    "selection_time" / I64,
+    "protect_larger_bids" / Bool,
+    "max_oracle_slot_age" / U64,
+    "randomness_source" / U8,
+    "reveal_timeout_slots" / U64,
+    "commit_bond" / U64,
+    "crank_tip_lamports" / U64,
+    "crank_tip_tokens" / U64,
+    "input_window_slots" / U64,
+    "mode_vote_slots" / U64,
+    "treasury" / Bytes(32),
+    "push_burn_bps" / U16,
+    "push_treasury_bps" / U16,
+    "cancel_refund_tokens_per_sol" / U64
 )
 
 DPPInstructions = Enum(
@@ -98,12 +121,26 @@ class Controller(QWidget):
 
         print(response)
 
+    # the treasury wallet is set in the config, and can be changed by its authority
+    def get_treasury_key(self, config_account):
+
+        response = dev_client.get_account_info(config_account)
+        data = base64.b64decode(response['result']['value']['data'][0])
+        config = config_account_type.parse(data)
+
+        return PublicKey(config.treasury)
+
     def get_press_button_idx(self, button, amount):
 
         wallet = self.load_key(self.wallet)
         program_data_account, data_bump = PublicKey.find_program_address([bytes("token_account", encoding="utf-8")], PROGRAM_KEY)
         program_token_account = spl_token_instructions.get_associated_token_address(program_data_account, MINT_KEY)
         user_token_account = spl_token_instructions.get_associated_token_address(wallet.public_key, MINT_KEY)
+        config_account, config_bump = PublicKey.find_program_address([bytes("config", encoding="utf-8")], PROGRAM_KEY)
+        vote_tally_account, vote_tally_bump = PublicKey.find_program_address([bytes("vote_tally", encoding="utf-8")], PROGRAM_KEY)
+        treasury_token_account = spl_token_instructions.get_associated_token_address(self.get_treasury_key(config_account), MINT_KEY)
+        push_stats_account, push_stats_bump = PublicKey.find_program_address([bytes("push_stats", encoding="utf-8")], PROGRAM_KEY)
+        input_log_account, input_log_bump = PublicKey.find_program_address([bytes("input_log", encoding="utf-8")], PROGRAM_KEY)
 
         amount = np.uint64(amount)
         button = np.uint8(button)
@@ -114,8 +151,13 @@ class Controller(QWidget):
                 AccountMeta(pubkey=wallet.public_key, is_signer=True, is_writable=True),
                 AccountMeta(pubkey=user_token_account, is_signer=False, is_writable=True),
                 AccountMeta(pubkey=program_token_account, is_signer=False, is_writable=True),
-                AccountMeta(pubkey=MINT_KEY, is_signer=False, is_writable=False),
-                AccountMeta(pubkey=TOKEN_PROGRAM_ID, is_signer=False, is_writable=False)
+                AccountMeta(pubkey=MINT_KEY, is_signer=False, is_writable=True),
+                AccountMeta(pubkey=TOKEN_PROGRAM_ID, is_signer=False, is_writable=False),
+                AccountMeta(pubkey=config_account, is_signer=False, is_writable=False),
+                AccountMeta(pubkey=vote_tally_account, is_signer=False, is_writable=True),
+                AccountMeta(pubkey=treasury_token_account, is_signer=False, is_writable=True),
+                AccountMeta(pubkey=push_stats_account, is_signer=False, is_writable=True),
+                AccountMeta(pubkey=input_log_account, is_signer=False, is_writable=True)
                 ]
         )
 
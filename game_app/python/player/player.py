@@ -1,11 +1,15 @@
 import time
 import numpy as np
 from pyboy import PyBoy, WindowEvent
-import random as random
 from datetime import datetime
 from os.path import exists
+import sys
 from sql_funcs import *
 
+# the input log is read with the streamer's rpc functions
+sys.path.append("../streamer")
+from rpc_funcs import get_resolved_inputs
+
 
 class bcolors:
     HEADER = '\033[95m'
@@ -24,16 +28,16 @@ def print_red(string):
 def print_blue(string):
 	print(bcolors.OKBLUE + string + bcolors.ENDC)
     
-buttons = ["Button.A()", "Button.B()", "Button.Left()", "Button.Right()", "Button.Up()", "Button.Down()", "Button.Start()", "Button.Select()"]
-button_map = {}
-for i in range(len(buttons)):
-	button = buttons[i]
-	button_map[button] = i   
-	
+# the buttons in the order of the program's Button variants, which is how the input log refers to them
+buttons = ["Button.A()", "Button.B()", "Button.Up()", "Button.Down()", "Button.Left()", "Button.Right()", "Button.Start()", "Button.Select()"]
+
+quick_node_dev = "my_node"
+# the program's input log account, the PDA with seed "input_log"
+input_log_address = "input_log_address"
 
 frames_per_block = 10
 rom_name = "rom_name"
-def handle_button(py, button):
+def handle_button(py, button, hold_frames = 5):
 
 	push = None
 	release = None
@@ -64,11 +68,16 @@ def handle_button(py, button):
 		release = WindowEvent.RELEASE_BUTTON_SELECT
 		
 	py.send_input(push)
-	for i in range(5):
+	for i in range(hold_frames):
 		py.tick()
 	py.send_input(release)
-	for i in range(frames_per_block - 5):
+	for i in range(frames_per_block - hold_frames):
 		py.tick() 
+
+def handle_sequence(py, steps):
+
+	for button, hold_frames in steps:
+		handle_button(py, button, hold_frames)
 		
 	
 def handle_no_button(block_idx, py):
@@ -76,29 +85,27 @@ def handle_no_button(block_idx, py):
 	for i in range(frames_per_block):
 		py.tick() 
 	
-def choose_button_from_rows(block_idx, rows):
-    weights = np.zeros(len(buttons))
-    for row in rows:
-        button = row[2]
-        weight = row[3]
-        button_idx = button_map[button]
-        weights[button_idx] += weight
-	
-    weights /= np.sum(weights)
-    c_dist = np.cumsum(weights)
-    random.seed(int(block_idx))
-    r = random.random()
-	
-    button_chosen = 0
-    for p in range(len(c_dist)):
-        if(c_dist[p] > r):
-            button_chosen = p
-            break
-			
-    #print(rows)
-    print(block_idx, c_dist, r, button_chosen, buttons[button_chosen])
-    return buttons[button_chosen]
+# returns the window resolved by ResolveInput that contains the block, or None if it is no longer in the input log
+def get_window_for_block(block_idx, resolved_inputs):
+    for window in resolved_inputs:
+        start_slot, end_slot, inputs = window
+        if(start_slot <= block_idx and block_idx < end_slot):
+            return window
+
+    return None
 
+# returns the last block the game can be played up to, which is the end of the last window resolved by ResolveInput,
+# or None if the input log can't be read
+def get_last_playable_block(conn):
+    resolved_inputs = get_resolved_inputs(quick_node_dev, input_log_address)
+    if(resolved_inputs == None):
+        return None
+
+    end_block = get_last_block_id(conn)
+    if(len(resolved_inputs) == 0):
+        return min(end_block, -1)
+
+    return min(end_block, resolved_inputs[-1][1] - 1)
 	
 # handles the range inclusive of start and end
 def handle_block_range(conn, pyboy, start_block, end_block):
@@ -124,24 +131,49 @@ def handle_block_range(conn, pyboy, start_block, end_block):
 
 	blocks = np.sort(list(block_rows.keys()))
 
+	resolved_inputs = get_resolved_inputs(quick_node_dev, input_log_address)
+	if(resolved_inputs == None):
+		resolved_inputs = []
+
 	pyboy.set_emulation_speed(len(blocks))
 
+	# each window's inputs are played once, at the first of its blocks
+	played_windows = set()
 	for block in blocks:
 		#print(block, block_rows[block])
-		process_block_rows(block_rows[block], pyboy, block)
+		process_block(pyboy, block, resolved_inputs, played_windows)
 
 	pyboy.set_emulation_speed(1)
 
 		
 		
-def process_block_rows(rows, pyboy, block_idx):
+def process_block(pyboy, block_idx, resolved_inputs, played_windows):
 
-	if(rows[0][2] == "no_button"):
+	window = get_window_for_block(block_idx, resolved_inputs)
+	if(window == None):
+		print_red("block " + str(block_idx) + " is no longer in the input log")
 		handle_no_button(block_idx, pyboy)
 		return
-			
-	pressed = choose_button_from_rows(block_idx, rows)
-	handle_button(pyboy, pressed)
+
+	start_slot, end_slot, inputs = window
+	if(start_slot in played_windows):
+		for i in range(frames_per_block):
+			pyboy.tick()
+		return
+
+	played_windows.add(start_slot)
+
+	if(inputs == None):
+		print_red("the presses for the window from " + str(start_slot) + " are no longer in the input log")
+		handle_no_button(block_idx, pyboy)
+		return
+
+	if(len(inputs) == 0):
+		handle_no_button(block_idx, pyboy)
+		return
+
+	print(block_idx, "window", start_slot, "to", end_slot, [(buttons[button], hold_frames) for button, hold_frames in inputs])
+	handle_sequence(pyboy, [(buttons[button], hold_frames) for button, hold_frames in inputs])
 	
 # initialises the game for testing
 def init_game(pyboy):
@@ -162,7 +194,9 @@ def skip_to_present(conn, pyboy):
 
 	# first just check if anything exists
 	start_block = get_first_block_id(conn)
-	end_block = get_last_block_id(conn)
+	end_block = get_last_playable_block(conn)
+	if(end_block == None):
+		end_block = -1
 	
 	if(end_block == -1):
 		init_game(pyboy)
@@ -245,9 +279,9 @@ conn = create_connection()
 current_block = skip_to_present(conn, pyboy)
 last_save = datetime.utcnow()
 while(True):
-	end_block = get_last_block_id(conn)
+	end_block = get_last_playable_block(conn)
 	
-	if(end_block == current_block):
+	if(end_block == None or end_block <= current_block):
 		#time.sleep(0.05)
 		for i in range(5):
 			pyboy.tick()
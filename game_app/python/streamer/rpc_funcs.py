@@ -1,6 +1,8 @@
 import time
-from borsh_construct import Enum, CStruct, U64
+from borsh_construct import Enum, CStruct, Vec, U8, U16, U64, I64, String, Bool
+from construct import Bytes
 import base58
+import base64
 import requests
 from requests.structures import CaseInsensitiveDict
 import json as json
@@ -9,6 +11,14 @@ import concurrent.futures as cf
 from datetime import datetime
 from log import *
 
+input_mode_type = Enum(
+    "Lottery",
+    "Anarchy",
+    "Democracy",
+    "Auction",
+    enum_name = "InputMode"
+)
+
 button_type = Enum(
     "A",
     "B",
@@ -21,26 +31,127 @@ button_type = Enum(
     enum_name = "Button"
 )
 
-charity_type = Enum(
-    "EvidenceAction",
-    "GirlsWhoCode",
-    "OneTreePlanted",
-    "OutrightActionInt",
-    "TheLifeYouCanSave",
-    "UkraineERF",
-    "WaterOrg",
-    enum_name = "Charity"
+# a step of a PushSequence, the button and the number of frames to hold it for
+sequence_step_type = CStruct(
+    "button" / button_type,
+    "hold_frames" / U8
+)
+
+# charities are referred to by their index in the program's charity registry
+charity_type = U8
+    
+config_type = CStruct(
+    "authority" / Bytes(32),
+    "data_account" / Bytes(32),
+    "token_mint" / Bytes(32),
+    "btc_oracle" / Bytes(32),
+    "eth_oracle" / Bytes(32),
+    "sol_oracle" / Bytes(32),
+    "tokens_won" / U64,
+    "max_winners" / U8,
+    "min_bid" / U64,
+    "selection_time" / I64,
+    "protect_larger_bids" / Bool,
+    "max_oracle_slot_age" / U64,
+    "randomness_source" / Enum("Oracles", "CommitReveal", enum_name="RandomnessSource"),
+    "reveal_timeout_slots" / U64,
+    "commit_bond" / U64,
+    "crank_tip_lamports" / U64,
+    "crank_tip_tokens" / U64,
+    "input_window_slots" / U64,
+    "mode_vote_slots" / U64,
+    "treasury" / Bytes(32),
+    "push_burn_bps" / U16,
+    "push_treasury_bps" / U16,
+    "cancel_refund_tokens_per_sol" / U64
 )
     
 message = Enum(
-"CreateDataAccount" / CStruct("amount" / U64),
+"CreateDataAccount" / CStruct("amount" / U64, "config" / config_type),
 "PushButton" / CStruct("button" / button_type, "amount" / U64),
 "PlaceBid"/ CStruct("amount_charity" / U64, "amount_dao" / U64, "charity" / charity_type),
 "SelectWinners",
 "SendTokens",
+"UpdateConfig" / CStruct("config" / config_type),
+"AddCharity" / CStruct("name" / String, "address" / Bytes(32)),
+"UpdateCharity" / CStruct("index" / U8, "address" / Bytes(32), "active" / Bool),
+"CancelBid",
+"CommitSeed" / CStruct("commitment" / Bytes(32)),
+"RevealSeed" / CStruct("secret" / Bytes(32)),
+"SlashCommit",
+"ResizeState" / CStruct("bid_capacity" / U16, "winner_capacity" / U8),
+"MigrateState",
+"ClaimPrize",
+"Crank",
+"ResolveInput",
+"VoteInputMode" / CStruct("mode" / input_mode_type, "amount" / U64),
+"PushSequence" / CStruct("steps" / Vec(sequence_step_type), "amount" / U64),
+"CreditWinners",
 enum_name="DPPInstruction", 
 )
 
+ENVELOPE_TAG = 255
+INSTRUCTION_VERSION = 1
+
+# the program's vote tally account, a ring buffer of the token weighted button votes of recent slots
+VOTE_HISTORY_SLOTS = 110
+
+slot_votes_type = CStruct(
+    "slot" / U64,
+    "n_presses" / U64,
+    "tallies" / U64[8],
+    "top_bid" / U64,
+    "top_button" / U8
+)
+
+vote_tally_type = CStruct(
+    "discriminator" / Bytes(8),
+    "layout_version" / U8,
+    "head" / U64,
+    "records" / slot_votes_type[VOTE_HISTORY_SLOTS]
+)
+
+# the program's input log account, a ring buffer of the resolved windows and of the steps of the presses made in them
+INPUT_LOG_SIZE = 128
+PRESS_LOG_SIZE = 224
+NO_BUTTON = 255
+DEFAULT_HOLD_FRAMES = 5
+
+# a window records its mode as the index of the InputMode variant
+ANARCHY_MODE = 1
+AUCTION_MODE = 3
+
+resolved_input_type = CStruct(
+    "start_slot" / U64,
+    "end_slot" / U64,
+    "button" / U8,
+    "mode" / U8,
+    "complete" / U8,
+    "total" / U64,
+    "random" / U64,
+    "first_press" / U64,
+    "n_presses" / U16
+)
+
+logged_press_type = CStruct(
+    "slot" / U64,
+    "amount" / U64,
+    "button" / U8,
+    "hold_frames" / U8,
+    "n_steps" / U8
+)
+
+input_log_type = CStruct(
+    "discriminator" / Bytes(8),
+    "layout_version" / U8,
+    "next_slot" / U64,
+    "n_resolved" / U64,
+    "n_logged" / U64,
+    "next_press" / U64,
+    "inputs" / resolved_input_type[INPUT_LOG_SIZE],
+    "presses" / logged_press_type[PRESS_LOG_SIZE]
+)
+
 
 sleep_time = 0.25
 
@@ -218,6 +329,118 @@ def get_request_header():
 
     return headers
 
+# returns the json request for getAccountInfo
+def get_account_info_request(address, id = 1):
+
+    new_request = json.loads('{"jsonrpc": "2.0","id": 1, "method":"getAccountInfo", "params":["", {"encoding": "base64", "commitment": "confirmed"}]}')
+    new_request["id"] = id
+    new_request["params"][0] = address
+
+    return new_request, get_request_header()
+
+# returns a dictionary of slot to the tokens pushed into each button in that slot, read from the vote tally
+# account rather than from the blocks
+def get_slot_votes(dev_client_url, vote_tally_address):
+
+    request, headers = get_account_info_request(vote_tally_address)
+
+    try:
+        resp = requests.post(dev_client_url, headers=headers, data=json.dumps(request), timeout=10)
+    except:
+        log_error("getAccountInfo request timed out")
+        return None
+
+    response_json = resp.json()
+    if (not perform_request_response_checks("get_slot_votes", response_json)):
+        return None
+
+    if (response_json["result"]["value"] == None):
+        log_error("vote tally account " + vote_tally_address + " does not exist")
+        return None
+
+    data = base64.b64decode(response_json["result"]["value"]["data"][0])
+    vote_tally = vote_tally_type.parse(data)
+
+    slot_votes = {}
+    for record in vote_tally.records:
+        if (record.n_presses > 0):
+            slot_votes[record.slot] = list(record.tallies)
+
+    return slot_votes
+
+# returns the (button index, hold frames) the game plays for a resolved window, as InputLog::get_window_inputs does.
+# in anarchy these are every step of every press in the window, in auction the steps of the largest press, and
+# otherwise the window's button. returns None if the presses the window needs are no longer in the log
+def get_window_inputs(input_log, window):
+
+    anarchy = window.mode == ANARCHY_MODE
+
+    if (window.button == NO_BUTTON and not anarchy):
+        return []
+
+    if (not anarchy and (window.mode != AUCTION_MODE or window.complete == 0)):
+        return [(window.button, DEFAULT_HOLD_FRAMES)]
+
+    end_press = window.first_press + window.n_presses
+    if (window.first_press < max(input_log.n_logged - PRESS_LOG_SIZE, 0) or end_press > input_log.n_logged):
+        return None
+
+    # put the steps back together into presses, dropping any steps whose press has already been overwritten
+    presses = []
+    for press_idx in range(window.first_press, end_press):
+        step = input_log.presses[press_idx % PRESS_LOG_SIZE]
+        if (step.n_steps > 0):
+            presses.append((step.amount, []))
+
+        if (len(presses) > 0):
+            presses[-1][1].append((step.button, step.hold_frames))
+
+    if (anarchy):
+        return [step for amount, steps in presses for step in steps]
+
+    # the earliest of the largest presses wins
+    top_press = None
+    for press in presses:
+        if (top_press == None or press[0] > top_press[0]):
+            top_press = press
+
+    if (top_press == None):
+        return []
+
+    return top_press[1]
+
+# returns the windows resolved by ResolveInput that are still in the input log account, oldest first, as a list of
+# (start_slot, end_slot, inputs), where inputs is the list of (button index, hold frames) for the window
+def get_resolved_inputs(dev_client_url, input_log_address):
+
+    request, headers = get_account_info_request(input_log_address)
+
+    try:
+        resp = requests.post(dev_client_url, headers=headers, data=json.dumps(request), timeout=10)
+    except:
+        log_error("getAccountInfo request timed out")
+        return None
+
+    response_json = resp.json()
+    if (not perform_request_response_checks("get_resolved_inputs", response_json)):
+        return None
+
+    if (response_json["result"]["value"] == None):
+        log_error("input log account " + input_log_address + " does not exist")
+        return None
+
+    data = base64.b64decode(response_json["result"]["value"]["data"][0])
+    input_log = input_log_type.parse(data)
+
+    n_kept = min(input_log.n_resolved, INPUT_LOG_SIZE)
+
+    resolved_inputs = []
+    for window_idx in range(input_log.n_resolved - n_kept, input_log.n_resolved):
+        window = input_log.inputs[window_idx % INPUT_LOG_SIZE]
+        resolved_inputs.append((window.start_slot, window.end_slot, get_window_inputs(input_log, window)))
+
+    return resolved_inputs
+
 # returns the json request for getSignaturesForAddress
 def get_signatures_request(current_signature = None, id = 1):
 
@@ -547,14 +770,21 @@ def get_data_from_block(block_idx, block):
             data = instruction["data"]
             decoded_data = base58.b58decode(data)
 
+            # instructions may be wrapped in a versioned envelope, [255, version, instruction]
+            if (len(decoded_data) > 1 and decoded_data[0] == ENVELOPE_TAG):
+                if (decoded_data[1] != INSTRUCTION_VERSION):
+                    log_error("unsupported instruction version: " + str(decoded_data[1]))
+                    continue
+                decoded_data = decoded_data[2:]
+
             try:
                 args = message.parse(decoded_data)
             except:
                 log_error("unable to parse data: " + str(decoded_data))
                 continue
 
-            if(not isinstance(args, message.enum.PushButton)):
-                print("Have data but not a PushButton:", args)
+            if(not isinstance(args, (message.enum.PushButton, message.enum.PushSequence))):
+                print("Have data but not a PushButton or PushSequence:", args)
                 continue
             
             data_vec.append(args)
@@ -562,6 +792,17 @@ def get_data_from_block(block_idx, block):
     return block_idx, data_vec	
 
 
+# the choice column of a row, the button for a PushButton, or each button and hold for a PushSequence,
+# e.g. Sequence(Button.Up():16,Button.A():5)
+def get_choice_from_data(args):
+
+    if(isinstance(args, message.enum.PushSequence)):
+        steps = [str(step.button) + ":" + str(step.hold_frames) for step in args.steps]
+        return "Sequence(" + ",".join(steps) + ")"
+
+    return str(args.button)
+
+
 # create the rows for the database from the block data
 def create_rows_from_data(row_id_to_insert, block_id, data, rows_vec):
 
@@ -574,7 +815,7 @@ def create_rows_from_data(row_id_to_insert, block_id, data, rows_vec):
         for i in range(len(data)):
             args = data[i]
             row_id = row_id_to_insert + i
-            new_row = (row_id, block_id, str(args.button), args.amount)
+            new_row = (row_id, block_id, get_choice_from_data(args), args.amount)
             log_db("adding row: " + str(new_row))
             rows_vec.append(new_row)
 			
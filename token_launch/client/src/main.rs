@@ -1,9 +1,15 @@
-pub mod utils;
+// the client error type wraps ClientError directly so `?` works on rpc calls
+#![allow(clippy::result_large_err)]
+
 pub mod state;
 
 use std::env;
 use std::str::FromStr;
 use crate::state::{Result};
+use dp_common::charity::{CharityRegistry, AddCharityData, DEFAULT_CHARITIES, get_expected_charity_registry_key};
+use dp_common::token_launch::instruction::TokenLaunchInstruction;
+use dp_common::token_launch::state::{JoinMeta, InitMeta, get_launch_layout_version};
+use dp_common::token_launch::accounts;
 
 use solana_client::rpc_client::RpcClient;
 use solana_program::{pubkey::Pubkey, system_program};
@@ -12,72 +18,12 @@ use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     transaction::Transaction, signer::keypair::read_keypair_file
 };
-use borsh::{BorshDeserialize, BorshSerialize};
 use solana_transaction_status::UiTransactionEncoding;
 use spl_associated_token_account::{get_associated_token_address};
-use enum_map::{enum_map, Enum};
-
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Enum, Copy)]
-pub enum Charity {
-
-    EvidenceAction,
-    GirlsWhoCode,   
-    OneTreePlanted,
-    OutrightActionInt,
-    TheLifeYouCanSave,
-    InvalidCharity,
-    UkraineERF,
-    WaterOrg
-    
-}
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct ICOMeta {
-    pub amount_charity : u64,
-    pub amount_dao : u64,
-    pub charity : Charity
-}
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct CreateAccountMeta {
-    pub amount : u64,
-    pub supporter_amount : u64
-}
-
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub enum ICOInstruction {
-
-    InitICO {
-        metadata: CreateAccountMeta
-    },
-
-    JoinICO {
-        metadata: ICOMeta
-    },
-
-    EndICO
-
-}
 
 
 const URL: &str = "https://api.mainnet-beta.solana.com";
 
-fn match_charity(index :  u8) ->  Charity 
-{
-    match index {
-        0 => Charity::EvidenceAction,
-        1 => Charity::GirlsWhoCode,
-        2 => Charity::OneTreePlanted,
-        3 => Charity::OutrightActionInt,
-        4 => Charity::TheLifeYouCanSave,
-        5 => Charity::UkraineERF,
-        6 => Charity::WaterOrg,
-        _ => Charity::InvalidCharity
-    }
-}
-
 fn main() {
 
     let args: Vec<String> = env::args().collect();
@@ -103,11 +49,18 @@ fn main() {
         let amount_dao_arg = &args[5];
 
         let charity_index : u8 = charity_arg.parse().unwrap();
-        let charity = match_charity(charity_index);
         let amount_charity: u64 = amount_charity_arg.parse().unwrap();
         let amount_dao: u64 = amount_dao_arg.parse().unwrap();
 
-        if let Err(err) = join_ico(key_file, charity, amount_charity, amount_dao) {
+        if let Err(err) = join_ico(key_file, charity_index, amount_charity, amount_dao) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "init_charities" {
+
+        if let Err(err) = init_charities(key_file) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
@@ -121,6 +74,14 @@ fn main() {
         }
 
     }
+    else if function == "migrate_state" {
+
+        if let Err(err) = migrate_state(key_file) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
     
 
 }
@@ -133,23 +94,17 @@ pub fn create_data_account(
     supporter_amount : u64
 ) -> Result<()> {
 
-    let (expected_pda, bump_seed) = Pubkey::find_program_address(&[b"launch_account"], program);
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
-    );
+    let (expected_pda, bump_seed) = accounts::get_expected_program_address_key(program);
+    let mint_address = accounts::get_expected_token_mint_key();
+    let program_token_address = accounts::get_expected_program_token_key(program);
 
     let my_token_address = get_associated_token_address(
         &creator.pubkey(), 
         &mint_address
     );
 
-    let supporter_mint_address = Pubkey::from_str("7B1yoU3EsbABt1kNXcJLeJRT8jwPy9rZfhrhWzuCA9Fq").unwrap();
-    let program_supporter_token_address = get_associated_token_address(
-        &expected_pda, 
-        &supporter_mint_address
-    );
+    let supporter_mint_address = accounts::get_expected_supporters_token_mint_key();
+    let program_supporter_token_address = accounts::get_expected_program_supporters_token_key(program);
 
     let my_supporter_token_address = get_associated_token_address(
         &creator.pubkey(), 
@@ -159,11 +114,11 @@ pub fn create_data_account(
     println!("pda: {} {}", expected_pda, bump_seed);
     println!("token_address: {} {} {}", program_token_address, my_token_address, my_supporter_token_address);
 
-    let meta_data =  CreateAccountMeta{amount : amount, supporter_amount: supporter_amount};
+    let meta_data =  InitMeta{amount, supporter_amount};
 
     let instruction = Instruction::new_with_borsh(
         *program,
-        &ICOInstruction::InitICO{metadata : meta_data},
+        &TokenLaunchInstruction::InitTokenLaunch{metadata : meta_data},
         vec![
             AccountMeta::new_readonly(creator.pubkey(), true),
             AccountMeta::new(expected_pda, false),
@@ -210,49 +165,87 @@ fn init_pda_account(key_file: &String, amount : u64, supporter_amount : u64) ->R
     // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    let program = Pubkey::from_str("GwsxvpsHURySgnLrkMcnYuSH2Sbd4v9eZwB5ruiVxgjE").unwrap();
+    let program = dp_common::token_launch::id();
   
     create_data_account(&wallet, &program, &connection, amount, supporter_amount)?;
 
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
 }
 
-fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
+// look up the address of an active charity in the program's charity registry
+fn get_charity_address(connection: &RpcClient, program: &Pubkey, charity : u8) -> Result<Pubkey> {
 
-    println!("In join_ico");
-    
-    let charity_map = enum_map!{
-        Charity::EvidenceAction => "9fF5EQV6FVy7V5SaHBXfAaTUBvuyimQ9X3jarc2mRHzi",
-        Charity::GirlsWhoCode => "5qrmDeRFhBTnEkqJsRKJAkTJzrZnyC9bWmRhL6RZqWt1",
-        Charity::OneTreePlanted => "GeCaNYhRswBFoTxtNaf9wKYJEBZoxHa9Fao6aQKzDDo2",
-        Charity::OutrightActionInt => "AiY4t79umvBqGvR43f5rL8jR8F2JZwG87mB55adAF2cf",
-        Charity::TheLifeYouCanSave => "8qQpHYjLkNiKvLtFzrjzgFZfveNJZ9AnQuBUoQj1t3DB",
-        Charity::UkraineERF  => "E6TPLh77cx9b5aWsmxM8geit2PBLVEBVAvF6ye9Qe4ZQ",
-        Charity::WaterOrg => "5UNSVwtiSdfsCbJokL4fHtzV28mVNi8fQkMjPQw6v7Xd",
-        Charity::InvalidCharity => "NULL"
-    };
+    let (charity_registry_account, _bump_seed) = get_expected_charity_registry_key(program);
+    let registry_data = connection.get_account_data(&charity_registry_account)?;
+    let registry = CharityRegistry::load(&registry_data).map_err(|e| state::Error::InvalidConfig(e.to_string()))?;
+
+    match registry.get_charity(charity) {
+        Some(entry) if entry.is_active() => Ok(entry.address),
+        _ => Err(state::Error::InvalidConfig(format!("charity {} is not an active charity", charity)))
+    }
+}
+
+// add the charities that were supported before the registry, so they keep their original indices
+fn init_charities(key_file: &String) -> Result<()> {
 
     let wallet = read_keypair_file(key_file).unwrap();
-    let program = Pubkey::from_str("GwsxvpsHURySgnLrkMcnYuSH2Sbd4v9eZwB5ruiVxgjE").unwrap();
+    let program = dp_common::token_launch::id();
 
+    let connection = RpcClient::new(URL);
 
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"launch_account"], &program);
+    let (charity_registry_account, _registry_bump_seed) = get_expected_charity_registry_key(&program);
+
+    let mut instructions = vec![];
+    for (name, address) in DEFAULT_CHARITIES {
+        let charity_data = AddCharityData{name : name.to_string(), address : Pubkey::from_str(address).unwrap()};
+
+        instructions.push(Instruction::new_with_borsh(
+            program,
+            &TokenLaunchInstruction::AddCharity { charity_data },
+            vec![
+                AccountMeta::new(wallet.pubkey(), true),
+                AccountMeta::new(charity_registry_account, false),
+                AccountMeta::new_readonly(system_program::id(), false)
+            ],
+        ));
+    }
 
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
+    let signers = [&wallet];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
     );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    println!("Success!");
+    Ok(())
+}
+
+fn join_ico(key_file: &String, charity : u8, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
+
+    println!("In join_ico");
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::token_launch::id();
+
+    let (expected_pda, _bump_seed) = accounts::get_expected_program_address_key(&program);
+
+    let mint_address = accounts::get_expected_token_mint_key();
+    let program_token_address = accounts::get_expected_program_token_key(&program);
     let my_token_address = get_associated_token_address(
         &wallet.pubkey(), 
         &mint_address
     );
 
-    let supporter_mint_address = Pubkey::from_str("7B1yoU3EsbABt1kNXcJLeJRT8jwPy9rZfhrhWzuCA9Fq").unwrap();
-    let program_supporter_token_address = get_associated_token_address(
-        &expected_pda, 
-        &supporter_mint_address
-    );
+    let supporter_mint_address = accounts::get_expected_supporters_token_mint_key();
+    let program_supporter_token_address = accounts::get_expected_program_supporters_token_key(&program);
 
     let my_supporter_token_address = get_associated_token_address(
         &wallet.pubkey(), 
@@ -260,31 +253,27 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
     );
 
 
-    let daoplays  = Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap();
-
-
-    if charity == Charity::InvalidCharity {
-        return Ok(println!("InvalidCharity!"));
-    }
-
-    let charity_key = Pubkey::from_str(charity_map[charity]).unwrap();
+    let daoplays  = accounts::get_expected_daoplays_key();
 
     // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    println!("wallet {}", wallet.pubkey().to_string());
-    println!("charity_key  {}", charity_key.to_string());
-    println!("mint_address {}", mint_address.to_string());
-    println!("program_token_address {}", program_token_address.to_string());
-    println!("my_token_address {}", my_token_address.to_string());
-    println!("daoplays {}", daoplays.to_string());
-    println!("expected_pda {}\n", expected_pda.to_string());
+    let charity_key = get_charity_address(&connection, &program, charity)?;
+    let (charity_registry_account, _registry_bump_seed) = get_expected_charity_registry_key(&program);
 
-    let meta_data =  ICOMeta{charity : charity, amount_charity : amount_charity,  amount_dao : amount_dao};
+    println!("wallet {}", wallet.pubkey());
+    println!("charity_key  {}", charity_key);
+    println!("mint_address {}", mint_address);
+    println!("program_token_address {}", program_token_address);
+    println!("my_token_address {}", my_token_address);
+    println!("daoplays {}", daoplays);
+    println!("expected_pda {}\n", expected_pda);
+
+    let meta_data =  JoinMeta{charity, amount_charity,  amount_dao};
 
     let instruction = Instruction::new_with_borsh(
         program,
-        &ICOInstruction::JoinICO{metadata : meta_data},
+        &TokenLaunchInstruction::JoinTokenLaunch{metadata : meta_data},
         vec![
             AccountMeta::new(wallet.pubkey(), true),
             AccountMeta::new(my_token_address, false),
@@ -302,7 +291,9 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
 
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_associated_token_account::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false)
+            AccountMeta::new_readonly(system_program::id(), false),
+
+            AccountMeta::new(charity_registry_account, false)
         ],
     );
 
@@ -324,7 +315,8 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
     //let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
     //println!("result: {:#?}", response);
 
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
 }
 
 
@@ -332,25 +324,19 @@ fn end_ico(key_file: &String) -> Result<()> {
 
 
     let wallet = read_keypair_file(key_file).unwrap();
-    let program = Pubkey::from_str("GwsxvpsHURySgnLrkMcnYuSH2Sbd4v9eZwB5ruiVxgjE").unwrap();
+    let program = dp_common::token_launch::id();
 
 
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"launch_account"], &program);
-    let mint_address = Pubkey::from_str("6PRgpKnwT9xgGF7cgS7ZMkPBeQmd5mdS97eg26ir8Kki").unwrap();
-    let program_token_address = get_associated_token_address(
-        &expected_pda, 
-        &mint_address
-    );
+    let (expected_pda, _bump_seed) = accounts::get_expected_program_address_key(&program);
+    let mint_address = accounts::get_expected_token_mint_key();
+    let program_token_address = accounts::get_expected_program_token_key(&program);
     let my_token_address = get_associated_token_address(
         &wallet.pubkey(), 
         &mint_address
     );
 
-    let supporter_mint_address = Pubkey::from_str("7B1yoU3EsbABt1kNXcJLeJRT8jwPy9rZfhrhWzuCA9Fq").unwrap();
-    let program_supporter_token_address = get_associated_token_address(
-        &expected_pda, 
-        &supporter_mint_address
-    );
+    let supporter_mint_address = accounts::get_expected_supporters_token_mint_key();
+    let program_supporter_token_address = accounts::get_expected_program_supporters_token_key(&program);
 
     let my_supporter_token_address = get_associated_token_address(
         &wallet.pubkey(), 
@@ -360,15 +346,15 @@ fn end_ico(key_file: &String) -> Result<()> {
     // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    println!("wallet {}", wallet.pubkey().to_string());
-    println!("mint_address {}", mint_address.to_string());
-    println!("program_token_address {}", program_token_address.to_string());
-    println!("my_token_address {}", my_token_address.to_string());
-    println!("expected_pda {}\n", expected_pda.to_string());
+    println!("wallet {}", wallet.pubkey());
+    println!("mint_address {}", mint_address);
+    println!("program_token_address {}", program_token_address);
+    println!("my_token_address {}", my_token_address);
+    println!("expected_pda {}\n", expected_pda);
 
     let instruction = Instruction::new_with_borsh(
         program,
-        &ICOInstruction::EndICO,
+        &TokenLaunchInstruction::EndTokenLaunch,
         vec![
             AccountMeta::new(wallet.pubkey(), true),
             AccountMeta::new(my_token_address, false),
@@ -404,5 +390,46 @@ fn end_ico(key_file: &String) -> Result<()> {
     let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
     println!("result: {:#?}", response);
 
-    Ok(println!("Success!"))
+    println!("Success!");
+    Ok(())
+}
+
+fn migrate_state(key_file: &String) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = dp_common::token_launch::id();
+
+    let (expected_pda, _bump_seed) = accounts::get_expected_program_address_key(&program);
+
+    let connection = RpcClient::new(URL);
+
+    let launch_data = connection.get_account_data(&expected_pda)?;
+    println!("launch account {} has layout version {:?}", expected_pda, get_launch_layout_version(&launch_data));
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &TokenLaunchInstruction::MigrateState,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(expected_pda, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    println!("Success!");
+    Ok(())
 }
@@ -1,4 +1,3 @@
-use borsh::{BorshDeserialize, BorshSerialize};
 use thiserror::Error;
 
 
@@ -22,11 +21,3 @@ pub enum Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
-pub struct ICOData {
-    pub charity_totals : [u64 ; 7],
-    pub donated_total : u64,
-    pub paid_total : u64,
-    pub n_donations : u64
-}
@@ -1,7 +1,7 @@
-use borsh::{BorshDeserialize, BorshSerialize};
-use std::str::FromStr;
-use crate::state::{JoinMeta, InitMeta, Charity, TokenLaunchData};
-use enum_map::{enum_map, EnumMap};
+use borsh::BorshDeserialize;
+use crate::state::{JoinMeta, InitMeta, CharityRegistry, AddCharityData, UpdateCharityData, LAUNCH_LAYOUT_VERSION, LEGACY_LAYOUT_VERSION, get_launch_layout_version, get_state_size, load_launch_data, store_launch_data, migrate_legacy_launch_data};
+use crate::error::RNGError;
+use dp_common::charity::{get_expected_charity_registry_key, check_charity_registry};
 use crate::accounts;
 use crate::utils;
 
@@ -24,7 +24,7 @@ impl Processor {
     
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
 
-        let instruction = TokenLaunchInstruction::try_from_slice(&instruction_data[..])?;
+        let instruction = TokenLaunchInstruction::try_from_slice(instruction_data)?;
 
         match instruction {
             TokenLaunchInstruction::InitTokenLaunch {metadata} => {
@@ -37,6 +37,15 @@ impl Processor {
             },
             TokenLaunchInstruction::EndTokenLaunch => {
                 Self::end_token_launch(program_id, accounts)
+            },
+            TokenLaunchInstruction::AddCharity {charity_data} => {
+                Self::add_charity(program_id, accounts, charity_data)
+            },
+            TokenLaunchInstruction::UpdateCharity {charity_data} => {
+                Self::update_charity(program_id, accounts, charity_data)
+            },
+            TokenLaunchInstruction::MigrateState => {
+                Self::migrate_state(program_id, accounts)
             }
         }
     } 
@@ -202,26 +211,6 @@ impl Processor {
 
 
 
-        let charity_key_map = enum_map!{
-            Charity::EvidenceAction => "9fF5EQV6FVy7V5SaHBXfAaTUBvuyimQ9X3jarc2mRHzi",
-            Charity::GirlsWhoCode => "5qrmDeRFhBTnEkqJsRKJAkTJzrZnyC9bWmRhL6RZqWt1",
-            Charity::OneTreePlanted => "GeCaNYhRswBFoTxtNaf9wKYJEBZoxHa9Fao6aQKzDDo2",
-            Charity::OutrightActionInt => "AiY4t79umvBqGvR43f5rL8jR8F2JZwG87mB55adAF2cf",
-            Charity::TheLifeYouCanSave => "8qQpHYjLkNiKvLtFzrjzgFZfveNJZ9AnQuBUoQj1t3DB",
-            Charity::UkraineERF  => "E6TPLh77cx9b5aWsmxM8geit2PBLVEBVAvF6ye9Qe4ZQ",
-            Charity::WaterOrg => "5UNSVwtiSdfsCbJokL4fHtzV28mVNi8fQkMjPQw6v7Xd"
-        };
-
-        let charity_index_map: EnumMap<Charity, usize> = enum_map!{
-            Charity::EvidenceAction => 0,
-            Charity::GirlsWhoCode => 1,
-            Charity::OneTreePlanted => 2,
-            Charity::OutrightActionInt => 3,
-            Charity::TheLifeYouCanSave => 4,
-            Charity::UkraineERF => 5,
-            Charity::WaterOrg => 6
-        };
-
         // get the accounts
         let account_info_iter = &mut accounts.iter();
 
@@ -243,6 +232,8 @@ impl Processor {
         let associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+
 
         // now check all the accounts
         // the joiners account should be the signer
@@ -253,8 +244,8 @@ impl Processor {
 
         // the second account should be the joiners associated token account
         let expected_joiner_token_key = get_associated_token_address(
-            &joiner_account_info.key, 
-            &token_mint_account_info.key
+            joiner_account_info.key, 
+            token_mint_account_info.key
         );
 
         if joiner_token_account_info.key != &expected_joiner_token_key
@@ -265,8 +256,8 @@ impl Processor {
 
         // the third account should be the joiners supporter associated token account
         let expected_joiner_supporters_token_key = get_associated_token_address(
-            &joiner_account_info.key, 
-            &supporters_token_mint_account_info.key
+            joiner_account_info.key, 
+            supporters_token_mint_account_info.key
         );
 
         if joiner_supporters_token_account_info.key != &expected_joiner_supporters_token_key
@@ -298,8 +289,17 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData); 
         }
 
-        // the seventh account is the charity SOL address, which we can check with the map
-        let expected_charity_key = Pubkey::from_str(charity_key_map[meta.charity]).unwrap();
+        // the seventh account is the charity SOL address, which we can check with the charity registry
+        // in the fourteenth account
+        check_charity_registry(program_id, charity_registry_account_info, RNGError::InvalidCharityRegistry.into())?;
+
+        let expected_charity_key = match CharityRegistry::load(&charity_registry_account_info.data.borrow())?.get_charity(meta.charity) {
+            Some(charity) if charity.is_active() => charity.address,
+            _ => {
+                msg!("charity {} is not an active charity in the registry", meta.charity);
+                return Err(RNGError::InvalidCharity.into());
+            }
+        };
 
         if charity_account_info.key != &expected_charity_key
         {
@@ -340,7 +340,7 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // the thirteenth and final account is the system_program
+        // the thirteenth account is the system_program
         if system_program_account_info.key != &solana_program::system_program::id() {
             msg!("expected thirteenth account to be the system program {}", solana_program::system_program::id());
             return Err(ProgramError::InvalidAccountData);
@@ -365,8 +365,10 @@ impl Processor {
         msg!("Transfer {} {}", meta.amount_charity, meta.amount_dao);
         msg!("Balance {}", joiner_account_info.try_borrow_lamports()?);
 
+        let total_paid = meta.amount_charity.checked_add(meta.amount_dao).ok_or(RNGError::ArithmeticOverflow)?;
+
         let min_amount : u64 = 100000;
-        if meta.amount_charity + meta.amount_dao < min_amount {
+        if total_paid < min_amount {
             msg!("Amount paid is less than the minimum of 0.0001 SOL");
             return Err(ProgramError::InvalidArgument);
         }
@@ -383,14 +385,10 @@ impl Processor {
         }
 
         // get the data stored in the program account to access current state
-        let mut current_state = TokenLaunchData::try_from_slice(&program_data_account_info.data.borrow()[..])?;
+        let mut current_state = load_launch_data(&program_data_account_info.data.borrow())?;
 
         // calculate the current average to see if this individual has paid more
-        let mut current_average = 0;
-        if current_state.n_donations > 0 {
-            current_average = current_state.paid_total / current_state.n_donations;
-        }
-        let total_paid = meta.amount_charity + meta.amount_dao;
+        let current_average = current_state.paid_total.get().checked_div(current_state.n_donations.get()).unwrap_or(0);
         let mut token_launch_amount : u64 = 1000;
 
         let mut supporter = false;
@@ -452,16 +450,18 @@ impl Processor {
 
         // update the data
 
-        let charity_index = charity_index_map[meta.charity];
+        current_state.record_donation(meta.charity, meta.amount_charity, total_paid).ok_or(RNGError::ArithmeticOverflow)?;
 
-        current_state.charity_totals[charity_index] += meta.amount_charity;
-        current_state.donated_total += meta.amount_charity;
-        current_state.paid_total += total_paid;
-        current_state.n_donations += 1;
+        let mut charity_registry_data = charity_registry_account_info.data.borrow_mut();
+        let charity_registry = CharityRegistry::load_mut(&mut charity_registry_data)?;
+        let charity_total = match charity_registry.get_charity_mut(meta.charity) {
+            Some(charity) => charity.add_donation(meta.amount_charity).ok_or(RNGError::ArithmeticOverflow)?,
+            None => 0
+        };
 
-        msg!("Updating current state: {} {} {} {}", current_state.charity_totals[charity_index], current_state.donated_total, current_state.paid_total,  current_state.n_donations);
+        msg!("Updating current state: {} {} {} {}", charity_total, current_state.donated_total, current_state.paid_total,  current_state.n_donations);
 
-        current_state.serialize(&mut &mut program_data_account_info.data.borrow_mut()[..])?;
+        store_launch_data(&current_state, &mut program_data_account_info.data.borrow_mut())?;
 
 
         Ok(())
@@ -585,4 +585,174 @@ impl Processor {
         Ok(())
 
     }
-}
\ No newline at end of file
+
+    fn add_charity(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        charity_data: AddCharityData
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let daoplays_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the daoplays account should be the signer
+        if !daoplays_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // only we should be able to call this function
+        if daoplays_account_info.key != &accounts::get_expected_daoplays_key() {
+            msg!("expected first account to be a daoplays account  {}", accounts::get_expected_daoplays_key());
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the second account is the charity registry, which we create the first time a charity is added
+        let (expected_registry_key, registry_bump_seed) = get_expected_charity_registry_key(program_id);
+
+        if charity_registry_account_info.key != &expected_registry_key {
+            msg!("expected second account to be the charity registry {}", expected_registry_key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the third and final account is the system_program
+        if system_program_account_info.key != &solana_program::system_program::id() {
+            msg!("expected third account to be the system program {}", solana_program::system_program::id());
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if **charity_registry_account_info.try_borrow_lamports()? == 0 {
+            utils::create_charity_registry_account(
+                daoplays_account_info,
+                charity_registry_account_info,
+                program_id,
+                registry_bump_seed
+            )?;
+        }
+
+        check_charity_registry(program_id, charity_registry_account_info, RNGError::InvalidCharityRegistry.into())?;
+
+        let mut charity_registry_data = charity_registry_account_info.data.borrow_mut();
+        let charity_registry = CharityRegistry::load_mut(&mut charity_registry_data)?;
+
+        match charity_registry.add_charity(&charity_data.name, charity_data.address) {
+            Some(index) => {
+                msg!("added charity {} at index {} with address {}", charity_data.name, index, charity_data.address);
+            },
+            None => {
+                msg!("unable to add charity {}, the registry may be full", charity_data.name);
+                return Err(RNGError::InvalidCharity.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_charity(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        charity_data: UpdateCharityData
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let daoplays_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+
+        // the daoplays account should be the signer
+        if !daoplays_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // only we should be able to call this function
+        if daoplays_account_info.key != &accounts::get_expected_daoplays_key() {
+            msg!("expected first account to be a daoplays account  {}", accounts::get_expected_daoplays_key());
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the second account is the charity registry
+        check_charity_registry(program_id, charity_registry_account_info, RNGError::InvalidCharityRegistry.into())?;
+
+        let mut charity_registry_data = charity_registry_account_info.data.borrow_mut();
+        let charity_registry = CharityRegistry::load_mut(&mut charity_registry_data)?;
+
+        let charity = match charity_registry.get_charity_mut(charity_data.index) {
+            Some(charity) => charity,
+            None => {
+                msg!("no charity at index {}", charity_data.index);
+                return Err(RNGError::InvalidCharity.into());
+            }
+        };
+
+        charity.address = charity_data.address;
+        charity.active = charity_data.active as u8;
+
+        msg!("updated charity {} : {} active {}", charity.get_name(), charity.address, charity_data.active);
+
+        Ok(())
+    }
+
+    fn migrate_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let daoplays_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the daoplays account should be the signer, as it pays the extra rent
+        if !daoplays_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // only we should be able to call this function
+        if daoplays_account_info.key != &accounts::get_expected_daoplays_key() {
+            msg!("expected first account to be a daoplays account  {}", accounts::get_expected_daoplays_key());
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the second account should be the programs derived account
+        let (expected_pda_key, _bump_seed) = accounts::get_expected_program_address_key(program_id);
+
+        if program_data_account_info.key != &expected_pda_key
+        {
+            msg!("expected second account to be the programs derived account {}", expected_pda_key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the third account should be the system program
+        if system_program_account_info.key != &solana_program::system_program::id()
+        {
+            msg!("expected third account to be the system program {}", solana_program::system_program::id());
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let layout_version = get_launch_layout_version(&program_data_account_info.data.borrow());
+        match layout_version {
+            Some(LAUNCH_LAYOUT_VERSION) => {
+                msg!("launch account already has layout version {}", LAUNCH_LAYOUT_VERSION);
+                return Ok(());
+            },
+            Some(LEGACY_LAYOUT_VERSION) => {},
+            _ => {
+                msg!("can't migrate a launch account with layout version {:?}", layout_version);
+                return Err(RNGError::UnsupportedStateLayout.into());
+            }
+        }
+
+        utils::resize_program_account(daoplays_account_info, program_data_account_info, get_state_size())?;
+        migrate_legacy_launch_data(&mut program_data_account_info.data.borrow_mut())?;
+
+        msg!("migrated the launch account to layout version {}", LAUNCH_LAYOUT_VERSION);
+
+        Ok(())
+    }
+}
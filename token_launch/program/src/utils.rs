@@ -7,7 +7,8 @@ use solana_program::{
     program_error::ProgramError, native_token::LAMPORTS_PER_SOL
 };
 use spl_associated_token_account::instruction::create_associated_token_account;
-use crate::state::{get_state_size};
+use crate::state::{TokenLaunchData, get_state_size, store_launch_data};
+use dp_common::charity::{CHARITY_REGISTRY_SEED, get_charity_registry_size};
 
 pub fn create_program_account<'a>(
     funding_account: &AccountInfo<'a>,
@@ -46,6 +47,69 @@ pub fn create_program_account<'a>(
         &[&[b"launch_account", &[bump_seed]]]
     )?;
 
+    // the account starts with the layout header so later versions of the program can tell which layout it has
+    store_launch_data(&TokenLaunchData::default(), &mut pda.data.borrow_mut())?;
+
+    Ok(())
+}
+
+// grow the program's derived account to data_size, topping up its lamports so it stays rent exempt
+pub fn resize_program_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    pda : &AccountInfo<'a>,
+    data_size : usize
+) -> ProgramResult
+{
+    if pda.data_len() >= data_size {
+        return Ok(());
+    }
+
+    msg!("Resizing programs derived account from {} to {}", pda.data_len(), data_size);
+
+    let lamports = rent::Rent::default().minimum_balance(data_size);
+    let top_up = lamports.saturating_sub(pda.lamports());
+    if top_up > 0 {
+        invoke(
+            &solana_program::system_instruction::transfer(funding_account.key, pda.key, top_up),
+            &[funding_account.clone(), pda.clone()],
+        )?;
+    }
+
+    pda.realloc(data_size, true)?;
+
+    Ok(())
+}
+
+pub fn create_charity_registry_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    charity_registry_account : &AccountInfo<'a>,
+    program_id :  &Pubkey,
+    bump_seed : u8
+) -> ProgramResult
+{
+
+    msg!("Creating charity registry account");
+
+    let data_size = get_charity_registry_size();
+    let space : u64 = data_size.try_into().unwrap();
+    let lamports = rent::Rent::default().minimum_balance(data_size);
+
+    msg!("Require {} lamports for {} size data", lamports, data_size);
+    let ix = solana_program::system_instruction::create_account(
+        funding_account.key,
+        charity_registry_account.key,
+        lamports,
+        space,
+        program_id,
+    );
+
+    // Sign and submit transaction
+    invoke_signed(
+        &ix,
+        &[funding_account.clone(), charity_registry_account.clone()],
+        &[&[CHARITY_REGISTRY_SEED, &[bump_seed]]]
+    )?;
+
     Ok(())
 }
 
@@ -93,7 +157,7 @@ pub fn create_token_account<'a>(
     }
 
     msg!("creating Token account");
-    let create_ata_idx = create_associated_token_account(&funding_account.key, &wallet_account.key,&token_mint_account.key);
+    let create_ata_idx = create_associated_token_account(funding_account.key, wallet_account.key,token_mint_account.key);
 
     invoke(
         &create_ata_idx,